@@ -0,0 +1,40 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// ── App data ─────────────────────────────────────────────────────────
+//
+// Files the app keeps for itself rather than next to recovered data, so nothing in the
+// evidence folder can vouch for the evidence: the audit key and the recovery indexes.
+// The GUI points this at its Tauri local app data folder at startup; the CLI finds the
+// same folder through LOCALAPPDATA.
+
+// Bundle identifier from tauri.conf.json, which Tauri names the folder after
+const DIR_NAME: &str = "com.kil0bit.winfrpro";
+
+static DATA_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+pub fn set_data_dir(path: &Path) {
+    *DATA_DIR.lock().unwrap_or_else(|e| e.into_inner()) = Some(path.to_path_buf());
+}
+
+// Configured folder, else "%LOCALAPPDATA%\com.kil0bit.winfrpro" (under the temp folder
+// where that is unset)
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = DATA_DIR.lock().unwrap_or_else(|e| e.into_inner()).clone() {
+        return dir;
+    }
+    std::env::var_os("LOCALAPPDATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join(DIR_NAME)
+}
+
+// Path of `name` inside the data folder, creating the folders on the way
+pub fn data_path(name: &str) -> Result<PathBuf, String> {
+    let path = data_dir().join(name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create app data folder: {}", e))?;
+    }
+    Ok(path)
+}
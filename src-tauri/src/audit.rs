@@ -0,0 +1,357 @@
+use crate::appdata;
+use crate::hashing::{self, to_hex};
+use crate::index;
+use crate::report::session_recovery_path;
+use crate::session::{self, RecoverySession};
+use crate::timestamps::now_secs;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// ── Types ────────────────────────────────────────────────────────────
+//
+// Entries are chained with an HMAC under a key kept in the app data folder, away from the
+// logs, so an edited log cannot simply be re-chained. Every session closes its log with a
+// seal entry, and later actions seal it again, so a log cut short no longer verifies.
+//
+// Each action goes to the log of the recovery destination it applies to, found through
+// that destination's session, and the chain is picked up from the log's last entry.
+// Actions that apply to no destination (selecting a drive, imaging, checking evidence) go
+// to the app's own log, which is sealed after every action.
+
+type HmacSha256 = Hmac<Sha256>;
+
+const KEY_FILE_NAME: &str = "audit.key";
+const APP_LOG_NAME: &str = "audit.jsonl";
+const SEAL_ACTION: &str = "sealed";
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub timestamp: u64, // seconds since the Unix epoch
+    pub action: String,
+    pub details: Value,
+    #[serde(rename = "prevHash")]
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditVerification {
+    pub path: String,
+    pub valid: bool,
+    pub entries: u64,
+    #[serde(rename = "firstInvalidSeq")]
+    pub first_invalid_seq: Option<u64>,
+    pub error: Option<String>,
+    // Whether the recovery folder still matches the last recorded manifest hash
+    #[serde(rename = "manifestMatches")]
+    pub manifest_matches: Option<bool>,
+    // Whether the log ends in a seal entry; only the running session's log may lack one
+    pub sealed: bool,
+    // Actions this run of the app failed to write to the log
+    #[serde(rename = "missedActions")]
+    pub missed_actions: Vec<String>,
+}
+
+// Appends are serialized so two actions cannot chain onto the same entry
+static WRITES: Mutex<()> = Mutex::new(());
+
+// Log and action of every failed append, for verification to report
+static MISSED: Mutex<Vec<(PathBuf, String)>> = Mutex::new(Vec::new());
+
+// ── Recording ────────────────────────────────────────────────────────
+
+pub fn log_path_for(session: &RecoverySession) -> PathBuf {
+    Path::new(&session.config.destination).join(format!("{}audit_{}.jsonl", index::APP_FILE_PREFIX, session.id))
+}
+
+pub fn app_log_path() -> Result<PathBuf, String> {
+    appdata::data_path(APP_LOG_NAME)
+}
+
+// The running session's log, if a recovery is in progress or has just ended
+pub fn current_log_path() -> Option<String> {
+    session::current().map(|s| log_path_for(&s).to_string_lossy().to_string())
+}
+
+// Records an action on the files recovered into `destination`
+pub fn record(destination: &str, action: &str, details: Value) -> Result<(), String> {
+    let session = destination_session(destination)?;
+    write_entries(&log_path_for(&session), vec![(action.to_string(), details)], false)
+}
+
+// Records an action that changed the recovered files, followed by a fresh manifest of them
+pub fn record_change(destination: &str, action: &str, details: Value) -> Result<(), String> {
+    let session = destination_session(destination)?;
+    let entries = vec![(action.to_string(), details), manifest_entry(&session_recovery_path(&session))];
+    write_entries(&log_path_for(&session), entries, false)
+}
+
+// Records an action that applies to no recovery destination in the app's own log
+pub fn record_app(action: &str, details: Value) -> Result<(), String> {
+    write_entries(&app_log_path()?, vec![(action.to_string(), details)], true)
+}
+
+fn destination_session(destination: &str) -> Result<RecoverySession, String> {
+    session::load(destination)
+        .ok_or_else(|| format!("No recovery session was found for {}, so its audit log is unknown.", destination))
+}
+
+// Appends `entries` to the log at `path`, then a seal if `seal` is set or the log was
+// already sealed. Fails without writing when the key or the end of the chain is missing.
+fn write_entries(path: &Path, entries: Vec<(String, Value)>, seal: bool) -> Result<(), String> {
+    let _writes = WRITES.lock().unwrap_or_else(|e| e.into_inner());
+    let actions: Vec<String> = entries.iter().map(|(action, _)| action.clone()).collect();
+    let result = append_entries(path, entries, seal);
+    if result.is_err() {
+        let mut missed = MISSED.lock().unwrap_or_else(|e| e.into_inner());
+        missed.extend(actions.into_iter().map(|action| (path.to_path_buf(), action)));
+    }
+    result
+}
+
+fn append_entries(path: &Path, mut entries: Vec<(String, Value)>, seal: bool) -> Result<(), String> {
+    // Without the key nothing is written that could not be verified later
+    let key = load_or_create_key()?;
+    let (mut seq, mut prev_hash, sealed) = chain_end(path)?;
+
+    if seal || sealed {
+        let count = seq + entries.len() as u64;
+        entries.push((SEAL_ACTION.to_string(), serde_json::json!({ "entries": count })));
+    }
+    let mut lines = String::new();
+    for (action, details) in entries {
+        let mut entry = AuditEntry { seq, timestamp: now_secs(), action, details, prev_hash, hash: String::new() };
+        entry.hash = entry_hash(&entry, &key);
+        lines.push_str(&serde_json::to_string(&entry).unwrap_or_default());
+        lines.push('\n');
+        seq += 1;
+        prev_hash = entry.hash;
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut f| f.write_all(lines.as_bytes()).and_then(|_| f.sync_data()))
+        .map_err(|e| format!("Failed to write the audit log {}: {}", path.display(), e))
+}
+
+// Sequence number and hash the next entry chains onto, and whether the log is sealed
+fn chain_end(path: &Path) -> Result<(u64, String, bool), String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((0, GENESIS_HASH.to_string(), false)),
+        Err(e) => return Err(format!("Failed to read the audit log {}: {}", path.display(), e)),
+    };
+    let Some(last) = text.lines().rev().find(|l| !l.trim().is_empty()) else {
+        return Ok((0, GENESIS_HASH.to_string(), false));
+    };
+    let entry: AuditEntry = serde_json::from_str(last).map_err(|_| {
+        format!("The audit log {} ends in a damaged entry; nothing more can be chained to it.", path.display())
+    })?;
+    Ok((entry.seq + 1, entry.hash, entry.action == SEAL_ACTION))
+}
+
+fn entry_hash(entry: &AuditEntry, key: &[u8]) -> String {
+    let material = format!(
+        "{}|{}|{}|{}|{}",
+        entry.seq,
+        entry.timestamp,
+        entry.action,
+        serde_json::to_string(&entry.details).unwrap_or_default(),
+        entry.prev_hash
+    );
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(material.as_bytes());
+    to_hex(&mac.finalize().into_bytes())
+}
+
+// ── Key ──────────────────────────────────────────────────────────────
+
+fn load_key() -> Result<Vec<u8>, String> {
+    fs::read(appdata::data_dir().join(KEY_FILE_NAME)).map_err(|e| {
+        format!("No audit key on this computer ({}); a log can only be verified where it was written.", e)
+    })
+}
+
+// Generated on first use; existing logs stay verifiable only while the key is kept
+fn load_or_create_key() -> Result<Vec<u8>, String> {
+    if let Ok(key) = load_key() {
+        return Ok(key);
+    }
+    let mut key = vec![0u8; 32];
+    getrandom::getrandom(&mut key).map_err(|e| format!("Failed to generate the audit key: {}", e))?;
+    let path = appdata::data_path(KEY_FILE_NAME)?;
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .and_then(|mut f| f.write_all(&key).and_then(|_| f.sync_all()))
+        .map_err(|e| format!("Failed to store the audit key: {}", e))?;
+    Ok(key)
+}
+
+// ── Manifest ─────────────────────────────────────────────────────────
+
+// SHA-256 over the sorted "path|size|sha256" lines of every file in the recovery folder.
+// Files are walked and hashed from disk, without the index, so cached digests cannot mask a
+// change and verifying writes nothing.
+pub fn manifest_hash(recovery_path: &str) -> Result<(String, u64), String> {
+    let recovery_dir = index::resolve_recovery_dir(recovery_path)?;
+    let mut lines: Vec<String> = Vec::new();
+    for (entry, path) in index::recovered_entries(&recovery_dir) {
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        let sha256 = hashing::hash_file(entry.path())?.sha256;
+        lines.push(format!("{}|{}|{}", path, size, sha256));
+    }
+    lines.sort();
+
+    let mut hasher = Sha256::new();
+    for line in &lines {
+        hasher.update(line.as_bytes());
+        hasher.update(b"\n");
+    }
+    Ok((to_hex(&hasher.finalize()), lines.len() as u64))
+}
+
+fn manifest_entry(recovery_path: &str) -> (String, Value) {
+    match manifest_hash(recovery_path) {
+        Ok((hash, files)) => (
+            "manifest".to_string(),
+            serde_json::json!({ "recoveryPath": recovery_path, "manifestHash": hash, "files": files }),
+        ),
+        Err(e) => (
+            "manifest-failed".to_string(),
+            serde_json::json!({ "recoveryPath": recovery_path, "error": e }),
+        ),
+    }
+}
+
+// ── Verification ─────────────────────────────────────────────────────
+
+pub fn verify_audit_log(path: &str) -> Result<AuditVerification, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read audit log: {}", e))?;
+    let mut result = AuditVerification {
+        path: path.to_string(),
+        valid: true,
+        entries: 0,
+        first_invalid_seq: None,
+        error: None,
+        manifest_matches: None,
+        sealed: false,
+        missed_actions: missed_actions(Path::new(path)),
+    };
+    let key = load_key()?;
+
+    let mut prev_hash = GENESIS_HASH.to_string();
+    let mut last_manifest: Option<(String, String)> = None;
+
+    for (line_no, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let expected_seq = result.entries;
+        let failure = match serde_json::from_str::<AuditEntry>(line) {
+            Err(e) => Some(format!("Line {} is not a valid audit entry: {}", line_no + 1, e)),
+            Ok(entry) if entry.seq != expected_seq => {
+                Some(format!("Entry {} is out of sequence (expected {})", entry.seq, expected_seq))
+            }
+            Ok(entry) if entry.prev_hash != prev_hash => {
+                Some(format!("Entry {} does not chain to the previous entry", entry.seq))
+            }
+            Ok(entry) if entry_hash(&entry, &key) != entry.hash => {
+                Some(format!("Entry {} was modified after it was written", entry.seq))
+            }
+            Ok(entry) => {
+                if entry.action == "manifest" {
+                    let folder = entry.details.get("recoveryPath").and_then(|v| v.as_str());
+                    let hash = entry.details.get("manifestHash").and_then(|v| v.as_str());
+                    if let (Some(folder), Some(hash)) = (folder, hash) {
+                        last_manifest = Some((folder.to_string(), hash.to_string()));
+                    }
+                }
+                result.sealed = entry.action == SEAL_ACTION;
+                prev_hash = entry.hash;
+                None
+            }
+        };
+
+        if let Some(error) = failure {
+            result.valid = false;
+            result.first_invalid_seq = Some(expected_seq);
+            result.error = Some(error);
+            return Ok(result);
+        }
+        result.entries += 1;
+    }
+
+    // A log that stops short of its seal lost entries at the end, unless it is still being written
+    if !result.sealed && !is_open_log(path) {
+        result.valid = false;
+        result.first_invalid_seq = Some(result.entries);
+        result.error = Some("The log does not end with a seal entry; entries may have been removed.".to_string());
+        return Ok(result);
+    }
+
+    if let Some((folder, hash)) = last_manifest {
+        result.manifest_matches = Some(manifest_hash(&folder).map(|(current, _)| current == hash).unwrap_or(false));
+        if result.manifest_matches == Some(false) {
+            result.valid = false;
+            result.error = Some("Recovered files no longer match the recorded manifest.".to_string());
+            return Ok(result);
+        }
+    }
+
+    if !result.missed_actions.is_empty() {
+        result.valid = false;
+        result.error = Some(format!(
+            "{} action(s) could not be written to this log: {}.",
+            result.missed_actions.len(),
+            result.missed_actions.join(", ")
+        ));
+    }
+    Ok(result)
+}
+
+// Only the log of a session that is still running may lack its seal
+fn is_open_log(path: &str) -> bool {
+    session::current().is_some_and(|s| s.status == "running" && log_path_for(&s) == Path::new(path))
+}
+
+fn missed_actions(path: &Path) -> Vec<String> {
+    let missed = MISSED.lock().unwrap_or_else(|e| e.into_inner());
+    missed.iter().filter(|(log, _)| log == path).map(|(_, action)| action.clone()).collect()
+}
+
+// ── Session hooks ────────────────────────────────────────────────────
+
+// Opens the session's log; a session whose actions cannot be recorded is not started
+pub fn record_session_start(session: &RecoverySession) -> Result<(), String> {
+    let details = serde_json::json!({
+        "sessionId": session.id,
+        "source": session.config.source,
+        "destination": session.config.destination,
+        "argv": std::iter::once("winfr".to_string()).chain(session.args.iter().cloned()).collect::<Vec<_>>(),
+    });
+    write_entries(&log_path_for(session), vec![("recovery-started".to_string(), details)], false)
+}
+
+// Seals the session with its outcome and a manifest hash of everything winfr wrote
+pub fn record_session_end(session: &RecoverySession) -> Result<(), String> {
+    let entries = vec![
+        (
+            "recovery-finished".to_string(),
+            serde_json::json!({ "status": session.status, "exitCode": session.exit_code }),
+        ),
+        manifest_entry(&session_recovery_path(session)),
+    ];
+    write_entries(&log_path_for(session), entries, true)
+}
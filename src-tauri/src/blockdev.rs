@@ -0,0 +1,363 @@
+use crate::safety::open_source;
+use crate::{ewf, vhd, vhdx};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+// ── Block devices ────────────────────────────────────────────────────
+//
+// Everything the native scanners, the partition parser and imaging read from: a drive or
+// raw image file, the virtual disk inside a VHD or VHDX container, or the disk acquired
+// into an E01 evidence file. Offsets are bytes from the start of the (virtual) disk.
+
+// Raw volume handles only accept sector-aligned reads; 4 KiB covers 512e and 4Kn disks
+const ALIGNMENT: u64 = 4096;
+// Nested differencing disks are followed up to this depth, so a loop cannot recurse forever
+pub(crate) const MAX_PARENT_DEPTH: u32 = 16;
+
+pub trait BlockDevice: Send {
+    // None when the size is unknown
+    fn size(&mut self) -> Option<u64>;
+    fn read_at(&mut self, offset: u64, len: usize) -> Result<Vec<u8>, String>;
+    // Files the disk is read from: the image with its segments or parent disks. Empty for
+    // drives.
+    fn files(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+}
+
+// Opens `path` as a device, looking inside VHD, VHDX and E01 containers and reading the
+// parts of a split raw image as one disk
+pub fn open_device(path: &str) -> Result<Box<dyn BlockDevice>, String> {
+    let file = open_source(Path::new(path))?;
+    if fs::metadata(path).is_ok_and(|m| m.is_file()) {
+        if let Some(disk) = open_container(Path::new(path), &file, 0)? {
+            return Ok(disk);
+        }
+        let parts = split_parts(Path::new(path));
+        if parts.len() > 1 {
+            return Ok(Box::new(SplitImage::open(&parts)?));
+        }
+    }
+    Ok(Box::new(RawDevice { file, name: path.to_string() }))
+}
+
+fn open_container(path: &Path, file: &File, depth: u32) -> Result<Option<Box<dyn BlockDevice>>, String> {
+    if ewf::is_ewf(file) {
+        Ok(Some(Box::new(ewf::EwfImage::open(path)?)))
+    } else if vhdx::is_vhdx(file) {
+        Ok(Some(Box::new(vhdx::VhdxDisk::open(path, depth)?)))
+    } else if vhd::is_vhd(file) {
+        Ok(Some(Box::new(vhd::VhdDisk::open(path, depth)?)))
+    } else {
+        Ok(None)
+    }
+}
+
+// ── Raw drives and image files ───────────────────────────────────────
+
+pub struct RawDevice {
+    file: File,
+    name: String,
+}
+
+impl RawDevice {
+    pub fn open(path: &str) -> Result<RawDevice, String> {
+        let file = open_source(Path::new(path))?;
+        Ok(RawDevice { file, name: path.to_string() })
+    }
+}
+
+impl BlockDevice for RawDevice {
+    fn size(&mut self) -> Option<u64> {
+        // Seeking to the end of a raw disk or volume handle gives no length on Windows
+        self.file
+            .seek(SeekFrom::End(0))
+            .ok()
+            .filter(|size| *size > 0)
+            .or_else(|| device_length(&self.file))
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        let path = PathBuf::from(&self.name);
+        if path.is_file() {
+            vec![path]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn read_at(&mut self, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+        let start = offset - offset % ALIGNMENT;
+        let end = (offset + len as u64).div_ceil(ALIGNMENT) * ALIGNMENT;
+        let mut buffer = vec![0u8; (end - start) as usize];
+
+        self.file
+            .seek(SeekFrom::Start(start))
+            .map_err(|e| format!("Failed to seek to {}: {}", start, e))?;
+        let mut filled = 0;
+        while filled < buffer.len() {
+            match self.file.read(&mut buffer[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(format!("Failed to read at offset {}: {}", start + filled as u64, e)),
+            }
+        }
+
+        let skip = (offset - start) as usize;
+        if filled < skip + len {
+            return Err(format!("Read past the end of {} at offset {}", self.name, offset));
+        }
+        buffer.truncate(skip + len);
+        buffer.drain(..skip);
+        Ok(buffer)
+    }
+}
+
+// ── Split raw images ─────────────────────────────────────────────────
+//
+// Imaging tools split raw images into numbered parts (disk.001, disk.002, ...) that are
+// the disk's bytes end to end.
+
+const MAX_SPLIT_PARTS: u32 = 999;
+
+pub struct SplitImage {
+    parts: Vec<(PathBuf, File, u64, u64)>, // part, its file, and where it starts and ends on the disk
+    size: u64,
+}
+
+// `first` and the parts that follow it, when it is the .001 part of a split image
+fn split_parts(first: &Path) -> Vec<PathBuf> {
+    let mut parts = vec![first.to_path_buf()];
+    if first.extension().is_some_and(|e| e == "001") {
+        for number in 2..=MAX_SPLIT_PARTS {
+            let part = first.with_extension(format!("{:03}", number));
+            if !part.is_file() {
+                break;
+            }
+            parts.push(part);
+        }
+    }
+    parts
+}
+
+impl SplitImage {
+    pub fn open(parts: &[PathBuf]) -> Result<SplitImage, String> {
+        let mut opened = Vec::with_capacity(parts.len());
+        let mut size = 0;
+        for part in parts {
+            let file = open_source(part)?;
+            let len = file_len(&file);
+            opened.push((part.clone(), file, size, size + len));
+            size += len;
+        }
+        Ok(SplitImage { parts: opened, size })
+    }
+}
+
+impl BlockDevice for SplitImage {
+    fn size(&mut self) -> Option<u64> {
+        Some(self.size).filter(|size| *size > 0)
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        self.parts.iter().map(|(path, ..)| path.clone()).collect()
+    }
+
+    fn read_at(&mut self, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+        let end = offset + len as u64;
+        if end > self.size {
+            return Err(format!("Read past the end of the split image at offset {}", offset));
+        }
+        let mut buffer = Vec::with_capacity(len);
+        let mut at = offset;
+        // A read that crosses from one part into the next is stitched together
+        for (_, file, start, part_end) in self.parts.iter_mut() {
+            if at >= end {
+                break;
+            }
+            if at >= *part_end {
+                continue;
+            }
+            let take = (*part_end).min(end) - at;
+            buffer.extend(read_file_at(file, at - *start, take as usize)?);
+            at += take;
+        }
+        Ok(buffer)
+    }
+}
+
+// ── Helpers for container formats ────────────────────────────────────
+
+// Reads exactly `len` bytes at `offset` of a container file
+pub(crate) fn read_file_at(file: &mut File, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+    let mut buffer = vec![0u8; len];
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.read_exact(&mut buffer))
+        .map_err(|e| format!("Failed to read at offset {}: {}", offset, e))?;
+    Ok(buffer)
+}
+
+pub(crate) fn file_len(file: &File) -> u64 {
+    file.metadata().map(|m| m.len()).unwrap_or(0)
+}
+
+// Parent disks of differencing images are referenced by a path that is usually relative
+// to the child and written with Windows separators
+pub(crate) fn resolve_parent(child: &Path, reference: &str) -> Option<PathBuf> {
+    let reference = reference.trim_end_matches('\0');
+    if reference.is_empty() {
+        return None;
+    }
+    let is_absolute = reference.starts_with("\\\\") || reference.chars().nth(1) == Some(':');
+    let mut path = if is_absolute { PathBuf::new() } else { child.parent()?.to_path_buf() };
+    if is_absolute {
+        path.push(reference);
+    } else {
+        for part in reference.split(['\\', '/']).filter(|p| !p.is_empty() && *p != ".") {
+            path.push(part);
+        }
+    }
+    path.is_file().then_some(path)
+}
+
+// Sectors a differencing disk does not hold come from its parent; other disks read as zeros
+pub(crate) fn read_parent_or_zero(
+    parent: &mut Option<Box<dyn BlockDevice>>,
+    offset: u64,
+    len: usize,
+) -> Result<Vec<u8>, String> {
+    match parent {
+        Some(parent) => parent.read_at(offset, len),
+        None => Ok(vec![0u8; len]),
+    }
+}
+
+// Splits [start, end) into (start, end, present) runs of whole sectors, given whether the
+// sector at an offset is present in the container's sector bitmap
+pub(crate) fn sector_runs(start: u64, end: u64, sector: u64, present: impl Fn(u64) -> bool) -> Vec<(u64, u64, bool)> {
+    let mut runs: Vec<(u64, u64, bool)> = Vec::new();
+    let mut at = start;
+    while at < end {
+        let next = ((at / sector + 1) * sector).min(end);
+        let here = present(at);
+        match runs.last_mut() {
+            Some(run) if run.2 == here => run.1 = next,
+            _ => runs.push((at, next, here)),
+        }
+        at = next;
+    }
+    runs
+}
+
+// ── Windows device queries ───────────────────────────────────────────
+
+#[cfg(windows)]
+extern "system" {
+    fn DeviceIoControl(
+        device: *mut std::ffi::c_void,
+        code: u32,
+        input: *const std::ffi::c_void,
+        input_len: u32,
+        output: *mut std::ffi::c_void,
+        output_len: u32,
+        returned: *mut u32,
+        overlapped: *mut std::ffi::c_void,
+    ) -> i32;
+    fn GetVolumePathNameW(file_name: *const u16, volume_path_name: *mut u16, buffer_length: u32) -> i32;
+    fn GetVolumeNameForVolumeMountPointW(mount_point: *const u16, volume_name: *mut u16, buffer_length: u32) -> i32;
+}
+
+// Sends an IOCTL without input to an open device; the bytes written to `output`, or None
+// when the device refused it
+#[cfg(windows)]
+pub(crate) fn device_io_control(device: &File, code: u32, output: &mut [u8]) -> Option<usize> {
+    use std::os::windows::io::AsRawHandle;
+    let mut returned = 0u32;
+    let ok = unsafe {
+        DeviceIoControl(
+            device.as_raw_handle(),
+            code,
+            std::ptr::null(),
+            0,
+            output.as_mut_ptr().cast(),
+            output.len() as u32,
+            &mut returned,
+            std::ptr::null_mut(),
+        )
+    };
+    (ok != 0).then_some(returned as usize)
+}
+
+// Length of a disk or volume opened as a device (\\.\PhysicalDrive1, \\.\E:)
+#[cfg(windows)]
+fn device_length(device: &File) -> Option<u64> {
+    const IOCTL_DISK_GET_LENGTH_INFO: u32 = 0x0007_405C;
+    let mut output = [0u8; 8];
+    let len = device_io_control(device, IOCTL_DISK_GET_LENGTH_INFO, &mut output)?;
+    (len == output.len()).then(|| u64::from_le_bytes(output)).filter(|size| *size > 0)
+}
+
+#[cfg(not(windows))]
+fn device_length(_device: &File) -> Option<u64> {
+    None
+}
+
+// Volume GUID path ("\\?\Volume{...}\") of the volume `path` is on. Windows finds the
+// mount point first, so a volume mounted in a folder is told apart from the drive holding
+// the folder. None when the path is on no local volume.
+#[cfg(windows)]
+pub(crate) fn path_volume(path: &Path) -> Option<String> {
+    use std::os::windows::ffi::OsStrExt;
+    // The mount point of a path that does not exist yet is that of its nearest existing folder
+    let existing = path.ancestors().find(|p| p.exists())?;
+    let name: Vec<u16> = existing.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut mount_point = [0u16; 1024];
+    if unsafe { GetVolumePathNameW(name.as_ptr(), mount_point.as_mut_ptr(), mount_point.len() as u32) } == 0 {
+        return None;
+    }
+    let mut volume = [0u16; 64];
+    if unsafe { GetVolumeNameForVolumeMountPointW(mount_point.as_ptr(), volume.as_mut_ptr(), volume.len() as u32) }
+        == 0
+    {
+        return None;
+    }
+    let len = volume.iter().position(|c| *c == 0)?;
+    Some(String::from_utf16_lossy(&volume[..len]))
+}
+
+#[cfg(not(windows))]
+pub(crate) fn path_volume(_path: &Path) -> Option<String> {
+    None
+}
+
+// Numbers of the physical disks a volume (as named by `path_volume`) lies on; a spanned
+// or mirrored volume can cover several. Empty when Windows cannot tell.
+#[cfg(windows)]
+pub(crate) fn volume_disks(volume: &str) -> Vec<u32> {
+    use std::os::windows::fs::OpenOptionsExt;
+    const IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS: u32 = 0x0056_0000;
+    // No read access is needed to ask for the layout, so this works without elevation.
+    // Without its trailing backslash the GUID path opens the volume device.
+    let Ok(volume) = fs::OpenOptions::new().access_mode(0).open(volume.trim_end_matches('\\')) else {
+        return Vec::new();
+    };
+    // VOLUME_DISK_EXTENTS: a count padded to 8 bytes, then 24-byte DISK_EXTENTs that start
+    // with the disk number
+    let mut output = [0u8; 8 + 24 * 32];
+    let Some(len) = device_io_control(&volume, IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS, &mut output) else {
+        return Vec::new();
+    };
+    let count = crate::volume::u32_at(&output, 0) as usize;
+    (0..count)
+        .map(|i| 8 + 24 * i)
+        .filter(|at| at + 4 <= len)
+        .map(|at| crate::volume::u32_at(&output, at))
+        .collect()
+}
+
+#[cfg(not(windows))]
+pub(crate) fn volume_disks(_volume: &str) -> Vec<u32> {
+    Vec::new()
+}
@@ -0,0 +1,367 @@
+// Headless entry point for scripted and remote recoveries.
+// Shares the recovery engine with the GUI but never creates a webview.
+
+use serde::Serialize;
+use std::process::ExitCode;
+use std::sync::{mpsc, Arc};
+use tauri_app_lib::events::{ChannelSink, LogSource, RecoveryEvent};
+use tauri_app_lib::recovery::RecoveryConfig;
+use tauri_app_lib::{drives, preflight, recovery, report, session};
+
+// ── Exit codes ───────────────────────────────────────────────────────
+
+const EXIT_OK: u8 = 0;
+const EXIT_FAILED: u8 = 1; // command failed (I/O, missing session, ...)
+const EXIT_USAGE: u8 = 2;
+const EXIT_PREFLIGHT: u8 = 3; // preflight checks failed, nothing was run
+const EXIT_WINFR_ERROR: u8 = 4; // winfr exited with a non-zero code
+const EXIT_WINFR_CRASH: u8 = 5; // winfr crashed (0xC0000005)
+
+const USAGE: &str = "\
+Winfr Pro command-line interface
+
+USAGE:
+    winfr-pro-cli <COMMAND> [ARGS] [--json]
+
+COMMANDS:
+    list-drives                          List mounted drives
+    health <DRIVE>                       Run a read-only chkdsk on DRIVE
+    preflight <SOURCE> <DEST> [OPTIONS]  Validate a recovery without running it
+    recover <SOURCE> <DEST> [OPTIONS]    Run winfr and stream its output
+    scan-results <DEST>                  List files in the latest recovery folder
+    report <DEST> --format <html|csv|json> --output <FILE>
+                                         Write a report for the last session in DEST
+
+RECOVERY OPTIONS:
+    --mode <regular|extensive>   Scan mode (default: regular)
+    --segment                    Segment mode (/r)
+    --signature                  Signature mode (/x)
+    --filter <FILTER>            Category (Images, Documents, Videos, Audio, Archives)
+                                 or winfr filter such as *.docx; repeatable
+    --non-deleted                Also recover non-deleted files (/u)
+    --keep-both                  Keep both files on name conflicts (/o:b)
+    --system-files               Recover system files (/k)
+    --keep-extensions            Keep files with any extension (/e)
+    --verbose                    Verbose winfr output (/v)
+    --fs <NAME>                  Source filesystem (NTFS, FAT32, exFAT, ...)
+    --no-auto-accept             Do not pass /a (winfr will prompt)
+    --winfr <PATH>               winfr executable to run (default: winfr on PATH)
+
+EXIT CODES:
+    0 success, 1 failure, 2 usage error, 3 preflight failed,
+    4 winfr returned an error, 5 winfr crashed
+";
+
+// ── Argument parsing ─────────────────────────────────────────────────
+
+struct Args {
+    positional: Vec<String>,
+    json: bool,
+    config: RecoveryConfig,
+    format: Option<String>,
+    output: Option<String>,
+}
+
+fn parse_args(raw: &[String]) -> Result<Args, String> {
+    let mut args = Args {
+        positional: Vec::new(),
+        json: false,
+        config: RecoveryConfig {
+            source: String::new(),
+            destination: String::new(),
+            mode: "regular".to_string(),
+            filters: Vec::new(),
+            segment_mode: false,
+            signature_mode: false,
+            recover_non_deleted: false,
+            keep_both: false,
+            auto_accept: true,
+            recover_system_files: false,
+            keep_all_extensions: false,
+            source_fs: None,
+            verbose_mode: false,
+            winfr_path: None,
+        },
+        format: None,
+        output: None,
+    };
+
+    let mut iter = raw.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("{} requires a value", name))
+        };
+        match arg.as_str() {
+            "--json" => args.json = true,
+            "--mode" => {
+                let mode = value("--mode")?.to_lowercase();
+                if mode != "regular" && mode != "extensive" {
+                    return Err(format!("Unknown mode '{}'", mode));
+                }
+                args.config.mode = mode;
+            }
+            "--segment" => args.config.segment_mode = true,
+            "--signature" => args.config.signature_mode = true,
+            "--filter" => args.config.filters.push(value("--filter")?),
+            "--non-deleted" => args.config.recover_non_deleted = true,
+            "--keep-both" => args.config.keep_both = true,
+            "--system-files" => args.config.recover_system_files = true,
+            "--keep-extensions" => args.config.keep_all_extensions = true,
+            "--verbose" => args.config.verbose_mode = true,
+            "--fs" => args.config.source_fs = Some(value("--fs")?),
+            "--no-auto-accept" => args.config.auto_accept = false,
+            "--winfr" => args.config.winfr_path = Some(value("--winfr")?),
+            "--format" => args.format = Some(value("--format")?),
+            "--output" => args.output = Some(value("--output")?),
+            other if other.starts_with("--") => return Err(format!("Unknown option '{}'", other)),
+            other => args.positional.push(other.to_string()),
+        }
+    }
+
+    Ok(args)
+}
+
+fn recovery_config(args: &Args) -> Result<RecoveryConfig, String> {
+    let (source, destination) = match args.positional.as_slice() {
+        [source, destination] => (source, destination),
+        _ => return Err("Expected <SOURCE> <DEST>".to_string()),
+    };
+    let mut config = args.config.clone();
+    config.source = source.clone();
+    config.destination = destination.clone();
+    Ok(config)
+}
+
+// ── Output ───────────────────────────────────────────────────────────
+
+fn print_json<T: Serialize>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("error: {}", e),
+    }
+}
+
+fn print_json_line<T: Serialize>(value: &T) {
+    if let Ok(json) = serde_json::to_string(value) {
+        println!("{}", json);
+    }
+}
+
+// ── Commands ─────────────────────────────────────────────────────────
+
+fn cmd_list_drives(args: &Args) -> u8 {
+    let drives = drives::get_drives();
+    if args.json {
+        print_json(&drives);
+    } else {
+        for d in &drives {
+            println!(
+                "{:<4} {:<20} {:<8} {:<6} {:>10} used of {:<10}{}",
+                d.id,
+                d.label,
+                d.drive_type,
+                d.fs,
+                d.used,
+                d.size,
+                if d.is_system { " (system)" } else { "" }
+            );
+        }
+    }
+    EXIT_OK
+}
+
+fn cmd_health(args: &Args) -> u8 {
+    let [drive] = args.positional.as_slice() else {
+        eprintln!("error: health expects <DRIVE>");
+        return EXIT_USAGE;
+    };
+    match recovery::get_disk_health(drive.clone()) {
+        Ok(output) => {
+            if args.json {
+                print_json(&serde_json::json!({ "drive": drive, "ok": true, "output": output }));
+            } else {
+                print!("{}", output);
+            }
+            EXIT_OK
+        }
+        Err(e) => {
+            if args.json {
+                print_json(&serde_json::json!({ "drive": drive, "ok": false, "output": e }));
+            } else {
+                eprintln!("error: {}", e);
+            }
+            EXIT_FAILED
+        }
+    }
+}
+
+fn print_preflight(report: &preflight::PreflightReport, json: bool) {
+    if json {
+        print_json(report);
+        return;
+    }
+    for check in &report.checks {
+        let mark = if !check.ok { "FAIL" } else if check.warning { "WARN" } else { " OK " };
+        println!("[{}] {:<12} {}", mark, check.name, check.message);
+    }
+    println!("Command: winfr {}", report.args.join(" "));
+}
+
+fn cmd_preflight(args: &Args) -> u8 {
+    let config = match recovery_config(args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return EXIT_USAGE;
+        }
+    };
+    let report = preflight::run_preflight(&config);
+    print_preflight(&report, args.json);
+    if report.ok { EXIT_OK } else { EXIT_PREFLIGHT }
+}
+
+fn cmd_recover(args: &Args) -> u8 {
+    let config = match recovery_config(args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return EXIT_USAGE;
+        }
+    };
+
+    let checks = preflight::run_preflight(&config);
+    if !checks.ok {
+        print_preflight(&checks, args.json);
+        return EXIT_PREFLIGHT;
+    }
+    if !args.json {
+        println!("Command: {} {}", recovery::winfr_program(&config), checks.args.join(" "));
+    }
+
+    let (tx, rx) = mpsc::channel();
+    if let Err(e) = recovery::start_recovery_with(Arc::new(ChannelSink::new(tx)), config) {
+        if args.json {
+            print_json_line(&serde_json::json!({ "type": "error", "message": e }));
+        } else {
+            eprintln!("error: {}", e);
+        }
+        return EXIT_FAILED;
+    }
+
+    // Stream events until the engine reports that winfr has exited
+    let mut exit_code = None;
+    for event in rx {
+        if args.json {
+            print_json_line(&event);
+        } else {
+            match &event {
+                RecoveryEvent::Log { source: LogSource::Stderr, message } => eprintln!("{}", message),
+                RecoveryEvent::Log { message, .. } => println!("{}", message),
+                RecoveryEvent::Warning { message } => eprintln!("warning: {}", message),
+                RecoveryEvent::Progress { line, .. } => println!("{}", line),
+                _ => {}
+            }
+        }
+        if let RecoveryEvent::Exited { code } = event {
+            exit_code = Some(code);
+            break;
+        }
+    }
+
+    if args.json {
+        print_json_line(&serde_json::json!({ "type": "session", "session": session::current() }));
+    }
+    match exit_code.flatten() {
+        Some(0) => EXIT_OK,
+        Some(-1073741819) => EXIT_WINFR_CRASH, // 0xC0000005 Access Violation
+        Some(_) => EXIT_WINFR_ERROR,
+        None => EXIT_FAILED, // cancelled or could not be waited on
+    }
+}
+
+fn cmd_scan_results(args: &Args) -> u8 {
+    let [destination] = args.positional.as_slice() else {
+        eprintln!("error: scan-results expects <DEST>");
+        return EXIT_USAGE;
+    };
+    match recovery::scan_recovered_files(destination, None) {
+        Ok(files) => {
+            if args.json {
+                print_json(&files);
+            } else {
+                for f in &files {
+                    println!("{:<10} {:>12}  {}", f.category, f.size, f.path);
+                }
+                println!("{} files", files.len());
+            }
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            EXIT_FAILED
+        }
+    }
+}
+
+fn cmd_report(args: &Args) -> u8 {
+    let ([destination], Some(format), Some(output)) = (args.positional.as_slice(), &args.format, &args.output) else {
+        eprintln!("error: report expects <DEST> --format <html|csv|json> --output <FILE>");
+        return EXIT_USAGE;
+    };
+    let Some(session) = session::load(destination) else {
+        eprintln!("error: no recovery session found in {}", destination);
+        return EXIT_FAILED;
+    };
+    match report::export_report(&session, format, output) {
+        Ok(path) => {
+            if args.json {
+                print_json(&serde_json::json!({ "output": path }));
+            } else {
+                println!("Report written to {}", path);
+            }
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            EXIT_FAILED
+        }
+    }
+}
+
+// ── Main ─────────────────────────────────────────────────────────────
+
+fn main() -> ExitCode {
+    let raw: Vec<String> = std::env::args().skip(1).collect();
+    let Some((command, rest)) = raw.split_first() else {
+        eprint!("{}", USAGE);
+        return ExitCode::from(EXIT_USAGE);
+    };
+    if command == "--help" || command == "-h" || command == "help" {
+        print!("{}", USAGE);
+        return ExitCode::from(EXIT_OK);
+    }
+
+    let args = match parse_args(rest) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    let code = match command.as_str() {
+        "list-drives" => cmd_list_drives(&args),
+        "health" => cmd_health(&args),
+        "preflight" => cmd_preflight(&args),
+        "recover" => cmd_recover(&args),
+        "scan-results" => cmd_scan_results(&args),
+        "report" => cmd_report(&args),
+        other => {
+            eprintln!("error: unknown command '{}'\n\n{}", other, USAGE);
+            EXIT_USAGE
+        }
+    };
+    ExitCode::from(code)
+}
@@ -0,0 +1,122 @@
+use crate::drives::{self, DriveInfo};
+use crate::index;
+use crate::recovery::RecoveredFile;
+use crate::report::session_recovery_path;
+use crate::session::RecoverySession;
+use crate::timestamps::{format_iso8601, now_secs};
+use std::fs;
+use sysinfo::System;
+
+// ── DFXML export (Digital Forensics XML, schema 1.2) ────────────────
+
+const DFXML_VERSION: &str = "1.2.0";
+
+pub fn export_dfxml(session: &RecoverySession, output: &str) -> Result<String, String> {
+    let recovery_path = session_recovery_path(session);
+    index::refresh_index(&recovery_path, |_| {})?;
+    // Other tools match on digests, so every fileobject carries them
+    index::ensure_hashes(&recovery_path)?;
+    let files = index::all_files(&recovery_path)?;
+
+    let source_id = session.config.source.trim_end_matches('\\').to_uppercase();
+    let drive = drives::get_drives().into_iter().find(|d| d.id == source_id);
+
+    let xml = render_dfxml(session, drive.as_ref(), &files);
+    fs::write(output, xml).map_err(|e| format!("Failed to write DFXML: {}", e))?;
+    Ok(output.to_string())
+}
+
+pub fn render_dfxml(session: &RecoverySession, drive: Option<&DriveInfo>, files: &[RecoveredFile]) -> String {
+    let mut x = String::new();
+    x.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    x.push_str(&format!(
+        "<dfxml xmlns=\"http://www.forensicswiki.org/wiki/Category:Digital_Forensics_XML\" \
+xmlns:dc=\"http://purl.org/dc/elements/1.1/\" version=\"{}\">\n",
+        DFXML_VERSION
+    ));
+
+    x.push_str("  <metadata>\n    <dc:type>Recovered Files</dc:type>\n  </metadata>\n");
+
+    // Creator and execution environment, including the exact winfr argv
+    x.push_str("  <creator version=\"1.0\">\n");
+    x.push_str("    <program>Winfr Pro</program>\n");
+    x.push_str(&format!("    <version>{}</version>\n", env!("CARGO_PKG_VERSION")));
+    x.push_str("    <build_environment>\n      <compiler>rustc</compiler>\n    </build_environment>\n");
+    x.push_str("    <execution_environment>\n");
+    x.push_str(&format!("      <os_sysname>{}</os_sysname>\n", esc(std::env::consts::OS)));
+    x.push_str(&format!(
+        "      <os_version>{}</os_version>\n",
+        esc(&System::long_os_version().unwrap_or_default())
+    ));
+    x.push_str(&format!("      <host>{}</host>\n", esc(&System::host_name().unwrap_or_default())));
+    x.push_str(&format!("      <arch>{}</arch>\n", esc(std::env::consts::ARCH)));
+    x.push_str(&format!(
+        "      <command_line>{}</command_line>\n",
+        esc(&format!("winfr {}", session.args.join(" ")))
+    ));
+    x.push_str("      <argv>\n        <arg>winfr</arg>\n");
+    for arg in &session.args {
+        x.push_str(&format!("        <arg>{}</arg>\n", esc(arg)));
+    }
+    x.push_str("      </argv>\n");
+    x.push_str(&format!("      <start_time>{}</start_time>\n", format_iso8601(session.started_at)));
+    if let Some(end) = session.finished_at {
+        x.push_str(&format!("      <end_time>{}</end_time>\n", format_iso8601(end)));
+    }
+    if let Some(code) = session.exit_code {
+        x.push_str(&format!("      <exit_code>{}</exit_code>\n", code));
+    }
+    x.push_str("    </execution_environment>\n");
+    x.push_str(&format!("    <report_time>{}</report_time>\n", format_iso8601(now_secs())));
+    x.push_str("  </creator>\n");
+
+    x.push_str("  <source>\n");
+    x.push_str(&format!("    <image_filename>{}</image_filename>\n", esc(&session.config.source)));
+    x.push_str("  </source>\n");
+
+    x.push_str("  <volume>\n");
+    let ftype = drive
+        .map(|d| d.fs.clone())
+        .or_else(|| session.config.source_fs.clone())
+        .unwrap_or_default();
+    x.push_str(&format!("    <ftype_str>{}</ftype_str>\n", esc(&ftype)));
+    if let Some(d) = drive {
+        x.push_str(&format!("    <volume_label>{}</volume_label>\n", esc(&d.label)));
+        x.push_str(&format!("    <volume_size>{}</volume_size>\n", d.size_bytes));
+    }
+
+    // winfr recreates the original path under the recovery folder, except in signature mode,
+    // which names the files it carves itself. The copies' own timestamps say nothing about
+    // the originals, so no times are written.
+    let keeps_paths = !session.config.signature_mode;
+    for file in files {
+        x.push_str("    <fileobject>\n");
+        if keeps_paths {
+            let original = format!("{}{}", session.config.source.trim_end_matches('\\'), file.path);
+            x.push_str(&format!("      <filename>{}</filename>\n", esc(&original)));
+        }
+        x.push_str(&format!("      <filesize>{}</filesize>\n", file.size));
+        if let Some(h) = &file.hashes {
+            x.push_str(&format!("      <hashdigest type=\"md5\">{}</hashdigest>\n", h.md5));
+            x.push_str(&format!("      <hashdigest type=\"sha1\">{}</hashdigest>\n", h.sha1));
+            x.push_str(&format!("      <hashdigest type=\"sha256\">{}</hashdigest>\n", h.sha256));
+        }
+        x.push_str("    </fileobject>\n");
+    }
+
+    x.push_str("  </volume>\n");
+    x.push_str("</dfxml>\n");
+    x
+}
+
+fn esc(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .collect::<String>()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
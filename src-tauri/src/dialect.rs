@@ -0,0 +1,185 @@
+use crate::engine::{EngineInfo, EngineSyntax};
+use crate::filters::{compile, FilterRule};
+use crate::recovery::RecoveryConfig;
+use crate::signatures::{builtin_groups, select_groups, SignatureGroup};
+use serde::Serialize;
+
+// ── winfr argument dialects ──────────────────────────────────────────
+//
+// Legacy (0.1.13xxx):  default mode (no switch), /r segment, /x signature.
+//                      /u /k /e apply to default and segment mode.
+// Modern (0.1.20151+): /regular, /extensive, and /segment, /signature as advanced modes.
+//                      /u /k /e apply to regular and extensive mode only.
+// Both: /n filters outside signature mode, /y: groups in signature mode only.
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WinfrCommand {
+    pub args: Vec<String>,
+    // Options that were dropped because the dialect or mode does not support them
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanMode {
+    Regular,
+    Extensive,
+    Segment,
+    Signature,
+}
+
+pub fn render(config: &RecoveryConfig, syntax: EngineSyntax) -> WinfrCommand {
+    render_with_groups(config, syntax, &builtin_groups())
+}
+
+// Uses the syntax and signature groups detected for the installed winfr
+pub fn render_for_engine(config: &RecoveryConfig, engine: &EngineInfo) -> WinfrCommand {
+    if engine.signature_groups.is_empty() {
+        render(config, engine.syntax)
+    } else {
+        render_with_groups(config, engine.syntax, &engine.signature_groups)
+    }
+}
+
+// `groups` are the signature groups of the installed winfr (see `winfr /#`)
+pub fn render_with_groups(config: &RecoveryConfig, syntax: EngineSyntax, groups: &[SignatureGroup]) -> WinfrCommand {
+    let mut args: Vec<String> = Vec::new();
+    let mut warnings: Vec<String> = Vec::new();
+
+    args.push(config.source.clone());
+    let mut dest = config.destination.clone();
+    if !dest.ends_with('\\') && !dest.ends_with('/') {
+        dest.push('\\');
+    }
+    args.push(dest);
+
+    // Modes are mutually exclusive in winfr
+    let mode = scan_mode(config);
+    if let Some(switch) = mode_switch(mode, syntax) {
+        args.push(switch.to_string());
+    }
+
+    if config.auto_accept {
+        args.push("/a".to_string());
+    }
+
+    if config.keep_both {
+        // Safe Mode Mitigation: /o:b is known to crash winfr.exe on exFAT drives.
+        // Also strictly incompatible with Signature Mode.
+        let is_exfat = config.source_fs.as_ref().is_some_and(|fs| fs.eq_ignore_ascii_case("exfat"));
+        if mode == ScanMode::Signature {
+            warnings.push("'Keep both' (/o:b) is not supported in signature mode and was dropped.".to_string());
+        } else if is_exfat {
+            warnings.push("'Keep both' (/o:b) is dropped for exFAT sources.".to_string());
+        } else {
+            args.push("/o:b".to_string());
+        }
+    }
+
+    for (enabled, switch, label) in [
+        (config.recover_non_deleted, "/u", "Recover non-deleted files"),
+        (config.recover_system_files, "/k", "Recover system files"),
+        (config.keep_all_extensions, "/e", "Keep all extensions"),
+    ] {
+        if !enabled {
+            continue;
+        }
+        if supports_file_record_switches(mode, syntax) {
+            args.push(switch.to_string());
+        } else {
+            warnings.push(format!("'{}' ({}) is not supported in {} mode and was dropped.", label, switch, mode_name(mode)));
+        }
+    }
+
+    if config.verbose_mode && mode != ScanMode::Signature {
+        // Verbose mode (/v) is documented as incompatible with signature mode in some versions.
+        args.push("/v".to_string());
+    }
+
+    let compiled = compile(&config.filters);
+    if mode == ScanMode::Signature {
+        // Signature mode uses /y: for file type groups, NOT /n
+        let mut includes: Vec<String> = Vec::new();
+        for filter in &compiled.filters {
+            match (&filter.rule, filter.exclude) {
+                (Some(_), true) => warnings.push(format!(
+                    "Exclusions are not supported in signature mode; '{}' was ignored.",
+                    filter.input.trim()
+                )),
+                (Some(FilterRule::Category { name }), false) => includes.push(name.clone()),
+                (Some(FilterRule::Extensions { extensions }), false) => {
+                    includes.extend(extensions.iter().map(|e| format!("*.{}", e)))
+                }
+                (Some(FilterRule::Path { path }), false) => includes.push(path.clone()),
+                (Some(FilterRule::Name { pattern }), false) => includes.push(pattern.clone()),
+                (None, _) => {} // reported by filter validation
+            }
+        }
+        let selection = select_groups(&includes, groups);
+        if !selection.groups.is_empty() {
+            args.push(format!("/y:{}", selection.groups.join(",")));
+        }
+        warnings.extend(selection.warnings);
+    } else {
+        for pattern in compiled.patterns {
+            args.push("/n".to_string());
+            args.push(pattern);
+        }
+    }
+
+    WinfrCommand { args, warnings }
+}
+
+fn scan_mode(config: &RecoveryConfig) -> ScanMode {
+    if config.signature_mode {
+        ScanMode::Signature
+    } else if config.segment_mode {
+        ScanMode::Segment
+    } else if config.mode == "extensive" {
+        ScanMode::Extensive
+    } else {
+        ScanMode::Regular
+    }
+}
+
+fn mode_switch(mode: ScanMode, syntax: EngineSyntax) -> Option<&'static str> {
+    match (syntax, mode) {
+        (EngineSyntax::Modern, ScanMode::Regular) => Some("/regular"),
+        (EngineSyntax::Modern, ScanMode::Extensive) => Some("/extensive"),
+        (EngineSyntax::Modern, ScanMode::Segment) => Some("/segment"),
+        (EngineSyntax::Modern, ScanMode::Signature) => Some("/signature"),
+        // Legacy default mode has no switch; segment is its closest match for an extensive scan
+        (EngineSyntax::Legacy, ScanMode::Regular) => None,
+        (EngineSyntax::Legacy, ScanMode::Extensive | ScanMode::Segment) => Some("/r"),
+        (EngineSyntax::Legacy, ScanMode::Signature) => Some("/x"),
+    }
+}
+
+fn mode_name(mode: ScanMode) -> &'static str {
+    match mode {
+        ScanMode::Regular => "regular",
+        ScanMode::Extensive => "extensive",
+        ScanMode::Segment => "segment",
+        ScanMode::Signature => "signature",
+    }
+}
+
+// /u, /k and /e work on file records, which signature mode never reads
+fn supports_file_record_switches(mode: ScanMode, syntax: EngineSyntax) -> bool {
+    match syntax {
+        EngineSyntax::Modern => matches!(mode, ScanMode::Regular | ScanMode::Extensive),
+        EngineSyntax::Legacy => mode != ScanMode::Signature,
+    }
+}
+
+// ── Filters ──────────────────────────────────────────────────────────
+
+pub(crate) fn category_extensions(category: &str) -> Option<&'static [&'static str]> {
+    match category {
+        "Images" => Some(&["*.jpg", "*.jpeg", "*.png", "*.gif", "*.bmp", "*.webp", "*.heic", "*.raw"]),
+        "Documents" => Some(&["*.pdf", "*.doc", "*.docx", "*.xls", "*.xlsx", "*.ppt", "*.pptx", "*.txt", "*.rtf", "*.odt", "*.csv"]),
+        "Videos" => Some(&["*.mp4", "*.avi", "*.mkv", "*.mov", "*.wmv", "*.flv", "*.webm", "*.m4v"]),
+        "Audio" => Some(&["*.mp3", "*.wav", "*.flac", "*.aac", "*.ogg", "*.wma", "*.m4a"]),
+        "Archives" => Some(&["*.zip", "*.rar", "*.7z", "*.tar", "*.gz", "*.bz2", "*.iso"]),
+        _ => None,
+    }
+}
@@ -9,6 +9,8 @@ pub struct DriveInfo {
     pub drive_type: String,   // "SSD", "HDD", "Removable", "Network"
     pub fs: String,           // "NTFS", "exFAT", "FAT32", etc.
     pub size: String,         // "476 GB"
+    #[serde(rename = "sizeBytes")]
+    pub size_bytes: u64,
     pub used: String,         // "234 GB"
     pub percent: u8,          // 0-100
     #[serde(rename = "isSystem")]
@@ -76,6 +78,7 @@ pub fn get_drives() -> Vec<DriveInfo> {
             drive_type,
             fs,
             size: format_bytes(total),
+            size_bytes: total,
             used: format_bytes(used),
             percent,
             is_system,
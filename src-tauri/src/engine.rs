@@ -0,0 +1,214 @@
+use crate::preflight::find_in_path;
+use crate::recovery::{hidden_command, read_utf16_lines};
+use crate::signatures::{parse_groups, SignatureGroup};
+use regex::Regex;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// ── Types ────────────────────────────────────────────────────────────
+
+// First release with the /regular and /extensive modes (Windows File Recovery, winter 2021)
+const MODERN_SINCE: [u64; 3] = [0, 1, 20151];
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EngineSource {
+    Configured,  // RecoveryConfig.winfr_path
+    Path,        // winfr.exe on PATH
+    WindowsApps, // Microsoft Store execution alias
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EngineSyntax {
+    Modern, // /regular and /extensive, deep scans behind /segment and /signature
+    Legacy, // default mode, /r segment and /x signature
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EngineInfo {
+    pub found: bool,
+    pub path: Option<String>,
+    pub source: Option<EngineSource>,
+    pub version: Option<String>,
+    pub syntax: EngineSyntax,
+    pub switches: Vec<String>, // as listed by `winfr /?` and `winfr /!`, e.g. "/n", "/y:"
+    #[serde(rename = "signatureGroups")]
+    pub signature_groups: Vec<SignatureGroup>, // from `winfr /#`; empty if it could not be read
+    pub error: Option<String>,
+}
+
+// Last probe result, keyed by the configured path it was made for
+static ENGINE: Mutex<Option<(Option<String>, EngineInfo)>> = Mutex::new(None);
+
+// ── Discovery ────────────────────────────────────────────────────────
+
+pub fn locate(configured: Option<&str>) -> Option<(PathBuf, EngineSource)> {
+    // An explicit path never falls back to another winfr
+    if let Some(custom) = configured.filter(|p| !p.trim().is_empty()) {
+        return validate_configured(custom).ok().map(|path| (path, EngineSource::Configured));
+    }
+
+    if let Some(path) = find_in_path("winfr.exe") {
+        return Some((path, EngineSource::Path));
+    }
+
+    // Store apps are started through an alias that elevated shells often lack on PATH.
+    // The alias is a reparse point, so only check that the entry exists.
+    let local = std::env::var_os("LOCALAPPDATA")?;
+    let alias = PathBuf::from(local).join("Microsoft").join("WindowsApps").join("winfr.exe");
+    std::fs::symlink_metadata(&alias).ok().map(|_| (alias, EngineSource::WindowsApps))
+}
+
+// A configured engine is run with the app's rights, so it must be an existing winfr.exe
+// given by its full path rather than any program the settings point at
+pub fn validate_configured(custom: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(custom);
+    if !path.is_absolute() {
+        return Err(format!("Configured winfr executable {} must be a full path.", custom));
+    }
+    if !path.file_name().is_some_and(|n| n.eq_ignore_ascii_case("winfr.exe")) {
+        return Err(format!("Configured winfr executable {} is not winfr.exe.", custom));
+    }
+    if !path.is_file() {
+        return Err(format!("Configured winfr executable {} does not exist.", custom));
+    }
+    Ok(path)
+}
+
+// Cached engine info for `configured`, probing winfr the first time
+pub fn detect(configured: Option<&str>) -> EngineInfo {
+    let key = configured.map(|p| p.to_string());
+    if let Some((cached_key, info)) = ENGINE.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        if *cached_key == key {
+            return info.clone();
+        }
+    }
+    probe(configured)
+}
+
+// Locates winfr and runs it to read its version and supported switches
+pub fn probe(configured: Option<&str>) -> EngineInfo {
+    let info = match locate(configured) {
+        Some((path, source)) => {
+            let mut lines = Vec::new();
+            let mut error = None;
+            for switch in ["/?", "/!"] {
+                match run_help(&path, switch) {
+                    Ok(output) => lines.extend(output),
+                    Err(e) => error = Some(e),
+                }
+            }
+            let (version, switches, syntax) = parse_help(&lines);
+            let signature_groups = run_help(&path, "/#").map(|out| parse_groups(&out)).unwrap_or_default();
+            EngineInfo {
+                found: true,
+                path: Some(path.to_string_lossy().to_string()),
+                source: Some(source),
+                version,
+                syntax,
+                switches,
+                signature_groups,
+                // Only a problem if neither help screen could be read
+                error: if lines.is_empty() { error } else { None },
+            }
+        }
+        None => EngineInfo {
+            found: false,
+            path: None,
+            source: None,
+            version: None,
+            syntax: EngineSyntax::Modern,
+            switches: Vec::new(),
+            signature_groups: Vec::new(),
+            error: Some(match configured.filter(|p| !p.trim().is_empty()) {
+                Some(custom) => validate_configured(custom).err().unwrap_or_default(),
+                None => "winfr.exe was not found. Install 'Windows File Recovery' from the Microsoft Store.".to_string(),
+            }),
+        },
+    };
+
+    *ENGINE.lock().unwrap_or_else(|e| e.into_inner()) = Some((configured.map(|p| p.to_string()), info.clone()));
+    info
+}
+
+fn run_help(path: &std::path::Path, switch: &str) -> Result<Vec<String>, String> {
+    let mut child = hidden_command(&path.to_string_lossy())
+        .arg(switch)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run winfr {}: {}", switch, e))?;
+
+    let stdout = child.stdout.take();
+    let reader = thread::spawn(move || {
+        let mut lines = Vec::new();
+        if let Some(stdout) = stdout {
+            read_utf16_lines(stdout, |line| lines.push(line));
+        }
+        lines
+    });
+
+    // winfr waits for input on anything it does not understand, so never block on it
+    let deadline = Instant::now() + PROBE_TIMEOUT;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(50)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break;
+            }
+        }
+    }
+
+    reader.join().map_err(|_| format!("Failed to read winfr {} output", switch))
+}
+
+// ── Help parsing ─────────────────────────────────────────────────────
+
+pub fn parse_help(lines: &[String]) -> (Option<String>, Vec<String>, EngineSyntax) {
+    let version_re = Regex::new(r"(?i)^version:\s*(\d+(?:\.\d+)+)").ok();
+    let switch_re = Regex::new(r"^(/[a-z#!?]+:?)").ok();
+
+    let mut version = None;
+    let mut switches: Vec<String> = Vec::new();
+    for line in lines {
+        if version.is_none() {
+            if let Some(caps) = version_re.as_ref().and_then(|re| re.captures(line)) {
+                version = Some(caps[1].to_string());
+            }
+        }
+        if let Some(caps) = switch_re.as_ref().and_then(|re| re.captures(line)) {
+            let switch = caps[1].to_lowercase();
+            if !switches.contains(&switch) {
+                switches.push(switch);
+            }
+        }
+    }
+
+    let has = |s: &str| switches.iter().any(|x| x == s);
+    let syntax = if has("/regular") || has("/extensive") {
+        EngineSyntax::Modern
+    } else if has("/r") || has("/x") {
+        EngineSyntax::Legacy
+    } else {
+        match version.as_deref().map(version_parts) {
+            Some(parts) if parts.as_slice() < MODERN_SINCE.as_slice() => EngineSyntax::Legacy,
+            _ => EngineSyntax::Modern,
+        }
+    };
+
+    (version, switches, syntax)
+}
+
+fn version_parts(version: &str) -> Vec<u64> {
+    version.split('.').map(|p| p.parse().unwrap_or(0)).collect()
+}
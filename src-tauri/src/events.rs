@@ -0,0 +1,167 @@
+use serde::Serialize;
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+// ── Typed recovery events ────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecoveryStatus {
+    Scanning,
+    Recovering,
+    Completed,
+    Error,
+    Aborted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSource {
+    App,    // messages written by Winfr Pro itself
+    Stdout, // winfr output
+    Stderr,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum RecoveryEvent {
+    Status { status: RecoveryStatus, progress: Option<f64> },
+    // Overall percentage: pass 1 (scanning) maps to 0-50, pass 2 (recovering) to 50-100
+    Progress { percent: f64, phase: RecoveryStatus, line: String },
+    Log { source: LogSource, message: String },
+    Warning { message: String },
+    RecoveryFolder { folder: String },
+    // Share of an image source hashed, before the session starts or after it ends
+    Hashing { path: String, percent: f64 },
+    // Always the last event of a run; `code` is None when winfr could not be waited on
+    // or was terminated by a signal
+    Exited { code: Option<i32> },
+}
+
+pub trait EventSink: Send + Sync {
+    fn emit(&self, event: RecoveryEvent);
+}
+
+// ── Tauri (frontend) ─────────────────────────────────────────────────
+
+// Payload shape the frontend listens for on the `recovery-*` (and `imaging-*`) channels
+#[derive(Debug, Clone, Serialize)]
+struct WireEvent {
+    event_type: String,
+    message: String,
+    progress: Option<f64>,
+    path: Option<String>,
+}
+
+pub struct TauriSink {
+    app: AppHandle,
+    prefix: &'static str,
+}
+
+impl TauriSink {
+    pub fn new(app: AppHandle) -> Self {
+        TauriSink::with_prefix(app, "recovery")
+    }
+
+    // Other long-running jobs (imaging) reuse the event shape on their own channels
+    pub fn with_prefix(app: AppHandle, prefix: &'static str) -> Self {
+        TauriSink { app, prefix }
+    }
+
+    fn send(&self, channel: &str, event_type: &str, message: String, progress: Option<f64>, path: Option<String>) {
+        let channel = format!("{}-{}", self.prefix, channel);
+        let _ = self.app.emit(&channel, WireEvent {
+            event_type: event_type.to_string(),
+            message,
+            progress,
+            path,
+        });
+    }
+}
+
+impl EventSink for TauriSink {
+    fn emit(&self, event: RecoveryEvent) {
+        match event {
+            RecoveryEvent::Status { status, progress } => {
+                self.send("status", "status", status_name(status), progress, None);
+            }
+            RecoveryEvent::Progress { percent, phase, line } => {
+                self.send("progress", "progress", line, Some(percent.min(100.0)), None);
+                self.send("status", "status", status_name(phase), Some(percent), None);
+            }
+            RecoveryEvent::Log { source, message } => {
+                let message = match source {
+                    LogSource::Stderr => format!("[stderr] {}", message),
+                    _ => message,
+                };
+                self.send("log", "log", message, None, None);
+            }
+            RecoveryEvent::Warning { message } => {
+                self.send("log", "log", format!("WARNING: {}", message), None, None);
+            }
+            RecoveryEvent::RecoveryFolder { folder } => {
+                self.send("path", "path", folder.clone(), None, Some(folder));
+            }
+            RecoveryEvent::Hashing { path, percent } => {
+                self.send("hashing", "hashing", format!("Hashing {}", path), Some(percent), Some(path));
+            }
+            RecoveryEvent::Exited { code } => {
+                let _ = self.app.emit(&format!("{}-exited", self.prefix), code);
+            }
+        }
+    }
+}
+
+fn status_name(status: RecoveryStatus) -> String {
+    match status {
+        RecoveryStatus::Scanning => "scanning",
+        RecoveryStatus::Recovering => "recovering",
+        RecoveryStatus::Completed => "completed",
+        RecoveryStatus::Error => "error",
+        RecoveryStatus::Aborted => "aborted",
+    }
+    .to_string()
+}
+
+// ── Channel (CLI, services) ──────────────────────────────────────────
+
+pub struct ChannelSink {
+    tx: Mutex<Sender<RecoveryEvent>>,
+}
+
+impl ChannelSink {
+    pub fn new(tx: Sender<RecoveryEvent>) -> Self {
+        ChannelSink { tx: Mutex::new(tx) }
+    }
+}
+
+impl EventSink for ChannelSink {
+    fn emit(&self, event: RecoveryEvent) {
+        let tx = self.tx.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = tx.send(event);
+    }
+}
+
+// ── Recording (tests) ────────────────────────────────────────────────
+
+#[derive(Default)]
+pub struct RecordingSink {
+    events: Mutex<Vec<RecoveryEvent>>,
+}
+
+impl RecordingSink {
+    pub fn new() -> Self {
+        RecordingSink::default()
+    }
+
+    pub fn events(&self) -> Vec<RecoveryEvent> {
+        self.events.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl EventSink for RecordingSink {
+    fn emit(&self, event: RecoveryEvent) {
+        self.events.lock().unwrap_or_else(|e| e.into_inner()).push(event);
+    }
+}
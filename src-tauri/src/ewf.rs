@@ -0,0 +1,406 @@
+use crate::blockdev::{read_file_at, BlockDevice};
+use crate::hashing::to_hex;
+use crate::safety::open_source;
+use flate2::read::ZlibDecoder;
+use md5::Md5;
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::Digest;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+// ── EWF evidence files ───────────────────────────────────────────────
+//
+// Expert Witness Format (EnCase .E01) splits an acquired disk over segment files .E01,
+// .E02, ... .E99, .EAA, ... Each segment is a chain of sections; "sectors" sections hold
+// the disk in chunks (32 KiB by default), zlib-compressed or stored with an Adler-32,
+// and "table" sections list where each chunk starts. The last segment ends with "done"
+// and usually carries the MD5 (and SHA-1) of the whole disk as it was acquired.
+
+const SIGNATURE: &[u8] = b"EVF\x09\x0d\x0a\xff\x00";
+// Ex01 (EnCase 7+) is a different container with the same extension scheme
+const EWF2_SIGNATURE: &[u8] = b"EVF2\x0d\x0a\x81\x00";
+const FILE_HEADER_SIZE: u64 = 13;
+const DESCRIPTOR_SIZE: u64 = 76;
+const TABLE_HEADER_SIZE: usize = 24;
+const COMPRESSED: u32 = 0x8000_0000;
+// Chunks are 32 KiB by default; anything past this is a damaged volume section
+const MAX_CHUNK_SIZE: u64 = 64 * 1024 * 1024;
+// .E01-.E99, then .EAA-.ZZZ
+const MAX_SEGMENTS: u32 = 99 + 22 * 26 * 26;
+const VERIFY_CHUNK: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyProgress {
+    #[serde(rename = "bytesDone")]
+    pub bytes_done: u64,
+    #[serde(rename = "bytesTotal")]
+    pub bytes_total: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EvidenceVerification {
+    pub source: String,
+    pub segments: usize,
+    pub size: u64,
+    pub md5: String,
+    pub sha1: String,
+    // Hashes recorded at acquisition, None when the evidence file has none
+    #[serde(rename = "storedMd5")]
+    pub stored_md5: Option<String>,
+    #[serde(rename = "storedSha1")]
+    pub stored_sha1: Option<String>,
+    // At least one hash was stored and every stored hash matches
+    pub verified: bool,
+}
+
+struct Chunk {
+    segment: usize,
+    offset: u64, // in the segment file
+    stored: u64, // bytes in the file, including the Adler-32 of uncompressed chunks
+    compressed: bool,
+}
+
+struct Geometry {
+    chunk_size: u64,
+    size: u64,
+}
+
+pub struct EwfImage {
+    segments: Vec<File>,
+    paths: Vec<PathBuf>, // of the segments
+    name: String,
+    size: u64,
+    chunk_size: u64,
+    chunks: Vec<Chunk>,
+    md5: Option<String>,
+    sha1: Option<String>,
+    // Native scanners read a few bytes at a time, so the last chunk is kept decompressed
+    cached: Option<(usize, Vec<u8>)>,
+}
+
+fn le32(b: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]])
+}
+
+fn le64(b: &[u8], at: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&b[at..at + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+pub(crate) fn is_ewf(file: &File) -> bool {
+    let mut file = file;
+    let mut signature = [0u8; 8];
+    file.seek(SeekFrom::Start(0)).is_ok()
+        && file.read_exact(&mut signature).is_ok()
+        && (signature == SIGNATURE || signature == EWF2_SIGNATURE)
+}
+
+// Segment `number` (1-based) of the set whose first segment is `first`, keeping its case
+fn segment_path(first: &Path, number: u32) -> PathBuf {
+    let extension = if number <= 99 {
+        format!("E{:02}", number)
+    } else {
+        let i = number - 100;
+        let letters = [b'E' + (i / 676) as u8, b'A' + (i / 26 % 26) as u8, b'A' + (i % 26) as u8];
+        String::from_utf8_lossy(&letters).to_string()
+    };
+    let lowercase = first.extension().is_some_and(|e| e.to_string_lossy().starts_with('e'));
+    first.with_extension(if lowercase { extension.to_lowercase() } else { extension })
+}
+
+impl EwfImage {
+    pub fn open(path: &Path) -> Result<EwfImage, String> {
+        let name = path.display().to_string();
+        let mut image = EwfImage {
+            segments: Vec::new(),
+            paths: Vec::new(),
+            name: name.clone(),
+            size: 0,
+            chunk_size: 0,
+            chunks: Vec::new(),
+            md5: None,
+            sha1: None,
+            cached: None,
+        };
+        let mut geometry = None;
+        let mut done = false;
+        for number in 1..=MAX_SEGMENTS {
+            let segment_path = if number == 1 { path.to_path_buf() } else { segment_path(path, number) };
+            let file = open_source(&segment_path).map_err(|_| {
+                format!("Segment {} of {} is missing; the evidence file cannot be read without it.", number, name)
+            })?;
+            image.segments.push(file);
+            image.paths.push(segment_path.clone());
+            done = image.read_segment(number, &segment_path, &mut geometry)?;
+            if done {
+                break;
+            }
+        }
+        if !done {
+            return Err(format!("{} has more segments than Expert Witness Format allows.", name));
+        }
+
+        let geometry = geometry.ok_or_else(|| format!("{} has no volume section.", name))?;
+        let needed = geometry.size.div_ceil(geometry.chunk_size);
+        if (image.chunks.len() as u64) < needed {
+            return Err(format!("{} lists {} of its {} chunks.", name, image.chunks.len(), needed));
+        }
+        image.size = geometry.size;
+        image.chunk_size = geometry.chunk_size;
+        Ok(image)
+    }
+
+    // Reads the sections of one segment; true once the "done" section is reached
+    fn read_segment(&mut self, number: u32, path: &Path, geometry: &mut Option<Geometry>) -> Result<bool, String> {
+        let segment = self.segments.len() - 1;
+        let file = &mut self.segments[segment];
+        let name = path.display().to_string();
+        let header = read_file_at(file, 0, FILE_HEADER_SIZE as usize)?;
+        if header.starts_with(EWF2_SIGNATURE) {
+            return Err(format!(
+                "{} is an Ex01 evidence file, which cannot be read yet. Export it as E01 or a raw image.",
+                name
+            ));
+        }
+        if !header.starts_with(SIGNATURE) {
+            return Err(format!("{} is not an EWF segment file.", name));
+        }
+        let found = u16::from_le_bytes([header[9], header[10]]) as u32;
+        if found != number {
+            return Err(format!("{} is segment {} of its evidence file, not segment {}.", name, found, number));
+        }
+
+        let file_size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        let mut sectors: Vec<(u64, u64)> = Vec::new(); // data ranges of "sectors" sections
+        let mut table_ok = true;
+        let mut offset = FILE_HEADER_SIZE;
+        loop {
+            let descriptor = read_file_at(file, offset, DESCRIPTOR_SIZE as usize)?;
+            if adler32(&descriptor[..72]) != le32(&descriptor, 72) {
+                return Err(format!("{} has a damaged section at offset {}.", name, offset));
+            }
+            let kind = String::from_utf8_lossy(&descriptor[..16]).trim_end_matches('\0').to_string();
+            let next = le64(&descriptor, 16);
+            let size = le64(&descriptor, 24);
+            let data_start = offset + DESCRIPTOR_SIZE;
+            let data_end = if size > DESCRIPTOR_SIZE { offset + size } else { data_start };
+            if data_end > file_size {
+                return Err(format!("{} is truncated in its {} section.", name, kind));
+            }
+            let data = |file: &mut File| read_file_at(file, data_start, (data_end - data_start) as usize);
+
+            match kind.as_str() {
+                "volume" | "disk" | "data" if geometry.is_none() => {
+                    let volume = read_volume(&data(file)?);
+                    *geometry = Some(volume.ok_or_else(|| format!("{} has an invalid volume section.", name))?);
+                }
+                "sectors" => sectors.push((data_start, data_end)),
+                "table" => {
+                    let entries = read_table(&data(file)?, segment, offset, &sectors);
+                    table_ok = entries.is_some();
+                    self.chunks.extend(entries.unwrap_or_default());
+                }
+                // Backup of the table before it, only needed when that one is damaged
+                "table2" if !table_ok => {
+                    let entries = read_table(&data(file)?, segment, offset, &sectors)
+                        .ok_or_else(|| format!("{} has a damaged chunk table at offset {}.", name, offset))?;
+                    self.chunks.extend(entries);
+                    table_ok = true;
+                }
+                "hash" => {
+                    let hash = data(file)?;
+                    if hash.len() >= 36 && adler32(&hash[..32]) == le32(&hash, 32) {
+                        self.md5 = Some(to_hex(&hash[..16]));
+                    }
+                }
+                "digest" => {
+                    let digest = data(file)?;
+                    if digest.len() >= 80 && adler32(&digest[..76]) == le32(&digest, 76) {
+                        self.md5 = Some(to_hex(&digest[..16]));
+                        if digest[16..36].iter().any(|b| *b != 0) {
+                            self.sha1 = Some(to_hex(&digest[16..36]));
+                        }
+                    }
+                }
+                "next" => return Ok(false),
+                "done" => return Ok(true),
+                _ => {}
+            }
+            if !table_ok && kind != "table" {
+                return Err(format!("{} has a damaged chunk table and no backup copy.", name));
+            }
+            if next <= offset {
+                return Err(format!("{} ends without a done or next section.", name));
+            }
+            offset = next;
+        }
+    }
+
+    // MD5 and SHA-1 recorded when the evidence was acquired
+    pub fn stored_hashes(&self) -> (Option<String>, Option<String>) {
+        (self.md5.clone(), self.sha1.clone())
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    fn read_chunk(&mut self, index: usize) -> Result<&[u8], String> {
+        if self.cached.as_ref().is_none_or(|(cached, _)| *cached != index) {
+            let chunk = &self.chunks[index];
+            let expected = self.chunk_size.min(self.size - index as u64 * self.chunk_size) as usize;
+            let stored = read_file_at(&mut self.segments[chunk.segment], chunk.offset, chunk.stored as usize)?;
+            let mut data = if chunk.compressed {
+                // A damaged chunk could inflate without end; one byte past a chunk is enough to tell
+                let mut data = Vec::with_capacity(self.chunk_size as usize + 1);
+                ZlibDecoder::new(&stored[..])
+                    .take(self.chunk_size + 1)
+                    .read_to_end(&mut data)
+                    .map_err(|e| format!("Chunk {} of {} does not decompress: {}", index, self.name, e))?;
+                data
+            } else {
+                let split = stored.len().saturating_sub(4);
+                if stored.len() < 4 || adler32(&stored[..split]) != le32(&stored, split) {
+                    return Err(format!("Chunk {} of {} fails its checksum.", index, self.name));
+                }
+                stored[..split].to_vec()
+            };
+            if data.len() as u64 > self.chunk_size {
+                return Err(format!("Chunk {} of {} inflates past the chunk size.", index, self.name));
+            }
+            if data.len() < expected {
+                return Err(format!("Chunk {} of {} is shorter than the chunk size.", index, self.name));
+            }
+            data.truncate(expected);
+            self.cached = Some((index, data));
+        }
+        Ok(&self.cached.as_ref().unwrap().1)
+    }
+}
+
+// Chunk size and disk size; SMART and EnCase volume sections agree on the fields used
+fn read_volume(data: &[u8]) -> Option<Geometry> {
+    if data.len() < 24 {
+        return None;
+    }
+    let sectors_per_chunk = le32(data, 8) as u64;
+    let bytes_per_sector = le32(data, 12) as u64;
+    let sector_count = if data.len() >= 1052 { le64(data, 16) } else { le32(data, 16) as u64 };
+    let chunk_size = sectors_per_chunk * bytes_per_sector;
+    let size = sector_count.checked_mul(bytes_per_sector)?;
+    (chunk_size > 0 && chunk_size <= MAX_CHUNK_SIZE && size > 0).then_some(Geometry { chunk_size, size })
+}
+
+// Chunk entries are 31-bit offsets from the table's base; the top bit marks compression.
+// A chunk runs to the next one, the last up to the end of its sectors section.
+fn read_table(data: &[u8], segment: usize, table_offset: u64, sectors: &[(u64, u64)]) -> Option<Vec<Chunk>> {
+    if data.len() < TABLE_HEADER_SIZE || adler32(&data[..20]) != le32(data, 20) {
+        return None;
+    }
+    let count = le32(data, 0) as usize;
+    let base = le64(data, 8);
+    let entries = data.get(TABLE_HEADER_SIZE..TABLE_HEADER_SIZE + count * 4)?;
+    // EnCase 6 and later follow the entries with their own checksum
+    let end = TABLE_HEADER_SIZE + count * 4;
+    if data.len() >= end + 4 && adler32(entries) != le32(data, end) {
+        return None;
+    }
+
+    let starts: Vec<(u64, bool)> = entries
+        .chunks_exact(4)
+        .map(|e| le32(e, 0))
+        .map(|e| (base + (e & !COMPRESSED) as u64, e & COMPRESSED != 0))
+        .collect();
+    let mut chunks = Vec::with_capacity(count);
+    for (i, (offset, compressed)) in starts.iter().enumerate() {
+        let end = match starts.get(i + 1) {
+            Some((next, _)) => *next,
+            None => sectors.iter().find(|(s, e)| (*s..*e).contains(offset)).map_or(table_offset, |(_, e)| *e),
+        };
+        if end <= *offset {
+            return None;
+        }
+        chunks.push(Chunk { segment, offset: *offset, stored: end - offset, compressed: *compressed });
+    }
+    Some(chunks)
+}
+
+impl BlockDevice for EwfImage {
+    fn size(&mut self) -> Option<u64> {
+        Some(self.size)
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        self.paths.clone()
+    }
+
+    fn read_at(&mut self, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+        let end = offset + len as u64;
+        if end > self.size {
+            return Err(format!("Read past the end of {} at offset {}", self.name, offset));
+        }
+        let mut data = Vec::with_capacity(len);
+        let mut at = offset;
+        while at < end {
+            let index = (at / self.chunk_size) as usize;
+            let chunk_start = index as u64 * self.chunk_size;
+            let piece_end = (chunk_start + self.chunk_size).min(end);
+            let chunk = self.read_chunk(index)?;
+            data.extend_from_slice(&chunk[(at - chunk_start) as usize..(piece_end - chunk_start) as usize]);
+            at = piece_end;
+        }
+        Ok(data)
+    }
+}
+
+// ── Verification ─────────────────────────────────────────────────────
+
+// Hashes the whole acquired disk and compares it with the hashes stored at acquisition
+pub fn verify_evidence<F>(path: &str, mut on_progress: F) -> Result<EvidenceVerification, String>
+where
+    F: FnMut(&VerifyProgress),
+{
+    let mut image = EwfImage::open(Path::new(path))?;
+    let size = image.size;
+    let mut md5 = Md5::new();
+    let mut sha1 = Sha1::new();
+    let mut offset = 0;
+    while offset < size {
+        let len = (size - offset).min(VERIFY_CHUNK);
+        let data = image.read_at(offset, len as usize)?;
+        md5.update(&data);
+        sha1.update(&data);
+        offset += len;
+        on_progress(&VerifyProgress { bytes_done: offset, bytes_total: size });
+    }
+
+    let (md5, sha1) = (to_hex(&md5.finalize()), to_hex(&sha1.finalize()));
+    let (stored_md5, stored_sha1) = image.stored_hashes();
+    let matches = |stored: &Option<String>, computed: &str| stored.as_deref().is_none_or(|s| s == computed);
+    let verified = (stored_md5.is_some() || stored_sha1.is_some())
+        && matches(&stored_md5, &md5)
+        && matches(&stored_sha1, &sha1);
+    let verification = EvidenceVerification {
+        source: path.to_string(),
+        segments: image.segment_count(),
+        size,
+        md5,
+        sha1,
+        stored_md5,
+        stored_sha1,
+        verified,
+    };
+    Ok(verification)
+}
@@ -0,0 +1,475 @@
+use crate::ntfs::DataState;
+use crate::safety::ensure_target_outside;
+use crate::timestamps::dos_datetime_to_unix;
+use crate::volume::{
+    read_contiguous, recover_selected, u16_at, u32_at, u64_at, NativeProgress, NativeReader, NativeRecoverySummary,
+    Volume,
+};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+// ── Types ────────────────────────────────────────────────────────────
+//
+// winfr crashes on exFAT in its deeper modes, so deleted entries are read here instead.
+// Deleting a file on exFAT clears the InUse bit (0x80) of each entry in its directory
+// entry set and its bits in the allocation bitmap, leaving the rest of the set intact.
+
+const ENTRY_SIZE: usize = 32;
+const IN_USE: u8 = 0x80;
+
+const TYPE_END: u8 = 0x00;
+const TYPE_BITMAP: u8 = 0x81;
+const TYPE_LABEL: u8 = 0x83;
+const TYPE_FILE: u8 = 0x85;
+const TYPE_STREAM: u8 = 0xC0;
+const TYPE_NAME: u8 = 0xC1;
+
+const ATTR_DIRECTORY: u16 = 0x10;
+const FLAG_NO_FAT_CHAIN: u8 = 0x02;
+const NAME_CHARS_PER_ENTRY: usize = 15;
+
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExfatDeletedFile {
+    pub id: String, // "exfat-<byte offset of the File entry>"
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    #[serde(rename = "firstCluster")]
+    pub first_cluster: u32,
+    pub clusters: u64,
+    // NoFatChain files are one contiguous run; others depend on a FAT chain that is
+    // not trusted after deletion and cannot be recovered here
+    pub contiguous: bool,
+    #[serde(rename = "dataState")]
+    pub data_state: DataState,
+    #[serde(rename = "checksumValid")]
+    pub checksum_valid: bool, // the entry set checksum still matches
+    pub created: Option<u64>,
+    pub modified: Option<u64>,
+    #[serde(rename = "inDeletedFolder")]
+    pub in_deleted_folder: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExfatListing {
+    pub source: String,
+    #[serde(rename = "clusterSize")]
+    pub cluster_size: u64,
+    #[serde(rename = "totalClusters")]
+    pub total_clusters: u64,
+    #[serde(rename = "volumeLabel")]
+    pub volume_label: Option<String>,
+    pub files: Vec<ExfatDeletedFile>,
+}
+
+struct BootRegion {
+    fat_offset: u64,
+    fat_bytes: u64,
+    heap_offset: u64,
+    cluster_size: u64,
+    total_clusters: u64,
+    root_cluster: u32,
+}
+
+// Data location of a file or folder, from its Stream Extension entry
+#[derive(Clone, Copy)]
+struct Stream {
+    first_cluster: u32,
+    valid_length: u64,
+    length: u64,
+    no_fat_chain: bool,
+}
+
+struct EntrySet {
+    offset: u64,
+    deleted: bool,
+    attributes: u16,
+    name: String,
+    stream: Stream,
+    checksum_valid: bool,
+    created: Option<u64>,
+    modified: Option<u64>,
+}
+
+// ── Boot region ──────────────────────────────────────────────────────
+
+fn parse_boot_sector(b: &[u8]) -> Result<BootRegion, String> {
+    if b.len() < 512 || &b[3..11] != b"EXFAT   " || b[0x1FE] != 0x55 || b[0x1FF] != 0xAA {
+        return Err("Not an exFAT volume (no exFAT boot sector).".to_string());
+    }
+    let sector_shift = b[0x6C] as u32;
+    let cluster_shift = b[0x6D] as u32;
+    // 512 bytes to 4 KiB sectors, clusters up to 32 MiB
+    if !(9..=12).contains(&sector_shift) || sector_shift + cluster_shift > 25 {
+        return Err("Invalid exFAT boot sector: sector or cluster size out of range.".to_string());
+    }
+    let sector_size = 1u64 << sector_shift;
+    let boot = BootRegion {
+        fat_offset: u32_at(b, 0x50) as u64 * sector_size,
+        fat_bytes: u32_at(b, 0x54) as u64 * sector_size,
+        heap_offset: u32_at(b, 0x58) as u64 * sector_size,
+        cluster_size: sector_size << cluster_shift,
+        total_clusters: u32_at(b, 0x5C) as u64,
+        root_cluster: u32_at(b, 0x60),
+    };
+    if boot.fat_bytes < (boot.total_clusters + 2) * 4 || boot.root_cluster < 2 {
+        return Err("Invalid exFAT boot sector: FAT too small for the cluster count.".to_string());
+    }
+    Ok(boot)
+}
+
+// Size in bytes of the volume whose boot sector this is, if it is exFAT
+pub(crate) fn probe(b: &[u8]) -> Option<u64> {
+    parse_boot_sector(b).ok()?;
+    Some(u64_at(b, 0x48) << b[0x6C])
+}
+
+// True if `boot` describes the volume starting at the beginning of `volume`: its FAT
+// starts with the media descriptor entries. `boot` may come from the backup boot region.
+pub(crate) fn verify(volume: &mut Volume, boot: &[u8]) -> bool {
+    let Ok(boot) = parse_boot_sector(boot) else {
+        return false;
+    };
+    volume.read_at(boot.fat_offset, 8).is_ok_and(|f| f == [0xF8, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF])
+}
+
+// ── Volume structures ────────────────────────────────────────────────
+
+struct Exfat<'a> {
+    volume: &'a mut Volume,
+    boot: BootRegion,
+    fat: Vec<u8>,
+    bitmap: Option<Vec<u8>>,
+    label: Option<String>,
+    visited: HashSet<u32>,
+    files: Vec<(ExfatDeletedFile, Stream)>,
+}
+
+impl Exfat<'_> {
+    fn valid_cluster(&self, cluster: u32) -> bool {
+        cluster >= 2 && (cluster as u64) < self.boot.total_clusters + 2
+    }
+
+    fn cluster_offset(&self, cluster: u32) -> u64 {
+        self.boot.heap_offset + (cluster as u64 - 2) * self.boot.cluster_size
+    }
+
+    fn chain(&self, start: u32) -> Vec<u32> {
+        let mut chain = Vec::new();
+        let mut cluster = start;
+        while self.valid_cluster(cluster) && (chain.len() as u64) < self.boot.total_clusters {
+            chain.push(cluster);
+            match self.fat.get(cluster as usize * 4..cluster as usize * 4 + 4) {
+                Some(next) => cluster = u32_at(next, 0),
+                None => break,
+            }
+        }
+        chain
+    }
+
+    // Byte ranges holding a stream, in order
+    fn spans(&self, stream: &Stream, deleted: bool) -> Vec<(u64, u64)> {
+        if !self.valid_cluster(stream.first_cluster) {
+            return Vec::new();
+        }
+        if stream.no_fat_chain {
+            let clusters = stream.length.div_ceil(self.boot.cluster_size);
+            if stream.first_cluster as u64 + clusters > self.boot.total_clusters + 2 {
+                return Vec::new();
+            }
+            return vec![(self.cluster_offset(stream.first_cluster), stream.length)];
+        }
+        if deleted {
+            // Only the first cluster is certain once the file is gone
+            return vec![(self.cluster_offset(stream.first_cluster), self.boot.cluster_size)];
+        }
+        let mut remaining = stream.length;
+        let mut spans = Vec::new();
+        for cluster in self.chain(stream.first_cluster) {
+            if remaining == 0 {
+                break;
+            }
+            let len = remaining.min(self.boot.cluster_size);
+            spans.push((self.cluster_offset(cluster), len));
+            remaining -= len;
+        }
+        spans
+    }
+
+    fn read_entries(&mut self, spans: &[(u64, u64)]) -> Result<Vec<(u64, Vec<u8>)>, String> {
+        let mut entries = Vec::new();
+        for (offset, len) in spans {
+            let data = self.volume.read_at(*offset, *len as usize)?;
+            for (i, entry) in data.chunks_exact(ENTRY_SIZE).enumerate() {
+                entries.push((offset + (i * ENTRY_SIZE) as u64, entry.to_vec()));
+            }
+        }
+        Ok(entries)
+    }
+
+    fn walk(&mut self, spans: Vec<(u64, u64)>, path: &str, in_deleted: bool, depth: usize) -> Result<(), String> {
+        let entries = self.read_entries(&spans)?;
+        let mut i = 0;
+        while i < entries.len() {
+            let (offset, entry) = &entries[i];
+            let kind = entry[0];
+            if kind == TYPE_END {
+                break;
+            }
+            match kind {
+                TYPE_LABEL if path.is_empty() => {
+                    let chars = (entry[1] as usize).min(11);
+                    let units: Vec<u16> = (0..chars).map(|c| u16_at(entry, 2 + c * 2)).collect();
+                    self.label = Some(String::from_utf16_lossy(&units));
+                }
+                TYPE_BITMAP if path.is_empty() && self.bitmap.is_none() => {
+                    let stream = Stream {
+                        first_cluster: u32_at(entry, 20),
+                        valid_length: u64_at(entry, 24),
+                        length: u64_at(entry, 24),
+                        no_fat_chain: false,
+                    };
+                    let spans = self.spans(&stream, false);
+                    let mut bitmap = Vec::new();
+                    for (at, len) in spans {
+                        bitmap.extend(self.volume.read_at(at, len as usize)?);
+                    }
+                    self.bitmap = Some(bitmap);
+                }
+                _ if kind | IN_USE == TYPE_FILE => {
+                    let count = entry[1] as usize;
+                    if let Some(set) = entries.get(i..=i + count).and_then(|s| parse_entry_set(*offset, s)) {
+                        i += count;
+                        self.visit(set, path, in_deleted, depth);
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        Ok(())
+    }
+
+    fn visit(&mut self, set: EntrySet, path: &str, in_deleted: bool, depth: usize) {
+        let full_path = format!("{}\\{}", path, set.name);
+        let gone = set.deleted || in_deleted;
+        if set.attributes & ATTR_DIRECTORY != 0 {
+            // Only live folders are tracked: a deleted folder's clusters may since belong to a live one,
+            // so it is walked only while the bitmap still shows them free
+            let walkable = if gone {
+                let stream = &set.stream;
+                let clusters = if stream.no_fat_chain { stream.length.div_ceil(self.boot.cluster_size) } else { 1 };
+                self.data_state(set.stream.first_cluster, clusters) == DataState::Intact
+            } else {
+                self.visited.insert(set.stream.first_cluster)
+            };
+            if depth < MAX_DEPTH && walkable {
+                let spans = self.spans(&set.stream, gone);
+                // Its entry set may point at reused clusters; the parent is listed either way
+                let _ = self.walk(spans, &full_path, gone, depth + 1);
+            }
+            return;
+        }
+        if !gone {
+            return;
+        }
+
+        let clusters = set.stream.length.div_ceil(self.boot.cluster_size);
+        let data_state = if set.stream.length == 0 {
+            DataState::Empty
+        } else if set.stream.no_fat_chain {
+            self.data_state(set.stream.first_cluster, clusters)
+        } else {
+            DataState::Unknown
+        };
+        let file = ExfatDeletedFile {
+            id: format!("exfat-{}", set.offset),
+            name: set.name,
+            path: full_path,
+            size: set.stream.length,
+            first_cluster: set.stream.first_cluster,
+            clusters,
+            contiguous: set.stream.no_fat_chain,
+            data_state,
+            checksum_valid: set.checksum_valid,
+            created: set.created,
+            modified: set.modified,
+            in_deleted_folder: in_deleted,
+        };
+        self.files.push((file, set.stream));
+    }
+
+    fn data_state(&self, first: u32, clusters: u64) -> DataState {
+        let Some(bitmap) = &self.bitmap else { return DataState::Unknown };
+        if first as u64 + clusters > self.boot.total_clusters + 2 {
+            return DataState::Unknown;
+        }
+        let used = (first as u64..first as u64 + clusters)
+            .filter(|c| {
+                let bit = c - 2;
+                bitmap.get((bit / 8) as usize).is_some_and(|b| b & (1 << (bit % 8)) != 0)
+            })
+            .count() as u64;
+        match used {
+            0 => DataState::Intact,
+            n if n == clusters => DataState::Overwritten,
+            _ => DataState::Partial,
+        }
+    }
+}
+
+// A File entry followed by its Stream Extension and File Name entries. All entries of
+// a deleted set have InUse cleared; a mix means the slots were partly reused.
+fn parse_entry_set(offset: u64, set: &[(u64, Vec<u8>)]) -> Option<EntrySet> {
+    let file = &set[0].1;
+    let deleted = file[0] & IN_USE == 0;
+    if set.len() < 3 || set.iter().any(|(_, e)| (e[0] & IN_USE == 0) != deleted) {
+        return None;
+    }
+    let stream = &set[1].1;
+    if stream[0] | IN_USE != TYPE_STREAM {
+        return None;
+    }
+    let name_length = stream[3] as usize;
+    let mut units: Vec<u16> = Vec::new();
+    for (_, entry) in &set[2..] {
+        if entry[0] | IN_USE != TYPE_NAME {
+            break;
+        }
+        units.extend((0..NAME_CHARS_PER_ENTRY).map(|c| u16_at(entry, 2 + c * 2)));
+    }
+    if units.len() < name_length || name_length == 0 {
+        return None;
+    }
+    units.truncate(name_length);
+
+    // The checksum was computed while the entries were in use
+    let mut checksum: u16 = 0;
+    for (index, (_, entry)) in set.iter().enumerate() {
+        for (pos, byte) in entry.iter().enumerate() {
+            if index == 0 && (pos == 2 || pos == 3) {
+                continue;
+            }
+            let byte = if pos == 0 { byte | IN_USE } else { *byte };
+            checksum = checksum.rotate_right(1).wrapping_add(byte as u16);
+        }
+    }
+
+    let timestamp = |at: usize| dos_datetime_to_unix(u16_at(file, at + 2), u16_at(file, at));
+    Some(EntrySet {
+        offset,
+        deleted,
+        attributes: u16_at(file, 4),
+        name: String::from_utf16_lossy(&units),
+        stream: Stream {
+            first_cluster: u32_at(stream, 20),
+            valid_length: u64_at(stream, 8),
+            length: u64_at(stream, 24),
+            no_fat_chain: stream[1] & FLAG_NO_FAT_CHAIN != 0,
+        },
+        checksum_valid: checksum == u16_at(file, 2),
+        created: timestamp(8),
+        modified: timestamp(12),
+    })
+}
+
+// ── Public API ───────────────────────────────────────────────────────
+
+fn scan(volume: &mut Volume) -> Result<(ExfatListing, BootRegion, Vec<Stream>), String> {
+    let boot = parse_boot_sector(&volume.read_at(0, 512)?)?;
+    let fat = volume.read_at(boot.fat_offset, boot.fat_bytes as usize)?;
+    let mut exfat = Exfat {
+        volume,
+        boot,
+        fat,
+        bitmap: None,
+        label: None,
+        visited: HashSet::new(),
+        files: Vec::new(),
+    };
+
+    // The root directory always follows its FAT chain
+    let root = exfat.boot.root_cluster;
+    exfat.visited.insert(root);
+    let spans: Vec<(u64, u64)> = exfat
+        .chain(root)
+        .into_iter()
+        .map(|c| (exfat.cluster_offset(c), exfat.boot.cluster_size))
+        .collect();
+    exfat.walk(spans, "", false, 0)?;
+
+    let mut found = std::mem::take(&mut exfat.files);
+    found.sort_by(|(a, _), (b, _)| a.path.to_lowercase().cmp(&b.path.to_lowercase()).then(a.id.cmp(&b.id)));
+    let (files, streams): (Vec<_>, Vec<_>) = found.into_iter().unzip();
+    let listing = ExfatListing {
+        source: exfat.volume.source().to_string(),
+        cluster_size: exfat.boot.cluster_size,
+        total_clusters: exfat.boot.total_clusters,
+        volume_label: exfat.label,
+        files,
+    };
+    Ok((listing, exfat.boot, streams))
+}
+
+// Lists deleted entry sets on an exFAT volume
+pub fn list_deleted_files(source: &str) -> Result<ExfatListing, String> {
+    let mut volume = Volume::open(source)?;
+    scan(&mut volume).map(|(listing, _, _)| listing)
+}
+
+// Copies the selected files (ids from the listing) into `target`. Only contiguous
+// (NoFatChain) files can be recovered.
+pub fn recover_files<F>(
+    source: &str,
+    ids: &[String],
+    target: &str,
+    mut on_progress: F,
+) -> Result<NativeRecoverySummary, String>
+where
+    F: FnMut(&NativeProgress),
+{
+    ensure_target_outside(source, target)?;
+    let mut volume = Volume::open(source)?;
+    let (listing, boot, streams) = scan(&mut volume)?;
+    let index = listing.files.iter().enumerate().map(|(i, f)| (f.id.clone(), i)).collect();
+    let mut reader = ExfatReader { volume, boot, files: listing.files, streams, index };
+    Ok(recover_selected(&mut reader, ids, target, &mut on_progress))
+}
+
+struct ExfatReader {
+    volume: Volume,
+    boot: BootRegion,
+    files: Vec<ExfatDeletedFile>,
+    streams: Vec<Stream>,
+    index: HashMap<String, usize>, // by id
+}
+
+impl NativeReader for ExfatReader {
+    fn find(&self, id: &str) -> Option<usize> {
+        self.index.get(id).copied()
+    }
+
+    fn describe(&self, index: usize) -> (&str, u64) {
+        (&self.files[index].path, self.files[index].size)
+    }
+
+    fn check(&self, index: usize) -> Result<(), String> {
+        let (file, stream) = (&self.files[index], &self.streams[index]);
+        if !file.contiguous && file.size > self.boot.cluster_size {
+            return Err("The file was fragmented; its cluster chain cannot be trusted after deletion.".to_string());
+        }
+        let past_end = stream.first_cluster as u64 + file.clusters > self.boot.total_clusters + 2;
+        if file.size > 0 && (stream.first_cluster < 2 || past_end) {
+            return Err("The first cluster of this file is invalid.".to_string());
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, index: usize, out: &mut dyn FnMut(&[u8]) -> Result<(), String>) -> Result<(), String> {
+        let (file, stream) = (&self.files[index], &self.streams[index]);
+        let offset = self.boot.heap_offset + (stream.first_cluster as u64).saturating_sub(2) * self.boot.cluster_size;
+        read_contiguous(&mut self.volume, offset, file.size, stream.valid_length.min(file.size), out)
+    }
+}
@@ -0,0 +1,317 @@
+use crate::hashing;
+use crate::index;
+use crate::recovery::RecoveredFile;
+use crate::safety;
+use crate::session;
+use crate::timestamps::format_date;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// ── Types ────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportMode {
+    #[default]
+    Copy,
+    Move,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportLayout {
+    #[default]
+    Preserve,
+    Flatten,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CollisionPolicy {
+    #[default]
+    Rename, // "name (1).ext"
+    Overwrite,
+    Skip,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExportOptions {
+    #[serde(default)]
+    pub mode: ExportMode,
+    #[serde(default)]
+    pub layout: ExportLayout,
+    // Tokens: {name} {stem} {ext} {category} {date} {id}, e.g. "{date}_{category}_{stem}"
+    #[serde(rename = "renamePattern")]
+    pub rename_pattern: Option<String>,
+    #[serde(default)]
+    pub collision: CollisionPolicy,
+    #[serde(default)]
+    pub verify: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportProgress {
+    pub current: usize,
+    pub total: usize,
+    pub file: String,
+    #[serde(rename = "bytesDone")]
+    pub bytes_done: u64,
+    #[serde(rename = "bytesTotal")]
+    pub bytes_total: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportFailure {
+    pub id: String,
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedFile {
+    pub id: String,
+    pub source: String,
+    pub target: String,
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExportSummary {
+    pub target: String,
+    pub exported: Vec<ExportedFile>,
+    pub skipped: Vec<String>,
+    pub failed: Vec<ExportFailure>,
+    pub verified: bool,
+}
+
+// ── Export ───────────────────────────────────────────────────────────
+
+pub fn export_recovered_files<F>(
+    destination: &str,
+    ids: &[String],
+    target: &str,
+    options: &ExportOptions,
+    mut on_progress: F,
+) -> Result<ExportSummary, String>
+where
+    F: FnMut(&ExportProgress),
+{
+    let recovery_dir = index::resolve_recovery_dir(destination)?;
+    let target_dir = PathBuf::from(target);
+
+    // Exporting into the recovery folder itself would make the index chase its own output.
+    // Both sides are resolved so "..", a relative path or a junction cannot hide it.
+    let recovery_resolved = safety::resolve_target(&recovery_dir.to_string_lossy());
+    if safety::resolve_target(target).starts_with(&recovery_resolved) {
+        return Err("Export target must be outside the recovery folder.".to_string());
+    }
+    // Nor onto the drive, disk or image the session recovered from
+    if let Some(session) = session::load(destination) {
+        safety::ensure_target_outside(&session.config.source, target)?;
+    }
+    fs::create_dir_all(&target_dir).map_err(|e| format!("Failed to create export folder: {}", e))?;
+
+    let ids: HashSet<&String> = ids.iter().collect();
+    let selected: Vec<RecoveredFile> = index::all_files(destination)?
+        .into_iter()
+        .filter(|f| ids.contains(&f.id))
+        .collect();
+    if selected.is_empty() {
+        return Err("None of the selected files were found in the recovery folder.".to_string());
+    }
+
+    let bytes_total: u64 = selected.iter().map(|f| f.size).sum();
+    let mut bytes_done = 0u64;
+    let mut summary = ExportSummary {
+        target: target.to_string(),
+        verified: options.verify,
+        ..Default::default()
+    };
+
+    for (i, file) in selected.iter().enumerate() {
+        on_progress(&ExportProgress {
+            current: i + 1,
+            total: selected.len(),
+            file: file.path.clone(),
+            bytes_done,
+            bytes_total,
+        });
+
+        let source = index::absolute_path(&recovery_dir, file);
+        let planned = target_dir.join(target_relative_path(file, options));
+        let dest = match resolve_collision(&planned, options.collision) {
+            Some(dest) => dest,
+            None => {
+                summary.skipped.push(file.id.clone());
+                bytes_done += file.size;
+                continue;
+            }
+        };
+
+        match export_one(&source, &dest, options) {
+            Ok(sha256) => summary.exported.push(ExportedFile {
+                id: file.id.clone(),
+                source: file.path.clone(),
+                target: dest.to_string_lossy().to_string(),
+                sha256,
+            }),
+            Err(error) => summary.failed.push(ExportFailure {
+                id: file.id.clone(),
+                path: file.path.clone(),
+                error,
+            }),
+        }
+        bytes_done += file.size;
+    }
+
+    on_progress(&ExportProgress {
+        current: selected.len(),
+        total: selected.len(),
+        file: String::new(),
+        bytes_done,
+        bytes_total,
+    });
+
+    // Moved files are gone from the recovery folder, so drop them from the index too
+    if options.mode == ExportMode::Move && !summary.exported.is_empty() {
+        index::refresh_index(destination, |_| {})?;
+    }
+
+    Ok(summary)
+}
+
+fn export_one(source: &Path, dest: &Path, options: &ExportOptions) -> Result<Option<String>, String> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    // A plain rename keeps the same bytes, so only fall back to copy when it fails (e.g. across volumes)
+    if options.mode == ExportMode::Move && !options.verify && fs::rename(source, dest).is_ok() {
+        return Ok(None);
+    }
+
+    fs::copy(source, dest).map_err(|e| format!("Copy failed: {}", e))?;
+
+    let sha256 = if options.verify {
+        let before = hashing::hash_file(source)?;
+        let after = hashing::hash_file(dest)?;
+        if before.sha256 != after.sha256 {
+            let _ = fs::remove_file(dest);
+            return Err("Verification failed: copied file hash does not match the source.".to_string());
+        }
+        Some(after.sha256)
+    } else {
+        None
+    };
+
+    if options.mode == ExportMode::Move {
+        fs::remove_file(source).map_err(|e| format!("Copied but failed to remove source: {}", e))?;
+    }
+
+    Ok(sha256)
+}
+
+// ── Naming ───────────────────────────────────────────────────────────
+
+fn target_relative_path(file: &RecoveredFile, options: &ExportOptions) -> PathBuf {
+    let name = match &options.rename_pattern {
+        Some(pattern) if !pattern.trim().is_empty() => apply_rename_pattern(pattern, file),
+        _ => file.name.clone(),
+    };
+
+    let mut path = PathBuf::new();
+    if options.layout == ExportLayout::Preserve {
+        let parts: Vec<&str> = file.path.split('\\').filter(|p| !p.is_empty()).collect();
+        for dir in parts.iter().take(parts.len().saturating_sub(1)) {
+            path.push(dir);
+        }
+    }
+    path.push(name);
+    path
+}
+
+pub fn apply_rename_pattern(pattern: &str, file: &RecoveredFile) -> String {
+    let (stem, ext) = match file.name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem.to_string(), ext.to_string()),
+        _ => (file.name.clone(), String::new()),
+    };
+    let date = file.modified.map(format_date).unwrap_or_else(|| "unknown-date".to_string());
+
+    let mut name = pattern
+        .replace("{name}", &file.name)
+        .replace("{stem}", &stem)
+        .replace("{ext}", &ext)
+        .replace("{category}", &file.category)
+        .replace("{date}", &date)
+        .replace("{id}", &file.id);
+
+    // Keep the extension unless the pattern placed it explicitly
+    if !pattern.contains("{ext}") && !pattern.contains("{name}") && !ext.is_empty() {
+        name.push('.');
+        name.push_str(&ext);
+    }
+
+    sanitize_file_name(&name)
+}
+
+pub(crate) fn sanitize_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let trimmed = cleaned.trim_end_matches(['.', ' ']);
+    if trimmed.is_empty() { "_".to_string() } else { trimmed.to_string() }
+}
+
+pub(crate) fn resolve_collision(planned: &Path, policy: CollisionPolicy) -> Option<PathBuf> {
+    if !planned.exists() {
+        return Some(planned.to_path_buf());
+    }
+
+    match policy {
+        CollisionPolicy::Overwrite => Some(planned.to_path_buf()),
+        CollisionPolicy::Skip => None,
+        CollisionPolicy::Rename => {
+            let stem = planned.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            let ext = planned.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+            (1..)
+                .map(|n| planned.with_file_name(format!("{} ({}){}", stem, n, ext)))
+                .find(|candidate| !candidate.exists())
+        }
+    }
+}
+
+// ── Delete ───────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeleteSummary {
+    pub deleted: Vec<RecoveredFile>,
+    pub failed: Vec<ExportFailure>,
+}
+
+pub fn delete_recovered_files(destination: &str, ids: &[String]) -> Result<DeleteSummary, String> {
+    let recovery_dir = index::resolve_recovery_dir(destination)?;
+    let ids: HashSet<&String> = ids.iter().collect();
+    let mut summary = DeleteSummary { deleted: Vec::new(), failed: Vec::new() };
+
+    for file in index::all_files(destination)?.into_iter().filter(|f| ids.contains(&f.id)) {
+        match fs::remove_file(index::absolute_path(&recovery_dir, &file)) {
+            Ok(()) => summary.deleted.push(file),
+            Err(e) => summary.failed.push(ExportFailure {
+                id: file.id.clone(),
+                path: file.path.clone(),
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    // Drop the deleted entries from the index right away
+    index::refresh_index(destination, |_| {})?;
+    Ok(summary)
+}
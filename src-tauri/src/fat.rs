@@ -0,0 +1,496 @@
+use crate::safety::ensure_target_outside;
+use crate::timestamps::dos_datetime_to_unix;
+use crate::volume::{
+    read_contiguous, recover_selected, u16_at, u32_at, NativeProgress, NativeReader, NativeRecoverySummary, Volume,
+};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+// ── Types ────────────────────────────────────────────────────────────
+
+const ENTRY_SIZE: usize = 32;
+const END_OF_DIRECTORY: u8 = 0x00;
+const DELETED_MARKER: u8 = 0xE5;
+const ESCAPED_E5: u8 = 0x05; // a live name that really starts with 0xE5
+
+const ATTR_VOLUME_LABEL: u8 = 0x08;
+const ATTR_DIRECTORY: u8 = 0x10;
+const ATTR_LONG_NAME: u8 = 0x0F;
+
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum FatType {
+    Fat12,
+    Fat16,
+    Fat32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FatDeletedFile {
+    pub id: String, // "fat-<byte offset of the short name entry>"
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    #[serde(rename = "startCluster")]
+    pub start_cluster: u32,
+    pub clusters: u64,
+    #[serde(rename = "reusedClusters")]
+    pub reused_clusters: u64, // allocated to other files since the deletion
+    pub confidence: u8,       // 0-100, see `confidence`
+    pub created: Option<u64>,
+    pub modified: Option<u64>,
+    #[serde(rename = "inDeletedFolder")]
+    pub in_deleted_folder: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FatListing {
+    pub source: String,
+    #[serde(rename = "fatType")]
+    pub fat_type: FatType,
+    #[serde(rename = "clusterSize")]
+    pub cluster_size: u64,
+    #[serde(rename = "totalClusters")]
+    pub total_clusters: u64,
+    #[serde(rename = "volumeLabel")]
+    pub volume_label: Option<String>,
+    pub files: Vec<FatDeletedFile>,
+}
+
+struct Bpb {
+    fat_type: FatType,
+    cluster_size: u64,
+    fat_offset: u64,
+    fat_bytes: u64,
+    root_offset: u64, // fixed root directory (FAT12/16)
+    root_entries: u64,
+    root_cluster: u32, // FAT32
+    data_offset: u64,
+    total_clusters: u64,
+}
+
+enum DirLocation {
+    FixedRoot,
+    Chain(u32),
+    // A deleted folder has no FAT chain left; only its first cluster is read
+    Deleted(u32),
+}
+
+// ── Boot sector ──────────────────────────────────────────────────────
+
+fn parse_bpb(b: &[u8]) -> Result<Bpb, String> {
+    if b.len() < 512 || b[0x1FE] != 0x55 || b[0x1FF] != 0xAA {
+        return Err("Not a FAT volume (no boot sector signature).".to_string());
+    }
+    let bytes_per_sector = u16_at(b, 0x0B) as u64;
+    let sectors_per_cluster = b[0x0D] as u64;
+    let reserved = u16_at(b, 0x0E) as u64;
+    let fats = b[0x10] as u64;
+    let root_entries = u16_at(b, 0x11) as u64;
+    let total = match u16_at(b, 0x13) {
+        0 => u32_at(b, 0x20) as u64,
+        n => n as u64,
+    };
+    let fat_sectors = match u16_at(b, 0x16) {
+        0 => u32_at(b, 0x24) as u64,
+        n => n as u64,
+    };
+    if !bytes_per_sector.is_power_of_two()
+        || !(512..=4096).contains(&bytes_per_sector)
+        || !sectors_per_cluster.is_power_of_two()
+        || reserved == 0
+        || fats == 0
+        || fat_sectors == 0
+    {
+        return Err("Not a FAT volume (invalid BIOS parameter block).".to_string());
+    }
+
+    let root_sectors = (root_entries * ENTRY_SIZE as u64).div_ceil(bytes_per_sector);
+    let data_sector = reserved + fats * fat_sectors + root_sectors;
+    let total_clusters = total.checked_sub(data_sector).ok_or("Not a FAT volume (data area out of range).")?
+        / sectors_per_cluster;
+    // The cluster count alone decides the FAT type, whatever the label says
+    let fat_type = match total_clusters {
+        0..4085 => FatType::Fat12,
+        4085..65525 => FatType::Fat16,
+        _ => FatType::Fat32,
+    };
+    if fat_type == FatType::Fat32 && root_entries != 0 {
+        return Err("Not a FAT volume (FAT32 with a fixed root directory).".to_string());
+    }
+
+    Ok(Bpb {
+        fat_type,
+        cluster_size: bytes_per_sector * sectors_per_cluster,
+        fat_offset: reserved * bytes_per_sector,
+        fat_bytes: fat_sectors * bytes_per_sector,
+        root_offset: (reserved + fats * fat_sectors) * bytes_per_sector,
+        root_entries,
+        root_cluster: u32_at(b, 0x2C),
+        data_offset: data_sector * bytes_per_sector,
+        total_clusters,
+    })
+}
+
+// FAT type and size in bytes of the volume whose boot sector this is, if it is FAT. The
+// jump instruction is required here since any sector ending in 55 AA could pass the BPB checks.
+pub(crate) fn probe(b: &[u8]) -> Option<(FatType, u64)> {
+    if b.len() < 512 || !matches!(b[0], 0xEB | 0xE9) {
+        return None;
+    }
+    let bpb = parse_bpb(b).ok()?;
+    let total = match u16_at(b, 0x13) {
+        0 => u32_at(b, 0x20) as u64,
+        n => n as u64,
+    };
+    Some((bpb.fat_type, total * u16_at(b, 0x0B) as u64))
+}
+
+// True if `boot` describes the volume starting at the beginning of `volume`: its FAT
+// starts with the boot sector's media descriptor. `boot` may come from the FAT32 backup.
+pub(crate) fn verify(volume: &mut Volume, boot: &[u8]) -> bool {
+    let Ok(bpb) = parse_bpb(boot) else {
+        return false;
+    };
+    volume.read_at(bpb.fat_offset, 2).is_ok_and(|f| f[0] == boot[0x15] && f[1] == 0xFF)
+}
+
+// ── Allocation table ─────────────────────────────────────────────────
+
+struct Fat {
+    fat_type: FatType,
+    table: Vec<u8>,
+}
+
+impl Fat {
+    fn entry(&self, cluster: u32) -> Option<u32> {
+        let c = cluster as usize;
+        match self.fat_type {
+            FatType::Fat12 => {
+                let at = c + c / 2;
+                let pair = u16::from_le_bytes([*self.table.get(at)?, *self.table.get(at + 1)?]) as u32;
+                Some(if c & 1 == 0 { pair & 0x0FFF } else { pair >> 4 })
+            }
+            FatType::Fat16 => self.table.get(c * 2..c * 2 + 2).map(|b| u16_at(b, 0) as u32),
+            FatType::Fat32 => self.table.get(c * 4..c * 4 + 4).map(|b| u32_at(b, 0) & 0x0FFF_FFFF),
+        }
+    }
+
+    fn is_free(&self, cluster: u32) -> bool {
+        self.entry(cluster) == Some(0)
+    }
+
+    fn chain(&self, start: u32, total_clusters: u64) -> Vec<u32> {
+        let end_of_chain = match self.fat_type {
+            FatType::Fat12 => 0x0FF7,
+            FatType::Fat16 => 0xFFF7,
+            FatType::Fat32 => 0x0FFF_FFF7,
+        };
+        let mut chain = Vec::new();
+        let mut cluster = start;
+        while cluster >= 2 && (cluster as u64) < total_clusters + 2 && chain.len() as u64 <= total_clusters {
+            chain.push(cluster);
+            match self.entry(cluster) {
+                Some(next) if next < end_of_chain => cluster = next,
+                _ => break,
+            }
+        }
+        chain
+    }
+}
+
+// ── Directory walk ───────────────────────────────────────────────────
+
+struct Walk<'a> {
+    volume: &'a mut Volume,
+    bpb: &'a Bpb,
+    fat: &'a Fat,
+    visited: HashSet<u32>,
+    label: Option<String>,
+    files: Vec<FatDeletedFile>,
+}
+
+impl Walk<'_> {
+    fn cluster_offset(&self, cluster: u32) -> u64 {
+        self.bpb.data_offset + (cluster as u64 - 2) * self.bpb.cluster_size
+    }
+
+    // Directory entries with their byte offsets on the volume
+    fn read_dir(&mut self, location: &DirLocation) -> Result<Vec<(u64, Vec<u8>)>, String> {
+        let spans: Vec<(u64, u64)> = match location {
+            DirLocation::FixedRoot => vec![(self.bpb.root_offset, self.bpb.root_entries * ENTRY_SIZE as u64)],
+            DirLocation::Chain(start) => self
+                .fat
+                .chain(*start, self.bpb.total_clusters)
+                .into_iter()
+                .map(|c| (self.cluster_offset(c), self.bpb.cluster_size))
+                .collect(),
+            DirLocation::Deleted(start) if (*start as u64) < self.bpb.total_clusters + 2 => {
+                vec![(self.cluster_offset(*start), self.bpb.cluster_size)]
+            }
+            DirLocation::Deleted(_) => Vec::new(),
+        };
+        let mut entries = Vec::new();
+        for (offset, len) in spans {
+            let data = self.volume.read_at(offset, len as usize)?;
+            for (i, entry) in data.chunks_exact(ENTRY_SIZE).enumerate() {
+                entries.push((offset + (i * ENTRY_SIZE) as u64, entry.to_vec()));
+            }
+        }
+        Ok(entries)
+    }
+
+    // A deleted folder's cluster is free and still opens with the folder's own "." entry
+    // until something else is written there
+    fn still_holds_folder(&mut self, cluster: u32) -> bool {
+        if cluster as u64 >= self.bpb.total_clusters + 2 || !self.fat.is_free(cluster) {
+            return false;
+        }
+        let offset = self.cluster_offset(cluster);
+        self.volume.read_at(offset, ENTRY_SIZE).is_ok_and(|dot| {
+            &dot[0..11] == b".          "
+                && dot[11] & ATTR_DIRECTORY != 0
+                && start_cluster(&dot, self.bpb.fat_type) == cluster
+        })
+    }
+
+    fn walk(&mut self, location: DirLocation, path: &str, in_deleted: bool, depth: usize) -> Result<(), String> {
+        let is_root = matches!(location, DirLocation::FixedRoot) || path.is_empty();
+        let entries = self.read_dir(&location)?;
+        let mut long_parts: Vec<Vec<u8>> = Vec::new();
+
+        for (offset, entry) in entries {
+            if entry[0] == END_OF_DIRECTORY {
+                break;
+            }
+            let attr = entry[11];
+            if attr & 0x3F == ATTR_LONG_NAME {
+                long_parts.push(entry);
+                continue;
+            }
+            let parts = std::mem::take(&mut long_parts);
+            let deleted = entry[0] == DELETED_MARKER;
+            if attr & ATTR_VOLUME_LABEL != 0 {
+                if is_root && !deleted && self.label.is_none() {
+                    self.label = Some(String::from_utf8_lossy(&entry[0..11]).trim_end().to_string());
+                }
+                continue;
+            }
+            if entry[0] == b'.' {
+                continue; // "." and ".."
+            }
+
+            let long = long_name(&parts, &entry);
+            let name = match &long {
+                Some(long) => long.clone(),
+                None => short_name(&entry, None),
+            };
+            let full_path = format!("{}\\{}", path, name);
+            let cluster = start_cluster(&entry, self.bpb.fat_type);
+
+            if attr & ATTR_DIRECTORY != 0 {
+                if cluster < 2 || depth >= MAX_DEPTH {
+                    continue;
+                }
+                let gone = deleted || in_deleted;
+                // Only live chains are tracked: a deleted folder's cluster may since belong to a live one
+                let walkable = if gone { self.still_holds_folder(cluster) } else { self.visited.insert(cluster) };
+                if !walkable {
+                    continue;
+                }
+                let child = if gone { DirLocation::Deleted(cluster) } else { DirLocation::Chain(cluster) };
+                // A damaged or reused folder should not hide the rest of the listing
+                let _ = self.walk(child, &full_path, gone, depth + 1);
+            } else if deleted || in_deleted {
+                self.files.push(self.deleted_file(offset, &entry, name, full_path, cluster, in_deleted));
+            }
+        }
+        Ok(())
+    }
+
+    fn deleted_file(
+        &self,
+        offset: u64,
+        entry: &[u8],
+        name: String,
+        path: String,
+        start_cluster: u32,
+        in_deleted_folder: bool,
+    ) -> FatDeletedFile {
+        let size = u32_at(entry, 28) as u64;
+        let clusters = size.div_ceil(self.bpb.cluster_size);
+        let (confidence, reused_clusters) = confidence(self.fat, self.bpb, start_cluster, clusters);
+        FatDeletedFile {
+            id: format!("fat-{}", offset),
+            name,
+            path,
+            size,
+            start_cluster,
+            clusters,
+            reused_clusters,
+            confidence,
+            created: dos_datetime_to_unix(u16_at(entry, 16), u16_at(entry, 14)),
+            modified: dos_datetime_to_unix(u16_at(entry, 24), u16_at(entry, 22)),
+            in_deleted_folder,
+        }
+    }
+}
+
+fn start_cluster(entry: &[u8], fat_type: FatType) -> u32 {
+    let high = if fat_type == FatType::Fat32 { u16_at(entry, 20) as u32 } else { 0 };
+    high << 16 | u16_at(entry, 26) as u32
+}
+
+// 8.3 name; `first` replaces the first byte, which deletion overwrites with 0xE5
+fn short_name(entry: &[u8], first: Option<u8>) -> String {
+    let mut raw = [0u8; 11];
+    raw.copy_from_slice(&entry[0..11]);
+    match raw[0] {
+        DELETED_MARKER => raw[0] = first.unwrap_or(b'_'),
+        ESCAPED_E5 => raw[0] = DELETED_MARKER,
+        _ => {}
+    }
+    // Windows NT keeps the case of all-lowercase base names and extensions in byte 12
+    let case = entry[12];
+    let decode = |bytes: &[u8], lower: bool| -> String {
+        let text: String = bytes.iter().map(|b| *b as char).collect::<String>().trim_end().to_string();
+        if lower { text.to_lowercase() } else { text }
+    };
+    let base = decode(&raw[0..8], case & 0x08 != 0);
+    let ext = decode(&raw[8..11], case & 0x10 != 0);
+    if ext.is_empty() { base } else { format!("{}.{}", base, ext) }
+}
+
+fn short_name_checksum(raw: &[u8]) -> u8 {
+    raw.iter().fold(0u8, |sum, b| sum.rotate_right(1).wrapping_add(*b))
+}
+
+// Reassembles the long name stored in the entries before `entry`, physically last part
+// first. Deletion also overwrites their ordinal bytes, so the parts are trusted by
+// position and checksum alone. For a deleted entry the checksum is tried with the
+// first character of the long name in place of the lost first byte.
+fn long_name(parts: &[Vec<u8>], entry: &[u8]) -> Option<String> {
+    let checksum = parts.first()?[13];
+    if parts.iter().any(|p| p[13] != checksum) {
+        return None;
+    }
+    let mut units: Vec<u16> = Vec::new();
+    for part in parts.iter().rev() {
+        for (start, end) in [(1, 11), (14, 26), (28, 32)] {
+            units.extend(part[start..end].chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])));
+        }
+    }
+    if let Some(end) = units.iter().position(|u| *u == 0) {
+        units.truncate(end);
+    }
+    let name = String::from_utf16(&units).ok().filter(|n| !n.is_empty())?;
+
+    let mut raw = entry[0..11].to_vec();
+    if raw[0] == DELETED_MARKER {
+        raw[0] = name.chars().next().filter(|c| c.is_ascii()).map(|c| c.to_ascii_uppercase() as u8)?;
+    }
+    (short_name_checksum(&raw) == checksum).then_some(name)
+}
+
+// 100: the file's only cluster is still free. 90: every cluster is free, but the file
+// is assumed to be contiguous. Below that the score falls with the share of clusters
+// reused by other files, and an invalid start cluster scores 0.
+fn confidence(fat: &Fat, bpb: &Bpb, start: u32, clusters: u64) -> (u8, u64) {
+    if clusters == 0 {
+        return (100, 0);
+    }
+    if start < 2 || start as u64 + clusters > bpb.total_clusters + 2 {
+        return (0, 0);
+    }
+    let reused = (start as u64..start as u64 + clusters).filter(|c| !fat.is_free(*c as u32)).count() as u64;
+    let score = match (clusters, reused) {
+        (1, 0) => 100,
+        (_, 0) => 90,
+        _ => 80 * (clusters - reused) / clusters,
+    };
+    (score as u8, reused)
+}
+
+// ── Public API ───────────────────────────────────────────────────────
+
+fn scan(volume: &mut Volume) -> Result<(FatListing, Bpb), String> {
+    let bpb = parse_bpb(&volume.read_at(0, 512)?)?;
+    let fat = Fat { fat_type: bpb.fat_type, table: volume.read_at(bpb.fat_offset, bpb.fat_bytes as usize)? };
+    let root = match bpb.fat_type {
+        FatType::Fat32 => DirLocation::Chain(bpb.root_cluster),
+        _ => DirLocation::FixedRoot,
+    };
+
+    let mut walk = Walk { volume, bpb: &bpb, fat: &fat, visited: HashSet::new(), label: None, files: Vec::new() };
+    walk.walk(root, "", false, 0)?;
+    let (label, mut files) = (walk.label, walk.files);
+    files.sort_by(|a, b| a.path.to_lowercase().cmp(&b.path.to_lowercase()).then(a.id.cmp(&b.id)));
+
+    let listing = FatListing {
+        source: volume.source().to_string(),
+        fat_type: bpb.fat_type,
+        cluster_size: bpb.cluster_size,
+        total_clusters: bpb.total_clusters,
+        volume_label: label,
+        files,
+    };
+    Ok((listing, bpb))
+}
+
+// Lists deleted directory entries on a FAT12/16/32 volume
+pub fn list_deleted_files(source: &str) -> Result<FatListing, String> {
+    let mut volume = Volume::open(source)?;
+    scan(&mut volume).map(|(listing, _)| listing)
+}
+
+// Copies the selected entries (ids from the listing) into `target`, reading each file
+// as one contiguous run from its start cluster
+pub fn recover_files<F>(
+    source: &str,
+    ids: &[String],
+    target: &str,
+    mut on_progress: F,
+) -> Result<NativeRecoverySummary, String>
+where
+    F: FnMut(&NativeProgress),
+{
+    ensure_target_outside(source, target)?;
+    let mut volume = Volume::open(source)?;
+    let (listing, bpb) = scan(&mut volume)?;
+    let index = listing.files.iter().enumerate().map(|(i, f)| (f.id.clone(), i)).collect();
+    let mut reader = FatReader { volume, bpb, files: listing.files, index };
+    Ok(recover_selected(&mut reader, ids, target, &mut on_progress))
+}
+
+struct FatReader {
+    volume: Volume,
+    bpb: Bpb,
+    files: Vec<FatDeletedFile>,
+    index: HashMap<String, usize>, // by id
+}
+
+impl NativeReader for FatReader {
+    fn find(&self, id: &str) -> Option<usize> {
+        self.index.get(id).copied()
+    }
+
+    fn describe(&self, index: usize) -> (&str, u64) {
+        (&self.files[index].path, self.files[index].size)
+    }
+
+    fn check(&self, index: usize) -> Result<(), String> {
+        let file = &self.files[index];
+        if file.size > 0 && file.confidence == 0 && file.reused_clusters == 0 {
+            return Err("The start cluster of this file is invalid.".to_string());
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, index: usize, out: &mut dyn FnMut(&[u8]) -> Result<(), String>) -> Result<(), String> {
+        let file = &self.files[index];
+        let offset = self.bpb.data_offset + (file.start_cluster as u64).saturating_sub(2) * self.bpb.cluster_size;
+        read_contiguous(&mut self.volume, offset, file.size, file.size, out)
+    }
+}
@@ -0,0 +1,288 @@
+use crate::dialect::{category_extensions, render_for_engine};
+use crate::engine;
+use crate::recovery::RecoveryConfig;
+use serde::Serialize;
+use std::collections::HashSet;
+
+// ── Filter model ─────────────────────────────────────────────────────
+//
+// `RecoveryConfig.filters` entries are parsed as:
+//   Images, Documents, ...       category
+//   \Users\Bob\Documents\        folder (trailing backslash), relative to the source root
+//   Users\Bob\report.docx        single file path
+//   report*.docx, *.pdf          file name wildcard
+//   *.pdf, *.docx  or  .pdf .docx extension list (comma or space separated)
+//   !<any of the above>          exclusion, subtracted from the included patterns
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum FilterRule {
+    Category { name: String },
+    Path { path: String },
+    Name { pattern: String },
+    Extensions { extensions: Vec<String> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueSeverity {
+    Error, // the recovery must not start
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FilterIssue {
+    pub severity: IssueSeverity,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ParsedFilter {
+    pub input: String,
+    pub exclude: bool,
+    pub rule: Option<FilterRule>, // None when the input could not be parsed
+    pub issues: Vec<FilterIssue>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompiledFilters {
+    pub filters: Vec<ParsedFilter>,
+    pub patterns: Vec<String>, // values passed to /n, in order
+    pub issues: Vec<FilterIssue>, // issues that involve more than one filter
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FilterPreview {
+    pub ok: bool,
+    pub filters: Vec<ParsedFilter>,
+    pub issues: Vec<FilterIssue>,
+    pub switches: Vec<String>, // filter switches only, e.g. ["/n", "*.pdf"] or ["/y:PDF"]
+    pub args: Vec<String>,     // the full winfr command line
+    pub warnings: Vec<String>, // options dropped by the argument dialect
+}
+
+const INVALID_CHARS: [char; 5] = ['<', '>', ':', '"', '|'];
+
+// ── Parsing ──────────────────────────────────────────────────────────
+
+pub fn parse_filter(input: &str) -> ParsedFilter {
+    let mut issues = Vec::new();
+    let trimmed = input.trim();
+    let (exclude, body) = match trimmed.strip_prefix('!') {
+        Some(rest) => (true, rest.trim()),
+        None => (false, trimmed),
+    };
+    // Quotes are only needed on a command line; arguments are passed to winfr unquoted
+    let body = body.trim_matches('"');
+
+    let rule = if body.is_empty() {
+        issues.push(error("Empty filter."));
+        None
+    } else if category_extensions(body).is_some() {
+        Some(FilterRule::Category { name: body.to_string() })
+    } else if body.contains(['\\', '/']) {
+        parse_path(body, &mut issues)
+    } else if let Some(extensions) = parse_extension_list(body) {
+        Some(FilterRule::Extensions { extensions })
+    } else {
+        parse_name(body, &mut issues)
+    };
+
+    ParsedFilter { input: input.to_string(), exclude, rule, issues }
+}
+
+fn parse_path(body: &str, issues: &mut Vec<FilterIssue>) -> Option<FilterRule> {
+    if body.contains('/') {
+        issues.push(error(&format!("Use backslashes in folder filters: '{}'.", body.replace('/', "\\"))));
+        return None;
+    }
+    let mut chars = body.chars();
+    if let (Some(letter), Some(':')) = (chars.next(), chars.next()) {
+        if letter.is_ascii_alphabetic() {
+            issues.push(error(&format!(
+                "Filter paths are relative to the source drive; drop '{}:' from '{}'.",
+                letter, body
+            )));
+            return None;
+        }
+    }
+    if body.contains(INVALID_CHARS) {
+        issues.push(error(&format!("'{}' contains characters that cannot appear in a path.", body)));
+        return None;
+    }
+
+    // winfr only treats a filter as a folder when it ends with a backslash
+    let last = body.rsplit('\\').next().unwrap_or_default();
+    if !body.ends_with('\\') && !last.contains(['.', '*', '?']) {
+        issues.push(error(&format!(
+            "Folder filters must end with a backslash: '{}\\'. Without it winfr looks for a file named '{}'.",
+            body, last
+        )));
+        return None;
+    }
+    if body.contains("\\\\") {
+        issues.push(warning(&format!("'{}' contains an empty folder name.", body)));
+    }
+
+    Some(FilterRule::Path { path: body.to_string() })
+}
+
+// "*.pdf, *.docx" or ".pdf .docx"; a single "*.pdf" is a name pattern
+fn parse_extension_list(body: &str) -> Option<Vec<String>> {
+    let parts: Vec<&str> = body.split([',', ';', ' ']).filter(|p| !p.is_empty()).collect();
+    let is_extension = |p: &&str| {
+        let ext = p.strip_prefix("*.").or_else(|| p.strip_prefix('.'));
+        ext.is_some_and(|e| !e.is_empty() && e.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+    };
+    let single_dot = parts.len() == 1 && parts[0].starts_with('.');
+    if (parts.len() > 1 || single_dot) && parts.iter().all(is_extension) {
+        let mut extensions: Vec<String> = parts
+            .iter()
+            .map(|p| p.trim_start_matches('*').trim_start_matches('.').to_lowercase())
+            .collect();
+        let mut seen = HashSet::new();
+        extensions.retain(|e| seen.insert(e.clone()));
+        Some(extensions)
+    } else {
+        None
+    }
+}
+
+fn parse_name(body: &str, issues: &mut Vec<FilterIssue>) -> Option<FilterRule> {
+    if body.contains(INVALID_CHARS) {
+        issues.push(error(&format!("'{}' contains characters that cannot appear in a file name.", body)));
+        return None;
+    }
+    if body.ends_with(['.', ' ']) {
+        issues.push(error(&format!("File names cannot end with a dot or space: '{}'.", body)));
+        return None;
+    }
+    if body.contains([',', ';']) {
+        issues.push(error(&format!(
+            "'{}' looks like a list; separate extensions as '*.pdf, *.docx' or add one filter per pattern.",
+            body
+        )));
+        return None;
+    }
+    if !body.contains(['*', '?', '.']) {
+        issues.push(warning(&format!(
+            "'{}' has no wildcard or extension and only matches files named exactly '{}'.",
+            body, body
+        )));
+    }
+    Some(FilterRule::Name { pattern: body.to_string() })
+}
+
+fn error(message: &str) -> FilterIssue {
+    FilterIssue { severity: IssueSeverity::Error, message: message.to_string() }
+}
+
+fn warning(message: &str) -> FilterIssue {
+    FilterIssue { severity: IssueSeverity::Warning, message: message.to_string() }
+}
+
+// ── Compiling to /n patterns ─────────────────────────────────────────
+
+fn patterns_for(rule: &FilterRule) -> Vec<String> {
+    match rule {
+        FilterRule::Category { name } => category_extensions(name)
+            .unwrap_or_default()
+            .iter()
+            .map(|e| e.to_string())
+            .collect(),
+        FilterRule::Path { path } => vec![path.clone()],
+        FilterRule::Name { pattern } => vec![pattern.clone()],
+        FilterRule::Extensions { extensions } => extensions.iter().map(|e| format!("*.{}", e)).collect(),
+    }
+}
+
+pub fn compile(filters: &[String]) -> CompiledFilters {
+    let parsed: Vec<ParsedFilter> = filters.iter().map(|f| parse_filter(f)).collect();
+    let mut issues = Vec::new();
+
+    let mut patterns: Vec<String> = Vec::new();
+    for filter in parsed.iter().filter(|f| !f.exclude) {
+        for pattern in filter.rule.iter().flat_map(patterns_for) {
+            if !patterns.iter().any(|p| p.eq_ignore_ascii_case(&pattern)) {
+                patterns.push(pattern);
+            }
+        }
+    }
+
+    // winfr has no exclusion switch, so exclusions can only remove included patterns
+    for filter in parsed.iter().filter(|f| f.exclude) {
+        let Some(rule) = &filter.rule else { continue };
+        if patterns.is_empty() {
+            issues.push(error(&format!(
+                "'{}' cannot be applied without an include filter: winfr cannot exclude files on its own.",
+                filter.input.trim()
+            )));
+            continue;
+        }
+        if matches!(rule, FilterRule::Path { .. }) {
+            issues.push(error(&format!("winfr cannot exclude folders; remove '{}'.", filter.input.trim())));
+            continue;
+        }
+        // A folder or path pattern is recovered in full, whatever else is excluded
+        for include in parsed.iter().filter(|f| !f.exclude) {
+            if let Some(FilterRule::Path { path }) = &include.rule {
+                issues.push(warning(&format!(
+                    "'{}' cannot remove files from '{}': winfr still recovers everything that path matches.",
+                    filter.input.trim(),
+                    path
+                )));
+            }
+        }
+        let excluded = patterns_for(rule);
+        let before = patterns.len();
+        patterns.retain(|p| !excluded.iter().any(|e| e.eq_ignore_ascii_case(p)));
+        if patterns.len() == before {
+            issues.push(warning(&format!(
+                "'{}' does not match any included pattern and has no effect.",
+                filter.input.trim()
+            )));
+        } else if patterns.is_empty() {
+            issues.push(error("The exclusions remove every included pattern, so nothing would be recovered."));
+        }
+    }
+
+    CompiledFilters { filters: parsed, patterns, issues }
+}
+
+impl CompiledFilters {
+    pub fn all_issues(&self) -> impl Iterator<Item = &FilterIssue> {
+        self.filters.iter().flat_map(|f| f.issues.iter()).chain(self.issues.iter())
+    }
+
+    pub fn errors(&self) -> Vec<String> {
+        self.all_issues()
+            .filter(|i| i.severity == IssueSeverity::Error)
+            .map(|i| i.message.clone())
+            .collect()
+    }
+}
+
+// ── Preview ──────────────────────────────────────────────────────────
+
+pub fn preview_filters(config: &RecoveryConfig) -> FilterPreview {
+    let compiled = compile(&config.filters);
+    let engine = engine::detect(config.winfr_path.as_deref());
+    let command = render_for_engine(config, &engine);
+
+    // Everything after the general switches is filter-related
+    let switches = command
+        .args
+        .iter()
+        .skip_while(|a| *a != "/n" && !a.starts_with("/y:"))
+        .cloned()
+        .collect();
+
+    FilterPreview {
+        ok: compiled.errors().is_empty(),
+        filters: compiled.filters,
+        issues: compiled.issues,
+        switches,
+        args: command.args,
+        warnings: command.warnings,
+    }
+}
@@ -0,0 +1,56 @@
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// ── Types ────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileHashes {
+    pub md5: String,
+    pub sha1: String,
+    pub sha256: String,
+}
+
+// ── Hash a file (single pass, all three digests) ────────────────────
+
+pub fn hash_file(path: &Path) -> Result<FileHashes, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    hash_reader(file)
+}
+
+pub fn hash_reader<R: Read>(reader: R) -> Result<FileHashes, String> {
+    hash_reader_with(reader, |_| {})
+}
+
+// Like hash_reader, reporting the bytes read so far after each block
+pub fn hash_reader_with<R: Read, F: FnMut(u64)>(mut reader: R, mut on_read: F) -> Result<FileHashes, String> {
+    let mut md5 = Md5::new();
+    let mut sha1 = Sha1::new();
+    let mut sha256 = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    let mut done = 0u64;
+
+    loop {
+        let n = reader.read(&mut buffer).map_err(|e| e.to_string())?;
+        if n == 0 { break; }
+        md5.update(&buffer[..n]);
+        sha1.update(&buffer[..n]);
+        sha256.update(&buffer[..n]);
+        done += n as u64;
+        on_read(done);
+    }
+
+    Ok(FileHashes {
+        md5: to_hex(&md5.finalize()),
+        sha1: to_hex(&sha1.finalize()),
+        sha256: to_hex(&sha256.finalize()),
+    })
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
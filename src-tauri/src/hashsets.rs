@@ -0,0 +1,192 @@
+use crate::hashing::FileHashes;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Mutex;
+
+// ── Types ────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KnownStatus {
+    KnownGood,
+    KnownBad,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HashSetInfo {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub status: KnownStatus,
+    pub format: String, // "plain" or "nsrl"
+    pub entries: usize,
+}
+
+struct LoadedHashSet {
+    info: HashSetInfo,
+    // Lowercase hex digests; MD5, SHA-1 and SHA-256 share one set since their lengths differ
+    hashes: HashSet<String>,
+}
+
+// Hash sets loaded for the current session
+static HASH_SETS: Mutex<Vec<LoadedHashSet>> = Mutex::new(Vec::new());
+
+// ── Loading ──────────────────────────────────────────────────────────
+
+pub fn load_hash_set(path: &str, status: KnownStatus) -> Result<HashSetInfo, String> {
+    if status == KnownStatus::Unknown {
+        return Err("A hash set must be marked as known-good or known-bad.".to_string());
+    }
+
+    let file = File::open(path).map_err(|e| format!("Failed to open hash set {}: {}", path, e))?;
+    let (hashes, format) = parse_hash_set(BufReader::new(file))?;
+    if hashes.is_empty() {
+        return Err(format!("No MD5, SHA-1 or SHA-256 hashes found in {}", path));
+    }
+
+    let mut sets = HASH_SETS.lock().map_err(|e| e.to_string())?;
+    let id = sets.iter().map(|s| s.info.id.parse::<u64>().unwrap_or(0)).max().unwrap_or(0) + 1;
+    let info = HashSetInfo {
+        id: id.to_string(),
+        name: Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string()),
+        path: path.to_string(),
+        status,
+        format: format.to_string(),
+        entries: hashes.len(),
+    };
+    sets.push(LoadedHashSet { info: info.clone(), hashes });
+    Ok(info)
+}
+
+pub fn list_hash_sets() -> Vec<HashSetInfo> {
+    let sets = HASH_SETS.lock().unwrap_or_else(|e| e.into_inner());
+    sets.iter().map(|s| s.info.clone()).collect()
+}
+
+pub fn remove_hash_set(id: &str) -> Result<(), String> {
+    let mut sets = HASH_SETS.lock().map_err(|e| e.to_string())?;
+    let before = sets.len();
+    sets.retain(|s| s.info.id != id);
+    if sets.len() == before {
+        return Err(format!("No hash set with id {}", id));
+    }
+    Ok(())
+}
+
+pub fn has_hash_sets() -> bool {
+    let sets = HASH_SETS.lock().unwrap_or_else(|e| e.into_inner());
+    !sets.is_empty()
+}
+
+// ── Classification ───────────────────────────────────────────────────
+
+// Known-bad wins over known-good so a flagged file is never hidden as an OS file
+pub fn classify(hashes: &FileHashes) -> KnownStatus {
+    let sets = HASH_SETS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut status = KnownStatus::Unknown;
+
+    for set in sets.iter() {
+        let hit = set.hashes.contains(&hashes.md5)
+            || set.hashes.contains(&hashes.sha1)
+            || set.hashes.contains(&hashes.sha256);
+        if hit {
+            if set.info.status == KnownStatus::KnownBad {
+                return KnownStatus::KnownBad;
+            }
+            status = KnownStatus::KnownGood;
+        }
+    }
+
+    status
+}
+
+// ── Parsing ──────────────────────────────────────────────────────────
+
+fn parse_hash_set<R: BufRead>(reader: R) -> Result<(HashSet<String>, &'static str), String> {
+    let mut hashes = HashSet::new();
+    let mut lines = reader.lines();
+
+    let first = loop {
+        match lines.next() {
+            Some(line) => {
+                let line = line.map_err(|e| e.to_string())?;
+                if !line.trim().is_empty() {
+                    break line;
+                }
+            }
+            None => return Ok((hashes, "plain")),
+        }
+    };
+
+    // NSRL RDS files start with a quoted header row: "SHA-1","MD5","CRC32","FileName",...
+    let header = split_csv_line(first.trim_start_matches('\u{feff}'));
+    let hash_columns: Vec<usize> = header
+        .iter()
+        .enumerate()
+        .filter(|(_, col)| matches!(col.to_uppercase().as_str(), "MD5" | "SHA-1" | "SHA1" | "SHA-256" | "SHA256"))
+        .map(|(i, _)| i)
+        .collect();
+
+    if !hash_columns.is_empty() {
+        for line in lines {
+            let line = line.map_err(|e| e.to_string())?;
+            let fields = split_csv_line(&line);
+            for &i in &hash_columns {
+                if let Some(hash) = fields.get(i).and_then(|f| normalize_hash(f)) {
+                    hashes.insert(hash);
+                }
+            }
+        }
+        return Ok((hashes, "nsrl"));
+    }
+
+    // Plain lists: one hash per line, optionally followed by a file name (md5sum/sha1sum output)
+    for line in std::iter::once(Ok(first)).chain(lines) {
+        let line = line.map_err(|e| e.to_string())?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') { continue; }
+        if let Some(hash) = trimmed.split_whitespace().next().and_then(normalize_hash) {
+            hashes.insert(hash);
+        }
+    }
+
+    Ok((hashes, "plain"))
+}
+
+fn normalize_hash(value: &str) -> Option<String> {
+    let hash = value.trim().trim_matches('"').to_lowercase();
+    let valid_len = matches!(hash.len(), 32 | 40 | 64);
+    if valid_len && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(hash)
+    } else {
+        None
+    }
+}
+
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
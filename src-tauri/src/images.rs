@@ -0,0 +1,227 @@
+use crate::partitions::{self, Partition};
+use crate::recovery::hidden_command;
+use crate::volume::{drive_letter, split_partition_source};
+use serde::Serialize;
+use std::path::Path;
+
+// ── Types ────────────────────────────────────────────────────────────
+//
+// Disk image files as recovery sources. winfr only scans drive letters, so VHD and VHDX
+// images are attached read-only with Windows' built-in disk image support and scanned
+// through the drive letter their volume gets. Raw images cannot be attached; their
+// volumes are opened by the native scanners instead, which also read VHD and VHDX. E01
+// evidence files are like raw images: only the native scanners read them. A raw image
+// split into .001, .002, ... parts is read as one disk from its .001 part.
+
+const RAW_EXTENSIONS: [&str; 4] = ["img", "dd", "raw", "001"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Raw,
+    Vhd,
+    Vhdx,
+    #[serde(rename = "e01")]
+    Ewf,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceInfo {
+    pub source: String,
+    pub format: Option<ImageFormat>, // None for drive letters
+    // winfr can scan it, after attaching it for VHD and VHDX images
+    pub winfr: bool,
+    // Volumes the native scanners can open, with their `source`
+    pub volumes: Vec<Partition>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MountedImage {
+    pub image: String,
+    pub volumes: Vec<AttachedVolume>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AttachedVolume {
+    pub letter: String, // "F:"
+    pub offset: u64,    // byte offset of its partition in the image, as in a partition source
+}
+
+// ── Source detection ─────────────────────────────────────────────────
+
+pub fn image_format(source: &str) -> Option<ImageFormat> {
+    if drive_letter(source).is_some() {
+        return None;
+    }
+    let (path, _) = split_partition_source(source);
+    let extension = Path::new(path).extension()?.to_string_lossy().to_lowercase();
+    match extension.as_str() {
+        "vhd" => Some(ImageFormat::Vhd),
+        "vhdx" => Some(ImageFormat::Vhdx),
+        "e01" => Some(ImageFormat::Ewf),
+        e if RAW_EXTENSIONS.contains(&e) => Some(ImageFormat::Raw),
+        _ => None,
+    }
+}
+
+pub fn inspect_source(source: &str) -> Result<SourceInfo, String> {
+    if let Some(letter) = drive_letter(source) {
+        return Ok(SourceInfo {
+            source: source.to_string(),
+            format: None,
+            winfr: true,
+            volumes: Vec::new(),
+            message: format!("Drive {}: is scanned by winfr directly.", letter),
+        });
+    }
+    refuse_ex01(source)?;
+    let format = image_format(source).ok_or_else(|| {
+        format!("'{}' is neither a drive letter nor a disk image (.img, .dd, .raw, .001, .vhd, .vhdx, .E01).", source)
+    })?;
+    let (path, _) = split_partition_source(source);
+    if !Path::new(path).is_file() {
+        return Err(format!("Image {} was not found.", path));
+    }
+
+    let (winfr, volumes, message) = match format {
+        ImageFormat::Raw | ImageFormat::Ewf => {
+            let volumes = native_volumes(source)?;
+            let kind = if format == ImageFormat::Raw { "Raw images" } else { "Evidence files" };
+            let message = if volumes.is_empty() {
+                "No NTFS, FAT or exFAT volume was found in the image.".to_string()
+            } else {
+                format!("{} cannot be attached for winfr; recover from their volumes with the native scanners.", kind)
+            };
+            (false, volumes, message)
+        }
+        ImageFormat::Vhd | ImageFormat::Vhdx => {
+            // The native scanners read the container directly; Windows may still attach an
+            // image they refuse, such as a VHDX with a log to replay
+            let (volumes, message) = match native_volumes(source) {
+                Ok(volumes) => (volumes, "The image is attached read-only while winfr scans it.".to_string()),
+                Err(e) => (Vec::new(), format!("The image is attached read-only while winfr scans it. {}", e)),
+            };
+            (cfg!(windows), volumes, message)
+        }
+    };
+    Ok(SourceInfo { source: source.to_string(), format: Some(format), winfr, volumes, message })
+}
+
+// Ex01 (EnCase 7+) evidence files use a container the native scanners cannot read yet
+fn refuse_ex01(source: &str) -> Result<(), String> {
+    let (path, _) = split_partition_source(source);
+    match Path::new(path).extension() {
+        Some(e) if e.eq_ignore_ascii_case("ex01") => Err(format!(
+            "{} is an Ex01 evidence file, which cannot be read yet. Export it as E01 or a raw image.",
+            path
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Volumes in the image with a file system the native scanners read
+fn native_volumes(source: &str) -> Result<Vec<Partition>, String> {
+    let layout = partitions::list_partitions(source, true)?;
+    Ok(layout.partitions.into_iter().filter(|p| p.filesystem.is_some()).collect())
+}
+
+// ── Attaching VHD/VHDX images ────────────────────────────────────────
+
+// Resolves the drive letter winfr should scan for `source`, attaching VHD and VHDX images
+// read-only first. A partition source ("<image>|<offset>") picks the volume at that offset.
+// The attached image is returned so it can be detached after the run.
+pub fn winfr_source(source: &str) -> Result<(String, Option<MountedImage>), String> {
+    refuse_ex01(source)?;
+    match image_format(source) {
+        None => Ok((source.to_string(), None)),
+        Some(ImageFormat::Raw) => Err(format!(
+            "{} is a raw image, which winfr cannot scan. Recover from it with the native scanners.",
+            source
+        )),
+        Some(ImageFormat::Ewf) => Err(format!(
+            "{} is an evidence file, which winfr cannot scan. Recover from it with the native scanners.",
+            source
+        )),
+        Some(_) => {
+            let (image, offset) = split_partition_source(source);
+            let partition = image != source;
+            let mounted = mount_read_only(image)?;
+            let volumes: Vec<&AttachedVolume> =
+                mounted.volumes.iter().filter(|v| !partition || v.offset == offset).collect();
+            let letter = match volumes.as_slice() {
+                [volume] => Ok(volume.letter.clone()),
+                [] if partition => Err(format!(
+                    "{} has no volume at offset {} that Windows assigned a drive letter to.",
+                    image, offset
+                )),
+                [] => Err(format!("{} has no volume that Windows can assign a drive letter to.", image)),
+                volumes => {
+                    let list = volumes.iter().map(|v| v.letter.as_str()).collect::<Vec<_>>().join(", ");
+                    Err(format!(
+                        "{} contains several volumes ({}). Choose one of its volumes as the source.",
+                        image, list
+                    ))
+                }
+            };
+            match letter {
+                Ok(letter) => Ok((letter, Some(mounted))),
+                Err(e) => {
+                    let _ = dismount(image);
+                    Err(e)
+                }
+            }
+        }
+    }
+}
+
+// Attaches the whole image, also when given a partition source of it
+pub fn mount_read_only(image: &str) -> Result<MountedImage, String> {
+    if !cfg!(windows) {
+        return Err("Attaching disk images is only supported on Windows.".to_string());
+    }
+    let (image, _) = split_partition_source(image);
+    let path = image_path(image)?;
+    let script = format!(
+        "$ErrorActionPreference = 'Stop'; \
+         $image = Mount-DiskImage -ImagePath '{}' -Access ReadOnly -PassThru; \
+         $partitions = $image | Get-Disk | Get-Partition | Where-Object DriveLetter; \
+         ($partitions | ForEach-Object {{ \"$($_.DriveLetter):|$($_.Offset)\" }}) -join ','",
+        path
+    );
+    let stdout = powershell(&script).map_err(|e| format!("Failed to mount {}: {}", image, e))?;
+    let volumes = stdout
+        .trim()
+        .split(',')
+        .filter_map(|v| v.split_once('|'))
+        .filter_map(|(letter, offset)| {
+            Some(AttachedVolume { letter: letter.to_string(), offset: offset.parse().ok()? })
+        })
+        .collect();
+    Ok(MountedImage { image: image.to_string(), volumes })
+}
+
+pub fn dismount(image: &str) -> Result<(), String> {
+    let (image, _) = split_partition_source(image);
+    let path = image_path(image)?;
+    powershell(&format!("$ErrorActionPreference = 'Stop'; Dismount-DiskImage -ImagePath '{}' | Out-Null", path))
+        .map(|_| ())
+        .map_err(|e| format!("Failed to dismount {}: {}", image, e))
+}
+
+// Absolute path, quoted for a single-quoted PowerShell string
+fn image_path(image: &str) -> Result<String, String> {
+    let path = std::path::absolute(image).map_err(|e| format!("Invalid image path {}: {}", image, e))?;
+    Ok(path.to_string_lossy().replace('\'', "''"))
+}
+
+fn powershell(script: &str) -> Result<String, String> {
+    let output = hidden_command("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
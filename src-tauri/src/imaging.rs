@@ -0,0 +1,650 @@
+use crate::audit;
+use crate::blockdev::BlockDevice;
+use crate::events::{EventSink, LogSource, RecoveryEvent, RecoveryStatus, TauriSink};
+use crate::report::format_size;
+use crate::safety::{self, ensure_target_outside, SourceIntegrity};
+use crate::volume::Volume;
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+// ── Types ────────────────────────────────────────────────────────────
+//
+// Copies a disk or volume to an image file the way GNU ddrescue does: a fast first pass
+// reads large chunks and skips the ones that fail, later passes close in on the bad areas
+// sector by sector. A ddrescue-compatible map file next to the image records which
+// ranges were read, so an interrupted or partly failed run can be resumed and retried.
+
+// Reads from raw handles are 4 KiB-aligned anyway (see blockdev.rs), so smaller sectors
+// would only read the same block again
+const DEFAULT_SECTOR_SIZE: u64 = 4096;
+const DEFAULT_CHUNK_SIZE: u64 = 64 * 1024;
+const DEFAULT_RETRIES: u32 = 2;
+// Reads between progress events
+const PROGRESS_READS: u32 = 64;
+// Time between flushing the image and saving the map, as in ddrescue; a cancel, an error
+// or the end of the run saves it at once
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+
+static IMAGING_ACTIVE: Mutex<bool> = Mutex::new(false);
+static IMAGING_CANCELLED: Mutex<bool> = Mutex::new(false);
+
+#[derive(Debug, Clone)]
+pub struct ImagingOptions {
+    pub chunk_size: u64,  // bytes per read in the first pass
+    pub sector_size: u64, // smallest read when closing in on bad areas
+    pub retries: u32,     // extra passes over sectors that still failed
+    // Overrides the size reported by the source, for devices that report none
+    pub size: Option<u64>,
+}
+
+impl Default for ImagingOptions {
+    fn default() -> Self {
+        ImagingOptions {
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            sector_size: DEFAULT_SECTOR_SIZE,
+            retries: DEFAULT_RETRIES,
+            size: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImagingSummary {
+    pub source: String,
+    pub output: String,
+    pub map: String,
+    pub size: u64,
+    pub rescued: u64,
+    #[serde(rename = "badBytes")]
+    pub bad_bytes: u64,
+    #[serde(rename = "badAreas")]
+    pub bad_areas: usize,
+    // Not read yet; only non-zero after a cancel
+    pub pending: u64,
+    pub resumed: bool,
+    pub aborted: bool,
+    // Hashes of an image source before and after imaging it
+    #[serde(rename = "sourceIntegrity")]
+    pub source_integrity: Option<SourceIntegrity>,
+}
+
+// ── Map file ─────────────────────────────────────────────────────────
+
+// Block states, written with ddrescue's symbols
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockStatus {
+    NonTried,   // '?'
+    NonTrimmed, // '*': a chunk read failed, its edges are not read yet
+    NonScraped, // '/': the inside of a trimmed area
+    BadSector,  // '-'
+    Finished,   // '+'
+}
+
+impl BlockStatus {
+    fn symbol(self) -> char {
+        match self {
+            BlockStatus::NonTried => '?',
+            BlockStatus::NonTrimmed => '*',
+            BlockStatus::NonScraped => '/',
+            BlockStatus::BadSector => '-',
+            BlockStatus::Finished => '+',
+        }
+    }
+
+    fn parse(symbol: &str) -> Option<BlockStatus> {
+        match symbol {
+            "?" => Some(BlockStatus::NonTried),
+            "*" => Some(BlockStatus::NonTrimmed),
+            "/" => Some(BlockStatus::NonScraped),
+            "-" => Some(BlockStatus::BadSector),
+            "+" => Some(BlockStatus::Finished),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Block {
+    pos: u64,
+    size: u64,
+    status: BlockStatus,
+}
+
+// Contiguous blocks covering the whole source, plus where the last run stopped
+struct Map {
+    blocks: Vec<Block>,
+    current_pos: u64,
+    current_status: char, // '?' copying, '*' trimming, '/' scraping, '-' retrying, '+' done
+    current_pass: u32,
+}
+
+impl Map {
+    fn new(size: u64) -> Map {
+        Map {
+            blocks: vec![Block { pos: 0, size, status: BlockStatus::NonTried }],
+            current_pos: 0,
+            current_status: '?',
+            current_pass: 1,
+        }
+    }
+
+    fn load(path: &str, size: u64) -> Result<Map, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let invalid = |line: &str| format!("{} is not a valid map file (at \"{}\").", path, line);
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#'));
+
+        let status_line = lines.next().ok_or_else(|| invalid(""))?;
+        let fields: Vec<&str> = status_line.split_whitespace().collect();
+        let (current_pos, current_status) = match fields.as_slice() {
+            [pos, status, ..] if status.len() == 1 => {
+                (parse_number(pos).ok_or_else(|| invalid(status_line))?, status.chars().next().unwrap())
+            }
+            _ => return Err(invalid(status_line)),
+        };
+        let current_pass = fields.get(2).and_then(|p| p.parse().ok()).unwrap_or(1);
+
+        let mut blocks: Vec<Block> = Vec::new();
+        for line in lines {
+            let block = match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [pos, size, status] => Block {
+                    pos: parse_number(pos).ok_or_else(|| invalid(line))?,
+                    size: parse_number(size).ok_or_else(|| invalid(line))?,
+                    status: BlockStatus::parse(status).ok_or_else(|| invalid(line))?,
+                },
+                _ => return Err(invalid(line)),
+            };
+            let expected = blocks.last().map_or(0, |b| b.pos + b.size);
+            if block.pos != expected {
+                return Err(invalid(line));
+            }
+            blocks.push(block);
+        }
+
+        let mapped = blocks.last().map_or(0, |b| b.pos + b.size);
+        if mapped != size {
+            return Err(format!("{} was written for a source of {} bytes, not {}.", path, mapped, size));
+        }
+        Ok(Map { blocks, current_pos, current_status, current_pass })
+    }
+
+    // Written to a temporary file first so a crash never leaves half a map behind
+    fn save(&self, path: &str, source: &str) -> Result<(), String> {
+        let mut text = String::from("# Mapfile. Created by Winfr Pro\n");
+        text.push_str(&format!("# Source: {}\n", source));
+        text.push_str("# current_pos  current_status  current_pass\n");
+        text.push_str(&format!(
+            "{:#010X}     {}               {}\n",
+            self.current_pos, self.current_status, self.current_pass
+        ));
+        text.push_str("#      pos        size  status\n");
+        for b in &self.blocks {
+            text.push_str(&format!("{:#010X}  {:#010X}  {}\n", b.pos, b.size, b.status.symbol()));
+        }
+
+        let temp = format!("{}.tmp", path);
+        fs::write(&temp, text).map_err(|e| format!("Failed to write {}: {}", temp, e))?;
+        fs::rename(&temp, path).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+
+    fn set(&mut self, pos: u64, size: u64, status: BlockStatus) {
+        let end = pos + size;
+        let mut blocks: Vec<Block> = Vec::with_capacity(self.blocks.len() + 2);
+        let mut inserted = false;
+        for b in &self.blocks {
+            let b_end = b.pos + b.size;
+            if b_end <= pos || b.pos >= end {
+                blocks.push(*b);
+                continue;
+            }
+            if b.pos < pos {
+                blocks.push(Block { pos: b.pos, size: pos - b.pos, status: b.status });
+            }
+            if !inserted {
+                blocks.push(Block { pos, size, status });
+                inserted = true;
+            }
+            if b_end > end {
+                blocks.push(Block { pos: end, size: b_end - end, status: b.status });
+            }
+        }
+
+        // Neighbours with the same status merge, keeping the map short
+        self.blocks.clear();
+        for b in blocks {
+            match self.blocks.last_mut() {
+                Some(last) if last.status == b.status => last.size += b.size,
+                _ => self.blocks.push(b),
+            }
+        }
+    }
+
+    fn regions(&self, status: BlockStatus) -> Vec<(u64, u64)> {
+        self.blocks.iter().filter(|b| b.status == status).map(|b| (b.pos, b.size)).collect()
+    }
+
+    fn total(&self, status: BlockStatus) -> u64 {
+        self.blocks.iter().filter(|b| b.status == status).map(|b| b.size).sum()
+    }
+}
+
+fn parse_number(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+pub fn map_path(output: &str) -> String {
+    format!("{}.map", output)
+}
+
+// ── Imaging passes ───────────────────────────────────────────────────
+
+struct Imager<'a> {
+    reader: &'a mut dyn BlockDevice,
+    sink: &'a dyn EventSink,
+    source: &'a str,
+    options: &'a ImagingOptions,
+    cancelled: &'a dyn Fn() -> bool,
+    image: File,
+    map: Map,
+    map_path: String,
+    size: u64,
+    pass_name: &'static str,
+    reads: u32,
+    saved_at: Instant,
+}
+
+impl Imager<'_> {
+    // Reads one range into the image; on failure the range is marked `failed`
+    fn read(&mut self, pos: u64, len: u64, failed: BlockStatus) -> Result<bool, String> {
+        self.map.current_pos = pos;
+        let ok = match self.reader.read_at(pos, len as usize) {
+            Ok(data) => {
+                self.image
+                    .seek(SeekFrom::Start(pos))
+                    .and_then(|_| self.image.write_all(&data))
+                    .map_err(|e| format!("Failed to write the image at offset {}: {}", pos, e))?;
+                self.map.set(pos, len, BlockStatus::Finished);
+                true
+            }
+            Err(_) => {
+                self.map.set(pos, len, failed);
+                false
+            }
+        };
+
+        self.reads += 1;
+        if self.reads >= PROGRESS_READS {
+            self.reads = 0;
+            if self.saved_at.elapsed() >= CHECKPOINT_INTERVAL {
+                self.checkpoint()?;
+            } else {
+                self.report_progress();
+            }
+        }
+        Ok(ok)
+    }
+
+    // The image is flushed before the map so the map never claims data that is not on disk
+    fn checkpoint(&mut self) -> Result<(), String> {
+        self.image.sync_data().map_err(|e| format!("Failed to flush the image: {}", e))?;
+        self.map.save(&self.map_path, self.source)?;
+        self.saved_at = Instant::now();
+        self.report_progress();
+        Ok(())
+    }
+
+    fn report_progress(&self) {
+        let rescued = self.map.total(BlockStatus::Finished);
+        let bad = self.map.total(BlockStatus::BadSector);
+        self.sink.emit(RecoveryEvent::Progress {
+            percent: rescued as f64 * 100.0 / self.size as f64,
+            phase: RecoveryStatus::Recovering,
+            line: format!(
+                "Pass {} ({}): {} of {} rescued, {} bad",
+                self.map.current_pass,
+                self.pass_name,
+                format_size(rescued),
+                format_size(self.size),
+                format_size(bad)
+            ),
+        });
+    }
+
+    fn start_pass(&mut self, pass: u32, status: char, name: &'static str, todo: BlockStatus) -> bool {
+        let regions = self.map.regions(todo);
+        if regions.is_empty() {
+            return false;
+        }
+        self.map.current_pass = pass;
+        self.map.current_status = status;
+        self.pass_name = name;
+        let bytes: u64 = regions.iter().map(|(_, size)| size).sum();
+        log(self.sink, &format!("Pass {} ({}): {} in {} area(s)", pass, name, format_size(bytes), regions.len()));
+        true
+    }
+
+    // Sector-sized pieces of [pos, end); the last one may be short at the end of the source
+    fn sector_len(&self, at: u64, end: u64) -> u64 {
+        self.options.sector_size.min(end - at)
+    }
+
+    // Returns false when cancelled
+    fn run(&mut self) -> Result<bool, String> {
+        // Pass 1: large reads over everything not tried yet, skipping failed chunks
+        if self.start_pass(1, '?', "copy", BlockStatus::NonTried) {
+            for (pos, size) in self.map.regions(BlockStatus::NonTried) {
+                let mut at = pos;
+                while at < pos + size {
+                    if (self.cancelled)() {
+                        return Ok(false);
+                    }
+                    let len = self.options.chunk_size.min(pos + size - at);
+                    self.read(at, len, BlockStatus::NonTrimmed)?;
+                    at += len;
+                }
+            }
+        }
+
+        // Pass 2: read failed chunks sector by sector from both ends up to the first error
+        if self.start_pass(2, '*', "trim", BlockStatus::NonTrimmed) {
+            for (pos, size) in self.map.regions(BlockStatus::NonTrimmed) {
+                let end = pos + size;
+                let mut start = pos;
+                while start < end {
+                    if (self.cancelled)() {
+                        return Ok(false);
+                    }
+                    let len = self.sector_len(start, end);
+                    let ok = self.read(start, len, BlockStatus::BadSector)?;
+                    start += len;
+                    if !ok {
+                        break;
+                    }
+                }
+                let mut stop = end;
+                while stop > start {
+                    if (self.cancelled)() {
+                        return Ok(false);
+                    }
+                    // Backwards from the last sector boundary, which may leave a short tail
+                    let tail = stop % self.options.sector_size;
+                    let len = if tail > 0 { tail } else { self.options.sector_size }.min(stop - start);
+                    let ok = self.read(stop - len, len, BlockStatus::BadSector)?;
+                    stop -= len;
+                    if !ok {
+                        break;
+                    }
+                }
+                if stop > start {
+                    self.map.set(start, stop - start, BlockStatus::NonScraped);
+                }
+            }
+        }
+
+        // Pass 3: every sector between the trimmed edges
+        if self.start_pass(3, '/', "scrape", BlockStatus::NonScraped) && !self.read_sectors(BlockStatus::NonScraped)? {
+            return Ok(false);
+        }
+
+        // Passes 4 and up: bad sectors once more; weak sectors often read on a later try
+        for retry in 1..=self.options.retries {
+            if !self.start_pass(3 + retry, '-', "retry", BlockStatus::BadSector) {
+                break;
+            }
+            if !self.read_sectors(BlockStatus::BadSector)? {
+                return Ok(false);
+            }
+        }
+
+        self.map.current_status = '+';
+        Ok(true)
+    }
+
+    fn read_sectors(&mut self, todo: BlockStatus) -> Result<bool, String> {
+        for (pos, size) in self.map.regions(todo) {
+            let mut at = pos;
+            while at < pos + size {
+                if (self.cancelled)() {
+                    return Ok(false);
+                }
+                let len = self.sector_len(at, pos + size);
+                self.read(at, len, BlockStatus::BadSector)?;
+                at += len;
+            }
+        }
+        Ok(true)
+    }
+}
+
+// ── Entry points ─────────────────────────────────────────────────────
+
+pub fn start_imaging(app: AppHandle, source: String, output: String, options: ImagingOptions) -> Result<String, String> {
+    start_imaging_with(Arc::new(TauriSink::with_prefix(app, "imaging")), source, output, options)
+}
+
+// Runs `image_from` on a worker thread; the outcome is reported through `sink`
+pub fn start_imaging_with(
+    sink: Arc<dyn EventSink>,
+    source: String,
+    output: String,
+    options: ImagingOptions,
+) -> Result<String, String> {
+    {
+        let mut active = IMAGING_ACTIVE.lock().map_err(|e| e.to_string())?;
+        if *active {
+            return Err("An imaging operation is already in progress.".to_string());
+        }
+        *active = true;
+    }
+    *IMAGING_CANCELLED.lock().unwrap_or_else(|e| e.into_inner()) = false;
+
+    let mut volume = match Volume::open(&source) {
+        Ok(volume) => volume,
+        Err(e) => {
+            *IMAGING_ACTIVE.lock().unwrap_or_else(|e| e.into_inner()) = false;
+            return Err(e);
+        }
+    };
+
+    thread::spawn(move || {
+        // An image source is hashed before and after, like every other session
+        let sink = sink.as_ref();
+        let integrity = match safety::hash_source_image(&source, |progress| sink.emit(progress.event())) {
+            Ok(integrity) => integrity,
+            Err(e) => {
+                *IMAGING_ACTIVE.lock().unwrap_or_else(|e| e.into_inner()) = false;
+                sink.emit(RecoveryEvent::Status { status: RecoveryStatus::Error, progress: None });
+                log(sink, &format!("Imaging failed: {}", e));
+                return;
+            }
+        };
+        let result = image_with(&mut volume, &source, &output, &options, sink, &cancel_requested);
+        let integrity = integrity.map(|mut integrity| {
+            safety::verify_source_image(&mut integrity, |progress| sink.emit(progress.event()));
+            safety::report_integrity(sink, &integrity);
+            integrity
+        });
+        *IMAGING_ACTIVE.lock().unwrap_or_else(|e| e.into_inner()) = false;
+        if let Ok(mut summary) = result {
+            summary.source_integrity = integrity;
+            let recorded = audit::record_app(
+                "disk-imaged",
+                serde_json::json!({
+                    "source": summary.source,
+                    "output": summary.output,
+                    "size": summary.size,
+                    "rescued": summary.rescued,
+                    "badBytes": summary.bad_bytes,
+                    "resumed": summary.resumed,
+                    "aborted": summary.aborted,
+                    "sourceIntegrity": summary.source_integrity,
+                }),
+            );
+            if let Err(e) = recorded {
+                sink.emit(RecoveryEvent::Warning { message: format!("Not recorded in the audit log: {}", e) });
+            }
+        }
+    });
+    Ok("Imaging started".to_string())
+}
+
+pub fn cancel_imaging() -> Result<String, String> {
+    if !*IMAGING_ACTIVE.lock().map_err(|e| e.to_string())? {
+        return Err("No imaging operation is running.".to_string());
+    }
+    // Picked up between two reads; the map is saved so the run can be resumed
+    *IMAGING_CANCELLED.lock().unwrap_or_else(|e| e.into_inner()) = true;
+    Ok("Imaging cancelled".to_string())
+}
+
+fn cancel_requested() -> bool {
+    *IMAGING_CANCELLED.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Images `reader` to `output`. If `output`'s map file exists the run resumes from it and
+// only reads what is still missing; otherwise `output` must not exist yet.
+pub fn image_from(
+    reader: &mut dyn BlockDevice,
+    source: &str,
+    output: &str,
+    options: &ImagingOptions,
+    sink: &dyn EventSink,
+) -> Result<ImagingSummary, String> {
+    image_with(reader, source, output, options, sink, &|| false)
+}
+
+fn image_with(
+    reader: &mut dyn BlockDevice,
+    source: &str,
+    output: &str,
+    options: &ImagingOptions,
+    sink: &dyn EventSink,
+    cancelled: &dyn Fn() -> bool,
+) -> Result<ImagingSummary, String> {
+    let result = run_imaging(reader, source, output, options, sink, cancelled);
+    if let Err(e) = &result {
+        sink.emit(RecoveryEvent::Status { status: RecoveryStatus::Error, progress: None });
+        log(sink, &format!("Imaging failed: {}", e));
+    }
+    result
+}
+
+fn run_imaging(
+    reader: &mut dyn BlockDevice,
+    source: &str,
+    output: &str,
+    options: &ImagingOptions,
+    sink: &dyn EventSink,
+    cancelled: &dyn Fn() -> bool,
+) -> Result<ImagingSummary, String> {
+    if output.starts_with("\\\\.\\") || output.starts_with("\\\\?\\") || output == source {
+        return Err("The image must be saved to a file, never to a disk or the source itself.".to_string());
+    }
+    ensure_target_outside(source, output)?;
+    let sizes_valid = options.sector_size.is_power_of_two()
+        && options.chunk_size.is_power_of_two()
+        && options.chunk_size >= options.sector_size;
+    if !sizes_valid {
+        return Err("Chunk and sector sizes must be powers of two, with chunks no smaller than sectors.".to_string());
+    }
+    let size = options
+        .size
+        .or_else(|| reader.size())
+        .filter(|size| *size > 0)
+        .ok_or_else(|| format!("The size of {} is unknown; enter it to image the device.", source))?;
+
+    let map_path = map_path(output);
+    let resumed = Path::new(&map_path).exists();
+    let (image, map) = if resumed {
+        let map = Map::load(&map_path, size)?;
+        let image = OpenOptions::new()
+            .write(true)
+            .open(output)
+            .map_err(|e| format!("Failed to open {} to resume: {}", output, e))?;
+        (image, map)
+    } else {
+        let image = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(output)
+            .map_err(|e| format!("Failed to create {}: {}", output, e))?;
+        (image, Map::new(size))
+    };
+    // Unread areas stay zero in the image
+    if image.metadata().map(|m| m.len()).unwrap_or(0) < size {
+        image.set_len(size).map_err(|e| format!("Failed to size {}: {}", output, e))?;
+    }
+
+    sink.emit(RecoveryEvent::Status { status: RecoveryStatus::Recovering, progress: Some(0.0) });
+    log(sink, &format!("Imaging {} ({}) to {}", source, format_size(size), output));
+    if resumed {
+        let rescued = map.total(BlockStatus::Finished);
+        log(sink, &format!("Resuming from {}: {} already rescued", map_path, format_size(rescued)));
+    }
+
+    let mut imager = Imager {
+        reader,
+        sink,
+        source,
+        options,
+        cancelled,
+        image,
+        map,
+        map_path: map_path.clone(),
+        size,
+        pass_name: "copy",
+        reads: 0,
+        saved_at: Instant::now(),
+    };
+    let finished = imager.run();
+    // Saved even after a write error, so everything read so far is kept
+    let saved = imager.checkpoint();
+    let finished = finished?;
+    saved?;
+
+    let map = &imager.map;
+    let bad_areas = map.regions(BlockStatus::BadSector).len();
+    let summary = ImagingSummary {
+        source: source.to_string(),
+        output: output.to_string(),
+        map: map_path,
+        size,
+        rescued: map.total(BlockStatus::Finished),
+        bad_bytes: map.total(BlockStatus::BadSector),
+        bad_areas,
+        pending: size - map.total(BlockStatus::Finished) - map.total(BlockStatus::BadSector),
+        resumed,
+        aborted: !finished,
+        source_integrity: None,
+    };
+
+    if !finished {
+        sink.emit(RecoveryEvent::Status { status: RecoveryStatus::Aborted, progress: None });
+        log(sink, "! IMAGING ABORTED BY USER ! Start it again with the same image file to resume.");
+        return Ok(summary);
+    }
+    if summary.bad_bytes > 0 {
+        sink.emit(RecoveryEvent::Warning {
+            message: format!(
+                "{} in {} area(s) could not be read and are zero in the image. Run imaging again to retry them.",
+                format_size(summary.bad_bytes),
+                bad_areas
+            ),
+        });
+    }
+    sink.emit(RecoveryEvent::Status { status: RecoveryStatus::Completed, progress: Some(100.0) });
+    log(sink, &format!("✓ Imaging completed: {} rescued.", format_size(summary.rescued)));
+    Ok(summary)
+}
+
+fn log(sink: &dyn EventSink, message: &str) {
+    sink.emit(RecoveryEvent::Log { source: LogSource::App, message: message.to_string() });
+}
@@ -0,0 +1,403 @@
+use crate::appdata;
+use crate::hashing::{self, to_hex, FileHashes};
+use crate::hashsets::{self, KnownStatus};
+use crate::integrity;
+use crate::recovery::{categorize_file, find_latest_recovery_dir, RecoveredFile};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+use walkdir::{DirEntry, WalkDir};
+
+// ── Types ────────────────────────────────────────────────────────────
+
+// Indexes live in the app data folder, one per recovery folder, never among the evidence
+const INDEX_DIR_NAME: &str = "indexes";
+// Bookkeeping files the app writes next to recovered data (index, session, ...)
+pub const APP_FILE_PREFIX: &str = ".winfrpro_";
+// 2: entries carry the header integrity check; older indexes are rebuilt so it is filled in
+const INDEX_VERSION: u32 = 2;
+const BATCH_SIZE: usize = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveryIndex {
+    pub version: u32,
+    pub root: String,
+    pub next_id: u64,
+    pub files: Vec<RecoveredFile>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PageRequest {
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default)]
+    pub limit: usize, // 0 = everything from `offset` on
+    #[serde(rename = "sortKey")]
+    pub sort_key: Option<String>, // "name", "size", "category", "path", "modified"
+    #[serde(default)]
+    pub descending: bool,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    #[serde(rename = "minSize")]
+    pub min_size: Option<u64>,
+    #[serde(rename = "maxSize")]
+    pub max_size: Option<u64>,
+    pub name: Option<String>, // case-insensitive substring
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Page {
+    pub total: usize,
+    pub offset: usize,
+    pub files: Vec<RecoveredFile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RefreshSummary {
+    pub root: String,
+    pub total: usize,
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+// In-memory cache keyed by recovery directory, backed by the on-disk index file. Shared, so
+// a page query reads the cached index instead of copying it.
+static INDEXES: Mutex<Option<HashMap<PathBuf, Arc<RecoveryIndex>>>> = Mutex::new(None);
+// Held from reading an index to caching and saving the changed copy, so a refresh on the
+// indexing thread and a hashing pass cannot overwrite each other's changes
+static INDEX_WRITES: Mutex<()> = Mutex::new(());
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+// ── Location ─────────────────────────────────────────────────────────
+
+pub fn resolve_recovery_dir(destination: &str) -> Result<PathBuf, String> {
+    let dest_path = Path::new(destination);
+    if !dest_path.exists() {
+        return Err(format!("Destination path does not exist: {}", destination));
+    }
+
+    // winfr creates a subfolder like "Recovery_YYYYMMDD_HHMMSS" in the destination
+    Ok(find_latest_recovery_dir(dest_path).unwrap_or_else(|| dest_path.to_path_buf()))
+}
+
+// Named after a hash of the recovery folder's full path
+fn index_file_for(recovery_dir: &Path) -> Result<PathBuf, String> {
+    let folder = fs::canonicalize(recovery_dir).unwrap_or_else(|_| recovery_dir.to_path_buf());
+    let digest = to_hex(&Sha256::digest(folder.to_string_lossy().as_bytes()));
+    appdata::data_path(&format!("{}/{}.json", INDEX_DIR_NAME, &digest[..32]))
+}
+
+// `RecoveredFile.path` is stored Windows-style ("\Users\a.txt") relative to the recovery folder
+pub fn absolute_path(recovery_dir: &Path, file: &RecoveredFile) -> PathBuf {
+    file.path
+        .split('\\')
+        .filter(|part| !part.is_empty())
+        .fold(recovery_dir.to_path_buf(), |acc, part| acc.join(part))
+}
+
+// ── Refresh (incremental) ────────────────────────────────────────────
+
+// Walks the recovery folder, reusing entries whose size and modification time are
+// unchanged so cached hashes and ids survive. New or changed entries are streamed to
+// `on_batch` as they are discovered.
+pub fn refresh_index<F>(destination: &str, on_batch: F) -> Result<RefreshSummary, String>
+where
+    F: FnMut(&[RecoveredFile]),
+{
+    let recovery_dir = resolve_recovery_dir(destination)?;
+    refresh_dir(&recovery_dir, on_batch)
+}
+
+fn refresh_dir<F>(recovery_dir: &Path, mut on_batch: F) -> Result<RefreshSummary, String>
+where
+    F: FnMut(&[RecoveredFile]),
+{
+    let _writing = INDEX_WRITES.lock().unwrap_or_else(|e| e.into_inner());
+    let mut index = load_index(recovery_dir);
+
+    let mut previous: HashMap<String, RecoveredFile> = index
+        .files
+        .drain(..)
+        .map(|f| (f.path.clone(), f))
+        .collect();
+
+    let mut files: Vec<RecoveredFile> = Vec::with_capacity(previous.len());
+    let mut batch: Vec<RecoveredFile> = Vec::with_capacity(BATCH_SIZE);
+    let (mut added, mut updated) = (0usize, 0usize);
+
+    for (entry, relative_path) in recovered_entries(recovery_dir) {
+        let path = entry.path();
+        let metadata = entry.metadata().ok();
+        let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+        let modified = metadata
+            .as_ref()
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs());
+
+        match previous.remove(&relative_path) {
+            Some(existing) if existing.size == size && existing.modified == modified => {
+                files.push(existing);
+            }
+            existing => {
+                let id = match &existing {
+                    Some(old) => {
+                        updated += 1;
+                        old.id.clone()
+                    }
+                    None => {
+                        added += 1;
+                        index.next_id += 1;
+                        index.next_id.to_string()
+                    }
+                };
+                let ext = path
+                    .extension()
+                    .map(|e| e.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let file = RecoveredFile {
+                    id,
+                    name: path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    path: relative_path,
+                    size,
+                    category: categorize_file(&ext),
+                    modified,
+                    integrity: integrity::check_file(path, &ext, size),
+                    hashes: None,
+                    known_status: None,
+                };
+
+                batch.push(file.clone());
+                if batch.len() >= BATCH_SIZE {
+                    on_batch(&batch);
+                    batch.clear();
+                }
+                files.push(file);
+            }
+        }
+    }
+
+    if !batch.is_empty() {
+        on_batch(&batch);
+    }
+
+    let removed = previous.len();
+    files.sort_by(|a, b| a.category.cmp(&b.category).then(a.name.cmp(&b.name)));
+    index.files = files;
+
+    let summary = RefreshSummary {
+        root: recovery_dir.to_string_lossy().to_string(),
+        total: index.files.len(),
+        added,
+        updated,
+        removed,
+    };
+
+    if added > 0 || updated > 0 || removed > 0 {
+        save_index(recovery_dir, &index)?;
+    }
+    cache_index(recovery_dir.to_path_buf(), index);
+
+    Ok(summary)
+}
+
+// Files under `recovery_dir` with their path relative to it, leaving out the app's own
+// bookkeeping files
+pub fn recovered_entries(recovery_dir: &Path) -> impl Iterator<Item = (DirEntry, String)> + '_ {
+    WalkDir::new(recovery_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| !e.file_name().to_string_lossy().starts_with(APP_FILE_PREFIX))
+        .map(move |entry| {
+            let relative = entry.path().strip_prefix(recovery_dir).unwrap_or(entry.path());
+            let relative_path = format!("\\{}", relative.to_string_lossy().replace('/', "\\"));
+            (entry, relative_path)
+        })
+}
+
+// Hashes entries that are missing digests and persists them with the index. Hashing runs
+// without the write lock; the digests are then merged into the index as it is by then,
+// skipping entries a refresh changed in the meantime.
+pub fn ensure_hashes(destination: &str) -> Result<(), String> {
+    let recovery_dir = resolve_recovery_dir(destination)?;
+    let snapshot = get_index(&recovery_dir)?;
+    let hashed: HashMap<(String, u64, Option<u64>), FileHashes> = snapshot
+        .files
+        .iter()
+        .filter(|f| f.hashes.is_none())
+        .filter_map(|f| {
+            let hashes = hashing::hash_file(&absolute_path(&recovery_dir, f)).ok()?;
+            Some(((f.id.clone(), f.size, f.modified), hashes))
+        })
+        .collect();
+    if hashed.is_empty() {
+        return Ok(());
+    }
+
+    let _writing = INDEX_WRITES.lock().unwrap_or_else(|e| e.into_inner());
+    let mut index = current_index(&recovery_dir);
+    for file in index.files.iter_mut().filter(|f| f.hashes.is_none()) {
+        file.hashes = hashed.get(&(file.id.clone(), file.size, file.modified)).cloned();
+    }
+    save_index(&recovery_dir, &index)?;
+    cache_index(recovery_dir, index);
+    Ok(())
+}
+
+// Re-classifies entries against the loaded hash sets, hashing them first if needed
+pub fn apply_hash_sets(destination: &str) -> Result<(), String> {
+    let has_sets = hashsets::has_hash_sets();
+    if has_sets {
+        ensure_hashes(destination)?;
+    }
+
+    let recovery_dir = resolve_recovery_dir(destination)?;
+    get_index(&recovery_dir)?;
+    let _writing = INDEX_WRITES.lock().unwrap_or_else(|e| e.into_inner());
+    let mut index = current_index(&recovery_dir);
+    for file in index.files.iter_mut() {
+        file.known_status = if has_sets {
+            Some(file.hashes.as_ref().map(hashsets::classify).unwrap_or(KnownStatus::Unknown))
+        } else {
+            None
+        };
+    }
+    cache_index(recovery_dir, index);
+    Ok(())
+}
+
+// ── Queries ──────────────────────────────────────────────────────────
+
+pub fn all_files(destination: &str) -> Result<Vec<RecoveredFile>, String> {
+    Ok(shared_index(destination)?.files.clone())
+}
+
+// The cached index itself, for callers that only read it
+pub fn shared_index(destination: &str) -> Result<Arc<RecoveryIndex>, String> {
+    let recovery_dir = resolve_recovery_dir(destination)?;
+    get_index(&recovery_dir)
+}
+
+pub fn query_page(destination: &str, request: &PageRequest) -> Result<Page, String> {
+    query_page_where(destination, request, |_| true)
+}
+
+// Applies `predicate` on top of the page request's own filters before sorting and paging
+pub fn query_page_where<P>(destination: &str, request: &PageRequest, predicate: P) -> Result<Page, String>
+where
+    P: Fn(&RecoveredFile) -> bool,
+{
+    let index = shared_index(destination)?;
+    let name_filter = request.name.as_ref().map(|n| n.to_lowercase());
+
+    let mut matches: Vec<&RecoveredFile> = index
+        .files
+        .iter()
+        .filter(|f| predicate(f))
+        .filter(|f| request.categories.is_empty() || request.categories.contains(&f.category))
+        .filter(|f| request.min_size.is_none_or(|min| f.size >= min))
+        .filter(|f| request.max_size.is_none_or(|max| f.size <= max))
+        .filter(|f| {
+            name_filter
+                .as_ref()
+                .is_none_or(|n| f.name.to_lowercase().contains(n.as_str()))
+        })
+        .collect();
+
+    match request.sort_key.as_deref() {
+        Some("name") => matches.sort_by_key(|f| f.name.to_lowercase()),
+        Some("size") => matches.sort_by_key(|f| f.size),
+        Some("category") => matches.sort_by_key(|f| (f.category.to_lowercase(), f.name.to_lowercase())),
+        Some("path") => matches.sort_by(|a, b| a.path.cmp(&b.path)),
+        Some("modified") => matches.sort_by_key(|f| f.modified),
+        // Index order is already category, then name
+        _ => {}
+    }
+    if request.descending {
+        matches.reverse();
+    }
+
+    let total = matches.len();
+    let limit = if request.limit == 0 { total } else { request.limit };
+    let files = matches
+        .into_iter()
+        .skip(request.offset)
+        .take(limit)
+        .cloned()
+        .collect();
+
+    Ok(Page { total, offset: request.offset, files })
+}
+
+// ── Persistence ──────────────────────────────────────────────────────
+
+fn get_index(recovery_dir: &Path) -> Result<Arc<RecoveryIndex>, String> {
+    {
+        let cache = INDEXES.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(index) = cache.as_ref().and_then(|c| c.get(recovery_dir)) {
+            return Ok(Arc::clone(index));
+        }
+    }
+
+    // Nothing cached yet for this folder: bring the on-disk index up to date first
+    refresh_dir(recovery_dir, |_| {})?;
+    let cache = INDEXES.lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .as_ref()
+        .and_then(|c| c.get(recovery_dir))
+        .cloned()
+        .ok_or_else(|| "Failed to build recovered file index.".to_string())
+}
+
+// A copy of the cached index to change, or the saved one; callers hold INDEX_WRITES
+fn current_index(recovery_dir: &Path) -> RecoveryIndex {
+    let cache = INDEXES.lock().unwrap_or_else(|e| e.into_inner());
+    match cache.as_ref().and_then(|c| c.get(recovery_dir)) {
+        Some(index) => RecoveryIndex::clone(index),
+        None => {
+            drop(cache);
+            load_index(recovery_dir)
+        }
+    }
+}
+
+fn cache_index(recovery_dir: PathBuf, index: RecoveryIndex) {
+    let mut cache = INDEXES.lock().unwrap_or_else(|e| e.into_inner());
+    cache.get_or_insert_with(HashMap::new).insert(recovery_dir, Arc::new(index));
+}
+
+fn load_index(recovery_dir: &Path) -> RecoveryIndex {
+    let empty = RecoveryIndex {
+        version: INDEX_VERSION,
+        root: recovery_dir.to_string_lossy().to_string(),
+        next_id: 0,
+        files: Vec::new(),
+    };
+
+    index_file_for(recovery_dir)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str::<RecoveryIndex>(&text).ok())
+        .filter(|index| index.version == INDEX_VERSION && index.root == empty.root)
+        .unwrap_or(empty)
+}
+
+fn save_index(recovery_dir: &Path, index: &RecoveryIndex) -> Result<(), String> {
+    let path = index_file_for(recovery_dir)?;
+    // Unique per write, so an interrupted or concurrent save never renames another's file
+    let n = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let tmp = path.with_extension(format!("json.{}-{}.tmp", std::process::id(), n));
+    let json = serde_json::to_string(index).map_err(|e| e.to_string())?;
+    fs::write(&tmp, json).map_err(|e| format!("Failed to write index: {}", e))?;
+    fs::rename(&tmp, &path).map_err(|e| format!("Failed to write index: {}", e))
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// ── Types ────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IntegrityStatus {
+    Intact,     // header matches the file extension
+    Mismatch,   // header does not match the file extension
+    ZeroFilled, // first sector is all zeros, usually overwritten data
+    Empty,
+    #[default]
+    Unknown,    // no known signature for this extension
+}
+
+const HEADER_LEN: usize = 512;
+
+// ── Header check ─────────────────────────────────────────────────────
+
+pub fn check_file(path: &Path, ext: &str, size: u64) -> IntegrityStatus {
+    if size == 0 {
+        return IntegrityStatus::Empty;
+    }
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    let read = File::open(path).and_then(|f| f.take(HEADER_LEN as u64).read_to_end(&mut header));
+    if read.is_err() || header.is_empty() {
+        return IntegrityStatus::Unknown;
+    }
+
+    check_header(&header, ext)
+}
+
+pub fn check_header(header: &[u8], ext: &str) -> IntegrityStatus {
+    if header.iter().all(|b| *b == 0) {
+        return IntegrityStatus::ZeroFilled;
+    }
+
+    let starts = |magic: &[u8]| header.starts_with(magic);
+    let matches = match ext.to_lowercase().as_str() {
+        "jpg" | "jpeg" => starts(&[0xFF, 0xD8, 0xFF]),
+        "png" => starts(&[0x89, b'P', b'N', b'G']),
+        "gif" => starts(b"GIF8"),
+        "bmp" => starts(b"BM"),
+        "tif" | "tiff" => starts(b"II*\0") || starts(b"MM\0*"),
+        "pdf" => starts(b"%PDF"),
+        "rtf" => starts(b"{\\rtf"),
+        // OOXML, OpenDocument and plain archives are all ZIP containers
+        "zip" | "docx" | "xlsx" | "pptx" | "odt" | "ods" | "odp" => starts(b"PK\x03\x04") || starts(b"PK\x05\x06"),
+        // Legacy Office documents are OLE compound files
+        "doc" | "xls" | "ppt" => starts(&[0xD0, 0xCF, 0x11, 0xE0]),
+        "rar" => starts(b"Rar!"),
+        "7z" => starts(&[b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C]),
+        "gz" => starts(&[0x1F, 0x8B]),
+        "bz2" => starts(b"BZh"),
+        "xz" => starts(&[0xFD, b'7', b'z', b'X', b'Z', 0x00]),
+        "mp3" => starts(b"ID3") || (header.len() > 1 && header[0] == 0xFF && header[1] & 0xE0 == 0xE0),
+        "wav" | "avi" => starts(b"RIFF"),
+        "flac" => starts(b"fLaC"),
+        "ogg" | "opus" => starts(b"OggS"),
+        "mkv" | "webm" => starts(&[0x1A, 0x45, 0xDF, 0xA3]),
+        "wmv" | "wma" | "asf" => starts(&[0x30, 0x26, 0xB2, 0x75]),
+        "mp4" | "m4v" | "m4a" | "mov" | "3gp" | "heic" | "heif" => header.get(4..8) == Some(b"ftyp"),
+        "mpg" | "mpeg" => starts(&[0x00, 0x00, 0x01, 0xBA]) || starts(&[0x00, 0x00, 0x01, 0xB3]),
+        _ => return IntegrityStatus::Unknown,
+    };
+
+    if matches {
+        IntegrityStatus::Intact
+    } else {
+        IntegrityStatus::Mismatch
+    }
+}
@@ -1,26 +1,486 @@
-mod drives;
-mod recovery;
+pub mod appdata;
+pub mod audit;
+pub mod blockdev;
+pub mod dfxml;
+pub mod dialect;
+pub mod drives;
+pub mod engine;
+pub mod events;
+pub mod ewf;
+pub mod exfat;
+pub mod export;
+pub mod fat;
+pub mod filters;
+pub mod hashing;
+pub mod hashsets;
+pub mod images;
+pub mod imaging;
+pub mod index;
+mod integrity;
+pub mod ntfs;
+pub mod partition_search;
+pub mod partitions;
+pub mod preflight;
+pub mod query;
+pub mod recovery;
+pub mod report;
+pub mod safety;
+pub mod session;
+pub mod signatures;
+mod timestamps;
+pub mod tree;
+pub mod vhd;
+pub mod vhdx;
+pub mod volume;
 
+use hashsets::KnownStatus;
 use recovery::RecoveryConfig;
+use tauri::Emitter;
 
 #[tauri::command]
 fn list_drives() -> Vec<drives::DriveInfo> {
     drives::get_drives()
 }
 
+// Commands that may probe winfr (or attach an image) are async, so Tauri runs them off the
+// main thread and a slow first probe cannot freeze the window
 #[tauri::command]
-fn start_recovery(app: tauri::AppHandle, config: RecoveryConfig) -> Result<String, String> {
+async fn start_recovery(app: tauri::AppHandle, config: RecoveryConfig) -> Result<String, String> {
     recovery::start_recovery(app, config)
 }
 
+#[tauri::command]
+async fn run_preflight(config: RecoveryConfig) -> preflight::PreflightReport {
+    preflight::run_preflight(&config)
+}
+
+// Re-probes winfr so the UI reflects a newly installed or configured engine
+#[tauri::command]
+async fn get_engine_info(winfr_path: Option<String>) -> engine::EngineInfo {
+    engine::probe(winfr_path.as_deref())
+}
+
+#[tauri::command]
+async fn list_signature_groups(winfr_path: Option<String>) -> Vec<signatures::SignatureGroup> {
+    signatures::list_signature_groups(winfr_path.as_deref())
+}
+
+#[tauri::command]
+async fn preview_filters(config: RecoveryConfig) -> filters::FilterPreview {
+    filters::preview_filters(&config)
+}
+
 #[tauri::command]
 fn cancel_recovery() -> Result<String, String> {
     recovery::cancel_recovery()
 }
 
+// Hashes every recovered file while a hash set is loaded, so it runs off the main thread
+#[tauri::command]
+async fn scan_recovered_files(
+    destination: String,
+    known_filter: Option<Vec<KnownStatus>>,
+) -> Result<Vec<recovery::RecoveredFile>, String> {
+    recovery::scan_recovered_files(&destination, known_filter.as_deref())
+}
+
+#[tauri::command]
+fn index_recovered_files(app: tauri::AppHandle, destination: String) {
+    // Walk on a worker thread and stream batches so the UI can render while indexing
+    std::thread::spawn(move || {
+        let result = index::refresh_index(&destination, |batch| {
+            let _ = app.emit("recovered-files-batch", batch.to_vec());
+        });
+        match result {
+            Ok(summary) => {
+                let _ = app.emit("recovered-files-indexed", summary);
+            }
+            Err(e) => {
+                let _ = app.emit("recovered-files-error", e);
+            }
+        }
+    });
+}
+
+// Page, query and tree requests may build the index of a huge recovery folder first, so they
+// run off the main thread
+#[tauri::command]
+async fn get_recovered_files_page(destination: String, request: index::PageRequest) -> Result<index::Page, String> {
+    index::query_page(&destination, &request)
+}
+
+#[tauri::command]
+async fn query_recovered_files(
+    destination: String,
+    query: query::FileQuery,
+    page: Option<index::PageRequest>,
+) -> Result<index::Page, String> {
+    query::query_recovered_files(&destination, &query, &page.unwrap_or_default())
+}
+
+#[tauri::command]
+async fn get_recovered_tree(destination: String, path: Option<String>) -> Result<tree::TreeLevel, String> {
+    tree::get_recovered_tree(&destination, path.as_deref().unwrap_or("\\"))
+}
+
+#[tauri::command]
+fn export_recovered_files(
+    app: tauri::AppHandle,
+    destination: String,
+    ids: Vec<String>,
+    target: String,
+    options: Option<export::ExportOptions>,
+) -> Result<String, String> {
+    if ids.is_empty() {
+        return Err("No files selected for export.".to_string());
+    }
+
+    std::thread::spawn(move || {
+        let options = options.unwrap_or_default();
+        let result = export::export_recovered_files(&destination, &ids, &target, &options, |progress| {
+            let _ = app.emit("export-progress", progress.clone());
+        });
+        match result {
+            Ok(summary) => {
+                let details = serde_json::json!({
+                    "target": summary.target,
+                    "files": summary.exported,
+                    "failed": summary.failed.len(),
+                    "skipped": summary.skipped.len(),
+                    "mode": options.mode,
+                });
+                // Moving files out changes the recovery folder, so it gets a new manifest
+                let recorded = if options.mode == export::ExportMode::Move {
+                    audit::record_change(&destination, "files-exported", details)
+                } else {
+                    audit::record(&destination, "files-exported", details)
+                };
+                warn_unaudited(&app, recorded);
+                let _ = app.emit("export-complete", summary);
+            }
+            Err(e) => {
+                let _ = app.emit("export-error", e);
+            }
+        }
+    });
+
+    Ok("Export started".to_string())
+}
+
+// Hashes the whole recovery folder for the new manifest, so it runs off the main thread
+#[tauri::command]
+async fn delete_recovered_files(
+    app: tauri::AppHandle,
+    destination: String,
+    ids: Vec<String>,
+) -> Result<export::DeleteSummary, String> {
+    let summary = export::delete_recovered_files(&destination, &ids)?;
+    let recorded = audit::record_change(
+        &destination,
+        "files-deleted",
+        serde_json::json!({
+            "files": summary.deleted.iter().map(|f| serde_json::json!({
+                "id": f.id,
+                "path": f.path,
+                "size": f.size,
+                "sha256": f.hashes.as_ref().map(|h| h.sha256.clone()),
+            })).collect::<Vec<_>>(),
+            "failed": summary.failed,
+        }),
+    );
+    warn_unaudited(&app, recorded);
+    Ok(summary)
+}
+
+// The native scanners read the whole MFT, FAT or allocation bitmap, so listing and
+// recovering run on a worker thread like the partition search
+#[tauri::command]
+fn list_ntfs_deleted_files(app: tauri::AppHandle, source: String) -> Result<String, String> {
+    std::thread::spawn(move || match ntfs::list_deleted_files(&source) {
+        Ok(listing) => {
+            let _ = app.emit("ntfs-list-complete", listing);
+        }
+        Err(e) => {
+            let _ = app.emit("ntfs-list-error", e);
+        }
+    });
+    Ok("NTFS scan started".to_string())
+}
+
+#[tauri::command]
+fn recover_ntfs_files(
+    app: tauri::AppHandle,
+    source: String,
+    records: Vec<u64>,
+    target: String,
+) -> Result<String, String> {
+    if records.is_empty() {
+        return Err("No files selected for recovery.".to_string());
+    }
+    spawn_native_recovery(app, "ntfs-files-recovered", source, move |source, on_progress| {
+        ntfs::recover_files(source, &records, &target, on_progress)
+    });
+    Ok("Recovery started".to_string())
+}
+
 #[tauri::command]
-fn scan_recovered_files(destination: String) -> Result<Vec<recovery::RecoveredFile>, String> {
-    recovery::scan_recovered_files(&destination)
+fn list_fat_deleted_files(app: tauri::AppHandle, source: String) -> Result<String, String> {
+    std::thread::spawn(move || match fat::list_deleted_files(&source) {
+        Ok(listing) => {
+            let _ = app.emit("fat-list-complete", listing);
+        }
+        Err(e) => {
+            let _ = app.emit("fat-list-error", e);
+        }
+    });
+    Ok("FAT scan started".to_string())
+}
+
+#[tauri::command]
+fn recover_fat_files(app: tauri::AppHandle, source: String, ids: Vec<String>, target: String) -> Result<String, String> {
+    if ids.is_empty() {
+        return Err("No files selected for recovery.".to_string());
+    }
+    spawn_native_recovery(app, "fat-files-recovered", source, move |source, on_progress| {
+        fat::recover_files(source, &ids, &target, on_progress)
+    });
+    Ok("Recovery started".to_string())
+}
+
+#[tauri::command]
+fn list_exfat_deleted_files(app: tauri::AppHandle, source: String) -> Result<String, String> {
+    std::thread::spawn(move || match exfat::list_deleted_files(&source) {
+        Ok(listing) => {
+            let _ = app.emit("exfat-list-complete", listing);
+        }
+        Err(e) => {
+            let _ = app.emit("exfat-list-error", e);
+        }
+    });
+    Ok("exFAT scan started".to_string())
+}
+
+#[tauri::command]
+fn recover_exfat_files(
+    app: tauri::AppHandle,
+    source: String,
+    ids: Vec<String>,
+    target: String,
+) -> Result<String, String> {
+    if ids.is_empty() {
+        return Err("No files selected for recovery.".to_string());
+    }
+    spawn_native_recovery(app, "exfat-files-recovered", source, move |source, on_progress| {
+        exfat::recover_files(source, &ids, &target, on_progress)
+    });
+    Ok("Recovery started".to_string())
+}
+
+// Runs a native recovery, streaming per-file progress, and audits the result as `event`.
+// An image source is hashed before and after, with progress on `source-hash-progress`.
+fn spawn_native_recovery<F>(app: tauri::AppHandle, event: &'static str, source: String, recover: F)
+where
+    F: FnOnce(&str, &mut dyn FnMut(&volume::NativeProgress)) -> Result<volume::NativeRecoverySummary, String>
+        + Send
+        + 'static,
+{
+    std::thread::spawn(move || {
+        let result = safety::hashed_session(
+            &source,
+            |progress| {
+                let _ = app.emit("source-hash-progress", progress.clone());
+            },
+            || {
+                recover(&source, &mut |progress| {
+                    let _ = app.emit("native-recovery-progress", progress.clone());
+                })
+            },
+        );
+        match result {
+            Ok((mut summary, integrity)) => {
+                summary.source_integrity = integrity;
+                let recorded = audit::record_app(
+                    event,
+                    serde_json::json!({
+                        "source": source,
+                        "target": summary.target,
+                        "files": summary.recovered,
+                        "failed": summary.failed,
+                        "sourceIntegrity": summary.source_integrity,
+                    }),
+                );
+                warn_unaudited(&app, recorded);
+                let _ = app.emit("native-recovery-complete", summary);
+            }
+            Err(e) => {
+                let _ = app.emit("native-recovery-error", e);
+            }
+        }
+    });
+}
+
+// Looking for lost partitions reads a sector at every MiB of the disk, so it runs off the
+// main thread
+#[tauri::command]
+async fn list_partitions(source: String, find_lost: bool) -> Result<partitions::DiskLayout, String> {
+    partitions::list_partitions(&source, find_lost)
+}
+
+#[tauri::command]
+fn search_lost_partitions(app: tauri::AppHandle, source: String) -> Result<String, String> {
+    // Reads the whole disk, so it runs on a worker thread and streams progress. An image
+    // source is hashed before and after, like in every other session.
+    std::thread::spawn(move || {
+        let result = safety::hashed_session(
+            &source,
+            |progress| {
+                let _ = app.emit("source-hash-progress", progress.clone());
+            },
+            || {
+                partition_search::search_partitions(&source, |progress| {
+                    let _ = app.emit("partition-search-progress", progress.clone());
+                })
+            },
+        );
+        match result {
+            Ok((mut search, integrity)) => {
+                search.source_integrity = integrity;
+                let _ = app.emit("partition-search-complete", search);
+            }
+            Err(e) => {
+                let _ = app.emit("partition-search-error", e);
+            }
+        }
+    });
+    Ok("Partition search started".to_string())
+}
+
+// Inspecting runs the lost partition scan and mounting waits on PowerShell, so these run
+// off the main thread
+#[tauri::command]
+async fn inspect_source(source: String) -> Result<images::SourceInfo, String> {
+    images::inspect_source(&source)
+}
+
+// Attaches a VHD/VHDX read-only so its volumes can be picked as a source
+#[tauri::command]
+async fn mount_image(image: String) -> Result<images::MountedImage, String> {
+    images::mount_read_only(&image)
+}
+
+#[tauri::command]
+async fn dismount_image(image: String) -> Result<(), String> {
+    images::dismount(&image)
+}
+
+#[tauri::command]
+fn start_imaging(
+    app: tauri::AppHandle,
+    source: String,
+    output: String,
+    size: Option<u64>,
+    retries: Option<u32>,
+) -> Result<String, String> {
+    let mut options = imaging::ImagingOptions { size, ..Default::default() };
+    if let Some(retries) = retries {
+        options.retries = retries;
+    }
+    imaging::start_imaging(app, source, output, options)
+}
+
+#[tauri::command]
+fn cancel_imaging() -> Result<String, String> {
+    imaging::cancel_imaging()
+}
+
+#[tauri::command]
+fn verify_evidence(app: tauri::AppHandle, source: String) -> Result<String, String> {
+    // Hashes the whole acquired disk, so it runs on a worker thread like the partition search
+    std::thread::spawn(move || {
+        let result = ewf::verify_evidence(&source, |progress| {
+            let _ = app.emit("evidence-verify-progress", progress.clone());
+        });
+        match result {
+            Ok(verification) => {
+                let recorded = audit::record_app(
+                    "evidence-verified",
+                    serde_json::json!({
+                        "source": verification.source,
+                        "md5": verification.md5,
+                        "sha1": verification.sha1,
+                        "verified": verification.verified,
+                    }),
+                );
+                warn_unaudited(&app, recorded);
+                let _ = app.emit("evidence-verify-complete", verification);
+            }
+            Err(e) => {
+                let _ = app.emit("evidence-verify-error", e);
+            }
+        }
+    });
+    Ok("Evidence verification started".to_string())
+}
+
+#[tauri::command]
+fn write_partition_table(
+    app: tauri::AppHandle,
+    source: String,
+    partitions: Vec<partition_search::TableEntry>,
+    scheme: partitions::PartitionScheme,
+    output: String,
+) -> Result<partition_search::TableFile, String> {
+    let file = partition_search::write_partition_table(&source, &partitions, scheme, &output)?;
+    let recorded = audit::record_app(
+        "partition-table-written",
+        serde_json::json!({
+            "source": source,
+            "output": file.path,
+            "scheme": file.scheme,
+            "partitions": partitions.iter().map(|p| serde_json::json!({
+                "offset": p.offset,
+                "size": p.size,
+                "filesystem": p.filesystem,
+            })).collect::<Vec<_>>(),
+        }),
+    );
+    warn_unaudited(&app, recorded);
+    Ok(file)
+}
+
+#[tauri::command]
+fn get_last_session(destination: Option<String>) -> Option<session::RecoverySession> {
+    match destination {
+        Some(destination) => session::load(&destination),
+        None => session::current(),
+    }
+}
+
+// Refreshes the index of the whole recovery folder, so it runs off the main thread
+#[tauri::command]
+async fn export_report(session: session::RecoverySession, format: String, output: String) -> Result<String, String> {
+    report::export_report(&session, &format, &output)
+}
+
+// Hashes every recovered file that has no digests yet, so it runs off the main thread
+#[tauri::command]
+async fn export_dfxml(session: session::RecoverySession, output: String) -> Result<String, String> {
+    dfxml::export_dfxml(&session, &output)
+}
+
+#[tauri::command]
+fn load_hash_set(path: String, status: KnownStatus) -> Result<hashsets::HashSetInfo, String> {
+    hashsets::load_hash_set(&path, status)
+}
+
+#[tauri::command]
+fn list_hash_sets() -> Vec<hashsets::HashSetInfo> {
+    hashsets::list_hash_sets()
+}
+
+#[tauri::command]
+fn remove_hash_set(id: String) -> Result<(), String> {
+    hashsets::remove_hash_set(&id)
 }
 
 #[tauri::command]
@@ -29,8 +489,40 @@ fn reveal_path(path: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn get_disk_health(drive: String) -> Result<String, String> {
-    recovery::get_disk_health(drive)
+fn get_disk_health(app: tauri::AppHandle, drive: String) -> Result<String, String> {
+    let result = recovery::get_disk_health(drive.clone());
+    let recorded = audit::record_app(
+        "health-check",
+        serde_json::json!({ "drive": drive, "ok": result.is_ok() }),
+    );
+    warn_unaudited(&app, recorded);
+    result
+}
+
+#[tauri::command]
+fn record_drive_selected(drive: String, fs: Option<String>) -> Result<(), String> {
+    audit::record_app("drive-selected", serde_json::json!({ "drive": drive, "fs": fs }))
+}
+
+#[tauri::command]
+fn get_audit_log_path() -> Option<String> {
+    audit::current_log_path()
+}
+
+// Hashes the whole recovery folder to check the manifest, so it runs off the main thread
+#[tauri::command]
+async fn verify_audit_log(path: Option<String>) -> Result<audit::AuditVerification, String> {
+    let path = path
+        .or_else(audit::current_log_path)
+        .ok_or_else(|| "No audit log for the current session.".to_string())?;
+    audit::verify_audit_log(&path)
+}
+
+// An action that could not be written to the audit log is reported, never dropped
+fn warn_unaudited(app: &tauri::AppHandle, recorded: Result<(), String>) {
+    if let Err(e) = recorded {
+        let _ = app.emit("audit-warning", format!("Not recorded in the audit log: {}", e));
+    }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -40,6 +532,9 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .setup(|app| {
             use tauri::Manager;
+            if let Ok(dir) = app.path().app_local_data_dir() {
+                appdata::set_data_dir(&dir);
+            }
             if let Some(splash) = app.get_webview_window("splashscreen") {
                 splash.show().unwrap();
             }
@@ -48,10 +543,44 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             list_drives,
             start_recovery,
+            run_preflight,
+            get_engine_info,
+            list_signature_groups,
+            preview_filters,
             cancel_recovery,
             scan_recovered_files,
             reveal_path,
             get_disk_health,
+            index_recovered_files,
+            get_recovered_files_page,
+            query_recovered_files,
+            get_recovered_tree,
+            export_recovered_files,
+            get_last_session,
+            export_report,
+            export_dfxml,
+            delete_recovered_files,
+            list_ntfs_deleted_files,
+            recover_ntfs_files,
+            list_fat_deleted_files,
+            recover_fat_files,
+            list_exfat_deleted_files,
+            recover_exfat_files,
+            list_partitions,
+            search_lost_partitions,
+            write_partition_table,
+            inspect_source,
+            mount_image,
+            dismount_image,
+            start_imaging,
+            cancel_imaging,
+            verify_evidence,
+            record_drive_selected,
+            get_audit_log_path,
+            verify_audit_log,
+            load_hash_set,
+            list_hash_sets,
+            remove_hash_set,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,691 @@
+use crate::safety::ensure_target_outside;
+use crate::volume::{
+    recover_selected, u16_at, u32_at, u64_at, NativeProgress, NativeReader, NativeRecoverySummary, Volume,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+
+// ── Types ────────────────────────────────────────────────────────────
+
+const ROOT_RECORD: u64 = 5;
+const BITMAP_RECORD: u64 = 6;
+const FIRST_USER_RECORD: u64 = 16; // 0-15 are reserved for metadata files
+
+const ATTR_STANDARD_INFORMATION: u32 = 0x10;
+const ATTR_ATTRIBUTE_LIST: u32 = 0x20;
+const ATTR_FILE_NAME: u32 = 0x30;
+const ATTR_DATA: u32 = 0x80;
+const ATTR_END: u32 = 0xFFFF_FFFF;
+
+const RECORD_IN_USE: u16 = 0x01;
+const RECORD_IS_DIRECTORY: u16 = 0x02;
+const NAMESPACE_DOS: u8 = 2;
+
+// Update sequence arrays protect every 512 bytes, whatever the sector size
+const FIXUP_STRIDE: usize = 512;
+const FILETIME_UNIX_OFFSET: u64 = 11_644_473_600;
+const READ_CHUNK: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DataState {
+    Intact,      // every cluster is still free in $Bitmap
+    Partial,     // some clusters were reused by other files
+    Overwritten, // every cluster was reused
+    Resident,    // content is stored inside the MFT record itself
+    Empty,
+    Unknown, // no usable data runs or $Bitmap
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DataRun {
+    pub lcn: Option<u64>, // None for sparse runs
+    pub clusters: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeletedFile {
+    pub record: u64,
+    pub name: String,
+    pub path: String, // from the volume root; "\$OrphanFiles\..." when the parent folder is gone
+    pub size: u64,
+    pub created: Option<u64>, // unix seconds, from $STANDARD_INFORMATION
+    pub modified: Option<u64>,
+    pub accessed: Option<u64>,
+    #[serde(rename = "dataState")]
+    pub data_state: DataState,
+    pub runs: Vec<DataRun>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NtfsListing {
+    pub source: String,
+    #[serde(rename = "clusterSize")]
+    pub cluster_size: u64,
+    #[serde(rename = "recordSize")]
+    pub record_size: u64,
+    #[serde(rename = "totalRecords")]
+    pub total_records: u64,
+    #[serde(rename = "corruptRecords")]
+    pub corrupt_records: u64, // failed fixup or BAAD; their files cannot be listed
+    #[serde(rename = "unreadRecords")]
+    pub unread_records: u64, // past the $MFT extents that could be found; their files cannot be listed
+    pub files: Vec<DeletedFile>,
+}
+
+struct BootSector {
+    cluster_size: u64,
+    mft_lcn: u64,
+    record_size: u64,
+}
+
+enum DataAttribute {
+    Resident(Vec<u8>),
+    NonResident { start_vcn: u64, size: u64, runs: Vec<DataRun> },
+}
+
+struct FileName {
+    parent: u64,
+    parent_sequence: u16,
+    namespace: u8,
+    name: String,
+}
+
+struct MftRecord {
+    sequence: u16,
+    flags: u16,
+    base: u64, // 0 for base records
+    times: Option<(u64, u64, u64)>,
+    names: Vec<FileName>,
+    data: Vec<DataAttribute>,
+    attribute_list: Option<DataAttribute>,
+}
+
+struct NameEntry {
+    name: String,
+    parent: u64,
+    parent_sequence: u16,
+    sequence: u16,
+    in_use: bool,
+}
+
+// A listed file plus what is needed to read it back
+struct Candidate {
+    file: DeletedFile,
+    resident: Option<Vec<u8>>,
+}
+
+// ── Parsing ──────────────────────────────────────────────────────────
+
+fn parse_boot_sector(b: &[u8]) -> Result<BootSector, String> {
+    if b.len() < 512 || &b[3..11] != b"NTFS    " {
+        return Err("Not an NTFS volume (no NTFS boot sector).".to_string());
+    }
+    let bytes_per_sector = u16_at(b, 0x0B) as u64;
+    if !bytes_per_sector.is_power_of_two() || !(256..=4096).contains(&bytes_per_sector) {
+        return Err(format!("Invalid NTFS boot sector: {} bytes per sector.", bytes_per_sector));
+    }
+    // Values above 0x80 are a negative power of two (large clusters)
+    let sectors_per_cluster = match b[0x0D] {
+        0 => return Err("Invalid NTFS boot sector: 0 sectors per cluster.".to_string()),
+        raw if raw <= 0x80 => raw as u64,
+        raw => 1u64 << (256 - raw as u32).min(31),
+    };
+    let cluster_size = bytes_per_sector * sectors_per_cluster;
+    let record_size = match b[0x40] as i8 {
+        n if n > 0 => n as u64 * cluster_size,
+        n => 1u64 << (-(n as i32)).min(31),
+    };
+    if !record_size.is_power_of_two() || !(256..=65536).contains(&record_size) {
+        return Err(format!("Invalid NTFS boot sector: {} byte MFT records.", record_size));
+    }
+    Ok(BootSector { cluster_size, mft_lcn: u64_at(b, 0x30), record_size })
+}
+
+// Size in bytes of the volume whose boot sector this is, if it is NTFS. The sector count
+// leaves out the backup boot sector in the volume's last sector.
+pub(crate) fn probe(b: &[u8]) -> Option<u64> {
+    parse_boot_sector(b).ok()?;
+    Some((u64_at(b, 0x28) + 1) * u16_at(b, 0x0B) as u64)
+}
+
+// True if `boot` describes the volume starting at the beginning of `volume`: its $MFT
+// is where the boot sector puts it. `boot` may come from the backup copy.
+pub(crate) fn verify(volume: &mut Volume, boot: &[u8]) -> bool {
+    let Ok(boot) = parse_boot_sector(boot) else {
+        return false;
+    };
+    volume.read_at(boot.mft_lcn * boot.cluster_size, 4).is_ok_and(|r| r == b"FILE")
+}
+
+// Restores the last two bytes of every 512-byte stride; false if a stride was torn
+fn apply_fixups(record: &mut [u8]) -> bool {
+    let usa_offset = u16_at(record, 0x04) as usize;
+    let usa_count = u16_at(record, 0x06) as usize;
+    if usa_count < 2 || usa_offset + usa_count * 2 > record.len() || (usa_count - 1) * FIXUP_STRIDE > record.len() {
+        return false;
+    }
+    let check = [record[usa_offset], record[usa_offset + 1]];
+    for i in 1..usa_count {
+        let end = i * FIXUP_STRIDE;
+        if record[end - 2..end] != check {
+            return false;
+        }
+        record[end - 2] = record[usa_offset + i * 2];
+        record[end - 1] = record[usa_offset + i * 2 + 1];
+    }
+    true
+}
+
+fn filetime_to_unix(filetime: u64) -> Option<u64> {
+    (filetime / 10_000_000).checked_sub(FILETIME_UNIX_OFFSET)
+}
+
+// Decodes a mapping pairs array: a header nibble pair, then the run length and a
+// signed LCN delta. A zero-width delta marks a sparse run.
+fn parse_runlist(b: &[u8]) -> Option<Vec<DataRun>> {
+    let mut runs = Vec::new();
+    let mut pos = 0;
+    let mut lcn: i64 = 0;
+    while pos < b.len() && b[pos] != 0 {
+        let length_size = (b[pos] & 0x0F) as usize;
+        let offset_size = (b[pos] >> 4) as usize;
+        pos += 1;
+        if length_size == 0 || length_size > 8 || offset_size > 8 || pos + length_size + offset_size > b.len() {
+            return None;
+        }
+        let mut clusters = 0u64;
+        for (i, byte) in b[pos..pos + length_size].iter().enumerate() {
+            clusters |= (*byte as u64) << (8 * i);
+        }
+        pos += length_size;
+
+        if offset_size == 0 {
+            runs.push(DataRun { lcn: None, clusters });
+            continue;
+        }
+        let mut delta = 0i64;
+        for (i, byte) in b[pos..pos + offset_size].iter().enumerate() {
+            delta |= (*byte as i64) << (8 * i);
+        }
+        // Sign-extend from the top byte
+        if offset_size < 8 && b[pos + offset_size - 1] & 0x80 != 0 {
+            delta -= 1i64 << (8 * offset_size);
+        }
+        pos += offset_size;
+        lcn = lcn.checked_add(delta).filter(|l| *l >= 0)?;
+        runs.push(DataRun { lcn: Some(lcn as u64), clusters });
+    }
+    Some(runs)
+}
+
+// `record` must already have its fixups applied
+fn parse_record(record: &[u8]) -> Option<MftRecord> {
+    if &record[0..4] != b"FILE" {
+        return None;
+    }
+    let mut parsed = MftRecord {
+        sequence: u16_at(record, 0x10),
+        flags: u16_at(record, 0x16),
+        base: u64_at(record, 0x20) & 0x0000_FFFF_FFFF_FFFF,
+        times: None,
+        names: Vec::new(),
+        data: Vec::new(),
+        attribute_list: None,
+    };
+
+    let used = (u32_at(record, 0x18) as usize).min(record.len());
+    let mut offset = u16_at(record, 0x14) as usize;
+    while offset + 16 <= used {
+        let kind = u32_at(record, offset);
+        let length = u32_at(record, offset + 4) as usize;
+        if kind == ATTR_END || length < 16 || offset + length > used {
+            break;
+        }
+        let attr = &record[offset..offset + length];
+        let non_resident = attr[8] != 0;
+        let name_length = attr[9];
+
+        if !non_resident && attr.len() >= 24 {
+            let value_length = u32_at(attr, 0x10) as usize;
+            let value_offset = u16_at(attr, 0x14) as usize;
+            let value = attr.get(value_offset..value_offset + value_length);
+            match (kind, value) {
+                (ATTR_STANDARD_INFORMATION, Some(v)) if v.len() >= 32 => {
+                    // created, modified, MFT changed, accessed
+                    parsed.times = Some((u64_at(v, 0), u64_at(v, 8), u64_at(v, 24)));
+                }
+                (ATTR_FILE_NAME, Some(v)) if v.len() >= 0x42 => {
+                    let chars = v[0x40] as usize;
+                    if let Some(raw) = v.get(0x42..0x42 + chars * 2) {
+                        let units: Vec<u16> = raw.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+                        parsed.names.push(FileName {
+                            parent: u64_at(v, 0) & 0x0000_FFFF_FFFF_FFFF,
+                            parent_sequence: u16_at(v, 6),
+                            namespace: v[0x41],
+                            name: String::from_utf16_lossy(&units),
+                        });
+                    }
+                }
+                (ATTR_DATA, Some(v)) if name_length == 0 => parsed.data.push(DataAttribute::Resident(v.to_vec())),
+                (ATTR_ATTRIBUTE_LIST, Some(v)) => parsed.attribute_list = Some(DataAttribute::Resident(v.to_vec())),
+                _ => {}
+            }
+        } else if non_resident && matches!(kind, ATTR_DATA | ATTR_ATTRIBUTE_LIST) && name_length == 0 && attr.len() >= 0x40
+        {
+            let runlist_offset = u16_at(attr, 0x20) as usize;
+            if let Some(runs) = attr.get(runlist_offset..).and_then(parse_runlist) {
+                let part = DataAttribute::NonResident { start_vcn: u64_at(attr, 0x10), size: u64_at(attr, 0x30), runs };
+                if kind == ATTR_DATA {
+                    parsed.data.push(part);
+                } else {
+                    parsed.attribute_list = Some(part);
+                }
+            }
+        }
+        offset += length;
+    }
+    Some(parsed)
+}
+
+// Win32 names first; the DOS 8.3 alias is only used if nothing else exists
+fn preferred_name(names: &[FileName]) -> Option<&FileName> {
+    names.iter().find(|n| n.namespace != NAMESPACE_DOS).or_else(|| names.first())
+}
+
+// ── Reading the MFT ──────────────────────────────────────────────────
+
+// Streams `size` bytes of a non-resident attribute; sparse runs read as zeros
+fn read_runs(
+    volume: &mut Volume,
+    runs: &[DataRun],
+    cluster_size: u64,
+    size: u64,
+    out: &mut dyn FnMut(&[u8]) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut remaining = size;
+    for run in runs {
+        let mut run_left = (run.clusters * cluster_size).min(remaining);
+        let mut position = run.lcn.map(|lcn| lcn * cluster_size);
+        while run_left > 0 {
+            let len = run_left.min(READ_CHUNK);
+            let chunk = match position {
+                Some(offset) => volume.read_at(offset, len as usize)?,
+                None => vec![0u8; len as usize],
+            };
+            out(&chunk)?;
+            position = position.map(|p| p + len);
+            run_left -= len;
+            remaining -= len;
+        }
+        if remaining == 0 {
+            break;
+        }
+    }
+    if remaining > 0 {
+        return Err(format!("Data runs end {} bytes before the end of the file.", remaining));
+    }
+    Ok(())
+}
+
+fn read_stream(volume: &mut Volume, runs: &[DataRun], cluster_size: u64, size: u64) -> Result<Vec<u8>, String> {
+    let mut data = Vec::with_capacity(size as usize);
+    read_runs(volume, runs, cluster_size, size, &mut |chunk| {
+        data.extend_from_slice(chunk);
+        Ok(())
+    })?;
+    Ok(data)
+}
+
+// Non-resident data split across extension records is stitched together by VCN.
+// Parts after a missing one are dropped, so the runs only ever cover a prefix.
+fn merge_data(parts: &mut [DataAttribute]) -> Option<(u64, Vec<DataRun>)> {
+    parts.sort_by_key(|p| match p {
+        DataAttribute::NonResident { start_vcn, .. } => *start_vcn,
+        DataAttribute::Resident(_) => 0,
+    });
+    let mut size = None;
+    let mut runs = Vec::new();
+    let mut next_vcn = 0;
+    for part in parts.iter() {
+        if let DataAttribute::NonResident { start_vcn, size: s, runs: r } = part {
+            if *start_vcn != next_vcn {
+                break;
+            }
+            if *start_vcn == 0 {
+                size = Some(*s);
+            }
+            runs.extend(r.iter().cloned());
+            next_vcn += r.iter().map(|run| run.clusters).sum::<u64>();
+        }
+    }
+    size.map(|s| (s, runs))
+}
+
+// Records holding further $DATA extents, from an $ATTRIBUTE_LIST value
+fn data_extents(list: &[u8], own: u64) -> Vec<u64> {
+    let mut records = Vec::new();
+    let mut offset = 0;
+    while offset + 0x20 <= list.len() {
+        let e = &list[offset..];
+        let length = u16_at(e, 4) as usize;
+        if length < 0x1A {
+            break;
+        }
+        let record = u64_at(e, 16) & 0x0000_FFFF_FFFF_FFFF;
+        if u32_at(e, 0) == ATTR_DATA && e[6] == 0 && record != own && !records.contains(&record) {
+            records.push(record);
+        }
+        offset += length;
+    }
+    records
+}
+
+// Reads MFT record `number` through the $MFT runs known so far
+fn read_record(
+    volume: &mut Volume,
+    runs: &[DataRun],
+    cluster_size: u64,
+    record_size: u64,
+    number: u64,
+) -> Option<Vec<u8>> {
+    let mut offset = number * record_size;
+    let mut record = Vec::with_capacity(record_size as usize);
+    for run in runs {
+        let length = run.clusters * cluster_size;
+        if offset >= length {
+            offset -= length;
+            continue;
+        }
+        let len = (length - offset).min(record_size - record.len() as u64);
+        record.extend(volume.read_at(run.lcn? * cluster_size + offset, len as usize).ok()?);
+        offset = 0;
+        if record.len() as u64 == record_size {
+            break;
+        }
+    }
+    (record.len() as u64 == record_size && apply_fixups(&mut record)).then_some(record)
+}
+
+// Only deleted user files are recovered, and $Bitmap is read to tell their clusters apart
+fn keeps_data(number: u64, flags: u16) -> bool {
+    number == BITMAP_RECORD || (number >= FIRST_USER_RECORD && flags & (RECORD_IN_USE | RECORD_IS_DIRECTORY) == 0)
+}
+
+fn scan(volume: &mut Volume) -> Result<(NtfsListing, Vec<Candidate>, u64), String> {
+    let boot = parse_boot_sector(&volume.read_at(0, 512)?)?;
+    let record_size = boot.record_size;
+
+    let mut first = volume.read_at(boot.mft_lcn * boot.cluster_size, record_size as usize)?;
+    if !apply_fixups(&mut first) {
+        return Err("The $MFT record is damaged (fixup mismatch).".to_string());
+    }
+    let mut mft = parse_record(&first).ok_or("The $MFT record is damaged (bad signature).")?;
+    let (mft_size, mut mft_runs) = merge_data(&mut mft.data).ok_or("The $MFT record has no data runs.")?;
+    let total_records = mft_size / record_size;
+
+    // A fragmented $MFT keeps its later extents in records its $ATTRIBUTE_LIST points
+    // to. Each is read through the extents found so far until no more can be reached.
+    let list = match mft.attribute_list.take() {
+        Some(DataAttribute::Resident(v)) => v,
+        Some(DataAttribute::NonResident { size, runs, .. }) => {
+            read_stream(volume, &runs, boot.cluster_size, size).unwrap_or_default()
+        }
+        None => Vec::new(),
+    };
+    let mut extents = data_extents(&list, 0);
+    loop {
+        let found = extents.iter().enumerate().find_map(|(i, number)| {
+            let raw = read_record(volume, &mft_runs, boot.cluster_size, record_size, *number)?;
+            parse_record(&raw).map(|extension| (i, extension))
+        });
+        let Some((i, extension)) = found else { break };
+        extents.remove(i);
+        mft.data.extend(extension.data);
+        if let Some((_, runs)) = merge_data(&mut mft.data) {
+            mft_runs = runs;
+        }
+    }
+    // Records past the extents that were found are left out rather than misread
+    let covered = mft_runs.iter().map(|r| r.clusters).sum::<u64>() * boot.cluster_size;
+    let readable_records = total_records.min(covered / record_size);
+
+    // Streamed in chunks: the MFT of a large volume can run to gigabytes
+    let mut records: HashMap<u64, MftRecord> = HashMap::new();
+    let mut extensions: Vec<MftRecord> = Vec::new();
+    let mut corrupt_records = 0;
+    let mut pending: Vec<u8> = Vec::new();
+    let mut number = 0u64;
+    read_runs(volume, &mft_runs, boot.cluster_size, readable_records * record_size, &mut |chunk| {
+        pending.extend_from_slice(chunk);
+        let whole = pending.len() / record_size as usize * record_size as usize;
+        for raw in pending[..whole].chunks_exact(record_size as usize) {
+            match &raw[0..4] {
+                b"BAAD" => corrupt_records += 1,
+                b"FILE" => {
+                    let mut raw = raw.to_vec();
+                    if !apply_fixups(&mut raw) {
+                        corrupt_records += 1;
+                    } else if let Some(mut record) = parse_record(&raw) {
+                        // Live records are kept only for their names: holding every
+                        // resident $DATA of a large volume would cost gigabytes
+                        let owner = if record.base != 0 { record.base } else { number };
+                        if !keeps_data(owner, record.flags) {
+                            record.data.clear();
+                        }
+                        if record.base != 0 {
+                            extensions.push(record);
+                        } else {
+                            records.insert(number, record);
+                        }
+                    }
+                }
+                _ => {} // never used
+            }
+            number += 1;
+        }
+        pending.drain(..whole);
+        Ok(())
+    })?;
+    for extension in extensions {
+        if let Some(base) = records.get_mut(&extension.base) {
+            if keeps_data(extension.base, base.flags) {
+                base.data.extend(extension.data);
+            }
+            base.names.extend(extension.names);
+        }
+    }
+
+    let bitmap = match records.get_mut(&BITMAP_RECORD).and_then(|r| merge_data(&mut r.data)) {
+        Some((size, runs)) => read_stream(volume, &runs, boot.cluster_size, size).ok(),
+        None => None,
+    };
+
+    let names: HashMap<u64, NameEntry> = records
+        .iter()
+        .filter_map(|(number, r)| {
+            preferred_name(&r.names).map(|n| {
+                (
+                    *number,
+                    NameEntry {
+                        name: n.name.clone(),
+                        parent: n.parent,
+                        parent_sequence: n.parent_sequence,
+                        sequence: r.sequence,
+                        in_use: r.flags & RECORD_IN_USE != 0,
+                    },
+                )
+            })
+        })
+        .collect();
+
+    let mut candidates = Vec::new();
+    for (number, record) in records.iter_mut() {
+        let number = *number;
+        if number < FIRST_USER_RECORD || record.flags & (RECORD_IN_USE | RECORD_IS_DIRECTORY) != 0 {
+            continue;
+        }
+        let Some(entry) = names.get(&number) else { continue };
+
+        let resident = record.data.iter().find_map(|d| match d {
+            DataAttribute::Resident(v) => Some(v.clone()),
+            _ => None,
+        });
+        let (size, runs, data_state) = match (&resident, merge_data(&mut record.data)) {
+            (Some(v), _) if v.is_empty() => (0, Vec::new(), DataState::Empty),
+            (Some(v), _) => (v.len() as u64, Vec::new(), DataState::Resident),
+            (None, Some((0, runs))) => (0, runs, DataState::Empty),
+            (None, Some((size, runs))) => {
+                let state = data_state(&runs, bitmap.as_deref());
+                (size, runs, state)
+            }
+            (None, None) => (0, Vec::new(), DataState::Unknown),
+        };
+
+        let (created, modified, accessed) = match record.times {
+            Some((c, m, a)) => (filetime_to_unix(c), filetime_to_unix(m), filetime_to_unix(a)),
+            None => (None, None, None),
+        };
+        candidates.push(Candidate {
+            file: DeletedFile {
+                record: number,
+                name: entry.name.clone(),
+                path: build_path(&names, number),
+                size,
+                created,
+                modified,
+                accessed,
+                data_state,
+                runs,
+            },
+            resident,
+        });
+    }
+    candidates.sort_by(|a, b| {
+        let (pa, pb) = (a.file.path.to_lowercase(), b.file.path.to_lowercase());
+        pa.cmp(&pb).then(a.file.record.cmp(&b.file.record))
+    });
+
+    let listing = NtfsListing {
+        source: volume.source().to_string(),
+        cluster_size: boot.cluster_size,
+        record_size,
+        total_records,
+        corrupt_records,
+        unread_records: total_records - readable_records,
+        files: candidates.iter().map(|c| c.file.clone()).collect(),
+    };
+    Ok((listing, candidates, boot.cluster_size))
+}
+
+fn data_state(runs: &[DataRun], bitmap: Option<&[u8]>) -> DataState {
+    let Some(bitmap) = bitmap else { return DataState::Unknown };
+    let (mut free, mut used) = (0u64, 0u64);
+    for run in runs {
+        let Some(lcn) = run.lcn else { continue };
+        for cluster in lcn..lcn + run.clusters {
+            let Some(byte) = bitmap.get((cluster / 8) as usize) else { return DataState::Unknown };
+            if byte & (1 << (cluster % 8)) != 0 {
+                used += 1;
+            } else {
+                free += 1;
+            }
+        }
+    }
+    match (free, used) {
+        (0, 0) => DataState::Unknown,
+        (_, 0) => DataState::Intact,
+        (0, _) => DataState::Overwritten,
+        _ => DataState::Partial,
+    }
+}
+
+// Walks $FILE_NAME parents up to the root. A deleted record has its sequence number
+// bumped, so a deleted parent may be one ahead of the reference; anything else means
+// the folder record was reused and the file is an orphan.
+fn build_path(names: &HashMap<u64, NameEntry>, record: u64) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    let mut current = record;
+    let mut orphan = false;
+    while let Some(entry) = names.get(&current) {
+        parts.push(&entry.name);
+        if entry.parent == ROOT_RECORD {
+            break;
+        }
+        let parent_matches = names.get(&entry.parent).is_some_and(|p| {
+            p.sequence == entry.parent_sequence || (!p.in_use && p.sequence == entry.parent_sequence.wrapping_add(1))
+        });
+        if !parent_matches || parts.len() > 255 || entry.parent == current {
+            orphan = true;
+            break;
+        }
+        current = entry.parent;
+    }
+    if orphan {
+        parts.push("$OrphanFiles");
+    }
+    parts.reverse();
+    format!("\\{}", parts.join("\\"))
+}
+
+// ── Public API ───────────────────────────────────────────────────────
+
+// Lists files whose MFT records are no longer in use
+pub fn list_deleted_files(source: &str) -> Result<NtfsListing, String> {
+    let mut volume = Volume::open(source)?;
+    scan(&mut volume).map(|(listing, _, _)| listing)
+}
+
+// Copies the selected records (by MFT record number) into `target`, keeping their paths
+pub fn recover_files<F>(
+    source: &str,
+    records: &[u64],
+    target: &str,
+    mut on_progress: F,
+) -> Result<NativeRecoverySummary, String>
+where
+    F: FnMut(&NativeProgress),
+{
+    ensure_target_outside(source, target)?;
+    let mut volume = Volume::open(source)?;
+    let (_, candidates, cluster_size) = scan(&mut volume)?;
+    let ids: Vec<String> = records.iter().map(|number| format!("mft-{}", number)).collect();
+    let index = candidates.iter().enumerate().map(|(i, c)| (c.file.record, i)).collect();
+    let mut reader = NtfsReader { volume, candidates, index, cluster_size };
+    Ok(recover_selected(&mut reader, &ids, target, &mut on_progress))
+}
+
+struct NtfsReader {
+    volume: Volume,
+    candidates: Vec<Candidate>,
+    index: HashMap<u64, usize>, // by record number
+    cluster_size: u64,
+}
+
+impl NativeReader for NtfsReader {
+    fn find(&self, id: &str) -> Option<usize> {
+        let number: u64 = id.strip_prefix("mft-")?.parse().ok()?;
+        self.index.get(&number).copied()
+    }
+
+    fn describe(&self, index: usize) -> (&str, u64) {
+        (&self.candidates[index].file.path, self.candidates[index].file.size)
+    }
+
+    fn check(&self, index: usize) -> Result<(), String> {
+        let candidate = &self.candidates[index];
+        // Runs are read even when $Bitmap is missing and the data state is unknown
+        if candidate.resident.is_none() && candidate.file.runs.is_empty() && candidate.file.size > 0 {
+            return Err("The data runs of this file could not be read.".to_string());
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, index: usize, out: &mut dyn FnMut(&[u8]) -> Result<(), String>) -> Result<(), String> {
+        let candidate = &self.candidates[index];
+        match &candidate.resident {
+            Some(data) => out(data),
+            None => read_runs(&mut self.volume, &candidate.file.runs, self.cluster_size, candidate.file.size, out),
+        }
+    }
+}
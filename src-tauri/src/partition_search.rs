@@ -0,0 +1,449 @@
+use crate::partitions::{
+    self, crc32, probe_boot_sector, Filesystem, PartitionScheme, GPT_HEADER_MIN, GPT_SIGNATURE, PARTITION_TABLE,
+    PROTECTIVE_MBR,
+};
+use crate::safety::{ensure_target_outside, SourceIntegrity};
+use crate::volume::{partition_source, u16_at, Volume};
+use crate::{exfat, fat, ntfs};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::OpenOptions;
+use std::io::Write;
+
+// ── Types ────────────────────────────────────────────────────────────
+//
+// Walks a whole disk or image sector by sector for boot sectors, including the backup
+// copies NTFS, FAT32 and exFAT keep, so a volume is found even when its table entry and
+// its first sector are both gone. A repaired table is only ever written to a new file.
+
+const CHUNK: u64 = 1024 * 1024;
+const PROGRESS_CHUNKS: u64 = 64;
+// Boot sectors sit on 512-byte boundaries, which also covers 4Kn disks
+const SEARCH_STEP: usize = 512;
+const EXFAT_BACKUP_SECTOR: u64 = 12;
+
+const BASIC_DATA_GUID: &str = "EBD0A0A2-B9E5-4433-87C0-68B6B72699C7";
+const GPT_ENTRIES: usize = 128;
+const GPT_ENTRY_SIZE: usize = 128;
+const GPT_REVISION: u32 = 0x0001_0000;
+// Cylinder 1023, head 254, sector 63: "use the LBA fields" in an MBR entry
+const CHS_LBA_ONLY: [u8; 3] = [0xFE, 0xFF, 0xFF];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+    // Pass as `source` to the native scanners to read this volume
+    pub source: String,
+    pub offset: u64,
+    pub size: u64,
+    pub filesystem: Filesystem,
+    #[serde(rename = "primaryBoot")]
+    pub primary_boot: bool, // boot sector found at `offset`
+    #[serde(rename = "backupBoot")]
+    pub backup_boot: bool, // backup copy found where the file system keeps it
+    pub verified: bool, // the FAT or $MFT is where the boot sector says
+    #[serde(rename = "inTable")]
+    pub in_table: bool,
+    // Part of the proposed table: verified, inside the disk and not overlapping an earlier one
+    pub proposed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchProgress {
+    #[serde(rename = "bytesDone")]
+    pub bytes_done: u64,
+    #[serde(rename = "bytesTotal")]
+    pub bytes_total: Option<u64>,
+    #[serde(rename = "bootSectors")]
+    pub boot_sectors: usize, // found so far, backup copies included
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PartitionSearch {
+    pub source: String,
+    #[serde(rename = "sectorSize")]
+    pub sector_size: u64,
+    pub size: Option<u64>,
+    #[serde(rename = "currentScheme")]
+    pub current_scheme: PartitionScheme,
+    #[serde(rename = "suggestedScheme")]
+    pub suggested_scheme: PartitionScheme,
+    #[serde(rename = "unreadableBytes")]
+    pub unreadable_bytes: u64,
+    pub candidates: Vec<Candidate>,
+    // Hashes of an image source before and after, set by the session that ran the search
+    #[serde(rename = "sourceIntegrity")]
+    pub source_integrity: Option<SourceIntegrity>,
+}
+
+// One partition of a table to write, as picked from the candidates
+#[derive(Debug, Clone, Deserialize)]
+pub struct TableEntry {
+    pub offset: u64,
+    pub size: u64,
+    pub filesystem: Filesystem,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableFile {
+    pub path: String,
+    pub scheme: PartitionScheme,
+    pub bytes: u64,
+    pub partitions: usize,
+}
+
+// A boot sector copy and the volume start it implies
+struct Hit {
+    offset: u64,
+    filesystem: Filesystem,
+    size: u64,
+    boot: Vec<u8>,
+    primary: bool,
+    backup: bool,
+}
+
+// ── Search ───────────────────────────────────────────────────────────
+
+// Offset of the backup boot sector from the start of the volume
+fn backup_boot_offset(filesystem: Filesystem, size: u64, boot: &[u8]) -> Option<u64> {
+    match filesystem {
+        // Last sector of the volume
+        Filesystem::Ntfs => Some(size - u16_at(boot, 0x0B) as u64),
+        Filesystem::Fat32 => Some(u16_at(boot, 0x32) as u64 * u16_at(boot, 0x0B) as u64).filter(|o| *o > 0),
+        Filesystem::Exfat => Some(EXFAT_BACKUP_SECTOR << boot[0x6C]),
+        Filesystem::Fat12 | Filesystem::Fat16 => None,
+    }
+}
+
+fn add_hit(hits: &mut Vec<Hit>, offset: u64, filesystem: Filesystem, size: u64, boot: &[u8], primary: bool) {
+    let hit = match hits.iter_mut().find(|h| h.offset == offset && h.filesystem == filesystem) {
+        Some(hit) => hit,
+        None => {
+            hits.push(Hit { offset, filesystem, size, boot: boot.to_vec(), primary: false, backup: false });
+            hits.last_mut().unwrap()
+        }
+    };
+    if primary {
+        hit.primary = true;
+    } else {
+        hit.backup = true;
+    }
+}
+
+// Every boot sector could be a primary or a backup copy; both readings are kept and
+// told apart later by checking the file system structures
+fn record_boot_sector(hits: &mut Vec<Hit>, at: u64, boot: &[u8]) -> bool {
+    let Some((filesystem, size)) = probe_boot_sector(boot) else {
+        return false;
+    };
+    add_hit(hits, at, filesystem, size, boot, true);
+    if let Some(start) = backup_boot_offset(filesystem, size, boot).and_then(|o| at.checked_sub(o)) {
+        add_hit(hits, start, filesystem, size, boot, false);
+    }
+    true
+}
+
+fn verify(source: &str, hit: &Hit) -> bool {
+    let Ok(mut volume) = Volume::open(&partition_source(source, hit.offset)) else {
+        return false;
+    };
+    match hit.filesystem {
+        Filesystem::Ntfs => ntfs::verify(&mut volume, &hit.boot),
+        Filesystem::Exfat => exfat::verify(&mut volume, &hit.boot),
+        Filesystem::Fat12 | Filesystem::Fat16 | Filesystem::Fat32 => fat::verify(&mut volume, &hit.boot),
+    }
+}
+
+// Reads the whole disk or image. Unreadable chunks are skipped and counted; a device
+// that reports no length is read until the first failure.
+pub fn search_partitions<F>(source: &str, mut on_progress: F) -> Result<PartitionSearch, String>
+where
+    F: FnMut(&SearchProgress),
+{
+    let layout = partitions::list_partitions(source, false)?;
+    let mut volume = Volume::open(source)?;
+    let total = layout.size;
+
+    let mut hits: Vec<Hit> = Vec::new();
+    let mut boot_sectors = 0;
+    let mut unreadable_bytes = 0;
+    let mut offset = 0;
+    let mut chunks = 0;
+    loop {
+        let len = match total {
+            Some(total) if offset >= total => break,
+            Some(total) => (total - offset).min(CHUNK),
+            None => CHUNK,
+        };
+        match volume.read_at(offset, len as usize) {
+            Ok(chunk) => {
+                for (i, sector) in chunk.chunks_exact(SEARCH_STEP).enumerate() {
+                    let at = offset + (i * SEARCH_STEP) as u64;
+                    if sector[510..] == [0x55, 0xAA] && record_boot_sector(&mut hits, at, sector) {
+                        boot_sectors += 1;
+                    }
+                }
+            }
+            Err(_) if total.is_none() => break,
+            Err(_) => unreadable_bytes += len,
+        }
+        offset += len;
+        chunks += 1;
+        if chunks == PROGRESS_CHUNKS {
+            chunks = 0;
+            on_progress(&SearchProgress { bytes_done: offset, bytes_total: total, boot_sectors });
+        }
+    }
+    on_progress(&SearchProgress { bytes_done: offset, bytes_total: total, boot_sectors });
+
+    let mut candidates = Vec::new();
+    for hit in &hits {
+        let verified = verify(source, hit);
+        // A single copy that fails the structure check is a misreading of another volume's sector
+        let plausible = verified || (hit.primary && hit.backup);
+        if !plausible {
+            continue;
+        }
+        candidates.push(Candidate {
+            source: partition_source(source, hit.offset),
+            offset: hit.offset,
+            size: hit.size,
+            filesystem: hit.filesystem,
+            primary_boot: hit.primary,
+            backup_boot: hit.backup,
+            verified,
+            in_table: layout.partitions.iter().any(|p| p.offset == hit.offset),
+            proposed: false,
+        });
+    }
+    candidates.sort_by_key(|c| c.offset);
+
+    // Volumes nested in an earlier one (a disk image stored on it) are not proposed
+    let mut end = 0;
+    for c in candidates.iter_mut() {
+        let fits = total.is_none_or(|total| c.offset + c.size <= total);
+        if c.verified && fits && c.offset > 0 && c.offset >= end {
+            c.proposed = true;
+            end = c.offset + c.size;
+        }
+    }
+
+    let proposed: Vec<&Candidate> = candidates.iter().filter(|c| c.proposed).collect();
+    let beyond_mbr = proposed.iter().any(|c| (c.offset + c.size) / layout.sector_size > u32::MAX as u64);
+    let suggested_scheme = if layout.scheme == PartitionScheme::Gpt || proposed.len() > 4 || beyond_mbr {
+        PartitionScheme::Gpt
+    } else {
+        PartitionScheme::Mbr
+    };
+
+    Ok(PartitionSearch {
+        source: source.to_string(),
+        sector_size: layout.sector_size,
+        size: total,
+        current_scheme: layout.scheme,
+        suggested_scheme,
+        unreadable_bytes,
+        candidates,
+        source_integrity: None,
+    })
+}
+
+// ── Writing a repaired table ─────────────────────────────────────────
+
+fn mbr_type(filesystem: Filesystem) -> u8 {
+    match filesystem {
+        Filesystem::Ntfs | Filesystem::Exfat => 0x07,
+        Filesystem::Fat32 => 0x0C,
+        Filesystem::Fat16 => 0x0E,
+        Filesystem::Fat12 => 0x01,
+    }
+}
+
+fn filesystem_name(filesystem: Filesystem) -> &'static str {
+    match filesystem {
+        Filesystem::Ntfs => "NTFS",
+        Filesystem::Exfat => "exFAT",
+        Filesystem::Fat32 => "FAT32",
+        Filesystem::Fat16 => "FAT16",
+        Filesystem::Fat12 => "FAT12",
+    }
+}
+
+// Inverse of the GUID formatting in the partitions module
+fn guid_bytes(guid: &str) -> Option<[u8; 16]> {
+    let hex: String = guid.chars().filter(|c| *c != '-').collect();
+    if hex.len() != 32 {
+        return None;
+    }
+    let mut raw = [0u8; 16];
+    for (i, byte) in raw.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    raw[0..4].reverse();
+    raw[4..6].reverse();
+    raw[6..8].reverse();
+    Some(raw)
+}
+
+// Version 4 GUID derived from `seed`, so writing the same table twice gives the same file
+fn derived_guid(seed: &str) -> [u8; 16] {
+    let digest = Sha256::digest(seed.as_bytes());
+    let mut raw = [0u8; 16];
+    raw.copy_from_slice(&digest[..16]);
+    raw[7] = (raw[7] & 0x0F) | 0x40;
+    raw[8] = (raw[8] & 0x3F) | 0x80;
+    raw
+}
+
+fn write_mbr_entry(sector: &mut [u8], index: usize, kind: u8, first: u32, sectors: u32) {
+    let at = PARTITION_TABLE + index * 16;
+    sector[at + 1..at + 4].copy_from_slice(&CHS_LBA_ONLY);
+    sector[at + 4] = kind;
+    sector[at + 5..at + 8].copy_from_slice(&CHS_LBA_ONLY);
+    sector[at + 8..at + 12].copy_from_slice(&first.to_le_bytes());
+    sector[at + 12..at + 16].copy_from_slice(&sectors.to_le_bytes());
+}
+
+// Sector 0 with the boot code and disk signature of the current one
+fn build_mbr(current: &[u8], entries: &[TableEntry], sector_size: u64) -> Result<Vec<u8>, String> {
+    if entries.len() > 4 {
+        return Err("An MBR holds at most 4 partitions; write a GPT instead.".to_string());
+    }
+    let mut sector = vec![0u8; sector_size as usize];
+    // A file system in sector 0 has no boot code worth keeping
+    if probe_boot_sector(current).is_none() {
+        sector[..PARTITION_TABLE].copy_from_slice(&current[..PARTITION_TABLE]);
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        let (Ok(first), Ok(sectors)) =
+            (u32::try_from(entry.offset / sector_size), u32::try_from(entry.size / sector_size))
+        else {
+            return Err("A partition lies beyond what an MBR can address; write a GPT instead.".to_string());
+        };
+        write_mbr_entry(&mut sector, i, mbr_type(entry.filesystem), first, sectors);
+    }
+    sector[0x1FE] = 0x55;
+    sector[0x1FF] = 0xAA;
+    Ok(sector)
+}
+
+// Protective MBR, primary GPT header and entry array. The backup copy at the end of the
+// disk is left out; Windows and gdisk rebuild it from the primary.
+fn build_gpt(
+    source: &str,
+    disk_guid: Option<&str>,
+    entries: &[TableEntry],
+    sector_size: u64,
+    disk_size: Option<u64>,
+) -> Result<Vec<u8>, String> {
+    let disk_size = disk_size.ok_or("The disk size is unknown, so a GPT cannot be laid out.")?;
+    if entries.len() > GPT_ENTRIES {
+        return Err(format!("A GPT holds at most {} partitions.", GPT_ENTRIES));
+    }
+    let last_lba = disk_size / sector_size - 1;
+    let entry_sectors = (GPT_ENTRIES * GPT_ENTRY_SIZE) as u64 / sector_size;
+    let first_usable = 2 + entry_sectors;
+    let last_usable = last_lba - 1 - entry_sectors;
+    let outside = |e: &&TableEntry| {
+        e.offset / sector_size < first_usable || (e.offset + e.size) / sector_size > last_usable + 1
+    };
+    if let Some(entry) = entries.iter().find(outside) {
+        return Err(format!("The partition at offset {} overlaps the GPT itself.", entry.offset));
+    }
+
+    let mut region = vec![0u8; ((2 + entry_sectors) * sector_size) as usize];
+    write_mbr_entry(&mut region, 0, PROTECTIVE_MBR, 1, last_lba.min(u32::MAX as u64) as u32);
+    region[0x1FE] = 0x55;
+    region[0x1FF] = 0xAA;
+
+    let array_at = 2 * sector_size as usize;
+    let type_guid = guid_bytes(BASIC_DATA_GUID).unwrap_or_default();
+    for (i, entry) in entries.iter().enumerate() {
+        let e = &mut region[array_at + i * GPT_ENTRY_SIZE..array_at + (i + 1) * GPT_ENTRY_SIZE];
+        e[0..16].copy_from_slice(&type_guid);
+        e[16..32].copy_from_slice(&derived_guid(&partition_source(source, entry.offset)));
+        e[32..40].copy_from_slice(&(entry.offset / sector_size).to_le_bytes());
+        e[40..48].copy_from_slice(&((entry.offset + entry.size) / sector_size - 1).to_le_bytes());
+        let name = format!("Recovered {}", filesystem_name(entry.filesystem));
+        for (c, unit) in name.encode_utf16().enumerate() {
+            e[56 + c * 2..58 + c * 2].copy_from_slice(&unit.to_le_bytes());
+        }
+    }
+    let entries_crc = crc32(&region[array_at..]);
+
+    let disk_guid = disk_guid.and_then(guid_bytes).unwrap_or_else(|| derived_guid(source));
+    let h = &mut region[sector_size as usize..sector_size as usize + GPT_HEADER_MIN];
+    h[0..8].copy_from_slice(GPT_SIGNATURE);
+    h[8..12].copy_from_slice(&GPT_REVISION.to_le_bytes());
+    h[12..16].copy_from_slice(&(GPT_HEADER_MIN as u32).to_le_bytes());
+    h[24..32].copy_from_slice(&1u64.to_le_bytes());
+    h[32..40].copy_from_slice(&last_lba.to_le_bytes());
+    h[40..48].copy_from_slice(&first_usable.to_le_bytes());
+    h[48..56].copy_from_slice(&last_usable.to_le_bytes());
+    h[56..72].copy_from_slice(&disk_guid);
+    h[72..80].copy_from_slice(&2u64.to_le_bytes());
+    h[80..84].copy_from_slice(&(GPT_ENTRIES as u32).to_le_bytes());
+    h[84..88].copy_from_slice(&(GPT_ENTRY_SIZE as u32).to_le_bytes());
+    h[88..92].copy_from_slice(&entries_crc.to_le_bytes());
+    let header_crc = crc32(h);
+    h[16..20].copy_from_slice(&header_crc.to_le_bytes());
+    Ok(region)
+}
+
+// Writes a partition table for `entries` to the new file `output`, for review and for
+// copying onto the disk with a partition tool later. The source is never written.
+pub fn write_partition_table(
+    source: &str,
+    entries: &[TableEntry],
+    scheme: PartitionScheme,
+    output: &str,
+) -> Result<TableFile, String> {
+    if output.starts_with("\\\\.\\") || output.starts_with("\\\\?\\") || output == source {
+        return Err("The repaired table must be saved to a separate file, never to the disk.".to_string());
+    }
+    ensure_target_outside(source, output)?;
+    if entries.is_empty() {
+        return Err("No partitions selected for the table.".to_string());
+    }
+
+    let layout = partitions::list_partitions(source, false)?;
+    let sector_size = layout.sector_size;
+    let mut entries = entries.to_vec();
+    entries.sort_by_key(|e| e.offset);
+    for entry in &entries {
+        // Sector sizes are powers of two
+        if entry.offset == 0 || entry.size == 0 || (entry.offset | entry.size) & (sector_size - 1) != 0 {
+            return Err(format!(
+                "The partition at offset {} is not aligned to {}-byte sectors.",
+                entry.offset, sector_size
+            ));
+        }
+        if layout.size.is_some_and(|size| entry.offset + entry.size > size) {
+            return Err(format!("The partition at offset {} extends past the end of the disk.", entry.offset));
+        }
+    }
+    if let Some(pair) = entries.windows(2).find(|pair| pair[0].offset + pair[0].size > pair[1].offset) {
+        return Err(format!("The partitions at offsets {} and {} overlap.", pair[0].offset, pair[1].offset));
+    }
+
+    let bytes = match scheme {
+        PartitionScheme::Mbr => {
+            let current = Volume::open(source)?.read_at(0, 512)?;
+            build_mbr(&current, &entries, sector_size)?
+        }
+        PartitionScheme::Gpt => {
+            let disk_guid = layout.disk_id.as_deref().filter(|_| layout.scheme == PartitionScheme::Gpt);
+            build_gpt(source, disk_guid, &entries, sector_size, layout.size)?
+        }
+        PartitionScheme::None => return Err("Choose MBR or GPT for the repaired table.".to_string()),
+    };
+
+    // create_new: an existing file, the source image included, is never overwritten
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(output)
+        .map_err(|e| format!("Failed to create {}: {}", output, e))?;
+    file.write_all(&bytes).map_err(|e| format!("Failed to write {}: {}", output, e))?;
+
+    Ok(TableFile { path: output.to_string(), scheme, bytes: bytes.len() as u64, partitions: entries.len() })
+}
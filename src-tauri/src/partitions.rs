@@ -0,0 +1,472 @@
+use crate::fat::FatType;
+use crate::volume::{partition_source, u16_at, u32_at, u64_at, Volume};
+use crate::{exfat, fat, ntfs};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// ── Types ────────────────────────────────────────────────────────────
+//
+// `get_drives` only sees mounted volumes. This reads the partition table of a whole disk
+// or disk image instead, so deleted, hidden and damaged partitions can be scanned too.
+
+const SECTOR_SIZES: [u64; 2] = [512, 4096];
+pub(crate) const PARTITION_TABLE: usize = 0x1BE;
+pub(crate) const PROTECTIVE_MBR: u8 = 0xEE;
+const EXTENDED_TYPES: [u8; 3] = [0x05, 0x0F, 0x85];
+const MAX_LOGICAL_PARTITIONS: usize = 128;
+
+pub(crate) const GPT_SIGNATURE: &[u8] = b"EFI PART";
+pub(crate) const GPT_HEADER_MIN: usize = 92;
+const GPT_MAX_ENTRIES: usize = 4096;
+const GPT_ATTR_LEGACY_BOOTABLE: u64 = 1 << 2;
+
+// Windows has started partitions on 1 MiB boundaries since Vista; XP and older started
+// the first one at sector 63 (the second track)
+const ALIGNMENT: u64 = 1024 * 1024;
+const LEGACY_FIRST_SECTOR: u64 = 63;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartitionScheme {
+    Mbr,
+    Gpt,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PartitionOrigin {
+    Mbr,
+    MbrLogical, // inside an extended partition
+    Gpt,
+    WholeDisk, // a file system without a partition table, as on many USB sticks
+    Lost,      // a boot sector outside every table entry
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Filesystem {
+    #[serde(rename = "NTFS")]
+    Ntfs,
+    #[serde(rename = "FAT12")]
+    Fat12,
+    #[serde(rename = "FAT16")]
+    Fat16,
+    #[serde(rename = "FAT32")]
+    Fat32,
+    #[serde(rename = "exFAT")]
+    Exfat,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Partition {
+    // Pass as `source` to the native scanners to read this partition
+    pub source: String,
+    pub origin: PartitionOrigin,
+    pub offset: u64,
+    pub size: u64,
+    #[serde(rename = "typeId")]
+    pub type_id: String, // "0x07" for MBR, the type GUID for GPT
+    #[serde(rename = "typeName")]
+    pub type_name: String,
+    pub name: Option<String>, // GPT partition name
+    pub bootable: bool,
+    pub filesystem: Option<Filesystem>,
+    // Size recorded in the boot sector; differs from `size` when the table is damaged
+    #[serde(rename = "volumeSize")]
+    pub volume_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskLayout {
+    pub source: String,
+    pub scheme: PartitionScheme,
+    #[serde(rename = "sectorSize")]
+    pub sector_size: u64,
+    pub size: Option<u64>, // None when the device does not report a length
+    #[serde(rename = "diskId")]
+    pub disk_id: Option<String>, // MBR disk signature or GPT disk GUID
+    pub partitions: Vec<Partition>,
+    pub warnings: Vec<String>,
+}
+
+struct MbrEntry {
+    status: u8,
+    kind: u8,
+    first_sector: u64,
+    sectors: u64,
+}
+
+struct GptHeader {
+    disk_guid: String,
+    backup_lba: u64,
+    entries_lba: u64,
+    entry_count: usize,
+    entry_size: usize,
+    entries_crc: u32,
+}
+
+// ── Boot sectors ─────────────────────────────────────────────────────
+
+// File system and size in bytes of the volume whose boot sector this is
+pub(crate) fn probe_boot_sector(b: &[u8]) -> Option<(Filesystem, u64)> {
+    if let Some(size) = ntfs::probe(b) {
+        return Some((Filesystem::Ntfs, size));
+    }
+    if let Some(size) = exfat::probe(b) {
+        return Some((Filesystem::Exfat, size));
+    }
+    fat::probe(b).map(|(fat_type, size)| {
+        let filesystem = match fat_type {
+            FatType::Fat12 => Filesystem::Fat12,
+            FatType::Fat16 => Filesystem::Fat16,
+            FatType::Fat32 => Filesystem::Fat32,
+        };
+        (filesystem, size)
+    })
+}
+
+fn detect_filesystem(volume: &mut Volume, offset: u64) -> Option<(Filesystem, u64)> {
+    volume.read_at(offset, 512).ok().and_then(|b| probe_boot_sector(&b))
+}
+
+fn partition(volume: &mut Volume, origin: PartitionOrigin, offset: u64, size: u64) -> Partition {
+    let detected = detect_filesystem(volume, offset);
+    Partition {
+        source: partition_source(volume.source(), offset),
+        origin,
+        offset,
+        size,
+        type_id: String::new(),
+        type_name: String::new(),
+        name: None,
+        bootable: false,
+        filesystem: detected.map(|(filesystem, _)| filesystem),
+        volume_size: detected.map(|(_, size)| size),
+    }
+}
+
+// ── MBR ──────────────────────────────────────────────────────────────
+
+fn mbr_type_name(kind: u8) -> &'static str {
+    match kind {
+        0x01 => "FAT12",
+        0x04 | 0x06 | 0x0E => "FAT16",
+        0x05 | 0x0F | 0x85 => "Extended",
+        0x07 => "NTFS / exFAT",
+        0x0B | 0x0C => "FAT32",
+        0x11 | 0x14 | 0x16 | 0x1E => "Hidden FAT",
+        0x17 => "Hidden NTFS",
+        0x1B | 0x1C => "Hidden FAT32",
+        0x27 => "Windows recovery",
+        0x42 => "Windows dynamic disk",
+        0x82 => "Linux swap",
+        0x83 => "Linux",
+        0x8E => "Linux LVM",
+        0xEE => "GPT protective",
+        0xEF => "EFI system",
+        _ => "Unknown",
+    }
+}
+
+// The four entries of a partition sector, or None if it has no valid table
+fn parse_mbr(b: &[u8]) -> Option<Vec<MbrEntry>> {
+    if b.len() < 512 || b[0x1FE] != 0x55 || b[0x1FF] != 0xAA {
+        return None;
+    }
+    let entries: Vec<MbrEntry> = (0..4)
+        .map(|i| {
+            let e = &b[PARTITION_TABLE + i * 16..PARTITION_TABLE + (i + 1) * 16];
+            MbrEntry { status: e[0], kind: e[4], first_sector: u32_at(e, 8) as u64, sectors: u32_at(e, 12) as u64 }
+        })
+        .collect();
+    // Boot code in a non-MBR sector fails this; real tables only use 0x00 and 0x80
+    if entries.iter().any(|e| e.status != 0x00 && e.status != 0x80) {
+        return None;
+    }
+    Some(entries.into_iter().filter(|e| e.kind != 0 && e.sectors != 0).collect())
+}
+
+fn mbr_partition(
+    volume: &mut Volume,
+    origin: PartitionOrigin,
+    entry: &MbrEntry,
+    start: u64,
+    sector_size: u64,
+) -> Partition {
+    let mut p = partition(volume, origin, start * sector_size, entry.sectors * sector_size);
+    p.type_id = format!("0x{:02X}", entry.kind);
+    p.type_name = mbr_type_name(entry.kind).to_string();
+    p.bootable = entry.status == 0x80;
+    p
+}
+
+// Walks the chain of extended boot records. Each one holds a logical partition relative
+// to itself and a link to the next one relative to the start of the extended partition.
+fn read_logical_partitions(
+    volume: &mut Volume,
+    extended: &MbrEntry,
+    sector_size: u64,
+    warnings: &mut Vec<String>,
+) -> Vec<Partition> {
+    let mut partitions = Vec::new();
+    let mut visited = HashSet::new();
+    let mut ebr = extended.first_sector;
+    let end = extended.first_sector + extended.sectors;
+
+    while partitions.len() < MAX_LOGICAL_PARTITIONS {
+        if !visited.insert(ebr) || ebr >= end {
+            warnings.push(format!("The extended partition chain loops or leaves the partition at sector {}.", ebr));
+            break;
+        }
+        let Some(entries) = volume.read_at(ebr * sector_size, 512).ok().and_then(|b| parse_mbr(&b)) else {
+            warnings.push(format!("The extended boot record at sector {} is damaged.", ebr));
+            break;
+        };
+        let mut next = None;
+        for entry in &entries {
+            if EXTENDED_TYPES.contains(&entry.kind) {
+                next = Some(extended.first_sector + entry.first_sector);
+            } else {
+                let start = ebr + entry.first_sector;
+                partitions.push(mbr_partition(volume, PartitionOrigin::MbrLogical, entry, start, sector_size));
+            }
+        }
+        match next {
+            Some(sector) => ebr = sector,
+            None => break,
+        }
+    }
+    partitions
+}
+
+// ── GPT ──────────────────────────────────────────────────────────────
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+// GUIDs are stored with their first three groups little-endian
+pub(crate) fn format_guid(b: &[u8]) -> String {
+    let tail: String = b[10..16].iter().map(|x| format!("{:02X}", x)).collect();
+    format!("{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{}", u32_at(b, 0), u16_at(b, 4), u16_at(b, 6), b[8], b[9], tail)
+}
+
+fn gpt_type_name(guid: &str) -> &'static str {
+    match guid {
+        "C12A7328-F81F-11D2-BA4B-00A0C93EC93B" => "EFI system",
+        "E3C9E316-0B5C-4DB8-817D-F92DF00215AE" => "Microsoft reserved",
+        "EBD0A0A2-B9E5-4433-87C0-68B6B72699C7" => "Microsoft basic data",
+        "DE94BBA4-06D1-4D40-A16A-BFD50179D6AC" => "Windows recovery",
+        "5808C8AA-7E8F-42E0-85D2-E1E90434CFB3" => "Windows LDM metadata",
+        "AF9B60A0-1431-4F62-BC68-3311714A69AD" => "Windows LDM data",
+        "E75CAF8F-F680-4CEE-AFA3-B001E56EFC2D" => "Storage Spaces",
+        "0FC63DAF-8483-4772-8E79-3D69D8477DE4" => "Linux",
+        "0657FD6D-A4AB-43C4-84E5-0933C84B4F4F" => "Linux swap",
+        "E6D6D379-F507-44C2-A23C-238F2A3DF928" => "Linux LVM",
+        "48465300-0000-11AA-AA11-00306543ECAC" => "Apple HFS+",
+        "7C3457EF-0000-11AA-AA11-00306543ECAC" => "Apple APFS",
+        _ => "Unknown",
+    }
+}
+
+fn parse_gpt_header(b: &[u8], lba: u64) -> Result<GptHeader, String> {
+    if &b[..8] != GPT_SIGNATURE {
+        return Err(format!("No GPT header at sector {}.", lba));
+    }
+    let header_size = u32_at(b, 12) as usize;
+    if !(GPT_HEADER_MIN..=b.len()).contains(&header_size) {
+        return Err(format!("The GPT header at sector {} has an invalid size.", lba));
+    }
+    let mut header = b[..header_size].to_vec();
+    header[16..20].fill(0);
+    if crc32(&header) != u32_at(b, 16) {
+        return Err(format!("The GPT header at sector {} fails its checksum.", lba));
+    }
+    let entry_count = u32_at(b, 80) as usize;
+    let entry_size = u32_at(b, 84) as usize;
+    if entry_count > GPT_MAX_ENTRIES || entry_size < 128 || !entry_size.is_power_of_two() {
+        return Err(format!("The GPT header at sector {} has an invalid entry array.", lba));
+    }
+    Ok(GptHeader {
+        disk_guid: format_guid(&b[56..72]),
+        backup_lba: u64_at(b, 32),
+        entries_lba: u64_at(b, 72),
+        entry_count,
+        entry_size,
+        entries_crc: u32_at(b, 88),
+    })
+}
+
+fn read_gpt_entries(volume: &mut Volume, header: &GptHeader, sector_size: u64) -> Result<Vec<Partition>, String> {
+    let entries = volume.read_at(header.entries_lba * sector_size, header.entry_count * header.entry_size)?;
+    if crc32(&entries) != header.entries_crc {
+        return Err(format!("The GPT entry array at sector {} fails its checksum.", header.entries_lba));
+    }
+    let mut partitions = Vec::new();
+    for e in entries.chunks(header.entry_size) {
+        if e[..16].iter().all(|b| *b == 0) {
+            continue;
+        }
+        let (first, last) = (u64_at(e, 32), u64_at(e, 40));
+        if last < first {
+            continue;
+        }
+        let type_id = format_guid(&e[..16]);
+        let units: Vec<u16> =
+            e[56..128].chunks(2).map(|c| u16::from_le_bytes([c[0], c[1]])).take_while(|u| *u != 0).collect();
+        let mut p = partition(volume, PartitionOrigin::Gpt, first * sector_size, (last - first + 1) * sector_size);
+        p.type_name = gpt_type_name(&type_id).to_string();
+        p.type_id = type_id;
+        p.name = Some(String::from_utf16_lossy(&units)).filter(|n| !n.is_empty());
+        p.bootable = u64_at(e, 48) & GPT_ATTR_LEGACY_BOOTABLE != 0;
+        partitions.push(p);
+    }
+    Ok(partitions)
+}
+
+// Reads the primary GPT, falling back to the backup copy at the end of the disk
+fn read_gpt(
+    volume: &mut Volume,
+    sector_size: u64,
+    disk_size: Option<u64>,
+    warnings: &mut Vec<String>,
+) -> Option<(String, Vec<Partition>)> {
+    let primary = volume.read_at(sector_size, sector_size as usize).and_then(|b| parse_gpt_header(&b, 1));
+    let mut backup_lba = disk_size.map(|size| size / sector_size - 1);
+    match primary {
+        Ok(header) => {
+            backup_lba = Some(header.backup_lba);
+            match read_gpt_entries(volume, &header, sector_size) {
+                Ok(partitions) => return Some((header.disk_guid, partitions)),
+                Err(e) => warnings.push(e),
+            }
+        }
+        Err(e) => warnings.push(e),
+    }
+
+    let lba = backup_lba?;
+    let header = volume
+        .read_at(lba * sector_size, sector_size as usize)
+        .and_then(|b| parse_gpt_header(&b, lba))
+        .and_then(|header| {
+            read_gpt_entries(volume, &header, sector_size).map(|partitions| (header.disk_guid, partitions))
+        });
+    match header {
+        Ok(found) => {
+            warnings.push("The primary GPT is damaged; partitions were read from the backup copy.".to_string());
+            Some(found)
+        }
+        Err(e) => {
+            warnings.push(e);
+            None
+        }
+    }
+}
+
+// ── Lost partitions ──────────────────────────────────────────────────
+
+// Looks for boot sectors at the usual partition starts outside every known partition.
+// Only sector 63 and 1 MiB boundaries are read, so a pass over a large disk stays fast;
+// partitions on old cylinder boundaries need a deeper search.
+fn find_lost_partitions(volume: &mut Volume, known: &[Partition], sector_size: u64, end: u64) -> Vec<Partition> {
+    let mut found: Vec<Partition> = Vec::new();
+    let covering = |partitions: &[Partition], offset: u64| {
+        partitions.iter().find(|p| offset >= p.offset && offset < p.offset + p.size).map(|p| p.offset + p.size)
+    };
+
+    let mut offset = LEGACY_FIRST_SECTOR * sector_size;
+    while offset < end {
+        let next = if let Some(next) = covering(known, offset).or_else(|| covering(&found, offset)) {
+            next
+        } else if let Some((_, size)) = detect_filesystem(volume, offset) {
+            let mut p = partition(volume, PartitionOrigin::Lost, offset, size);
+            p.type_name = "Lost partition".to_string();
+            found.push(p);
+            offset + size
+        } else {
+            offset + 1
+        };
+        offset = next.next_multiple_of(ALIGNMENT);
+    }
+    found
+}
+
+// ── Public API ───────────────────────────────────────────────────────
+
+// Reads the partition table of a disk (\\.\PhysicalDrive1) or disk image. With
+// `find_lost`, also reads the usual partition starts outside the table for boot sectors.
+pub fn list_partitions(source: &str, find_lost: bool) -> Result<DiskLayout, String> {
+    let mut volume = Volume::open(source)?;
+    let disk_size = volume.size();
+    let first = volume.read_at(0, 512)?;
+    let mut warnings = Vec::new();
+
+    // 4Kn disks keep the GPT header in their second 4 KiB sector
+    let sector_size = SECTOR_SIZES
+        .into_iter()
+        .find(|size| volume.read_at(*size, 8).is_ok_and(|b| b == GPT_SIGNATURE))
+        .unwrap_or(512);
+
+    let mut layout = DiskLayout {
+        source: source.to_string(),
+        scheme: PartitionScheme::None,
+        sector_size,
+        size: disk_size,
+        disk_id: None,
+        partitions: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    // A file system in sector 0 means there is no partition table at all
+    if let Some((_, size)) = detect_filesystem(&mut volume, 0) {
+        let mut p = partition(&mut volume, PartitionOrigin::WholeDisk, 0, disk_size.unwrap_or(size));
+        p.type_name = "Whole disk".to_string();
+        layout.partitions.push(p);
+        return Ok(layout);
+    }
+
+    match parse_mbr(&first) {
+        Some(entries) if entries.iter().any(|e| e.kind == PROTECTIVE_MBR) => {
+            layout.scheme = PartitionScheme::Gpt;
+            if let Some((guid, partitions)) = read_gpt(&mut volume, sector_size, disk_size, &mut warnings) {
+                layout.disk_id = Some(guid);
+                layout.partitions = partitions;
+            }
+        }
+        Some(entries) => {
+            layout.scheme = PartitionScheme::Mbr;
+            layout.disk_id = Some(format!("{:08X}", u32_at(&first, 0x1B8)));
+            for entry in &entries {
+                if EXTENDED_TYPES.contains(&entry.kind) {
+                    layout.partitions.extend(read_logical_partitions(&mut volume, entry, sector_size, &mut warnings));
+                } else {
+                    let p = mbr_partition(&mut volume, PartitionOrigin::Mbr, entry, entry.first_sector, sector_size);
+                    layout.partitions.push(p);
+                }
+            }
+        }
+        None => warnings.push("The disk has no partition table.".to_string()),
+    }
+
+    if find_lost {
+        let table_end = layout.partitions.iter().map(|p| p.offset + p.size).max().unwrap_or(0);
+        match disk_size.or((table_end > 0).then_some(table_end)) {
+            Some(end) => {
+                let lost = find_lost_partitions(&mut volume, &layout.partitions, sector_size, end);
+                layout.partitions.extend(lost);
+            }
+            None => warnings.push("The disk size is unknown, so no lost partitions were searched for.".to_string()),
+        }
+    }
+
+    layout.partitions.sort_by_key(|p| p.offset);
+    layout.warnings = warnings;
+    Ok(layout)
+}
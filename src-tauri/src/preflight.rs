@@ -0,0 +1,144 @@
+use crate::dialect::render_for_engine;
+use crate::engine::detect;
+use crate::filters::{compile, IssueSeverity};
+use crate::images::inspect_source;
+use crate::recovery::RecoveryConfig;
+use crate::safety::ensure_target_outside;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+// ── Types ────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreflightCheck {
+    pub name: String,
+    pub ok: bool,
+    pub warning: bool, // true = allowed to proceed despite the issue
+    pub message: String,
+    // Something to do instead, offered next to the message
+    pub action: Option<PreflightAction>,
+}
+
+// Runs a command other than winfr on the same source, e.g. a native scanner
+#[derive(Debug, Clone, Serialize)]
+pub struct PreflightAction {
+    pub label: String,
+    pub command: String, // Tauri command to invoke
+    pub source: String,  // its `source` argument
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreflightReport {
+    pub ok: bool,
+    pub checks: Vec<PreflightCheck>,
+    pub args: Vec<String>,
+}
+
+// ── Checks ───────────────────────────────────────────────────────────
+
+pub fn run_preflight(config: &RecoveryConfig) -> PreflightReport {
+    let mut checks = Vec::new();
+    let mut check = |name: &str, ok: bool, warning: bool, message: String| {
+        checks.push(PreflightCheck { name: name.to_string(), ok, warning, message, action: None });
+    };
+
+    let source_letter = drive_letter(&config.source);
+    let source_root = source_letter.map(|l| PathBuf::from(format!("{}:\\", l)));
+    match &source_root {
+        Some(root) if root.exists() => check("source", true, false, format!("Source {} is available.", config.source)),
+        Some(_) => check("source", false, false, format!("Source {} is not mounted.", config.source)),
+        // Image files: VHD/VHDX are attached for winfr, raw images need the native scanners
+        None => match inspect_source(&config.source) {
+            Ok(info) => check("source", info.winfr, false, info.message),
+            Err(e) => check("source", false, false, e),
+        },
+    }
+
+    // winfr refuses to write recovered files onto the drive being scanned, and links or
+    // junctions must not lead there either
+    if config.destination.trim().is_empty() {
+        check("destination", false, false, "No destination folder selected.".to_string());
+    } else if let Err(e) = ensure_target_outside(&config.source, &config.destination) {
+        check("destination", false, false, e);
+    } else {
+        let dest = Path::new(&config.destination);
+        let usable = dest.exists() || dest.ancestors().skip(1).any(|p| !p.as_os_str().is_empty() && p.exists());
+        check(
+            "destination",
+            usable,
+            false,
+            if usable {
+                format!("Destination {} is writable.", config.destination)
+            } else {
+                format!("Destination {} cannot be created.", config.destination)
+            },
+        );
+    }
+
+    let engine = detect(config.winfr_path.as_deref());
+    match (&engine.path, &engine.error) {
+        (Some(path), _) => check(
+            "engine",
+            true,
+            false,
+            match &engine.version {
+                Some(version) => format!("winfr {} found at {}", version, path),
+                None => format!("winfr found at {}", path),
+            },
+        ),
+        (None, error) => check("engine", false, false, error.clone().unwrap_or_default()),
+    }
+
+    for issue in compile(&config.filters).all_issues() {
+        let is_warning = issue.severity == IssueSeverity::Warning;
+        check("filters", is_warning, is_warning, issue.message.clone());
+    }
+
+    let command = render_for_engine(config, &engine);
+    for warning in &command.warnings {
+        check("options", true, true, warning.clone());
+    }
+
+    // exFAT sources can always be scanned natively, and should be where winfr tends to crash
+    let is_exfat = config.source_fs.as_ref().is_some_and(|fs| fs.eq_ignore_ascii_case("exfat"));
+    if is_exfat {
+        let crashes = config.mode == "extensive" || config.segment_mode || config.signature_mode;
+        let message = if crashes {
+            "winfr is known to crash (0xC0000005) on exFAT in Extensive mode. The native exFAT scanner can recover \
+             deleted files without winfr."
+        } else {
+            "The native exFAT scanner can also recover deleted files from this source, without winfr."
+        };
+        checks.push(PreflightCheck {
+            name: "options".to_string(),
+            ok: true,
+            warning: crashes,
+            message: message.to_string(),
+            action: Some(PreflightAction {
+                label: "Scan with the native exFAT scanner".to_string(),
+                command: "list_exfat_deleted_files".to_string(),
+                source: config.source.clone(),
+            }),
+        });
+    }
+
+    let ok = checks.iter().all(|c| c.ok);
+    PreflightReport { ok, checks, args: command.args }
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────
+
+fn drive_letter(path: &str) -> Option<char> {
+    let mut chars = path.chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), Some(':')) if letter.is_ascii_alphabetic() => Some(letter.to_ascii_uppercase()),
+        _ => None,
+    }
+}
+
+pub fn find_in_path(program: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
@@ -0,0 +1,138 @@
+use crate::index::{self, Page, PageRequest};
+use crate::integrity::IntegrityStatus;
+use crate::recovery::RecoveredFile;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+
+// ── Query expression (as sent by the frontend) ───────────────────────
+
+// Example: { "op": "and", "terms": [
+//   { "op": "extension", "values": ["docx"] },
+//   { "op": "size", "min": 102400 },
+//   { "op": "pathPrefix", "prefix": "\\Users\\Alice\\Documents" } ] }
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum FileQuery {
+    And { terms: Vec<FileQuery> },
+    Or { terms: Vec<FileQuery> },
+    Not { term: Box<FileQuery> },
+    Name {
+        pattern: String,
+        #[serde(default)]
+        regex: bool, // false = glob (`*`, `?`)
+    },
+    Size { min: Option<u64>, max: Option<u64> },
+    Category { values: Vec<String> },
+    Extension { values: Vec<String> },
+    Modified { after: Option<u64>, before: Option<u64> }, // seconds since the Unix epoch
+    Integrity { values: Vec<IntegrityStatus> },
+    PathPrefix { prefix: String },
+}
+
+// ── Compiled form (regexes built once per query) ─────────────────────
+
+enum Matcher {
+    All(Vec<Matcher>),
+    Any(Vec<Matcher>),
+    Not(Box<Matcher>),
+    Name(Regex),
+    Size(Option<u64>, Option<u64>),
+    Category(Vec<String>),
+    Extension(Vec<String>),
+    Modified(Option<u64>, Option<u64>),
+    Integrity(Vec<IntegrityStatus>),
+    PathPrefix(String),
+}
+
+fn compile(query: &FileQuery) -> Result<Matcher, String> {
+    Ok(match query {
+        FileQuery::And { terms } => Matcher::All(terms.iter().map(compile).collect::<Result<_, _>>()?),
+        FileQuery::Or { terms } => Matcher::Any(terms.iter().map(compile).collect::<Result<_, _>>()?),
+        FileQuery::Not { term } => Matcher::Not(Box::new(compile(term)?)),
+        FileQuery::Name { pattern, regex } => {
+            let source = if *regex { pattern.clone() } else { glob_to_regex(pattern) };
+            let re = RegexBuilder::new(&source)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| format!("Invalid name pattern '{}': {}", pattern, e))?;
+            Matcher::Name(re)
+        }
+        FileQuery::Size { min, max } => Matcher::Size(*min, *max),
+        FileQuery::Category { values } => Matcher::Category(values.iter().map(|v| v.to_lowercase()).collect()),
+        FileQuery::Extension { values } => Matcher::Extension(
+            values
+                .iter()
+                .map(|v| v.trim_start_matches("*.").trim_start_matches('.').to_lowercase())
+                .collect(),
+        ),
+        FileQuery::Modified { after, before } => Matcher::Modified(*after, *before),
+        FileQuery::Integrity { values } => Matcher::Integrity(values.clone()),
+        FileQuery::PathPrefix { prefix } => Matcher::PathPrefix(normalize_path(prefix)),
+    })
+}
+
+impl Matcher {
+    fn matches(&self, file: &RecoveredFile) -> bool {
+        match self {
+            Matcher::All(terms) => terms.iter().all(|t| t.matches(file)),
+            Matcher::Any(terms) => terms.iter().any(|t| t.matches(file)),
+            Matcher::Not(term) => !term.matches(file),
+            Matcher::Name(re) => re.is_match(&file.name),
+            Matcher::Size(min, max) => {
+                min.is_none_or(|min| file.size >= min) && max.is_none_or(|max| file.size <= max)
+            }
+            Matcher::Category(values) => values.contains(&file.category.to_lowercase()),
+            Matcher::Extension(values) => {
+                let ext = file.name.rsplit_once('.').map(|(_, e)| e.to_lowercase()).unwrap_or_default();
+                values.contains(&ext)
+            }
+            Matcher::Modified(after, before) => match file.modified {
+                Some(t) => after.is_none_or(|a| t >= a) && before.is_none_or(|b| t <= b),
+                None => false,
+            },
+            Matcher::Integrity(values) => values.contains(&file.integrity),
+            Matcher::PathPrefix(prefix) => {
+                let path = normalize_path(&file.path);
+                // Match whole path components so "\Users\Al" does not match "\Users\Alice"
+                path == *prefix || path.starts_with(&format!("{}\\", prefix)) || prefix.is_empty()
+            }
+        }
+    }
+}
+
+// ── Query entry point ────────────────────────────────────────────────
+
+pub fn query_recovered_files(destination: &str, query: &FileQuery, page: &PageRequest) -> Result<Page, String> {
+    let matcher = compile(query)?;
+    index::query_page_where(destination, page, |file| matcher.matches(file))
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut re = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            _ => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    re
+}
+
+// Lowercase, backslash-separated, leading backslash, no trailing backslash.
+// A drive letter is dropped since winfr recreates original paths without it.
+fn normalize_path(path: &str) -> String {
+    let mut trimmed = path.replace('/', "\\").to_lowercase();
+    if trimmed.len() >= 2 && trimmed.as_bytes()[1] == b':' && trimmed.as_bytes()[0].is_ascii_alphabetic() {
+        trimmed.drain(..2);
+    }
+    let trimmed = trimmed.trim_matches('\\');
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("\\{}", trimmed)
+    }
+}
@@ -1,14 +1,30 @@
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::fs;
 use std::io::Read;
+#[cfg(windows)]
 use std::os::windows::process::CommandExt;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use tauri::{AppHandle, Emitter};
-use walkdir::WalkDir;
+use std::time::Duration;
+use tauri::AppHandle;
 use encoding_rs::UTF_16LE;
+use regex::Regex;
+use crate::events::{EventSink, LogSource, RecoveryEvent, RecoveryStatus, TauriSink};
+use crate::hashing::FileHashes;
+use crate::hashsets::KnownStatus;
+use crate::images::{self, MountedImage};
+use crate::audit;
+use crate::dialect;
+use crate::engine::{self, EngineSyntax};
+use crate::filters;
+use crate::index;
+use crate::integrity::IntegrityStatus;
+use crate::safety::{self, SourceIntegrity};
+use crate::session;
 
 // ── Types ────────────────────────────────────────────────────────────
 
@@ -27,150 +43,61 @@ pub struct RecoveryConfig {
     pub keep_all_extensions: bool,
     pub source_fs: Option<String>,
     pub verbose_mode: bool,
+    // Engine executable to spawn; `None` resolves `winfr` from PATH
+    #[serde(default)]
+    pub winfr_path: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct RecoveryEvent {
-    pub event_type: String,
-    pub message: String,
-    pub progress: Option<f64>,
-    pub path: Option<String>,
-}
-
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecoveredFile {
     pub id: String,
     pub name: String,
     pub path: String,
     pub size: u64,
     pub category: String,
+    pub modified: Option<u64>, // seconds since the Unix epoch
+    #[serde(default)]
+    pub integrity: IntegrityStatus,
+    // Only populated when at least one known-file hash set is loaded
+    pub hashes: Option<FileHashes>,
+    #[serde(rename = "knownStatus")]
+    pub known_status: Option<KnownStatus>,
 }
 
-// Global handle to track the running recovery process
-static RECOVERY_ACTIVE: Mutex<bool> = Mutex::new(false);
-
-// ── Build winfr command args ─────────────────────────────────────────
-
-fn build_winfr_args(config: &RecoveryConfig) -> Vec<String> {
-    let mut args: Vec<String> = Vec::new();
+// Cancel token of the running recovery. The waiter thread owns the winfr process, kills
+// it once the token is set, and clears this only after the session is sealed.
+static RECOVERY: Mutex<Option<Arc<AtomicBool>>> = Mutex::new(None);
 
-    args.push(config.source.clone());
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
 
-    let mut dest = config.destination.clone();
-    if !dest.ends_with('\\') && !dest.ends_with('/') {
-        dest.push('\\');
-    }
-    args.push(dest.clone());
-
-    // Mode selection — modes are mutually exclusive in winfr
-    // Signature (/x) and Segment (/r) are deep scans that should run on top of /extensive
-    if config.signature_mode {
-        args.push("/extensive".to_string());
-        args.push("/x".to_string());
-    } else if config.segment_mode {
-        args.push("/extensive".to_string());
-        args.push("/r".to_string());
-    } else if config.mode == "extensive" {
-        args.push("/extensive".to_string());
-    } else {
-        args.push("/regular".to_string());
-    }
-
-    if config.auto_accept {
-        args.push("/a".to_string());
-    }
+pub fn winfr_program(config: &RecoveryConfig) -> &str {
+    config.winfr_path.as_deref().filter(|p| !p.trim().is_empty()).unwrap_or("winfr")
+}
 
-    if config.keep_both && !config.signature_mode {
-        // Safe Mode Mitigation: /o:b is known to crash winfr.exe on exFAT drives.
-        // Also strictly incompatible with Signature Mode (/x).
-        let is_exfat = config.source_fs.as_ref().is_some_and(|fs| fs.to_lowercase() == "exfat");
-        if !is_exfat {
-            args.push("/o:b".to_string());
-        }
-    }
+// Console tools are spawned without flashing a window on Windows
+pub(crate) fn hidden_command(program: &str) -> Command {
+    #[allow(unused_mut)]
+    let mut command = Command::new(program);
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NO_WINDOW);
+    command
+}
 
-    if config.recover_non_deleted {
-        args.push("/u".to_string());
-    }
-    if config.recover_system_files {
-        args.push("/k".to_string());
-    }
-    if config.keep_all_extensions {
-        args.push("/e".to_string());
-    }
-    if config.verbose_mode && !config.signature_mode {
-        // Verbose mode (/v) is documented as incompatible with /x in some versions.
-        args.push("/v".to_string());
-    }
+// ── Build winfr command args ─────────────────────────────────────────
 
-    // Filter processing
-    if config.signature_mode {
-        // Signature mode (/x) uses /y: for file type groups, NOT /n
-        let mut groups: Vec<&str> = Vec::new();
-        for filter in &config.filters {
-            match filter.as_str() {
-                "Images" => groups.extend(["JPEG", "PNG"]),
-                "Documents" => groups.extend(["PDF", "ZIP"]),
-                "Videos" => groups.extend(["MPEG"]),
-                "Audio" => groups.extend(["MP3", "ASF"]),
-                "Archives" => groups.extend(["ZIP"]),
-                _ => {} // Custom filters not supported in /x mode
-            }
-        }
-        
-        if !groups.is_empty() {
-            groups.sort();
-            groups.dedup();
-            args.push("/y:".to_string() + &groups.join(","));
-        }
-    } else {
-        // Regular/Extensive/Segment use /n filters
-        for filter in &config.filters {
-            match filter.as_str() {
-                "Images" => {
-                    for ext in ["*.jpg", "*.jpeg", "*.png", "*.gif", "*.bmp", "*.webp", "*.heic", "*.raw"] {
-                        args.push("/n".to_string());
-                        args.push(ext.to_string());
-                    }
-                }
-                "Documents" => {
-                    for ext in ["*.pdf", "*.doc", "*.docx", "*.xls", "*.xlsx", "*.ppt", "*.pptx", "*.txt", "*.rtf", "*.odt", "*.csv"] {
-                        args.push("/n".to_string());
-                        args.push(ext.to_string());
-                    }
-                }
-                "Videos" => {
-                    for ext in ["*.mp4", "*.avi", "*.mkv", "*.mov", "*.wmv", "*.flv", "*.webm", "*.m4v"] {
-                        args.push("/n".to_string());
-                        args.push(ext.to_string());
-                    }
-                }
-                "Audio" => {
-                    for ext in ["*.mp3", "*.wav", "*.flac", "*.aac", "*.ogg", "*.wma", "*.m4a"] {
-                        args.push("/n".to_string());
-                        args.push(ext.to_string());
-                    }
-                }
-                "Archives" => {
-                    for ext in ["*.zip", "*.rar", "*.7z", "*.tar", "*.gz", "*.bz2", "*.iso"] {
-                        args.push("/n".to_string());
-                        args.push(ext.to_string());
-                    }
-                }
-                _ => {
-                    args.push("/n".to_string());
-                    args.push(filter.clone());
-                }
-            }
-        }
-    }
+pub fn build_winfr_args(config: &RecoveryConfig) -> Vec<String> {
+    build_winfr_args_for(config, EngineSyntax::Modern)
+}
 
-    args
+// Renders `config` in the argument dialect of the detected winfr release
+pub fn build_winfr_args_for(config: &RecoveryConfig, syntax: EngineSyntax) -> Vec<String> {
+    dialect::render(config, syntax).args
 }
 
 // ── Category detection from file extension ──────────────────────────
 
-fn categorize_file(ext: &str) -> String {
+pub(crate) fn categorize_file(ext: &str) -> String {
     match ext.to_lowercase().as_str() {
         "jpg" | "jpeg" | "png" | "gif" | "bmp" | "svg" | "webp" | "ico" | "tiff" | "tif" | "heic" | "heif" | "raw" | "cr2" | "nef" | "arw" => "Images".to_string(),
         "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" | "txt" | "rtf" | "odt" | "ods" | "odp" | "csv" | "md" => "Documents".to_string(),
@@ -184,442 +111,450 @@ fn categorize_file(ext: &str) -> String {
 // ── Start Recovery (direct spawn — app already runs as admin) ───────
 
 pub fn start_recovery(app: AppHandle, config: RecoveryConfig) -> Result<String, String> {
-    {
-        let mut active = RECOVERY_ACTIVE.lock().map_err(|e| e.to_string())?;
-        if *active {
+    start_recovery_with(Arc::new(TauriSink::new(app)), config)
+}
+
+// Engine entry point shared by the GUI, the CLI and tests: every lifecycle event goes through `sink`
+pub fn start_recovery_with(sink: Arc<dyn EventSink>, config: RecoveryConfig) -> Result<String, String> {
+    let cancelled = {
+        let mut running = RECOVERY.lock().map_err(|e| e.to_string())?;
+        if running.is_some() {
             return Err("A recovery operation is already in progress.".to_string());
         }
-        *active = true;
+        let token = Arc::new(AtomicBool::new(false));
+        *running = Some(token.clone());
+        token
+    };
+
+    // Refuse filters that would silently match nothing after a long scan
+    let compiled_filters = filters::compile(&config.filters);
+    let filter_errors = compiled_filters.errors();
+    if !filter_errors.is_empty() {
+        release(&cancelled);
+        return Err(format!("Invalid filters: {}", filter_errors.join(" ")));
     }
 
-    let winfr_args = build_winfr_args(&config);
+    // VHD/VHDX images are attached read-only and winfr scans their drive letter; the
+    // session keeps the image path as its source
+    let (scan_source, mounted) = match images::winfr_source(&config.source) {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            release(&cancelled);
+            return Err(e);
+        }
+    };
+    let scan_config = RecoveryConfig { source: scan_source, ..config.clone() };
+
+    // Located up front, so a missing engine is refused before the session begins
+    let engine = engine::detect(config.winfr_path.as_deref());
+    let Some(program) = engine.path.clone() else {
+        detach_image(&mounted);
+        release(&cancelled);
+        return Err(format!("Failed to launch winfr: {}", engine.error.unwrap_or_default()));
+    };
+    let command = dialect::render_for_engine(&scan_config, &engine);
+    let winfr_args = command.args;
+
+    // Pre-create destination directory to prevent winfr crashes, unless it resolves onto
+    // the source under any of its names
+    if let Err(e) = safety::create_destination(&[&config.source, &scan_config.source], &config.destination) {
+        detach_image(&mounted);
+        release(&cancelled);
+        return Err(e);
+    }
 
-    // Pre-create destination directory to prevent winfr crashes
-    if let Err(e) = std::fs::create_dir_all(&config.destination) {
-        return Err(format!("Failed to create destination directory: {}", e));
+    // Track config, argv and timings for reports and the audit log
+    let current_session = session::begin(&config, &winfr_args);
+    if let Err(e) = audit::record_session_start(&current_session) {
+        session::finish("error", None);
+        detach_image(&mounted);
+        release(&cancelled);
+        return Err(format!("The recovery was not started because it could not be audited. {}", e));
     }
 
     // Emit initial events
-    let _ = app.emit("recovery-status", RecoveryEvent {
-        event_type: "status".to_string(),
-        message: "scanning".to_string(),
-        progress: Some(0.0),
-        path: None,
-    });
-    let _ = app.emit("recovery-log", RecoveryEvent {
-        event_type: "log".to_string(),
-        message: "Windows File Recovery (Winfr Pro)".to_string(),
-        progress: None,
-        path: None,
-    });
-    let _ = app.emit("recovery-log", RecoveryEvent {
-        event_type: "log".to_string(),
-        message: format!("Command: winfr {}", winfr_args.join(" ")),
-        progress: None,
-        path: None,
-    });
-    let _ = app.emit("recovery-log", RecoveryEvent {
-        event_type: "log".to_string(),
-        message: "Starting recovery process...".to_string(),
-        progress: None,
-        path: None,
-    });
+    sink.emit(RecoveryEvent::Status { status: RecoveryStatus::Scanning, progress: Some(0.0) });
+    log(&sink, "Windows File Recovery (Winfr Pro)");
+    if let Some(version) = &engine.version {
+        log(&sink, &format!("Engine: winfr {} ({:?} syntax)", version, engine.syntax));
+    }
+    if let Some(image) = &mounted {
+        log(&sink, &format!("Attached {} read-only as {}", image.image, scan_config.source));
+    }
+    log(&sink, &format!("Command: winfr {}", winfr_args.join(" ")));
+    let filter_warnings = compiled_filters.all_issues().map(|i| i.message.clone());
+    for warning in filter_warnings.chain(command.warnings) {
+        sink.emit(RecoveryEvent::Warning { message: warning });
+    }
+
+    // Hashing an image source can take as long as the scan, so it runs on the waiter too
+    let run = RecoveryRun { sink, cancelled, mounted, integrity: None };
+    thread::spawn(move || run_winfr(run, &config.source, &program, &winfr_args));
+    Ok("Recovery started".to_string())
+}
+
+// What the waiter thread needs to close a session, however it ends
+struct RecoveryRun {
+    sink: Arc<dyn EventSink>,
+    cancelled: Arc<AtomicBool>,
+    mounted: Option<MountedImage>,
+    integrity: Option<SourceIntegrity>,
+}
+
+fn run_winfr(mut run: RecoveryRun, source: &str, program: &str, winfr_args: &[String]) {
+    // Image sources are hashed before winfr reads them and again once they are detached
+    let sink = run.sink.clone();
+    match safety::hash_source_image(source, |progress| sink.emit(progress.event())) {
+        Ok(integrity) => run.integrity = integrity,
+        Err(e) => return finish_run(run, Err(e)),
+    }
+    if let Some(integrity) = &run.integrity {
+        session::set_source_integrity(integrity);
+        for file in &integrity.files {
+            log(&sink, &format!("SHA-256 of {} before the scan: {}", file.path, file.before.sha256));
+        }
+    }
+    if run.cancelled.load(Ordering::SeqCst) {
+        return finish_run(run, Err("Cancelled before winfr started.".to_string()));
+    }
+    log(&sink, "Starting recovery process...");
 
     // Spawn winfr directly — app already has admin privileges
-    let child = Command::new("winfr")
-        .args(&winfr_args)
+    let child = hidden_command(program)
+        .args(winfr_args)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
-        .creation_flags(0x08000000) // CREATE_NO_WINDOW — prevent console flash
         .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => return finish_run(run, Err(format!("Failed to launch winfr: {}", e))),
+    };
+    let stdout_reader = child.stdout.take().expect("Failed to open stdout");
+    let stderr_reader = child.stderr.take().expect("Failed to open stderr");
+
+    // Shared log cache to prevent cross-pipe duplication
+    let recent_logs = Arc::new(Mutex::new(VecDeque::<String>::with_capacity(10)));
+    let recent_logs_stderr = recent_logs.clone();
+
+    // Spawn thread to read stdout
+    let sink_stdout = sink.clone();
+    let cancelled_stdout = run.cancelled.clone();
+    let stdout_thread = thread::spawn(move || {
+        let mut parser = OutputParser::new();
+        read_utf16_lines(stdout_reader, |line| {
+            // Ignore whatever winfr prints after a cancel
+            if cancelled_stdout.load(Ordering::SeqCst) {
+                return;
+            }
+            for event in parser.parse_line(&line) {
+                if let RecoveryEvent::RecoveryFolder { folder } = &event {
+                    session::set_recovery_folder(folder);
+                }
+                sink_stdout.emit(event);
+            }
+            // Only emit to logs if NOT a progress line to avoid clutter
+            if !parser.is_progress_line(&line) && remember_line(&recent_logs, &line) {
+                sink_stdout.emit(RecoveryEvent::Log { source: LogSource::Stdout, message: line });
+            }
+        });
+    });
 
-    match child {
-        Ok(mut child) => {
-            let app_clone = app.clone();
-            let mut stdout_reader = child.stdout.take().expect("Failed to open stdout");
-            let mut stderr_reader = child.stderr.take().expect("Failed to open stderr");
-
-            // Shared log cache to prevent cross-pipe duplication
-            let recent_logs = Arc::new(Mutex::new(std::collections::VecDeque::<String>::with_capacity(10)));
-            let recent_logs_stderr = recent_logs.clone();
-            let recent_logs_stdout = recent_logs.clone();
-
-            // Spawn thread to read stdout
-            let app_stdout = app_clone.clone();
-            let stdout_thread = thread::spawn(move || {
-                let mut buffer = [0u8; 4096];
-                let mut leftover = Vec::new();
-                let mut current_phase = "scanning";
-                let progress_re = regex::Regex::new(r"(\d+)%").ok();
-                let pass_re = regex::Regex::new(r"(?i)pass\s*(1|2|scanning|recovering)").ok();
-                let recovery_folder_re = regex::Regex::new(r"Recovery_\d{8}_\d{6}").ok();
-
-                while let Ok(n) = stdout_reader.read(&mut buffer) {
-                    if n == 0 { break; }
-                    
-                    // Combine leftover from previous read
-                    let mut current_bytes = leftover.clone();
-                    current_bytes.extend_from_slice(&buffer[..n]);
-
-                    // UTF-16LE characters are 2 bytes each
-                    // If we have an odd number of bytes, save the last one for next time
-                    if current_bytes.len() % 2 != 0 {
-                        leftover = vec![current_bytes.pop().unwrap()];
-                    } else {
-                        leftover.clear();
-                    }
+    // Spawn thread to read stderr
+    let sink_stderr = sink.clone();
+    let stderr_thread = thread::spawn(move || {
+        read_utf16_lines(stderr_reader, |line| {
+            if remember_line(&recent_logs_stderr, &line) {
+                sink_stderr.emit(RecoveryEvent::Log { source: LogSource::Stderr, message: line });
+            }
+        });
+    });
 
-                    if current_bytes.is_empty() { continue; }
+    let exit_status = wait_for_child(child, &run.cancelled);
 
-                    // Decode UTF-16LE to UTF-8
-                    let (decoded, _, _) = UTF_16LE.decode(&current_bytes);
-                    let text = decoded.into_owned();
+    // Wait for reader threads to finish
+    let _ = stdout_thread.join();
+    let _ = stderr_thread.join();
 
-                    // Split by lines and emit
-                    for line in text.lines() {
-                        let trimmed = line.trim().to_string();
-                        if trimmed.is_empty() { continue; }
+    finish_run(run, exit_status.map_err(|e| format!("Failed to wait for process: {}", e)));
+}
 
-                        // Check if cancelled
-                        {
-                            let active = RECOVERY_ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
-                            if !*active { break; }
-                        }
+// Detaches and re-hashes the source, reports how the session ended and seals it. `Err`
+// holds why winfr did not run to an exit status.
+fn finish_run(run: RecoveryRun, exit_status: Result<ExitStatus, String>) {
+    let RecoveryRun { sink, cancelled, mounted, integrity } = run;
+    detach_image(&mounted);
+    verify_source(&sink, integrity);
+    let code = exit_status.as_ref().ok().and_then(|s| s.code());
+    if cancelled.load(Ordering::SeqCst) {
+        // Was cancelled by user
+        session::finish("aborted", code);
+        sink.emit(RecoveryEvent::Status { status: RecoveryStatus::Aborted, progress: None });
+        log(&sink, "! OPERATION ABORTED BY USER !");
+    } else {
+        report_exit(&sink, exit_status);
+    }
 
-                        // Detect phase from log keywords
-                        if let Some(ref re) = pass_re {
-                            if let Some(caps) = re.captures(&trimmed) {
-                                let pass_id = caps[1].to_lowercase();
-                                if pass_id == "1" || pass_id == "scanning" {
-                                    current_phase = "scanning";
-                                } else if pass_id == "2" || pass_id == "recovering" {
-                                    current_phase = "recovering";
-                                }
-                            }
-                        }
+    // Sealed before the next recovery may start and begin its own session
+    seal_audit_log(&sink);
+    release(&cancelled);
+    sink.emit(RecoveryEvent::Exited { code });
+}
 
-                        // Try to extract progress percentage
-                        if let Some(ref re) = progress_re {
-                            if let Some(caps) = re.captures(&trimmed) {
-                                if let Ok(mut pct) = caps[1].parse::<f64>() {
-                                    // Weighted progress: 
-                                    // Pass 1 (Scanning) maps 0-100% to 0-50%
-                                    // Pass 2 (Recovering) maps 0-100% to 51-100%
-                                    if current_phase == "scanning" {
-                                        pct *= 0.5;
-                                    } else {
-                                        pct = 50.0 + (pct * 0.5);
-                                    }
-
-                                    let _ = app_stdout.emit("recovery-progress", RecoveryEvent {
-                                        event_type: "progress".to_string(),
-                                        message: trimmed.clone(),
-                                        progress: Some(pct.min(100.0)),
-                                        path: None,
-                                    });
-
-                                    let _ = app_stdout.emit("recovery-status", RecoveryEvent {
-                                        event_type: "status".to_string(),
-                                        message: current_phase.to_string(),
-                                        progress: Some(pct),
-                                        path: None,
-                                    });
-                                }
-                            }
-                        }
+fn report_exit(sink: &Arc<dyn EventSink>, exit_status: Result<ExitStatus, String>) {
+    match exit_status {
+        Ok(status) => {
+            session::finish(if status.success() { "completed" } else { "error" }, status.code());
+            if status.success() {
+                sink.emit(RecoveryEvent::Status { status: RecoveryStatus::Completed, progress: Some(100.0) });
+                log(sink, "✓ Recovery operation completed successfully.");
+            } else {
+                let code = status.code().unwrap_or(-1);
+                sink.emit(RecoveryEvent::Status { status: RecoveryStatus::Error, progress: None });
+
+                if is_access_violation(&status) {
+                    log(sink, "CRASH DETECTED: winfr.exe encountered an Access Violation (0xC0000005).");
+                    log(sink, "This is a known bug in winfr.exe when scanning exFAT drives in Extensive mode.");
+                    log(sink, "--- TROUBLESHOOTING ---");
+                    log(sink, "1. Run health check on source drive: chkdsk E: /f");
+                    log(sink, "2. Check Microsoft Store for 'Windows File Recovery' updates.");
+                    log(sink, "3. Try 'Advanced Options' -> Disable 'Keep Both' to reduce file conflicts.");
+                    log(sink, "4. On exFAT, use the native exFAT scanner, which does not run winfr.");
+                } else {
+                    log(sink, &format!("Recovery process exited with code: {}", code));
+                }
+            }
+        }
+        Err(e) => {
+            session::finish("error", None);
+            sink.emit(RecoveryEvent::Status { status: RecoveryStatus::Error, progress: None });
+            log(sink, &e);
+        }
+    }
+}
 
-                        // Extract the actual recovery folder path
-                        if let Some(caps) = recovery_folder_re.as_ref().and_then(|re| re.captures(&trimmed)) {
-                            let folder_name = &caps[0];
-                            let _ = app_stdout.emit("recovery-path", RecoveryEvent {
-                                event_type: "path".to_string(),
-                                message: folder_name.to_string(),
-                                progress: None,
-                                path: Some(folder_name.to_string()),
-                            });
-                        }
+// 0xC0000005 Access Violation on Windows; on other platforms (test engines) a fatal signal
+fn is_access_violation(status: &ExitStatus) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if status.signal().is_some() {
+            return true;
+        }
+    }
+    status.code() == Some(-1073741819)
+}
 
-                        // Only emit to logs if NOT a progress line to avoid clutter
-                        let is_progress = progress_re.as_ref().is_some_and(|re| re.is_match(&trimmed));
-                        if !is_progress {
-                            // De-duplicate check
-                            let should_emit = {
-                                let mut logs = recent_logs_stdout.lock().unwrap();
-                                if logs.contains(&trimmed) {
-                                    false
-                                } else {
-                                    if logs.len() >= 10 { logs.pop_front(); }
-                                    logs.push_back(trimmed.clone());
-                                    true
-                                }
-                            };
-
-                            if should_emit {
-                                let _ = app_stdout.emit("recovery-log", RecoveryEvent {
-                                    event_type: "log".to_string(),
-                                    message: trimmed,
-                                    progress: None,
-                                    path: None,
-                                });
-                            }
-                        }
-                    }
-                }
-            });
-
-            // Spawn thread to read stderr
-            let app_stderr = app_clone.clone();
-            let stderr_thread = thread::spawn(move || {
-                let mut buffer = [0u8; 4096];
-                let mut leftover = Vec::new();
-
-                while let Ok(n) = stderr_reader.read(&mut buffer) {
-                    if n == 0 { break; }
-                    
-                    let mut current_bytes = leftover.clone();
-                    current_bytes.extend_from_slice(&buffer[..n]);
-
-                    if current_bytes.len() % 2 != 0 {
-                        leftover = vec![current_bytes.pop().unwrap()];
-                    } else {
-                        leftover.clear();
-                    }
+fn detach_image(mounted: &Option<MountedImage>) {
+    if let Some(image) = mounted {
+        let _ = images::dismount(&image.image);
+    }
+}
 
-                    if current_bytes.is_empty() { continue; }
-
-                    let (decoded, _, _) = UTF_16LE.decode(&current_bytes);
-                    let text = decoded.into_owned();
-
-                    for line in text.lines() {
-                        let trimmed = line.trim().to_string();
-                        if !trimmed.is_empty() {
-                            // De-duplicate check
-                            let should_emit = {
-                                let mut logs = recent_logs_stderr.lock().unwrap();
-                                if logs.contains(&trimmed) {
-                                    false
-                                } else {
-                                    if logs.len() >= 10 { logs.pop_front(); }
-                                    logs.push_back(trimmed.clone());
-                                    true
-                                }
-                            };
-
-                            if should_emit {
-                                let _ = app_stderr.emit("recovery-log", RecoveryEvent {
-                                    event_type: "log".to_string(),
-                                    message: format!("[stderr] {}", trimmed),
-                                    progress: None,
-                                    path: None,
-                                });
-                            }
-                        }
-                    }
-                }
-            });
-
-            // Spawn thread to wait for process completion
-            thread::spawn(move || {
-                let exit_status = child.wait();
-
-                // Wait for reader threads to finish
-                let _ = stdout_thread.join();
-                let _ = stderr_thread.join();
-
-                let was_active = {
-                    let mut active = RECOVERY_ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
-                    let was = *active;
-                    *active = false;
-                    was
-                };
-
-                if !was_active {
-                    // Was cancelled by user
-                    let _ = app_clone.emit("recovery-status", RecoveryEvent {
-                        event_type: "status".to_string(),
-                        message: "aborted".to_string(),
-                        progress: None,
-                        path: None,
-                    });
-                    let _ = app_clone.emit("recovery-log", RecoveryEvent {
-                        event_type: "log".to_string(),
-                        message: "! OPERATION ABORTED BY USER !".to_string(),
-                        progress: None,
-                        path: None,
-                    });
-                    return;
-                }
+// Hashes an image source again after the session; a changed image is reported, never hidden
+fn verify_source(sink: &Arc<dyn EventSink>, integrity: Option<SourceIntegrity>) {
+    let Some(mut integrity) = integrity else { return };
+    safety::verify_source_image(&mut integrity, |progress| sink.emit(progress.event()));
+    safety::report_integrity(sink.as_ref(), &integrity);
+    let destination = session::current().map(|s| s.config.destination).unwrap_or_default();
+    let recorded = audit::record(
+        &destination,
+        "source-verified",
+        serde_json::json!({
+            "image": integrity.image,
+            "files": integrity.files.iter().map(|f| serde_json::json!({
+                "path": f.path,
+                "sha256Before": f.before.sha256,
+                "sha256After": f.after.as_ref().map(|h| h.sha256.clone()),
+            })).collect::<Vec<_>>(),
+            "unchanged": integrity.unchanged,
+        }),
+    );
+    warn_unaudited(sink, recorded);
+    session::set_source_integrity(&integrity);
+}
 
-                match exit_status {
-                    Ok(status) => {
-                        if status.success() {
-                            let _ = app_clone.emit("recovery-status", RecoveryEvent {
-                                event_type: "status".to_string(),
-                                message: "completed".to_string(),
-                                progress: Some(100.0),
-                                path: None,
-                            });
-                            let _ = app_clone.emit("recovery-log", RecoveryEvent {
-                                event_type: "log".to_string(),
-                                message: "✓ Recovery operation completed successfully.".to_string(),
-                                progress: None,
-                                path: None,
-                            });
-                        } else {
-                            let code = status.code().unwrap_or(-1);
-                            let _ = app_clone.emit("recovery-status", RecoveryEvent {
-                                event_type: "status".to_string(),
-                                message: "error".to_string(),
-                                progress: None,
-                                path: None,
-                            });
-                            
-                            if code == -1073741819 {
-                                // 0xC0000005 Access Violation
-                                let _ = app_clone.emit("recovery-log", RecoveryEvent {
-                                    event_type: "log".to_string(),
-                                    message: "CRASH DETECTED: winfr.exe encountered an Access Violation (0xC0000005).".to_string(),
-                                    progress: None,
-                                    path: None,
-                                });
-                                let _ = app_clone.emit("recovery-log", RecoveryEvent {
-                                    event_type: "log".to_string(),
-                                    message: "This is a known bug in winfr.exe when scanning exFAT drives in Extensive mode.".to_string(),
-                                    progress: None,
-                                    path: None,
-                                });
-                                let _ = app_clone.emit("recovery-log", RecoveryEvent {
-                                    event_type: "log".to_string(),
-                                    message: "--- TROUBLESHOOTING ---".to_string(),
-                                    progress: None,
-                                    path: None,
-                                });
-                                let _ = app_clone.emit("recovery-log", RecoveryEvent {
-                                    event_type: "log".to_string(),
-                                    message: "1. Run health check on source drive: chkdsk E: /f".to_string(),
-                                    progress: None,
-                                    path: None,
-                                });
-                                let _ = app_clone.emit("recovery-log", RecoveryEvent {
-                                    event_type: "log".to_string(),
-                                    message: "2. Check Microsoft Store for 'Windows File Recovery' updates.".to_string(),
-                                    progress: None,
-                                    path: None,
-                                });
-                                let _ = app_clone.emit("recovery-log", RecoveryEvent {
-                                    event_type: "log".to_string(),
-                                    message: "3. Try 'Advanced Options' -> Disable 'Keep Both' to reduce file conflicts.".to_string(),
-                                    progress: None,
-                                    path: None,
-                                });
-                            } else {
-                                let _ = app_clone.emit("recovery-log", RecoveryEvent {
-                                    event_type: "log".to_string(),
-                                    message: format!("Recovery process exited with code: {}", code),
-                                    progress: None,
-                                    path: None,
-                                });
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        let _ = app_clone.emit("recovery-status", RecoveryEvent {
-                            event_type: "status".to_string(),
-                            message: "error".to_string(),
-                            progress: None,
-                            path: None,
-                        });
-                        let _ = app_clone.emit("recovery-log", RecoveryEvent {
-                            event_type: "log".to_string(),
-                            message: format!("Failed to wait for process: {}", e),
-                            progress: None,
-                            path: None,
-                        });
-                    }
-                }
-            });
+fn seal_audit_log(sink: &Arc<dyn EventSink>) {
+    if let Some(finished) = session::current() {
+        warn_unaudited(sink, audit::record_session_end(&finished));
+    }
+}
+
+// An action that could not be written to the audit log is reported, never dropped
+fn warn_unaudited(sink: &Arc<dyn EventSink>, recorded: Result<(), String>) {
+    if let Err(e) = recorded {
+        sink.emit(RecoveryEvent::Warning { message: format!("Not recorded in the audit log: {}", e) });
+    }
+}
+
+fn log(sink: &Arc<dyn EventSink>, message: &str) {
+    sink.emit(RecoveryEvent::Log { source: LogSource::App, message: message.to_string() });
+}
+
+// De-duplicate check across both pipes; returns false if the line was seen recently
+fn remember_line(recent: &Mutex<VecDeque<String>>, line: &str) -> bool {
+    let mut logs = recent.lock().unwrap_or_else(|e| e.into_inner());
+    if logs.iter().any(|l| l == line) {
+        return false;
+    }
+    if logs.len() >= 10 { logs.pop_front(); }
+    logs.push_back(line.to_string());
+    true
+}
 
-            Ok("Recovery started".to_string())
+// Polls instead of blocking in wait() so a cancel can kill the child meanwhile
+fn wait_for_child(mut child: Child, cancelled: &AtomicBool) -> std::io::Result<ExitStatus> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
         }
-        Err(e) => {
-            let mut active = RECOVERY_ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
-            *active = false;
-            Err(format!("Failed to launch winfr: {}", e))
+        if cancelled.load(Ordering::SeqCst) {
+            let _ = child.kill();
+            return child.wait();
         }
+        thread::sleep(Duration::from_millis(100));
     }
 }
 
-// ── Cancel Recovery ─────────────────────────────────────────────────
-
-pub fn cancel_recovery() -> Result<String, String> {
-    let mut active = RECOVERY_ACTIVE.lock().map_err(|e| e.to_string())?;
-    if !*active {
-        return Err("No recovery operation is running.".to_string());
+// Ends the recovery that owns `cancelled`, so another can start
+fn release(cancelled: &Arc<AtomicBool>) {
+    let mut running = RECOVERY.lock().unwrap_or_else(|e| e.into_inner());
+    if running.as_ref().is_some_and(|token| Arc::ptr_eq(token, cancelled)) {
+        *running = None;
     }
-    *active = false;
+}
 
-    // Kill any running winfr processes
-    let _ = Command::new("taskkill")
-        .args(["/F", "/IM", "winfr.exe"])
-        .creation_flags(0x08000000)
-        .output();
+// ── winfr stdout parsing ────────────────────────────────────────────
 
-    Ok("Recovery cancelled".to_string())
+pub struct OutputParser {
+    current_phase: RecoveryStatus,
+    progress_re: Option<Regex>,
+    pass_re: Option<Regex>,
+    recovery_folder_re: Option<Regex>,
 }
 
-// ── Scan Recovered Files ────────────────────────────────────────────
+impl Default for OutputParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-pub fn scan_recovered_files(destination: &str) -> Result<Vec<RecoveredFile>, String> {
-    let dest_path = Path::new(destination);
+impl OutputParser {
+    pub fn new() -> Self {
+        OutputParser {
+            current_phase: RecoveryStatus::Scanning,
+            progress_re: Regex::new(r"(\d+)%").ok(),
+            pass_re: Regex::new(r"(?i)pass\s*(1|2|scanning|recovering)").ok(),
+            recovery_folder_re: Regex::new(r"Recovery_\d{8}_\d{6}").ok(),
+        }
+    }
 
-    if !dest_path.exists() {
-        return Err(format!("Destination path does not exist: {}", destination));
+    pub fn is_progress_line(&self, line: &str) -> bool {
+        self.progress_re.as_ref().is_some_and(|re| re.is_match(line))
     }
 
-    // winfr creates a subfolder like "Recovery_YYYYMMDD_HHMMSS" in the destination
-    let recovery_dir = find_latest_recovery_dir(dest_path)
-        .unwrap_or_else(|| dest_path.to_path_buf());
+    pub fn parse_line(&mut self, line: &str) -> Vec<RecoveryEvent> {
+        let mut events = Vec::new();
 
-    let mut files: Vec<RecoveredFile> = Vec::new();
-    let mut id_counter = 0u64;
+        // Detect phase from log keywords
+        if let Some(caps) = self.pass_re.as_ref().and_then(|re| re.captures(line)) {
+            let pass_id = caps[1].to_lowercase();
+            if pass_id == "1" || pass_id == "scanning" {
+                self.current_phase = RecoveryStatus::Scanning;
+            } else if pass_id == "2" || pass_id == "recovering" {
+                self.current_phase = RecoveryStatus::Recovering;
+            }
+        }
 
-    for entry in WalkDir::new(&recovery_dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        if entry.file_type().is_file() {
-            let path = entry.path();
-            let name = path
-                .file_name()
-                .map(|n| n.to_string_lossy().to_string())
-                .unwrap_or_default();
-            let ext = path
-                .extension()
-                .map(|e| e.to_string_lossy().to_string())
-                .unwrap_or_default();
-            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
-            let relative_path = path
-                .strip_prefix(&recovery_dir)
-                .unwrap_or(path)
-                .to_string_lossy()
-                .to_string();
-
-            id_counter += 1;
-            files.push(RecoveredFile {
-                id: id_counter.to_string(),
-                name,
-                path: format!("\\{}", relative_path.replace("/", "\\")),
-                size,
-                category: categorize_file(&ext),
-            });
+        // Try to extract progress percentage
+        if let Some(caps) = self.progress_re.as_ref().and_then(|re| re.captures(line)) {
+            if let Ok(mut pct) = caps[1].parse::<f64>() {
+                // Weighted progress:
+                // Pass 1 (Scanning) maps 0-100% to 0-50%
+                // Pass 2 (Recovering) maps 0-100% to 51-100%
+                if self.current_phase == RecoveryStatus::Scanning {
+                    pct *= 0.5;
+                } else {
+                    pct = 50.0 + (pct * 0.5);
+                }
+                events.push(RecoveryEvent::Progress {
+                    percent: pct,
+                    phase: self.current_phase,
+                    line: line.to_string(),
+                });
+            }
+        }
+
+        // Extract the actual recovery folder path
+        if let Some(caps) = self.recovery_folder_re.as_ref().and_then(|re| re.captures(line)) {
+            events.push(RecoveryEvent::RecoveryFolder { folder: caps[0].to_string() });
+        }
+
+        events
+    }
+}
+
+// Decodes winfr's UTF-16LE output into trimmed, non-empty lines
+pub(crate) fn read_utf16_lines<R: Read, F: FnMut(String)>(mut reader: R, mut on_line: F) {
+    let mut buffer = [0u8; 4096];
+    let mut leftover: Vec<u8> = Vec::new();
+
+    while let Ok(n) = reader.read(&mut buffer) {
+        if n == 0 { break; }
+        leftover.extend_from_slice(&buffer[..n]);
+
+        // Keep an odd trailing byte and any unterminated line for the next read
+        let even_len = leftover.len() - leftover.len() % 2;
+        // No BOM sniffing so decoded length maps back to bytes exactly
+        let (decoded, _) = UTF_16LE.decode_without_bom_handling(&leftover[..even_len]);
+        let text = decoded.into_owned();
+        let complete = text.rfind(['\n', '\r']).map(|i| i + 1).unwrap_or(0);
+        emit_lines(&text[..complete], &mut on_line);
+
+        let consumed = text[..complete].encode_utf16().count() * 2;
+        leftover.drain(..consumed);
+    }
+
+    let (decoded, _) = UTF_16LE.decode_without_bom_handling(&leftover);
+    emit_lines(&decoded, &mut on_line);
+}
+
+fn emit_lines<F: FnMut(String)>(text: &str, on_line: &mut F) {
+    // winfr redraws progress with a bare '\r', so treat it as a line break too
+    for line in text.split(['\n', '\r']) {
+        let trimmed = line.trim_matches(|c: char| c.is_whitespace() || c == '\u{feff}');
+        if !trimmed.is_empty() {
+            on_line(trimmed.to_string());
         }
     }
+}
+
+// ── Cancel Recovery ─────────────────────────────────────────────────
+
+pub fn cancel_recovery() -> Result<String, String> {
+    let running = RECOVERY.lock().map_err(|e| e.to_string())?;
+    match running.as_ref() {
+        // The waiter thread kills winfr and reports the abort
+        Some(cancelled) if !cancelled.swap(true, Ordering::SeqCst) => Ok("Recovery cancelled".to_string()),
+        _ => Err("No recovery operation is running.".to_string()),
+    }
+}
+
+// ── Scan Recovered Files ────────────────────────────────────────────
+
+pub fn scan_recovered_files(destination: &str, known_filter: Option<&[KnownStatus]>) -> Result<Vec<RecoveredFile>, String> {
+    // The persistent index only re-reads files that changed since the last scan
+    index::refresh_index(destination, |_| {})?;
+    index::apply_hash_sets(destination)?;
+    let mut files = index::all_files(destination)?;
+
+    if let Some(filter) = known_filter {
+        files.retain(|f| f.known_status.is_none_or(|status| filter.contains(&status)));
+    }
 
-    files.sort_by(|a, b| a.category.cmp(&b.category).then(a.name.cmp(&b.name)));
     Ok(files)
 }
 
-fn find_latest_recovery_dir(base: &Path) -> Option<PathBuf> {
+pub(crate) fn find_latest_recovery_dir(base: &Path) -> Option<PathBuf> {
     let mut latest: Option<(PathBuf, std::time::SystemTime)> = None;
 
     if let Ok(entries) = fs::read_dir(base) {
@@ -667,9 +602,8 @@ pub fn get_disk_health(drive: String) -> Result<String, String> {
         drive_fix.push(':');
     }
 
-    let output = Command::new("chkdsk")
+    let output = hidden_command("chkdsk")
         .arg(&drive_fix)
-        .creation_flags(0x08000000) // CREATE_NO_WINDOW
         .output()
         .map_err(|e| e.to_string())?;
 
@@ -0,0 +1,290 @@
+use crate::index;
+use crate::recovery::RecoveredFile;
+use crate::session::RecoverySession;
+use crate::timestamps::{format_datetime, now_secs};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+// ── Types ────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CategorySummary {
+    pub category: String,
+    pub count: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveryReport {
+    #[serde(rename = "generatedAt")]
+    pub generated_at: u64,
+    #[serde(rename = "appVersion")]
+    pub app_version: String,
+    pub session: RecoverySession,
+    #[serde(rename = "commandLine")]
+    pub command_line: String,
+    #[serde(rename = "durationSecs")]
+    pub duration_secs: Option<u64>,
+    #[serde(rename = "recoveryDir")]
+    pub recovery_dir: String,
+    #[serde(rename = "totalFiles")]
+    pub total_files: u64,
+    #[serde(rename = "totalSize")]
+    pub total_size: u64,
+    pub categories: Vec<CategorySummary>,
+    pub files: Vec<RecoveredFile>,
+}
+
+// ── Building ─────────────────────────────────────────────────────────
+
+// The folder winfr reported for this session, or the latest one in the destination
+pub fn session_recovery_path(session: &RecoverySession) -> String {
+    match &session.recovery_folder {
+        Some(folder) => Path::new(&session.config.destination).join(folder).to_string_lossy().to_string(),
+        None => session.config.destination.clone(),
+    }
+}
+
+pub fn build_report(session: &RecoverySession) -> Result<RecoveryReport, String> {
+    let recovery_path = session_recovery_path(session);
+    let recovery_dir = index::resolve_recovery_dir(&recovery_path)?;
+    index::refresh_index(&recovery_path, |_| {})?;
+    let files = index::all_files(&recovery_path)?;
+
+    let mut categories: BTreeMap<String, CategorySummary> = BTreeMap::new();
+    for file in &files {
+        let entry = categories.entry(file.category.clone()).or_insert_with(|| CategorySummary {
+            category: file.category.clone(),
+            count: 0,
+            size: 0,
+        });
+        entry.count += 1;
+        entry.size += file.size;
+    }
+
+    Ok(RecoveryReport {
+        generated_at: now_secs(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        session: session.clone(),
+        command_line: format!("winfr {}", session.args.join(" ")),
+        duration_secs: session.finished_at.map(|end| end.saturating_sub(session.started_at)),
+        recovery_dir: recovery_dir.to_string_lossy().to_string(),
+        total_files: files.len() as u64,
+        total_size: files.iter().map(|f| f.size).sum(),
+        categories: categories.into_values().collect(),
+        files,
+    })
+}
+
+// ── Rendering ────────────────────────────────────────────────────────
+
+pub fn render_report(report: &RecoveryReport, format: &str) -> Result<String, String> {
+    match format.to_lowercase().as_str() {
+        "json" => serde_json::to_string_pretty(report).map_err(|e| e.to_string()),
+        "csv" => Ok(render_csv(report)),
+        "html" => Ok(render_html(report)),
+        other => Err(format!("Unsupported report format: {}", other)),
+    }
+}
+
+pub fn export_report(session: &RecoverySession, format: &str, output: &str) -> Result<String, String> {
+    let report = build_report(session)?;
+    let content = render_report(&report, format)?;
+    fs::write(output, content).map_err(|e| format!("Failed to write report: {}", e))?;
+    Ok(output.to_string())
+}
+
+fn render_csv(report: &RecoveryReport) -> String {
+    let mut out = String::from("id,name,path,size,category,modified,integrity,known_status,md5,sha1,sha256\r\n");
+    for f in &report.files {
+        let hashes = f.hashes.as_ref();
+        let row = [
+            f.id.clone(),
+            f.name.clone(),
+            f.path.clone(),
+            f.size.to_string(),
+            f.category.clone(),
+            f.modified.map(format_datetime).unwrap_or_default(),
+            enum_name(&f.integrity),
+            f.known_status.as_ref().map(enum_name).unwrap_or_default(),
+            hashes.map(|h| h.md5.clone()).unwrap_or_default(),
+            hashes.map(|h| h.sha1.clone()).unwrap_or_default(),
+            hashes.map(|h| h.sha256.clone()).unwrap_or_default(),
+        ];
+        let escaped: Vec<String> = row.iter().map(|v| csv_escape(v)).collect();
+        out.push_str(&escaped.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn render_html(report: &RecoveryReport) -> String {
+    let s = &report.session;
+    let mut html = String::new();
+
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>Winfr Pro Recovery Report - {}</title>\n", esc(&s.id)));
+    // Inline styles only, the report must open offline
+    html.push_str(
+        "<style>\
+body{font-family:Segoe UI,Arial,sans-serif;margin:32px;color:#111}\
+h1{font-size:22px}h2{font-size:17px;margin-top:28px;border-bottom:1px solid #ccc}\
+table{border-collapse:collapse;width:100%;font-size:13px}\
+th,td{border:1px solid #ddd;padding:4px 8px;text-align:left}th{background:#f3f3f3}\
+td.num{text-align:right}code{background:#f3f3f3;padding:2px 4px}\
+</style>\n</head>\n<body>\n",
+    );
+    html.push_str("<h1>Winfr Pro Recovery Report</h1>\n");
+
+    html.push_str("<h2>Summary</h2>\n<table>\n");
+    let summary_rows = [
+        ("Session", s.id.clone()),
+        ("Generated", format_datetime(report.generated_at)),
+        ("Winfr Pro version", report.app_version.clone()),
+        ("Status", s.status.clone()),
+        ("Exit code", s.exit_code.map(|c| c.to_string()).unwrap_or_else(|| "-".to_string())),
+        ("Started", format_datetime(s.started_at)),
+        ("Finished", s.finished_at.map(format_datetime).unwrap_or_else(|| "-".to_string())),
+        ("Duration", report.duration_secs.map(format_duration).unwrap_or_else(|| "-".to_string())),
+        ("Recovery folder", report.recovery_dir.clone()),
+        ("Files recovered", report.total_files.to_string()),
+        ("Total size", format_size(report.total_size)),
+    ];
+    for (k, v) in summary_rows {
+        html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", k, esc(&v)));
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Configuration</h2>\n<table>\n");
+    let c = &s.config;
+    let config_rows = [
+        ("Source", c.source.clone()),
+        ("Destination", c.destination.clone()),
+        ("Source filesystem", c.source_fs.clone().unwrap_or_else(|| "-".to_string())),
+        ("Mode", c.mode.clone()),
+        ("Segment mode", c.segment_mode.to_string()),
+        ("Signature mode", c.signature_mode.to_string()),
+        ("Filters", if c.filters.is_empty() { "-".to_string() } else { c.filters.join(", ") }),
+        ("Recover non-deleted", c.recover_non_deleted.to_string()),
+        ("Keep both", c.keep_both.to_string()),
+        ("Recover system files", c.recover_system_files.to_string()),
+        ("Keep all extensions", c.keep_all_extensions.to_string()),
+        ("Verbose", c.verbose_mode.to_string()),
+    ];
+    for (k, v) in config_rows {
+        html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", k, esc(&v)));
+    }
+    html.push_str(&format!(
+        "<tr><th>Command line</th><td><code>{}</code></td></tr>\n</table>\n",
+        esc(&report.command_line)
+    ));
+
+    if let Some(integrity) = &s.source_integrity {
+        html.push_str("<h2>Source integrity</h2>\n<table>\n");
+        let result = match (integrity.unchanged, &integrity.error) {
+            (Some(true), _) => "Unchanged: the hashes after the session match".to_string(),
+            (Some(false), _) => "CHANGED: the hashes after the session differ".to_string(),
+            (None, Some(error)) => format!("Not verified: {}", error),
+            (None, None) => "Not verified: the session did not finish".to_string(),
+        };
+        let mut integrity_rows = vec![("Image", integrity.image.clone()), ("Result", result)];
+        // One block per file: an E01 set or a differencing disk is read from several
+        for file in &integrity.files {
+            let after = file.after.as_ref();
+            integrity_rows.extend([
+                ("File", file.path.clone()),
+                ("MD5 before", file.before.md5.clone()),
+                ("SHA-1 before", file.before.sha1.clone()),
+                ("SHA-256 before", file.before.sha256.clone()),
+                ("MD5 after", after.map(|h| h.md5.clone()).unwrap_or_else(|| "-".to_string())),
+                ("SHA-1 after", after.map(|h| h.sha1.clone()).unwrap_or_else(|| "-".to_string())),
+                ("SHA-256 after", after.map(|h| h.sha256.clone()).unwrap_or_else(|| "-".to_string())),
+            ]);
+        }
+        for (k, v) in integrity_rows {
+            html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", k, esc(&v)));
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("<h2>Categories</h2>\n<table>\n<tr><th>Category</th><th>Files</th><th>Size</th></tr>\n");
+    for cat in &report.categories {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>\n",
+            esc(&cat.category),
+            cat.count,
+            format_size(cat.size)
+        ));
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Files</h2>\n<table>\n<tr><th>#</th><th>Name</th><th>Path</th><th>Size</th><th>Category</th><th>Integrity</th><th>SHA-256</th></tr>\n");
+    for f in &report.files {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"num\">{}</td><td>{}</td><td>{}</td><td><code>{}</code></td></tr>\n",
+            esc(&f.id),
+            esc(&f.name),
+            esc(&f.path),
+            format_size(f.size),
+            esc(&f.category),
+            enum_name(&f.integrity),
+            f.hashes.as_ref().map(|h| h.sha256.as_str()).unwrap_or("-"),
+        ));
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────
+
+fn esc(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+// Recovered names are attacker-controlled, so a leading formula character is defused with
+// a quote before the cell reaches a spreadsheet
+fn csv_escape(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+// Serialized (kebab-case) name of a unit enum variant
+fn enum_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_default()
+}
+
+pub(crate) fn format_size(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    let b = bytes as f64;
+    if b >= KB * KB * KB {
+        format!("{:.2} GB", b / (KB * KB * KB))
+    } else if b >= KB * KB {
+        format!("{:.1} MB", b / (KB * KB))
+    } else if b >= KB {
+        format!("{:.1} KB", b / KB)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+fn format_duration(secs: u64) -> String {
+    format!("{:02}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+}
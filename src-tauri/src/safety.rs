@@ -0,0 +1,282 @@
+use crate::blockdev::{open_device, path_volume, volume_disks};
+use crate::events::{EventSink, LogSource, RecoveryEvent};
+use crate::hashing::{hash_reader_with, FileHashes};
+use crate::images::image_format;
+use crate::volume::{drive_letter, split_partition_source};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+// ── Write blocking ───────────────────────────────────────────────────
+//
+// Sources are only ever opened for reading, and nothing is written where it would land
+// on the source. Targets are compared after resolving symbolic links, junctions and
+// mount points, so another spelling of the path cannot hide the source volume. Image
+// sources are hashed before and after a recovery session to show they did not change,
+// including every segment and parent disk they are read from.
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceIntegrity {
+    pub image: String,
+    // The image first, then the E01 segments or parent disks that are read with it
+    #[serde(default)]
+    pub files: Vec<ImageFile>,
+    // None until the image has been hashed again after the session
+    pub unchanged: Option<bool>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageFile {
+    pub path: String,
+    pub before: FileHashes,
+    pub after: Option<FileHashes>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HashProgress {
+    pub path: String, // file being hashed
+    #[serde(rename = "bytesDone")]
+    pub bytes_done: u64, // over all files of the image
+    #[serde(rename = "bytesTotal")]
+    pub bytes_total: u64,
+}
+
+impl HashProgress {
+    pub fn event(&self) -> RecoveryEvent {
+        let percent = match self.bytes_total {
+            0 => 100.0,
+            total => self.bytes_done as f64 * 100.0 / total as f64,
+        };
+        RecoveryEvent::Hashing { path: self.path.clone(), percent }
+    }
+}
+
+impl SourceIntegrity {
+    // One line for the session log: whether the image is unchanged after the session
+    pub fn summary(&self) -> String {
+        match (self.unchanged, &self.error) {
+            (Some(true), _) => format!("Source image {} is unchanged after the session.", self.image),
+            (Some(false), _) => {
+                format!("Source image {} changed during the session; its hashes no longer match.", self.image)
+            }
+            (None, error) => format!(
+                "Source image {} could not be hashed after the session: {}",
+                self.image,
+                error.as_deref().unwrap_or("the session did not finish")
+            ),
+        }
+    }
+}
+
+// Opens a drive, disk or image for reading; every source reader goes through here
+pub(crate) fn open_source(path: &Path) -> Result<File, String> {
+    OpenOptions::new()
+        .read(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+}
+
+// ── Targets ──────────────────────────────────────────────────────────
+
+// Recovered files, images, tables and recovery folders must never be written onto the
+// source: not onto the same drive or disk, and not over the source image itself
+pub fn ensure_target_outside(source: &str, target: &str) -> Result<(), String> {
+    let (location, _) = split_partition_source(source);
+    let resolved = resolve_target(&share_to_local(target).unwrap_or_else(|| target.to_string()));
+    // Windows is asked which volume the target is on rather than reading a letter off the
+    // path, which a volume mounted in a folder or reached through a link would not show
+    let target_volume = path_volume(&resolved);
+    if let Some(letter) = source_letter(location) {
+        let source_volume = path_volume(Path::new(&format!("{}:\\", letter)));
+        if source_volume.is_some() && source_volume == target_volume {
+            return Err(format!(
+                "{} is on the source drive {}:. Recovered data must be saved to a different drive.",
+                target, letter
+            ));
+        }
+    } else if let Some(disk) = physical_drive(location) {
+        // A whole-disk source covers every volume on it
+        if target_volume.is_some_and(|v| volume_disks(&v).contains(&disk)) {
+            return Err(format!(
+                "{} is on a volume that lies on the source disk {}. Recovered data must be saved to a \
+                 different disk.",
+                target, location
+            ));
+        }
+    } else if Path::new(location).is_file() && resolve_target(location) == resolved {
+        return Err(format!("{} is the source image {} under another name.", target, location));
+    }
+    Ok(())
+}
+
+// Checks the recovery destination against every name of the source (an attached image
+// and its drive letter) before creating it
+pub fn create_destination(sources: &[&str], destination: &str) -> Result<(), String> {
+    for source in sources {
+        ensure_target_outside(source, destination)?;
+    }
+    fs::create_dir_all(destination).map_err(|e| format!("Failed to create destination directory: {}", e))
+}
+
+// `path` with its existing part resolved: symbolic links, junctions and mount points are
+// followed, and the part that does not exist yet is appended as written
+pub fn resolve_target(path: &str) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| PathBuf::from(path));
+    let mut existing = absolute.as_path();
+    let mut missing: Vec<OsString> = Vec::new();
+    loop {
+        if let Ok(mut resolved) = fs::canonicalize(existing) {
+            for part in missing.iter().rev() {
+                match part.to_str() {
+                    Some("..") => {
+                        resolved.pop();
+                    }
+                    Some(".") => {}
+                    _ => resolved.push(part),
+                }
+            }
+            return resolved;
+        }
+        // Components rather than file_name(), which is None for a trailing ".."
+        match (existing.parent(), existing.components().next_back()) {
+            (Some(parent), Some(last)) => {
+                missing.push(last.as_os_str().to_os_string());
+                existing = parent;
+            }
+            _ => return absolute,
+        }
+    }
+}
+
+// "E:", "E:\" and the volume device "\\.\E:" name drive E itself; a path inside it does not
+fn source_letter(location: &str) -> Option<char> {
+    let device = location.strip_prefix("\\\\.\\").or_else(|| location.strip_prefix("\\\\?\\"));
+    drive_letter(device.unwrap_or(location))
+}
+
+// Disk number of a physical disk device ("\\.\PhysicalDrive1")
+fn physical_drive(location: &str) -> Option<u32> {
+    let device = location.strip_prefix("\\\\.\\").or_else(|| location.strip_prefix("\\\\?\\"))?;
+    let number = device.get(..13).filter(|p| p.eq_ignore_ascii_case("PhysicalDrive")).and(device.get(13..))?;
+    number.parse().ok()
+}
+
+// The local path an administrative share of this computer stands for: "\\localhost\E$\x"
+// and "\\?\UNC\localhost\E$\x" are "E:\x". Windows reports shares as network paths on no
+// local volume, so they are mapped before the volume is looked up.
+fn share_to_local(path: &str) -> Option<String> {
+    let path = path.replace('/', "\\");
+    let rest = path.strip_prefix("\\\\?\\UNC\\").or_else(|| path.strip_prefix("\\\\"))?;
+    let mut parts = rest.splitn(3, '\\');
+    if !is_this_computer(parts.next()?) {
+        return None;
+    }
+    let share = parts.next()?.strip_suffix('$')?;
+    let letter = share.chars().next().filter(|l| share.len() == 1 && l.is_ascii_alphabetic())?;
+    Some(format!("{}:\\{}", letter, parts.next().unwrap_or("")))
+}
+
+fn is_this_computer(host: &str) -> bool {
+    let computer = std::env::var("COMPUTERNAME").unwrap_or_default();
+    ["localhost", "127.0.0.1", "::1", "."].iter().any(|h| host.eq_ignore_ascii_case(h))
+        || (!computer.is_empty() && host.eq_ignore_ascii_case(&computer))
+}
+
+// ── Source images ────────────────────────────────────────────────────
+
+// Image sources are hashed so a report can show they were not modified; drives are not,
+// as the system writes to mounted volumes on its own. Returns None for drives.
+pub fn hash_source_image<F>(source: &str, on_progress: F) -> Result<Option<SourceIntegrity>, String>
+where
+    F: FnMut(&HashProgress),
+{
+    if image_format(source).is_none() {
+        return Ok(None);
+    }
+    let (image, _) = split_partition_source(source);
+    let paths = image_files(image).map_err(|e| format!("Failed to hash the source image: {}", e))?;
+    let hashes = hash_files(&paths, on_progress).map_err(|e| format!("Failed to hash the source image: {}", e))?;
+    let files = paths
+        .iter()
+        .zip(hashes)
+        .map(|(path, before)| ImageFile { path: path.to_string_lossy().to_string(), before, after: None })
+        .collect();
+    Ok(Some(SourceIntegrity { image: image.to_string(), files, unchanged: None, error: None }))
+}
+
+// Hashes the image again after the session; a failure is recorded, not returned
+pub fn verify_source_image<F>(integrity: &mut SourceIntegrity, on_progress: F)
+where
+    F: FnMut(&HashProgress),
+{
+    let paths: Vec<PathBuf> = integrity.files.iter().map(|f| PathBuf::from(&f.path)).collect();
+    match hash_files(&paths, on_progress) {
+        Ok(hashes) => {
+            for (file, after) in integrity.files.iter_mut().zip(hashes) {
+                file.after = Some(after);
+            }
+            integrity.unchanged = Some(integrity.files.iter().all(|f| f.after.as_ref() == Some(&f.before)));
+        }
+        Err(e) => integrity.error = Some(e),
+    }
+}
+
+// A changed or unverified image is reported as a warning, never hidden
+pub fn report_integrity(sink: &dyn EventSink, integrity: &SourceIntegrity) {
+    let message = integrity.summary();
+    sink.emit(match integrity.unchanged {
+        Some(true) => RecoveryEvent::Log { source: LogSource::App, message },
+        _ => RecoveryEvent::Warning { message },
+    });
+}
+
+// Runs `session` between hashing an image source and hashing it again, for the native
+// scanners, partition search and imaging, which read the source themselves
+pub fn hashed_session<T, P, S>(
+    source: &str,
+    mut on_progress: P,
+    session: S,
+) -> Result<(T, Option<SourceIntegrity>), String>
+where
+    P: FnMut(&HashProgress),
+    S: FnOnce() -> Result<T, String>,
+{
+    let mut integrity = hash_source_image(source, &mut on_progress)?;
+    let result = session()?;
+    if let Some(integrity) = integrity.as_mut() {
+        verify_source_image(integrity, &mut on_progress);
+    }
+    Ok((result, integrity))
+}
+
+fn hash_files<F>(paths: &[PathBuf], mut on_progress: F) -> Result<Vec<FileHashes>, String>
+where
+    F: FnMut(&HashProgress),
+{
+    let sizes: Vec<u64> = paths.iter().map(|p| fs::metadata(p).map(|m| m.len()).unwrap_or(0)).collect();
+    let total = sizes.iter().sum();
+    let mut hashed = 0;
+    let mut hashes = Vec::new();
+    for (path, size) in paths.iter().zip(&sizes) {
+        let file = open_source(path)?;
+        let name = path.to_string_lossy().to_string();
+        let hash = hash_reader_with(file, |done| {
+            on_progress(&HashProgress { path: name.clone(), bytes_done: hashed + done, bytes_total: total })
+        })
+        .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+        hashed += size;
+        hashes.push(hash);
+    }
+    Ok(hashes)
+}
+
+// Every file opened to read `image`, as the block device reports them
+fn image_files(image: &str) -> Result<Vec<PathBuf>, String> {
+    let files = open_device(image)?.files();
+    if files.is_empty() {
+        return Err(format!("{} is not an image file.", image));
+    }
+    Ok(files)
+}
@@ -0,0 +1,110 @@
+use crate::recovery::RecoveryConfig;
+use crate::safety::SourceIntegrity;
+use crate::timestamps::now_secs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+// ── Types ────────────────────────────────────────────────────────────
+
+const SESSION_FILE_NAME: &str = ".winfrpro_session.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoverySession {
+    pub id: String,
+    pub config: RecoveryConfig,
+    pub args: Vec<String>, // exact winfr argv, excluding the program name
+    #[serde(rename = "startedAt")]
+    pub started_at: u64, // seconds since the Unix epoch
+    #[serde(rename = "finishedAt")]
+    pub finished_at: Option<u64>,
+    pub status: String, // "running", "completed", "error", "aborted"
+    #[serde(rename = "exitCode")]
+    pub exit_code: Option<i32>,
+    #[serde(rename = "recoveryFolder")]
+    pub recovery_folder: Option<String>, // "Recovery_YYYYMMDD_HHMMSS" as reported by winfr
+    // Hashes of an image source before and after the session; sessions on drives have none
+    #[serde(rename = "sourceIntegrity", default)]
+    pub source_integrity: Option<SourceIntegrity>,
+}
+
+// The session of the most recent (or currently running) recovery
+static CURRENT_SESSION: Mutex<Option<RecoverySession>> = Mutex::new(None);
+
+// ── Lifecycle ────────────────────────────────────────────────────────
+
+pub fn begin(config: &RecoveryConfig, args: &[String]) -> RecoverySession {
+    let started_at = now_secs();
+    let session = RecoverySession {
+        id: format!("session-{}", started_at),
+        config: config.clone(),
+        args: args.to_vec(),
+        started_at,
+        finished_at: None,
+        status: "running".to_string(),
+        exit_code: None,
+        recovery_folder: None,
+        source_integrity: None,
+    };
+    store(session.clone());
+    session
+}
+
+pub fn set_recovery_folder(folder: &str) {
+    update(|s| s.recovery_folder = Some(folder.to_string()));
+}
+
+pub fn set_source_integrity(integrity: &SourceIntegrity) {
+    update(|s| s.source_integrity = Some(integrity.clone()));
+}
+
+pub fn finish(status: &str, exit_code: Option<i32>) {
+    update(|s| {
+        s.status = status.to_string();
+        s.exit_code = exit_code;
+        s.finished_at = Some(now_secs());
+    });
+}
+
+pub fn current() -> Option<RecoverySession> {
+    CURRENT_SESSION.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+// Falls back to the session file left in the destination by an earlier run of the app
+pub fn load(destination: &str) -> Option<RecoverySession> {
+    if let Some(session) = current().filter(|s| s.config.destination == destination) {
+        return Some(session);
+    }
+    fs::read_to_string(Path::new(destination).join(SESSION_FILE_NAME))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+}
+
+// ── Persistence ──────────────────────────────────────────────────────
+
+fn update<F: FnOnce(&mut RecoverySession)>(f: F) {
+    let updated = {
+        let mut guard = CURRENT_SESSION.lock().unwrap_or_else(|e| e.into_inner());
+        match guard.as_mut() {
+            Some(session) => {
+                f(session);
+                session.clone()
+            }
+            None => return,
+        }
+    };
+    save(&updated);
+}
+
+fn store(session: RecoverySession) {
+    save(&session);
+    *CURRENT_SESSION.lock().unwrap_or_else(|e| e.into_inner()) = Some(session);
+}
+
+fn save(session: &RecoverySession) {
+    // Best effort: a missing session file only means reports cannot be rebuilt after a restart
+    if let Ok(json) = serde_json::to_string_pretty(session) {
+        let _ = fs::write(Path::new(&session.config.destination).join(SESSION_FILE_NAME), json);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::dialect::category_extensions;
+use crate::engine;
+use regex::Regex;
+use serde::Serialize;
+
+// ── Signature mode extension groups (`winfr /#`) ─────────────────────
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SignatureGroup {
+    pub name: String, // as passed to /y:
+    pub extensions: Vec<String>,
+}
+
+// Groups of winfr 0.1.20151, used when the installed winfr cannot be asked
+const BUILTIN_GROUPS: [(&str, &[&str]); 7] = [
+    ("ASF", &["wma", "wmv", "asf"]),
+    ("JPEG", &["jpg", "jpeg", "jpe", "jif", "jfif", "jfi"]),
+    ("MP3", &["mp3"]),
+    ("MPEG", &["mpeg", "mp4", "mpg", "m4a", "m4v", "m4b", "m4r", "mov", "3gp", "qt"]),
+    ("PDF", &["pdf"]),
+    ("PNG", &["png"]),
+    (
+        "ZIP",
+        &[
+            "zip", "docx", "xlsx", "pptx", "odt", "ods", "odp", "odg", "odi", "odf", "odc", "odm", "ott", "otg", "otp",
+            "ots", "otc", "oti", "otf", "oth",
+        ],
+    ),
+];
+
+pub fn builtin_groups() -> Vec<SignatureGroup> {
+    BUILTIN_GROUPS
+        .iter()
+        .map(|(name, extensions)| SignatureGroup {
+            name: name.to_string(),
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+        })
+        .collect()
+}
+
+// Groups reported by the installed winfr, or the built-in list if it could not be read
+pub fn list_signature_groups(configured: Option<&str>) -> Vec<SignatureGroup> {
+    let detected = engine::detect(configured).signature_groups;
+    if detected.is_empty() { builtin_groups() } else { detected }
+}
+
+// Parses `winfr /#` lines such as "JPEG:   jpg, jpeg, jpe"
+pub fn parse_groups(lines: &[String]) -> Vec<SignatureGroup> {
+    let Some(group_re) = Regex::new(r"^([A-Z0-9]+):\s+(\S.*)$").ok() else {
+        return Vec::new();
+    };
+    lines
+        .iter()
+        .filter_map(|line| group_re.captures(line.trim()))
+        .map(|caps| SignatureGroup {
+            name: caps[1].to_string(),
+            extensions: caps[2]
+                .split([',', ' '])
+                .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+                .filter(|e| !e.is_empty())
+                .collect(),
+        })
+        .collect()
+}
+
+// ── Filter mapping ───────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Default)]
+pub struct GroupSelection {
+    pub groups: Vec<String>, // sorted, for /y:
+    pub warnings: Vec<String>,
+}
+
+// Maps categories, group names and custom filters onto /y: groups. Anything that
+// cannot be expressed as a group is reported instead of being silently dropped.
+pub fn select_groups(filters: &[String], groups: &[SignatureGroup]) -> GroupSelection {
+    let mut selection = GroupSelection::default();
+    let group_for = |ext: &str| groups.iter().find(|g| g.extensions.iter().any(|e| e == ext));
+
+    for filter in filters {
+        if let Some(group) = groups.iter().find(|g| g.name.eq_ignore_ascii_case(filter)) {
+            selection.groups.push(group.name.clone());
+            continue;
+        }
+
+        if let Some(extensions) = category_extensions(filter) {
+            let mut unmatched = Vec::new();
+            for ext in extensions {
+                let ext = ext.trim_start_matches("*.");
+                match group_for(ext) {
+                    Some(group) => selection.groups.push(group.name.clone()),
+                    None => unmatched.push(format!(".{}", ext)),
+                }
+            }
+            if unmatched.len() == extensions.len() {
+                selection.warnings.push(format!("No signature group covers {}; the filter was ignored.", filter));
+            } else if !unmatched.is_empty() {
+                selection.warnings.push(format!(
+                    "{}: {} cannot be recovered in signature mode.",
+                    filter,
+                    unmatched.join(", ")
+                ));
+            }
+            continue;
+        }
+
+        if filter.contains(['\\', '/']) {
+            selection.warnings.push(format!("Path filter '{}' cannot be used in signature mode and was ignored.", filter));
+            continue;
+        }
+
+        let (stem, ext) = filter.rsplit_once('.').unwrap_or((filter.as_str(), ""));
+        let ext = ext.to_lowercase();
+        if ext.is_empty() || ext.contains(['*', '?']) {
+            selection.warnings.push(format!("'{}' has no file extension to match in signature mode and was ignored.", filter));
+            continue;
+        }
+        match group_for(&ext) {
+            Some(group) => {
+                selection.groups.push(group.name.clone());
+                if stem != "*" {
+                    selection.warnings.push(format!(
+                        "'{}' matches every .{} file in signature mode ({} group); file names cannot be filtered.",
+                        filter, ext, group.name
+                    ));
+                }
+            }
+            None => selection.warnings.push(format!("No signature group covers .{}; '{}' was ignored.", ext, filter)),
+        }
+    }
+
+    selection.groups.sort();
+    selection.groups.dedup();
+    if !filters.is_empty() && selection.groups.is_empty() {
+        selection.warnings.push("No filter maps to a signature group, so every group will be recovered.".to_string());
+    }
+    selection
+}
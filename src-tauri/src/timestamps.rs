@@ -0,0 +1,77 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// ── Unix time helpers (UTC, no timezone database needed) ─────────────
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// "YYYY-MM-DD"
+pub fn format_date(secs: u64) -> String {
+    let (y, m, d) = civil_from_days((secs / 86_400) as i64);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+// "YYYY-MM-DD HH:MM:SS UTC"
+pub fn format_datetime(secs: u64) -> String {
+    let rem = secs % 86_400;
+    format!(
+        "{} {:02}:{:02}:{:02} UTC",
+        format_date(secs),
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
+
+// "YYYY-MM-DDTHH:MM:SSZ"
+pub fn format_iso8601(secs: u64) -> String {
+    let rem = secs % 86_400;
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        format_date(secs),
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
+
+// FAT/exFAT packed date and time (local time on the writing machine, read as UTC).
+// None for a zero or out-of-range date.
+pub fn dos_datetime_to_unix(date: u16, time: u16) -> Option<u64> {
+    let (year, month, day) = (1980 + (date >> 9) as i64, ((date >> 5) & 0x0F) as u32, (date & 0x1F) as u32);
+    let (hour, minute, second) = ((time >> 11) as u64, ((time >> 5) & 0x3F) as u64, ((time & 0x1F) * 2) as u64);
+    if !(1..=12).contains(&month) || day == 0 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let days = days_from_civil(year, month, day) as u64;
+    Some(days * 86_400 + hour * 3600 + minute * 60 + second)
+}
+
+// Howard Hinnant's civil-to-days algorithm
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = if m > 2 { m - 3 } else { m + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// Howard Hinnant's days-to-civil algorithm
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
@@ -0,0 +1,124 @@
+use crate::index;
+use crate::recovery::RecoveredFile;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// ── Types ────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeNode {
+    pub name: String,
+    pub path: String, // "\Users\Alice", or "\[Images]" for virtual category folders
+    #[serde(rename = "isDir")]
+    pub is_dir: bool,
+    #[serde(rename = "isVirtual")]
+    pub is_virtual: bool,
+    #[serde(rename = "fileCount")]
+    pub file_count: u64, // recursive
+    #[serde(rename = "totalSize")]
+    pub total_size: u64, // recursive
+    #[serde(rename = "hasChildren")]
+    pub has_children: bool,
+    pub file: Option<RecoveredFile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeLevel {
+    pub path: String,
+    #[serde(rename = "fileCount")]
+    pub file_count: u64,
+    #[serde(rename = "totalSize")]
+    pub total_size: u64,
+    pub children: Vec<TreeNode>,
+}
+
+// ── Tree building ────────────────────────────────────────────────────
+
+// Returns the immediate children of `path` (the root when empty) with aggregated
+// counts, so the frontend can expand folders lazily. Signature mode output has no
+// folder structure, so flat results are grouped into virtual category folders.
+pub fn get_recovered_tree(destination: &str, path: &str) -> Result<TreeLevel, String> {
+    let index = index::shared_index(destination)?;
+    Ok(build_level(&index.files, path))
+}
+
+pub fn build_level(files: &[RecoveredFile], path: &str) -> TreeLevel {
+    let flat = files.iter().all(|f| components(&f.path).len() <= 1);
+    let parent = components(path);
+
+    let mut dirs: BTreeMap<String, TreeNode> = BTreeMap::new();
+    let mut leaves: Vec<TreeNode> = Vec::new();
+    let (mut file_count, mut total_size) = (0u64, 0u64);
+
+    for file in files {
+        let parts = tree_components(file, flat);
+        if parts.len() <= parent.len() || !parts[..parent.len()].iter().zip(&parent).all(|(a, b)| a.eq_ignore_ascii_case(b)) {
+            continue;
+        }
+        file_count += 1;
+        total_size += file.size;
+
+        let name = parts[parent.len()].clone();
+        if parts.len() == parent.len() + 1 {
+            leaves.push(TreeNode {
+                name,
+                path: file.path.clone(),
+                is_dir: false,
+                is_virtual: false,
+                file_count: 1,
+                total_size: file.size,
+                has_children: false,
+                file: Some(file.clone()),
+            });
+        } else {
+            let node = dirs.entry(name.to_lowercase()).or_insert_with(|| TreeNode {
+                path: join(&parts[..=parent.len()]),
+                name,
+                is_dir: true,
+                is_virtual: flat,
+                file_count: 0,
+                total_size: 0,
+                has_children: true,
+                file: None,
+            });
+            node.file_count += 1;
+            node.total_size += file.size;
+        }
+    }
+
+    leaves.sort_by_key(|n| n.name.to_lowercase());
+    let mut children: Vec<TreeNode> = dirs.into_values().collect();
+    children.extend(leaves);
+
+    TreeLevel {
+        path: join(&parent),
+        file_count,
+        total_size,
+        children,
+    }
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────
+
+fn components(path: &str) -> Vec<String> {
+    path.split(['\\', '/'])
+        .filter(|p| !p.is_empty())
+        .map(|p| p.to_string())
+        .collect()
+}
+
+fn tree_components(file: &RecoveredFile, flat: bool) -> Vec<String> {
+    if flat {
+        vec![format!("[{}]", file.category), file.name.clone()]
+    } else {
+        components(&file.path)
+    }
+}
+
+fn join(parts: &[String]) -> String {
+    if parts.is_empty() {
+        "\\".to_string()
+    } else {
+        format!("\\{}", parts.join("\\"))
+    }
+}
@@ -0,0 +1,241 @@
+use crate::blockdev::{
+    file_len, read_file_at, read_parent_or_zero, resolve_parent, sector_runs, BlockDevice, MAX_PARENT_DEPTH,
+};
+use crate::safety::open_source;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+// ── VHD containers ───────────────────────────────────────────────────
+//
+// A fixed VHD is the raw disk followed by a 512-byte footer. Dynamic and differencing
+// VHDs keep the disk in blocks (2 MiB by default) listed in a block allocation table,
+// each block led by a bitmap of the sectors it holds; a differencing disk reads every
+// other sector from its parent. All fields are big-endian.
+
+const FOOTER_SIZE: usize = 512;
+const FOOTER_COOKIE: &[u8] = b"conectix";
+const HEADER_COOKIE: &[u8] = b"cxsparse";
+const HEADER_SIZE: usize = 1024;
+const SECTOR: u64 = 512;
+const UNALLOCATED: u32 = 0xFFFF_FFFF;
+
+const DISK_FIXED: u32 = 2;
+const DISK_DYNAMIC: u32 = 3;
+const DISK_DIFFERENCING: u32 = 4;
+
+// Parent locators: Windows relative and absolute paths, UTF-16LE
+const LOCATOR_RELATIVE: &[u8] = b"W2ru";
+const LOCATOR_ABSOLUTE: &[u8] = b"W2ku";
+const LOCATORS: usize = 8;
+
+struct Blocks {
+    bat: Vec<u32>, // sector of each block in the file
+    block_size: u64,
+    bitmap_bytes: u64, // sector bitmap in front of each block, padded to a sector
+}
+
+pub struct VhdDisk {
+    file: File,
+    path: PathBuf,
+    name: String,
+    size: u64,
+    unique_id: [u8; 16],
+    blocks: Option<Blocks>, // None for fixed disks
+    parent: Option<Box<dyn BlockDevice>>,
+}
+
+fn be32(b: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]])
+}
+
+fn be64(b: &[u8], at: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&b[at..at + 8]);
+    u64::from_be_bytes(bytes)
+}
+
+// One's complement of the byte sum, with the checksum field itself left out
+fn checksum_ok(b: &[u8], field: usize) -> bool {
+    let sum = b
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !(field..field + 4).contains(i))
+        .fold(0u32, |sum, (_, byte)| sum.wrapping_add(*byte as u32));
+    !sum == be32(b, field)
+}
+
+// A VHD ends with its footer; dynamic disks also keep a copy at the start
+pub(crate) fn is_vhd(file: &File) -> bool {
+    let mut file = file;
+    let len = file_len(file);
+    let mut cookie = [0u8; 8];
+    [len.saturating_sub(FOOTER_SIZE as u64), 0].iter().any(|at| {
+        len >= FOOTER_SIZE as u64
+            && file.seek(SeekFrom::Start(*at)).is_ok()
+            && file.read_exact(&mut cookie).is_ok()
+            && cookie == FOOTER_COOKIE
+    })
+}
+
+impl VhdDisk {
+    pub fn open(path: &Path, depth: u32) -> Result<VhdDisk, String> {
+        let name = path.display().to_string();
+        let mut file = open_source(path)?;
+        let len = file_len(&file);
+        if len < FOOTER_SIZE as u64 {
+            return Err(format!("{} is too small to be a VHD.", name));
+        }
+
+        // A damaged footer at the end is replaced by the copy at the start
+        let footer = [len - FOOTER_SIZE as u64, 0]
+            .iter()
+            .filter_map(|at| read_file_at(&mut file, *at, FOOTER_SIZE).ok())
+            .find(|f| f.starts_with(FOOTER_COOKIE) && checksum_ok(f, 64))
+            .ok_or_else(|| format!("{} has no valid VHD footer.", name))?;
+        let size = be64(&footer, 48);
+        let mut unique_id = [0u8; 16];
+        unique_id.copy_from_slice(&footer[68..84]);
+
+        let disk_type = be32(&footer, 60);
+        let (blocks, parent) = match disk_type {
+            DISK_FIXED => {
+                if size > len - FOOTER_SIZE as u64 {
+                    return Err(format!("{} is shorter than its disk size.", name));
+                }
+                (None, None)
+            }
+            DISK_DYNAMIC | DISK_DIFFERENCING => {
+                let header = read_file_at(&mut file, be64(&footer, 16), HEADER_SIZE)?;
+                if !header.starts_with(HEADER_COOKIE) || !checksum_ok(&header, 36) {
+                    return Err(format!("{} has a damaged dynamic disk header.", name));
+                }
+                let entries = be32(&header, 28) as u64;
+                let block_size = be32(&header, 32) as u64;
+                let usable = block_size.is_power_of_two() && block_size >= SECTOR;
+                if !usable || entries * block_size < size {
+                    return Err(format!("{} has an invalid block table.", name));
+                }
+                let table = read_file_at(&mut file, be64(&header, 16), (entries * 4) as usize)?;
+                let bat = table.chunks_exact(4).map(|e| be32(e, 0)).collect();
+                let bitmap_bytes = (block_size / SECTOR / 8).next_multiple_of(SECTOR);
+
+                let parent = if disk_type == DISK_DIFFERENCING {
+                    Some(open_parent(path, &mut file, &header, size, depth)?)
+                } else {
+                    None
+                };
+                (Some(Blocks { bat, block_size, bitmap_bytes }), parent)
+            }
+            other => return Err(format!("{} is an unsupported VHD type ({}).", name, other)),
+        };
+
+        Ok(VhdDisk { file, path: path.to_path_buf(), name, size, unique_id, blocks, parent })
+    }
+}
+
+fn open_parent(
+    path: &Path,
+    file: &mut File,
+    header: &[u8],
+    size: u64,
+    depth: u32,
+) -> Result<Box<dyn BlockDevice>, String> {
+    if depth >= MAX_PARENT_DEPTH {
+        return Err("Too many nested differencing disks.".to_string());
+    }
+
+    // Locator paths first, then the parent's file name next to the child
+    let mut references: Vec<String> = Vec::new();
+    for kind in [LOCATOR_RELATIVE, LOCATOR_ABSOLUTE] {
+        for i in 0..LOCATORS {
+            let at = 576 + i * 24;
+            if &header[at..at + 4] != kind {
+                continue;
+            }
+            let data = read_file_at(file, be64(header, at + 16), be32(header, at + 8) as usize)?;
+            let units: Vec<u16> = data.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+            references.push(String::from_utf16_lossy(&units));
+        }
+    }
+    let units: Vec<u16> = header[64..576].chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+    references.push(String::from_utf16_lossy(&units));
+
+    let parent_name = references.last().cloned().unwrap_or_default();
+    let parent_path = references
+        .iter()
+        .find_map(|r| resolve_parent(path, r))
+        .ok_or_else(|| {
+            format!("Parent disk {} of {} was not found.", parent_name.trim_end_matches('\0'), path.display())
+        })?;
+    let parent = VhdDisk::open(&parent_path, depth + 1)?;
+    // The child records which parent it was created from
+    if parent.unique_id[..] != header[40..56] || parent.size != size {
+        return Err(format!(
+            "Parent disk {} has changed since {} was created from it.",
+            parent_path.display(),
+            path.display()
+        ));
+    }
+    Ok(Box::new(parent))
+}
+
+impl BlockDevice for VhdDisk {
+    fn size(&mut self) -> Option<u64> {
+        Some(self.size)
+    }
+
+    // A differencing disk is read together with its parents
+    fn files(&self) -> Vec<PathBuf> {
+        let mut files = vec![self.path.clone()];
+        files.extend(self.parent.iter().flat_map(|parent| parent.files()));
+        files
+    }
+
+    fn read_at(&mut self, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+        let end = offset + len as u64;
+        if end > self.size {
+            return Err(format!("Read past the end of {} at offset {}", self.name, offset));
+        }
+        let VhdDisk { file, blocks, parent, .. } = self;
+        let Some(blocks) = blocks else {
+            return read_file_at(file, offset, len);
+        };
+
+        let mut data = Vec::with_capacity(len);
+        let mut at = offset;
+        while at < end {
+            let block = at / blocks.block_size;
+            let block_start = block * blocks.block_size;
+            let piece_end = (block_start + blocks.block_size).min(end);
+            let entry = blocks.bat[block as usize];
+            if entry == UNALLOCATED {
+                data.extend(read_parent_or_zero(parent, at, (piece_end - at) as usize)?);
+            } else {
+                let bitmap_start = entry as u64 * SECTOR;
+                // File offset of the data for disk offset `o` inside this block
+                let data_at = |o: u64| bitmap_start + blocks.bitmap_bytes + (o - block_start);
+                if parent.is_none() {
+                    data.extend(read_file_at(file, data_at(at), (piece_end - at) as usize)?);
+                } else {
+                    // Sectors whose bit is clear were never written to this disk
+                    let bitmap = read_file_at(file, bitmap_start, blocks.bitmap_bytes as usize)?;
+                    let present = |o: u64| {
+                        let sector = (o - block_start) / SECTOR;
+                        bitmap[(sector / 8) as usize] & (0x80 >> (sector % 8)) != 0
+                    };
+                    for (run_start, run_end, here) in sector_runs(at, piece_end, SECTOR, present) {
+                        let run_len = (run_end - run_start) as usize;
+                        if here {
+                            data.extend(read_file_at(file, data_at(run_start), run_len)?);
+                        } else {
+                            data.extend(read_parent_or_zero(parent, run_start, run_len)?);
+                        }
+                    }
+                }
+            }
+            at = piece_end;
+        }
+        Ok(data)
+    }
+}
@@ -0,0 +1,291 @@
+use crate::blockdev::{read_file_at, read_parent_or_zero, resolve_parent, sector_runs, BlockDevice, MAX_PARENT_DEPTH};
+use crate::partitions::format_guid;
+use crate::safety::open_source;
+use crate::volume::{u16_at, u32_at, u64_at};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+// ── VHDX containers ──────────────────────────────────────────────────
+//
+// Two checksummed headers and region tables locate the metadata (disk size, block size,
+// parent locator) and the block allocation table. Payload blocks are 1-256 MiB; for
+// every `chunk ratio` of them the table holds one more entry pointing at a sector bitmap,
+// which says which sectors a differencing disk holds itself. Open logs are not replayed,
+// so nothing is ever written to the file.
+
+const SIGNATURE: &[u8] = b"vhdxfile";
+const HEADER_OFFSETS: [u64; 2] = [64 * 1024, 128 * 1024];
+const HEADER_SIZE: usize = 4096;
+const REGION_TABLE_OFFSETS: [u64; 2] = [192 * 1024, 256 * 1024];
+const REGION_TABLE_SIZE: usize = 64 * 1024;
+const MIB: u64 = 1024 * 1024;
+const SECTORS_PER_BITMAP: u64 = 1 << 23;
+
+const BAT_REGION: &str = "2DC27766-F623-4200-9D64-115E9BFD4A08";
+const METADATA_REGION: &str = "8B7CA206-4790-4B9A-B8FE-575F050F886E";
+const FILE_PARAMETERS: &str = "CAA16737-FA36-4D43-B3B6-33F0AA44E76B";
+const VIRTUAL_DISK_SIZE: &str = "2FA54224-CD1B-4876-B211-5DBED83BF4B8";
+const LOGICAL_SECTOR_SIZE: &str = "8141BF1D-A96F-4709-BA47-F233A8FAAB5F";
+const PARENT_LOCATOR: &str = "A8D35F2D-B30B-454D-ABF7-D3D84834AB0C";
+
+// Block allocation table entry states
+const BLOCK_NOT_PRESENT: u64 = 0;
+const BLOCK_UNDEFINED: u64 = 1;
+const BLOCK_ZERO: u64 = 2;
+const BLOCK_UNMAPPED: u64 = 3;
+const BLOCK_FULLY_PRESENT: u64 = 6;
+const BLOCK_PARTIALLY_PRESENT: u64 = 7;
+const STATE_MASK: u64 = 0x7;
+const OFFSET_MASK: u64 = !(MIB - 1);
+
+pub struct VhdxDisk {
+    file: File,
+    path: PathBuf,
+    name: String,
+    size: u64,
+    block_size: u64,
+    sector_size: u64,
+    chunk_ratio: u64,
+    bat: Vec<u64>,
+    data_write_guid: String,
+    parent: Option<Box<dyn BlockDevice>>,
+}
+
+// CRC-32C (Castagnoli), over the structure with its checksum field zeroed
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0x82F6_3B78 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn checksum_ok(b: &[u8]) -> bool {
+    let mut copy = b.to_vec();
+    copy[4..8].fill(0);
+    crc32c(&copy) == u32_at(b, 4)
+}
+
+fn utf16(b: &[u8]) -> String {
+    let units: Vec<u16> = b.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+    String::from_utf16_lossy(&units)
+}
+
+pub(crate) fn is_vhdx(file: &File) -> bool {
+    let mut file = file;
+    let mut signature = [0u8; 8];
+    file.seek(SeekFrom::Start(0)).is_ok() && file.read_exact(&mut signature).is_ok() && signature == SIGNATURE
+}
+
+impl VhdxDisk {
+    pub fn open(path: &Path, depth: u32) -> Result<VhdxDisk, String> {
+        let name = path.display().to_string();
+        let mut file = open_source(path)?;
+        if read_file_at(&mut file, 0, SIGNATURE.len())? != SIGNATURE {
+            return Err(format!("{} is not a VHDX file.", name));
+        }
+
+        // The valid header with the higher sequence number is the current one
+        let header = HEADER_OFFSETS
+            .iter()
+            .filter_map(|at| read_file_at(&mut file, *at, HEADER_SIZE).ok())
+            .filter(|h| h.starts_with(b"head") && checksum_ok(h))
+            .max_by_key(|h| u64_at(h, 8))
+            .ok_or_else(|| format!("{} has no valid VHDX header.", name))?;
+        if header[48..64].iter().any(|b| *b != 0) {
+            return Err(format!(
+                "{} has log entries that were never applied. Attach it in Windows once to replay them.",
+                name
+            ));
+        }
+        let data_write_guid = format_guid(&header[32..48]);
+
+        let regions = REGION_TABLE_OFFSETS
+            .iter()
+            .filter_map(|at| read_file_at(&mut file, *at, REGION_TABLE_SIZE).ok())
+            .find(|r| r.starts_with(b"regi") && checksum_ok(r))
+            .ok_or_else(|| format!("{} has no valid region table.", name))?;
+        let (mut bat_region, mut metadata_region) = (None, None);
+        for i in 0..(u32_at(&regions, 8) as usize).min((REGION_TABLE_SIZE - 16) / 32) {
+            let e = &regions[16 + i * 32..48 + i * 32];
+            let region = (u64_at(e, 16), u32_at(e, 24) as u64);
+            match format_guid(&e[0..16]).as_str() {
+                BAT_REGION => bat_region = Some(region),
+                METADATA_REGION => metadata_region = Some(region),
+                guid if u32_at(e, 28) & 1 != 0 => {
+                    return Err(format!("{} needs an unknown region ({}) to be read.", name, guid));
+                }
+                _ => {}
+            }
+        }
+        let ((bat_offset, bat_length), (metadata_offset, metadata_length)) = bat_region
+            .zip(metadata_region)
+            .ok_or_else(|| format!("{} has no block table or metadata region.", name))?;
+
+        let metadata = read_file_at(&mut file, metadata_offset, metadata_length as usize)?;
+        if !metadata.starts_with(b"metadata") {
+            return Err(format!("{} has a damaged metadata region.", name));
+        }
+        let invalid = || format!("{} has invalid disk parameters.", name);
+        let count = metadata.get(10..12).map(|c| u16_at(c, 0)).ok_or_else(invalid)?;
+        let item = |guid: &str| -> Option<&[u8]> {
+            (0..count as usize).find_map(|i| {
+                let e = metadata.get(32 + i * 32..64 + i * 32)?;
+                let (offset, length) = (u32_at(e, 16) as usize, u32_at(e, 20) as usize);
+                (format_guid(&e[0..16]) == guid).then(|| metadata.get(offset..offset + length)).flatten()
+            })
+        };
+        let parameters = item(FILE_PARAMETERS).filter(|p| p.len() >= 8).ok_or_else(invalid)?;
+        let block_size = u32_at(parameters, 0) as u64;
+        let has_parent = u32_at(parameters, 4) & 2 != 0;
+        let size = item(VIRTUAL_DISK_SIZE).filter(|p| p.len() >= 8).map(|p| u64_at(p, 0)).ok_or_else(invalid)?;
+        let sector_size = item(LOGICAL_SECTOR_SIZE).filter(|p| p.len() >= 4).map(|p| u32_at(p, 0) as u64);
+        let sector_size = sector_size.filter(|s| *s == 512 || *s == 4096).ok_or_else(invalid)?;
+        if !block_size.is_power_of_two() || !(MIB..=256 * MIB).contains(&block_size) || size == 0 {
+            return Err(invalid());
+        }
+
+        let chunk_ratio = SECTORS_PER_BITMAP * sector_size / block_size;
+        let data_blocks = size.div_ceil(block_size);
+        let entries = if has_parent {
+            data_blocks.div_ceil(chunk_ratio) * (chunk_ratio + 1)
+        } else {
+            data_blocks + (data_blocks - 1) / chunk_ratio
+        };
+        if entries * 8 > bat_length {
+            return Err(format!("{} has a block table too small for its disk size.", name));
+        }
+        let table = read_file_at(&mut file, bat_offset, (entries * 8) as usize)?;
+        let bat = table.chunks_exact(8).map(|e| u64_at(e, 0)).collect();
+
+        let parent = if has_parent {
+            let locator = item(PARENT_LOCATOR).ok_or_else(|| format!("{} has no parent locator.", name))?;
+            if locator.len() < 20 {
+                return Err(invalid());
+            }
+            Some(open_parent(path, locator, size, depth)?)
+        } else {
+            None
+        };
+
+        Ok(VhdxDisk {
+            file,
+            path: path.to_path_buf(),
+            name,
+            size,
+            block_size,
+            sector_size,
+            chunk_ratio,
+            bat,
+            data_write_guid,
+            parent,
+        })
+    }
+}
+
+fn open_parent(path: &Path, locator: &[u8], size: u64, depth: u32) -> Result<Box<dyn BlockDevice>, String> {
+    if depth >= MAX_PARENT_DEPTH {
+        return Err("Too many nested differencing disks.".to_string());
+    }
+    let mut values: Vec<(String, String)> = Vec::new();
+    for i in 0..u16_at(locator, 18) as usize {
+        let Some(e) = locator.get(20 + i * 12..32 + i * 12) else { break };
+        let (key, value) = (u32_at(e, 0) as usize, u32_at(e, 4) as usize);
+        let (key_len, value_len) = (u16_at(e, 8) as usize, u16_at(e, 10) as usize);
+        if let (Some(k), Some(v)) = (locator.get(key..key + key_len), locator.get(value..value + value_len)) {
+            values.push((utf16(k), utf16(v)));
+        }
+    }
+    let value = |key: &str| values.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+
+    let parent_path = ["relative_path", "absolute_win32_path", "volume_path"]
+        .iter()
+        .find_map(|key| value(key).and_then(|reference| resolve_parent(path, reference)))
+        .ok_or_else(|| {
+            let reference = value("relative_path").or(value("absolute_win32_path")).unwrap_or("?");
+            format!("Parent disk {} of {} was not found.", reference, path.display())
+        })?;
+    let parent = VhdxDisk::open(&parent_path, depth + 1)?;
+
+    // The child is linked to the parent's data as it was when the child was created
+    let linkage = value("parent_linkage").unwrap_or("").trim_matches(['{', '}']).to_uppercase();
+    if linkage != parent.data_write_guid || parent.size != size {
+        return Err(format!(
+            "Parent disk {} has changed since {} was created from it.",
+            parent_path.display(),
+            path.display()
+        ));
+    }
+    Ok(Box::new(parent))
+}
+
+impl BlockDevice for VhdxDisk {
+    fn size(&mut self) -> Option<u64> {
+        Some(self.size)
+    }
+
+    // A differencing disk is read together with its parents
+    fn files(&self) -> Vec<PathBuf> {
+        let mut files = vec![self.path.clone()];
+        files.extend(self.parent.iter().flat_map(|parent| parent.files()));
+        files
+    }
+
+    fn read_at(&mut self, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+        let end = offset + len as u64;
+        if end > self.size {
+            return Err(format!("Read past the end of {} at offset {}", self.name, offset));
+        }
+        let (block_size, sector_size, ratio) = (self.block_size, self.sector_size, self.chunk_ratio);
+        let VhdxDisk { file, name, bat, parent, .. } = self;
+
+        let mut data = Vec::with_capacity(len);
+        let mut at = offset;
+        while at < end {
+            let block = at / block_size;
+            let block_start = block * block_size;
+            let piece_end = (block_start + block_size).min(end);
+            let piece = (piece_end - at) as usize;
+            let entry = bat[(block + block / ratio) as usize];
+            let block_file = entry & OFFSET_MASK;
+
+            match entry & STATE_MASK {
+                BLOCK_FULLY_PRESENT => data.extend(read_file_at(file, block_file + (at - block_start), piece)?),
+                BLOCK_PARTIALLY_PRESENT if parent.is_some() => {
+                    let chunk = block / ratio;
+                    let bitmap_entry = bat[(chunk * (ratio + 1) + ratio) as usize];
+                    if bitmap_entry & STATE_MASK != BLOCK_FULLY_PRESENT {
+                        return Err(format!("{} is missing the sector bitmap for offset {}", name, at));
+                    }
+                    // Only the bitmap bytes covering this piece; bit 0 is the chunk's first sector
+                    let chunk_start = chunk * ratio * block_size;
+                    let first = (at - chunk_start) / sector_size / 8;
+                    let last = (piece_end - 1 - chunk_start) / sector_size / 8;
+                    let bitmap =
+                        read_file_at(file, (bitmap_entry & OFFSET_MASK) + first, (last - first + 1) as usize)?;
+                    let present = |o: u64| {
+                        let sector = (o - chunk_start) / sector_size;
+                        bitmap[(sector / 8 - first) as usize] & (1 << (sector % 8)) != 0
+                    };
+                    for (run_start, run_end, here) in sector_runs(at, piece_end, sector_size, present) {
+                        let run_len = (run_end - run_start) as usize;
+                        if here {
+                            data.extend(read_file_at(file, block_file + (run_start - block_start), run_len)?);
+                        } else {
+                            data.extend(read_parent_or_zero(parent, run_start, run_len)?);
+                        }
+                    }
+                }
+                BLOCK_NOT_PRESENT | BLOCK_UNDEFINED => data.extend(read_parent_or_zero(parent, at, piece)?),
+                BLOCK_ZERO | BLOCK_UNMAPPED => data.extend(vec![0u8; piece]),
+                state => return Err(format!("{} has an invalid block state ({}) at offset {}", name, state, at)),
+            }
+            at = piece_end;
+        }
+        Ok(data)
+    }
+}
@@ -0,0 +1,250 @@
+use crate::blockdev::{open_device, BlockDevice, RawDevice};
+use crate::export::{resolve_collision, sanitize_file_name, CollisionPolicy};
+use crate::hashing::to_hex;
+use crate::safety::SourceIntegrity;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const READ_CHUNK: u64 = 1024 * 1024;
+
+// ── Read-only volume access ──────────────────────────────────────────
+
+pub struct Volume {
+    device: Box<dyn BlockDevice>,
+    source: String,
+    base: u64, // byte offset of the partition on the disk, 0 for volumes and images
+}
+
+impl Volume {
+    // `source` is a drive letter ("E:"), the path of a raw, VHD or VHDX image or of a disk
+    // (\\.\PhysicalDrive1), or a partition on one as listed by the partitions module
+    pub fn open(source: &str) -> Result<Volume, String> {
+        let (location, base) = split_partition_source(source);
+        // Opened read-only: nothing in the native scanners may write to the source
+        let device: Box<dyn BlockDevice> = match drive_letter(location) {
+            Some(letter) if cfg!(windows) => Box::new(RawDevice::open(&format!("\\\\.\\{}:", letter))?),
+            Some(_) => return Err("Reading a drive directly is only supported on Windows.".to_string()),
+            None => open_device(location)?,
+        };
+        Ok(Volume { device, source: source.to_string(), base })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    // Bytes from the start of the volume to the end of the device. Some devices report no
+    // length, so callers must cope with None.
+    pub fn size(&mut self) -> Option<u64> {
+        self.device.size()?.checked_sub(self.base).filter(|size| *size > 0)
+    }
+
+    pub fn read_at(&mut self, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+        self.device.read_at(self.base + offset, len)
+    }
+}
+
+// Imaging reads a volume like any other device
+impl BlockDevice for Volume {
+    fn size(&mut self) -> Option<u64> {
+        Volume::size(self)
+    }
+
+    fn read_at(&mut self, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+        Volume::read_at(self, offset, len)
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        self.device.files()
+    }
+}
+
+// A partition inside a disk or image is addressed as "<disk>|<byte offset>"; '|' cannot
+// appear in a Windows path, so the suffix is never part of a file name
+pub fn partition_source(disk: &str, offset: u64) -> String {
+    format!("{}|{}", disk, offset)
+}
+
+pub(crate) fn split_partition_source(source: &str) -> (&str, u64) {
+    match source.rsplit_once('|') {
+        Some((disk, offset)) => match offset.parse() {
+            Ok(offset) => (disk, offset),
+            Err(_) => (source, 0),
+        },
+        None => (source, 0),
+    }
+}
+
+pub fn drive_letter(source: &str) -> Option<char> {
+    let trimmed = source.trim_end_matches(['\\', '/']);
+    let mut chars = trimmed.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(letter), Some(':'), None) if letter.is_ascii_alphabetic() => Some(letter.to_ascii_uppercase()),
+        _ => None,
+    }
+}
+
+// ── Little-endian fields ─────────────────────────────────────────────
+
+pub(crate) fn u16_at(b: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([b[at], b[at + 1]])
+}
+
+pub(crate) fn u32_at(b: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]])
+}
+
+pub(crate) fn u64_at(b: &[u8], at: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&b[at..at + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+// ── Writing recovered files ──────────────────────────────────────────
+
+// Creates the file for a recovered item under `target_dir`, keeping its original folder
+// layout ("\\Users\\Bob\\a.txt") and renaming on collisions
+pub fn create_recovered_file(target_dir: &Path, original_path: &str) -> Result<(File, PathBuf), String> {
+    let mut planned = target_dir.to_path_buf();
+    for part in original_path.split(['\\', '/']).filter(|p| !p.is_empty()) {
+        planned.push(sanitize_file_name(part));
+    }
+    if let Some(parent) = planned.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let dest = resolve_collision(&planned, CollisionPolicy::Rename).unwrap_or(planned);
+    let file = File::create(&dest).map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+    Ok((file, dest))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NativeRecoveredFile {
+    pub id: String,
+    pub path: String,   // original path on the source
+    pub target: String, // where it was written
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NativeFailure {
+    pub id: String,
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NativeProgress {
+    #[serde(rename = "filesDone")]
+    pub files_done: usize,
+    #[serde(rename = "filesTotal")]
+    pub files_total: usize,
+    pub path: String, // the file just finished
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NativeRecoverySummary {
+    pub target: String,
+    pub recovered: Vec<NativeRecoveredFile>,
+    pub failed: Vec<NativeFailure>,
+    // Hashes of an image source before and after, set by the session that ran the recovery
+    #[serde(rename = "sourceIntegrity")]
+    pub source_integrity: Option<SourceIntegrity>,
+}
+
+// ── Recovering selected files ────────────────────────────────────────
+
+// What a native scanner supplies to copy back the files it listed
+pub(crate) trait NativeReader {
+    // Position of a listed file, or None if `id` is not a deleted file on this volume
+    fn find(&self, id: &str) -> Option<usize>;
+    // Original path and size of the file at `index`
+    fn describe(&self, index: usize) -> (&str, u64);
+    // Why the file cannot be read back; checked before anything is created for it
+    fn check(&self, index: usize) -> Result<(), String>;
+    // Streams the file's content to `out`
+    fn read(&mut self, index: usize, out: &mut dyn FnMut(&[u8]) -> Result<(), String>) -> Result<(), String>;
+}
+
+// Copies the selected files into `target`, keeping their folder layout and hashing each
+// copy. A file that fails is reported and the rest are still copied.
+pub(crate) fn recover_selected(
+    reader: &mut dyn NativeReader,
+    ids: &[String],
+    target: &str,
+    on_progress: &mut dyn FnMut(&NativeProgress),
+) -> NativeRecoverySummary {
+    let mut summary = NativeRecoverySummary { target: target.to_string(), ..Default::default() };
+    for (done, id) in ids.iter().enumerate() {
+        let path = match reader.find(id) {
+            Some(index) => {
+                let (path, size) = reader.describe(index);
+                let path = path.to_string();
+                match copy_out(reader, index, &path, Path::new(target)) {
+                    Ok((dest, sha256)) => summary.recovered.push(NativeRecoveredFile {
+                        id: id.clone(),
+                        path: path.clone(),
+                        target: dest,
+                        size,
+                        sha256,
+                    }),
+                    Err(error) => summary.failed.push(NativeFailure { id: id.clone(), path: path.clone(), error }),
+                }
+                path
+            }
+            None => {
+                summary.failed.push(NativeFailure {
+                    id: id.clone(),
+                    path: String::new(),
+                    error: "Not a deleted file on this volume.".to_string(),
+                });
+                String::new()
+            }
+        };
+        on_progress(&NativeProgress { files_done: done + 1, files_total: ids.len(), path });
+    }
+    summary
+}
+
+fn copy_out(reader: &mut dyn NativeReader, index: usize, path: &str, target: &Path) -> Result<(String, String), String> {
+    reader.check(index)?;
+    let (mut out, dest) = create_recovered_file(target, path)?;
+    let mut hasher = Sha256::new();
+    let result = reader.read(index, &mut |chunk| {
+        hasher.update(chunk);
+        out.write_all(chunk).map_err(|e| format!("Failed to write {}: {}", dest.display(), e))
+    });
+    if let Err(e) = result {
+        // Never leave a truncated copy that looks like a complete recovery
+        let _ = fs::remove_file(&dest);
+        return Err(e);
+    }
+    Ok((dest.to_string_lossy().to_string(), to_hex(&hasher.finalize())))
+}
+
+// Streams `size` bytes stored as one run at `offset`. Bytes past `valid` were never
+// written (exFAT's valid data length) and read as zeros.
+pub(crate) fn read_contiguous(
+    volume: &mut Volume,
+    mut offset: u64,
+    size: u64,
+    mut valid: u64,
+    out: &mut dyn FnMut(&[u8]) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut remaining = size;
+    while remaining > 0 {
+        let len = remaining.min(READ_CHUNK);
+        let mut chunk = if valid > 0 { volume.read_at(offset, len as usize)? } else { vec![0u8; len as usize] };
+        if valid < len {
+            chunk[valid as usize..].fill(0);
+        }
+        out(&chunk)?;
+        offset += len;
+        remaining -= len;
+        valid = valid.saturating_sub(len);
+    }
+    Ok(())
+}
@@ -0,0 +1,153 @@
+// Tests for the chain-of-custody audit log: recording a session, sealing and the
+// tampering that verification must catch.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/recovered_files.rs"]
+#[allow(dead_code)]
+mod recovered_files;
+
+use common::{scratch_dir, text};
+use recovered_files::{recovered, recovery_folder, session, use_test_app_data, FOLDER};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use tauri_app_lib::audit::{self, AuditEntry};
+use tauri_app_lib::session as sessions;
+
+// The running session is process-wide state
+static SERIAL: Mutex<()> = Mutex::new(());
+
+// Runs a whole session over `dir`, as a recovery records it, and returns the path of its log
+fn recorded_session(dir: &Path) -> String {
+    use_test_app_data();
+    recovery_folder(dir, &[("Users/Bob/a.pdf", b"%PDF-1.7 a"), ("b.txt", b"b")]);
+    let started = sessions::begin(&session(dir).config, &[]);
+    audit::record_session_start(&started).unwrap();
+    sessions::set_recovery_folder(FOLDER);
+    sessions::finish("completed", Some(0));
+    let finished = sessions::current().unwrap();
+    audit::record_session_end(&finished).unwrap();
+    audit::log_path_for(&finished).to_string_lossy().to_string()
+}
+
+fn entries(log: &str) -> Vec<AuditEntry> {
+    fs::read_to_string(log).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect()
+}
+
+fn write_entries(log: &str, entries: &[AuditEntry]) {
+    let lines: Vec<String> = entries.iter().map(|e| serde_json::to_string(e).unwrap()).collect();
+    fs::write(log, lines.join("\n") + "\n").unwrap();
+}
+
+#[test]
+fn a_finished_session_verifies_and_stays_sealed_after_later_changes() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = scratch_dir("session");
+    let log = recorded_session(&dir);
+
+    let actions: Vec<String> = entries(&log).into_iter().map(|e| e.action).collect();
+    assert_eq!(actions, vec!["recovery-started", "recovery-finished", "manifest", "sealed"]);
+    let verified = audit::verify_audit_log(&log).unwrap();
+    assert!(verified.valid, "{:?}", verified.error);
+    assert_eq!((verified.entries, verified.sealed, verified.manifest_matches), (4, true, Some(true)));
+
+    // A later deletion is logged with a new manifest and the log is sealed again
+    fs::remove_file(recovered(&dir, "b.txt")).unwrap();
+    audit::record_change(&text(&dir), "files-deleted", json!({ "files": ["\\b.txt"] })).unwrap();
+    let actions: Vec<String> = entries(&log).into_iter().skip(4).map(|e| e.action).collect();
+    assert_eq!(actions, vec!["files-deleted", "manifest", "sealed"]);
+    let verified = audit::verify_audit_log(&log).unwrap();
+    assert!(verified.valid, "{:?}", verified.error);
+    assert_eq!(verified.manifest_matches, Some(true));
+}
+
+#[test]
+fn edited_removed_or_truncated_entries_are_detected() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = scratch_dir("tamper");
+    let log = recorded_session(&dir);
+    let original = entries(&log);
+
+    let mut edited = original.clone();
+    edited[0].details["source"] = json!("F:");
+    write_entries(&log, &edited);
+    let verified = audit::verify_audit_log(&log).unwrap();
+    assert!(!verified.valid);
+    assert_eq!(verified.first_invalid_seq, Some(0));
+    assert_eq!(verified.error.as_deref(), Some("Entry 0 was modified after it was written"));
+
+    let mut removed = original.clone();
+    removed.remove(1);
+    write_entries(&log, &removed);
+    let verified = audit::verify_audit_log(&log).unwrap();
+    assert_eq!((verified.valid, verified.first_invalid_seq), (false, Some(1)));
+    assert!(verified.error.unwrap().contains("out of sequence"));
+
+    // Dropping the tail keeps the chain intact but loses the seal
+    write_entries(&log, &original[..2]);
+    let verified = audit::verify_audit_log(&log).unwrap();
+    assert_eq!((verified.valid, verified.sealed, verified.first_invalid_seq), (false, false, Some(2)));
+    assert!(verified.error.unwrap().contains("does not end with a seal entry"));
+}
+
+#[test]
+fn recovered_files_changed_outside_the_app_fail_the_manifest() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = scratch_dir("manifest");
+    let log = recorded_session(&dir);
+
+    fs::write(recovered(&dir, "Users/Bob/a.pdf"), b"%PDF-1.7 altered").unwrap();
+    let verified = audit::verify_audit_log(&log).unwrap();
+    assert_eq!((verified.valid, verified.sealed, verified.manifest_matches), (false, true, Some(false)));
+    assert_eq!(verified.error.as_deref(), Some("Recovered files no longer match the recorded manifest."));
+}
+
+#[test]
+fn actions_go_to_the_log_of_the_destination_they_apply_to() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let (first, second) = (scratch_dir("first"), scratch_dir("second"));
+    let first_log = recorded_session(&first);
+    let second_log = recorded_session(&second);
+
+    // Changes to the first destination land in its log although the second session ran last
+    audit::record(&text(&first), "files-exported", json!({ "files": [] })).unwrap();
+    assert_eq!(entries(&first_log).last().map(|e| e.action.as_str()), Some("sealed"));
+    assert!(entries(&first_log).iter().any(|e| e.action == "files-exported"));
+    assert!(!entries(&second_log).iter().any(|e| e.action == "files-exported"));
+
+    // Actions on no destination go to the app's own log, sealed after each one
+    audit::record_app("drive-selected", json!({ "drive": "E:" })).unwrap();
+    let app_log = text(&audit::app_log_path().unwrap());
+    let actions: Vec<String> = entries(&app_log).into_iter().rev().take(2).map(|e| e.action).collect();
+    assert_eq!(actions, vec!["sealed", "drive-selected"]);
+    assert!(audit::verify_audit_log(&app_log).unwrap().valid);
+    for log in [&first_log, &second_log] {
+        assert!(!entries(log).iter().any(|e| e.action == "drive-selected"));
+    }
+}
+
+#[test]
+fn actions_that_cannot_be_recorded_fail_and_are_reported_by_verification() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = scratch_dir("missed");
+    let log = recorded_session(&dir);
+
+    let unknown = scratch_dir("no-session");
+    let err = audit::record(&text(&unknown), "files-exported", json!({})).unwrap_err();
+    assert!(err.contains("No recovery session"), "{}", err);
+
+    // A log whose last entry cannot be chained to is not written to
+    let original = fs::read_to_string(&log).unwrap();
+    fs::write(&log, format!("{}{{\"seq\": \n", original)).unwrap();
+    let err = audit::record_change(&text(&dir), "files-deleted", json!({ "files": [] })).unwrap_err();
+    assert!(err.contains("damaged entry"), "{}", err);
+
+    fs::write(&log, &original).unwrap();
+    let verified = audit::verify_audit_log(&log).unwrap();
+    assert!(!verified.valid);
+    assert_eq!(verified.missed_actions, vec!["files-deleted", "manifest"]);
+    assert!(verified.error.unwrap().contains("could not be written"));
+}
@@ -0,0 +1,91 @@
+// Tests for the DFXML export of a recovery session.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/recovered_files.rs"]
+#[allow(dead_code)]
+mod recovered_files;
+
+use common::scratch_dir;
+use recovered_files::{recovery_folder, session, use_test_app_data};
+use std::fs;
+use tauri_app_lib::dfxml;
+use tauri_app_lib::drives::DriveInfo;
+use tauri_app_lib::index;
+
+#[test]
+fn exported_fileobjects_carry_original_paths_sizes_and_digests() {
+    use_test_app_data();
+    let dir = scratch_dir("export");
+    recovery_folder(&dir, &[("Users/Bob/a&b.pdf", b"abc"), ("c.txt", b"")]);
+    let output = dir.join("session.dfxml");
+    dfxml::export_dfxml(&session(&dir), &output.to_string_lossy()).unwrap();
+    let xml = fs::read_to_string(&output).unwrap();
+
+    assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<dfxml "));
+    assert!(xml.contains("      <command_line>winfr E: "));
+    assert!(xml.contains("        <arg>/extensive</arg>\n"));
+    assert!(xml.contains("      <start_time>2024-01-01T12:00:00Z</start_time>\n"));
+    assert!(xml.contains("      <exit_code>0</exit_code>\n"));
+    assert!(xml.contains("    <image_filename>E:</image_filename>\n"));
+    assert!(xml.contains("    <ftype_str>NTFS</ftype_str>\n"));
+
+    assert_eq!(xml.matches("<fileobject>").count(), 2);
+    assert!(xml.contains("      <filename>E:\\Users\\Bob\\a&amp;b.pdf</filename>\n      <filesize>3</filesize>\n"));
+    assert!(xml.contains("<hashdigest type=\"md5\">900150983cd24fb0d6963f7d28e17f72</hashdigest>"));
+    assert!(xml.contains(
+        "<hashdigest type=\"sha256\">ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad</hashdigest>"
+    ));
+    assert!(xml.contains("      <filename>E:\\c.txt</filename>\n      <filesize>0</filesize>\n"));
+    // The recovered copies' timestamps are not the originals'
+    assert!(!xml.contains("<mtime>"));
+}
+
+#[test]
+fn signature_mode_fileobjects_have_no_filename() {
+    use_test_app_data();
+    let dir = scratch_dir("signature");
+    let dest = recovery_folder(&dir, &[("JPEG/f0001.jpg", b"\xFF\xD8\xFF")]);
+    index::refresh_index(&dest, |_| {}).unwrap();
+    let files = index::all_files(&dest).unwrap();
+    let mut session = session(&dir);
+    session.config.signature_mode = true;
+
+    let xml = dfxml::render_dfxml(&session, None, &files);
+    assert_eq!(xml.matches("<fileobject>").count(), 1);
+    assert!(!xml.contains("<filename>"));
+    assert!(xml.contains("    <fileobject>\n      <filesize>3</filesize>\n"));
+}
+
+#[test]
+fn volume_fields_are_only_written_when_known() {
+    use_test_app_data();
+    let dir = scratch_dir("volume");
+    let dest = recovery_folder(&dir, &[("a.pdf", b"%PDF")]);
+    index::refresh_index(&dest, |_| {}).unwrap();
+    let files = index::all_files(&dest).unwrap();
+    let drive = DriveInfo {
+        id: "E:".to_string(),
+        label: "EVIDENCE".to_string(),
+        drive_type: "SSD".to_string(),
+        fs: "exFAT".to_string(),
+        size: "476 GB".to_string(),
+        size_bytes: 511_101_108_224,
+        used: "234 GB".to_string(),
+        percent: 49,
+        is_system: false,
+    };
+
+    let xml = dfxml::render_dfxml(&session(&dir), Some(&drive), &files);
+    assert!(xml.contains("    <ftype_str>exFAT</ftype_str>\n"));
+    assert!(xml.contains("    <volume_label>EVIDENCE</volume_label>\n"));
+    assert!(xml.contains("    <volume_size>511101108224</volume_size>\n"));
+    // Nothing is known about the device model or whether a file's clusters were allocated
+    assert!(!xml.contains("<device_model>"));
+    assert!(!xml.contains("<unalloc>"));
+
+    let xml = dfxml::render_dfxml(&session(&dir), None, &files);
+    assert!(!xml.contains("<volume_size>") && !xml.contains("<volume_label>"));
+    assert!(!xml.contains("<hashdigest"));
+}
@@ -0,0 +1,175 @@
+// Golden tests for the winfr argument dialects.
+//
+// Each dialect has a golden file in tests/fixtures/dialects listing the rendered arguments,
+// warnings and filter issues for every case below. After an intended change, regenerate
+// them with `UPDATE_GOLDEN=1 cargo test --test dialects` and review the diff.
+
+use tauri_app_lib::dialect::render;
+use tauri_app_lib::engine::EngineSyntax;
+use tauri_app_lib::filters::compile;
+use tauri_app_lib::recovery::RecoveryConfig;
+
+fn base() -> RecoveryConfig {
+    RecoveryConfig {
+        source: "C:".to_string(),
+        destination: "D:\\Recovered".to_string(),
+        mode: "regular".to_string(),
+        filters: Vec::new(),
+        segment_mode: false,
+        signature_mode: false,
+        recover_non_deleted: false,
+        keep_both: false,
+        auto_accept: true,
+        recover_system_files: false,
+        keep_all_extensions: false,
+        source_fs: Some("NTFS".to_string()),
+        verbose_mode: false,
+        winfr_path: None,
+    }
+}
+
+fn cases() -> Vec<(&'static str, RecoveryConfig)> {
+    let with = |f: &dyn Fn(&mut RecoveryConfig)| {
+        let mut config = base();
+        f(&mut config);
+        config
+    };
+    let all_options = |c: &mut RecoveryConfig| {
+        c.recover_non_deleted = true;
+        c.recover_system_files = true;
+        c.keep_all_extensions = true;
+        c.keep_both = true;
+        c.verbose_mode = true;
+    };
+
+    vec![
+        ("regular", base()),
+        ("regular-prompts", with(&|c| c.auto_accept = false)),
+        ("regular-path-filter", with(&|c| c.filters = vec!["Users\\Bob\\Documents\\".to_string()])),
+        ("regular-all-options", with(&all_options)),
+        (
+            "extensive-categories",
+            with(&|c| {
+                c.mode = "extensive".to_string();
+                c.filters = vec!["Images".to_string(), "*.docx".to_string()];
+            }),
+        ),
+        (
+            "extensive-exfat-keep-both",
+            with(&|c| {
+                c.mode = "extensive".to_string();
+                c.source_fs = Some("exFAT".to_string());
+                c.keep_both = true;
+            }),
+        ),
+        (
+            "extensive-exclusions",
+            with(&|c| {
+                c.mode = "extensive".to_string();
+                c.filters = vec!["Images".to_string(), "!*.raw".to_string(), "\\Users\\Bob\\".to_string()];
+            }),
+        ),
+        (
+            "segment-all-options",
+            with(&|c| {
+                c.segment_mode = true;
+                all_options(c);
+                c.filters = vec!["*.pdf".to_string()];
+            }),
+        ),
+        (
+            "signature-groups",
+            with(&|c| {
+                c.signature_mode = true;
+                c.filters = vec!["Images".to_string(), "Documents".to_string(), "Archives".to_string()];
+            }),
+        ),
+        (
+            "signature-custom-filters",
+            with(&|c| {
+                c.signature_mode = true;
+                c.filters = vec!["*.docx".to_string(), "*.psd".to_string(), "Users\\Bob\\".to_string()];
+            }),
+        ),
+        (
+            "signature-exclusions",
+            with(&|c| {
+                c.signature_mode = true;
+                c.filters = vec!["*.pdf, *.png".to_string(), "!*.png".to_string()];
+            }),
+        ),
+        (
+            "signature-all-options",
+            with(&|c| {
+                c.signature_mode = true;
+                all_options(c);
+                c.filters = vec!["Audio".to_string()];
+            }),
+        ),
+        (
+            "signature-over-extensive",
+            with(&|c| {
+                c.mode = "extensive".to_string();
+                c.signature_mode = true;
+                c.segment_mode = true;
+            }),
+        ),
+    ]
+}
+
+fn render_all(syntax: EngineSyntax) -> String {
+    let mut out = String::new();
+    for (name, config) in cases() {
+        let command = render(&config, syntax);
+        out.push_str(&format!("# {}\n", name));
+        out.push_str(&format!("winfr {}\n", command.args.join(" ")));
+        for warning in &command.warnings {
+            out.push_str(&format!("warning: {}\n", warning));
+        }
+        for issue in compile(&config.filters).all_issues() {
+            let severity = format!("{:?}", issue.severity).to_lowercase();
+            out.push_str(&format!("filter {}: {}\n", severity, issue.message));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn check_golden(syntax: EngineSyntax, file: &str) {
+    let path = format!("{}/tests/fixtures/dialects/{}", env!("CARGO_MANIFEST_DIR"), file);
+    let actual = render_all(syntax);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        actual == expected,
+        "{} is out of date (rerun with UPDATE_GOLDEN=1 if intended)\n--- actual ---\n{}",
+        file,
+        actual
+    );
+}
+
+#[test]
+fn modern_dialect_matches_golden() {
+    check_golden(EngineSyntax::Modern, "modern.golden");
+}
+
+#[test]
+fn legacy_dialect_matches_golden() {
+    check_golden(EngineSyntax::Legacy, "legacy.golden");
+}
+
+#[test]
+fn signature_mode_never_mixes_with_another_mode() {
+    for syntax in [EngineSyntax::Modern, EngineSyntax::Legacy] {
+        for (name, config) in cases().into_iter().filter(|(_, c)| c.signature_mode) {
+            let args = render(&config, syntax).args;
+            let modes = ["/regular", "/extensive", "/segment", "/signature", "/r", "/x"];
+            let used: Vec<&String> = args.iter().filter(|a| modes.contains(&a.as_str())).collect();
+            assert_eq!(used.len(), 1, "{} ({:?}): {:?}", name, syntax, args);
+            assert!(!args.iter().any(|a| a == "/n"), "{} ({:?}) passes /n in signature mode", name, syntax);
+        }
+    }
+}
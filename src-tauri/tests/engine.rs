@@ -0,0 +1,111 @@
+// Engine discovery and help-screen parsing.
+
+#[path = "support/fake_engine.rs"]
+mod fake_engine;
+
+use fake_engine::fake_winfr;
+use tauri_app_lib::engine::{self, EngineSource, EngineSyntax};
+use tauri_app_lib::recovery::{build_winfr_args_for, RecoveryConfig};
+
+fn fixture_lines(name: &str) -> Vec<String> {
+    let path = format!("{}/tests/fixtures/winfr/{}", env!("CARGO_MANIFEST_DIR"), name);
+    std::fs::read_to_string(path).unwrap().lines().map(|l| l.trim().to_string()).collect()
+}
+
+fn config(mode: &str) -> RecoveryConfig {
+    RecoveryConfig {
+        source: "C:".to_string(),
+        destination: "D:\\Recovered".to_string(),
+        mode: mode.to_string(),
+        filters: vec!["*.pdf".to_string()],
+        segment_mode: false,
+        signature_mode: false,
+        recover_non_deleted: false,
+        keep_both: false,
+        auto_accept: true,
+        recover_system_files: false,
+        keep_all_extensions: false,
+        source_fs: None,
+        verbose_mode: false,
+        winfr_path: None,
+    }
+}
+
+#[test]
+fn probe_reads_version_and_switches_from_winfr() {
+    let winfr = fake_winfr();
+    let info = engine::probe(Some(&winfr));
+
+    assert!(info.found);
+    assert_eq!(info.source, Some(EngineSource::Configured));
+    assert_eq!(info.path.as_deref(), Some(winfr.as_str()));
+    assert_eq!(info.version.as_deref(), Some("0.1.20151.0"));
+    assert_eq!(info.syntax, EngineSyntax::Modern);
+    for switch in ["/regular", "/extensive", "/n", "/segment", "/signature", "/y:", "/o:", "/e", "/e:"] {
+        assert!(info.switches.iter().any(|s| s == switch), "missing {} in {:?}", switch, info.switches);
+    }
+    let groups: Vec<&str> = info.signature_groups.iter().map(|g| g.name.as_str()).collect();
+    assert_eq!(groups, vec!["ASF", "JPEG", "MP3", "MPEG", "PDF", "PNG", "ZIP"]);
+    assert!(info.error.is_none());
+
+    // Later lookups for the same path come from the cache
+    assert_eq!(engine::detect(Some(&winfr)).version, info.version);
+}
+
+#[test]
+fn configured_path_that_does_not_exist_is_not_replaced_by_path_lookup() {
+    let missing = std::env::temp_dir().join("winfr-pro-nonexistent").join("winfr.exe");
+    let missing = missing.to_string_lossy();
+    let info = engine::probe(Some(&missing));
+
+    assert!(!info.found);
+    assert!(info.path.is_none());
+    assert_eq!(info.error, Some(format!("Configured winfr executable {} does not exist.", missing)));
+}
+
+#[test]
+fn configured_path_must_be_winfr_exe_by_its_full_path() {
+    // The fake engine under its own name is a program, but not winfr.exe
+    let other = engine::probe(Some(env!("CARGO_BIN_EXE_fake-winfr")));
+    assert!(!other.found);
+    assert!(other.error.unwrap().ends_with("is not winfr.exe."));
+
+    let relative = engine::probe(Some("winfr.exe"));
+    assert!(!relative.found);
+    assert_eq!(relative.error.as_deref(), Some("Configured winfr executable winfr.exe must be a full path."));
+}
+
+#[test]
+fn legacy_help_is_detected_from_its_switches() {
+    let (version, switches, syntax) = engine::parse_help(&fixture_lines("help_legacy.txt"));
+
+    assert_eq!(version.as_deref(), Some("0.1.13362.0"));
+    assert_eq!(syntax, EngineSyntax::Legacy);
+    assert_eq!(switches, vec!["/r", "/n", "/x", "/y:", "/#", "/?", "/!"]);
+}
+
+#[test]
+fn syntax_falls_back_to_the_version_number() {
+    let lines = |v: &str| vec![format!("Version:            {}", v)];
+
+    assert_eq!(engine::parse_help(&lines("0.1.13362.0")).2, EngineSyntax::Legacy);
+    assert_eq!(engine::parse_help(&lines("0.1.20151.0")).2, EngineSyntax::Modern);
+    assert_eq!(engine::parse_help(&lines("0.2.1.0")).2, EngineSyntax::Modern);
+    assert_eq!(engine::parse_help(&[]).2, EngineSyntax::Modern);
+}
+
+#[test]
+fn arguments_follow_the_detected_syntax() {
+    assert_eq!(
+        build_winfr_args_for(&config("regular"), EngineSyntax::Modern),
+        vec!["C:", "D:\\Recovered\\", "/regular", "/a", "/n", "*.pdf"]
+    );
+    assert_eq!(
+        build_winfr_args_for(&config("regular"), EngineSyntax::Legacy),
+        vec!["C:", "D:\\Recovered\\", "/a", "/n", "*.pdf"]
+    );
+    assert_eq!(
+        build_winfr_args_for(&config("extensive"), EngineSyntax::Legacy),
+        vec!["C:", "D:\\Recovered\\", "/r", "/a", "/n", "*.pdf"]
+    );
+}
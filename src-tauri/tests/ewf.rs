@@ -0,0 +1,174 @@
+// Tests for E01 evidence files: segmented sets from the builder in tests/support read as
+// the acquired disk for the partition parser and native scanners, stored hashes are
+// verified, and damaged or incomplete sets are refused.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/disk_image.rs"]
+#[allow(dead_code)]
+mod disk_image;
+#[path = "support/ewf_image.rs"]
+#[allow(dead_code)]
+mod ewf_image;
+#[path = "support/exfat_image.rs"]
+#[allow(dead_code)]
+mod exfat_image;
+
+use common::scratch_dir;
+use disk_image::{DiskImage, MIB, SECTOR_SIZE};
+use ewf_image::{EwfImage, CHUNK_SIZE};
+use exfat_image::{Dir, ExfatImage};
+use std::fs;
+use tauri_app_lib::blockdev;
+use tauri_app_lib::ewf;
+use tauri_app_lib::exfat;
+use tauri_app_lib::images::{self, ImageFormat};
+use tauri_app_lib::partitions::{self, Filesystem};
+
+// ── Helpers ──────────────────────────────────────────────────────────
+
+// 16 MiB and three sectors, so the last chunk is short. An exFAT volume at 1 MiB holds one
+// deleted clip; noise at 12 MiB does not compress and is stored as plain chunks.
+fn acquired_disk() -> Vec<u8> {
+    let mut disk = DiskImage::new(16 * MIB + 3 * SECTOR_SIZE);
+    let mib = MIB / SECTOR_SIZE;
+    disk.partition_sector(0, &[(0x07, mib, 8 * mib)]);
+    let mut volume = ExfatImage::new();
+    let clip = volume.write_file(Dir::Root, "clip.mp4", &[7u8; 6000]);
+    volume.delete(&clip);
+    disk.put(MIB, &volume.build());
+    let mut state = 0x2545_F491u32;
+    let noise: Vec<u8> = (0..3 * CHUNK_SIZE)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    disk.put(12 * MIB, &noise);
+    disk.build()
+}
+
+fn read_all(path: &str) -> Vec<u8> {
+    let mut device = blockdev::open_device(path).unwrap();
+    let size = device.size().unwrap();
+    device.read_at(0, size as usize).unwrap()
+}
+
+fn open_error(path: &str) -> String {
+    match blockdev::open_device(path) {
+        Ok(_) => panic!("{} opened", path),
+        Err(e) => e,
+    }
+}
+
+// ── Tests ────────────────────────────────────────────────────────────
+
+#[test]
+fn segmented_evidence_reads_as_the_acquired_disk() {
+    let dir = scratch_dir("read");
+    let raw = acquired_disk();
+    let source = EwfImage::new(raw.clone()).segment_chunks(200).save(&dir, "card");
+    assert!(dir.join("card.E03").exists());
+
+    assert!(read_all(&source) == raw);
+    let mut device = blockdev::open_device(&source).unwrap();
+    let at = 200 * CHUNK_SIZE as u64 - 5;
+    assert_eq!(device.read_at(at, 10).unwrap(), raw[at as usize..at as usize + 10], "across segments");
+    let tail = raw.len() as u64 - 700;
+    assert_eq!(device.read_at(tail, 700).unwrap(), raw[tail as usize..]);
+    let past = device.read_at(tail, 701).unwrap_err();
+    assert!(past.contains("Read past the end"), "{}", past);
+
+    // The native scanners recover straight from the evidence file
+    let info = images::inspect_source(&source).unwrap();
+    assert_eq!(info.format, Some(ImageFormat::Ewf));
+    assert!(!info.winfr);
+    assert_eq!(info.volumes.len(), 1);
+    let layout = partitions::list_partitions(&source, false).unwrap();
+    assert_eq!(layout.partitions[0].filesystem, Some(Filesystem::Exfat));
+    let camera = &layout.partitions[0].source;
+    let listing = exfat::list_deleted_files(camera).unwrap();
+    let target = dir.join("out");
+    let ids = [listing.files[0].id.clone()];
+    let recovered = exfat::recover_files(camera, &ids, &target.to_string_lossy(), |_| {}).unwrap();
+    assert!(recovered.failed.is_empty(), "{:?}", recovered.failed);
+    assert_eq!(fs::read(target.join("clip.mp4")).unwrap(), vec![7u8; 6000]);
+
+    // Nothing was written to the segments
+    for (i, segment) in EwfImage::new(raw).segment_chunks(200).build().iter().enumerate() {
+        assert!(fs::read(dir.join(format!("card.E{:02}", i + 1))).unwrap() == *segment);
+    }
+}
+
+#[test]
+fn stored_hashes_are_verified() {
+    let dir = scratch_dir("verify");
+    let raw = acquired_disk();
+    let mut updates = 0;
+    let good = EwfImage::new(raw.clone()).segment_chunks(300).save(&dir, "good");
+    let verification = ewf::verify_evidence(&good, |_| updates += 1).unwrap();
+    assert!(verification.verified);
+    assert_eq!(verification.segments, 2);
+    assert_eq!(verification.size, raw.len() as u64);
+    assert_eq!(verification.stored_md5.as_deref(), Some(verification.md5.as_str()));
+    assert_eq!(verification.stored_sha1.as_deref(), Some(verification.sha1.as_str()));
+    assert!(updates > 0);
+
+    // An acquisition hash that does not match what the segments hold now
+    let altered = EwfImage::new(raw.clone()).stored_hashes(Some(vec![0xAB; 16]), None).save(&dir, "altered");
+    let verification = ewf::verify_evidence(&altered, |_| {}).unwrap();
+    assert!(!verification.verified);
+    assert_eq!(verification.stored_sha1, None);
+
+    // Without stored hashes there is nothing to verify against
+    let bare = EwfImage::new(raw).stored_hashes(None, None).save(&dir, "bare");
+    let verification = ewf::verify_evidence(&bare, |_| {}).unwrap();
+    assert!(!verification.verified);
+    assert_eq!(verification.stored_md5, None);
+}
+
+#[test]
+fn damaged_or_incomplete_evidence_is_refused() {
+    let dir = scratch_dir("damaged");
+    let raw = acquired_disk();
+
+    // table2 stands in for a damaged table
+    let patched = EwfImage::new(raw.clone()).segment_chunks(200).damaged_table().save(&dir, "patched");
+    assert!(read_all(&patched) == raw);
+
+    let missing = EwfImage::new(raw.clone()).segment_chunks(200).save(&dir, "missing");
+    fs::remove_file(dir.join("missing.E02")).unwrap();
+    let error = open_error(&missing);
+    assert!(error.contains("Segment 2") && error.contains("is missing"), "{}", error);
+
+    // A flipped bit inside a plain (uncompressed) chunk fails that chunk only
+    let segment = EwfImage::new(raw.clone()).build().remove(0);
+    let noise = &raw[12 * MIB as usize..12 * MIB as usize + 64];
+    let at = segment.windows(64).position(|w| w == noise).unwrap();
+    let mut corrupt = segment.clone();
+    corrupt[at + 100] ^= 0x01;
+    let path = dir.join("corrupt.E01");
+    fs::write(&path, &corrupt).unwrap();
+    let mut device = blockdev::open_device(&path.to_string_lossy()).unwrap();
+    let error = device.read_at(12 * MIB, 512).unwrap_err();
+    assert!(error.contains("fails its checksum"), "{}", error);
+    assert_eq!(device.read_at(MIB, 512).unwrap(), raw[MIB as usize..MIB as usize + 512]);
+
+    // A chunk that inflates past the chunk size is refused, not decompressed without end
+    let path = EwfImage::new(raw.clone()).oversized_chunk(0).save(&dir, "oversized");
+    let mut device = blockdev::open_device(&path).unwrap();
+    let error = device.read_at(0, 512).unwrap_err();
+    assert!(error.contains("inflates past the chunk size"), "{}", error);
+    assert_eq!(device.read_at(MIB, 512).unwrap(), raw[MIB as usize..MIB as usize + 512]);
+
+    // Ex01 uses another container layout
+    let mut ex01 = b"EVF2\r\n\x81\x00".to_vec();
+    ex01.resize(4096, 0);
+    let path = dir.join("disk.Ex01");
+    fs::write(&path, ex01).unwrap();
+    let error = open_error(&path.to_string_lossy());
+    assert!(error.contains("Ex01"), "{}", error);
+}
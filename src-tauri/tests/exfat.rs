@@ -0,0 +1,176 @@
+// Tests for the native exFAT deleted-entry scanner, run against images from tests/support.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/exfat_image.rs"]
+mod exfat_image;
+
+use common::{find, pattern, save, scratch_dir};
+use exfat_image::{Dir, ExfatImage, CLUSTER_SIZE, TIMESTAMP};
+use std::fs;
+use tauri_app_lib::exfat;
+use tauri_app_lib::ntfs::DataState;
+use tauri_app_lib::preflight;
+use tauri_app_lib::recovery::RecoveryConfig;
+
+// ── Tests ────────────────────────────────────────────────────────────
+
+#[test]
+fn lists_and_recovers_deleted_entry_sets() {
+    let dir = scratch_dir("recover");
+    let mut image = ExfatImage::new();
+    image.label("CAMERA");
+    let dcim = image.mkdir(Dir::Root, "DCIM").dir.unwrap();
+    let media = image.mkdir(dcim, "100MEDIA").dir.unwrap();
+    let clip = image.write_file(media, "clip 0001.mp4", &pattern(10_000, 1));
+    let notes = image.write_file(Dir::Root, "meeting notes from january.txt", &pattern(700, 2));
+    image.write_file(Dir::Root, "keep.txt", b"still here");
+    image.delete(&clip);
+    image.delete(&notes);
+    let source = save(&dir, "card.img", &image.build());
+    let before = fs::read(&source).unwrap();
+
+    let listing = exfat::list_deleted_files(&source).unwrap();
+    assert_eq!(listing.cluster_size, CLUSTER_SIZE as u64);
+    assert_eq!(listing.volume_label.as_deref(), Some("CAMERA"));
+    let paths: Vec<&str> = listing.files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, ["\\DCIM\\100MEDIA\\clip 0001.mp4", "\\meeting notes from january.txt"]);
+
+    let clip_entry = find(&listing.files, "\\DCIM\\100MEDIA\\clip 0001.mp4");
+    assert_eq!(clip_entry.size, 10_000);
+    assert_eq!(clip_entry.clusters, 3);
+    assert_eq!(clip_entry.first_cluster, clip.clusters[0]);
+    assert!(clip_entry.contiguous);
+    assert!(clip_entry.checksum_valid);
+    assert_eq!(clip_entry.data_state, DataState::Intact);
+    assert_eq!(clip_entry.modified, Some(TIMESTAMP));
+    assert_eq!(clip_entry.created, Some(TIMESTAMP));
+
+    let ids: Vec<String> = listing.files.iter().map(|f| f.id.clone()).collect();
+    let target = dir.join("out");
+    let summary = exfat::recover_files(&source, &ids, &target.to_string_lossy(), |_| {}).unwrap();
+    assert!(summary.failed.is_empty(), "{:?}", summary.failed);
+    let clip_path = target.join("DCIM").join("100MEDIA").join("clip 0001.mp4");
+    assert_eq!(fs::read(clip_path).unwrap(), pattern(10_000, 1));
+    assert_eq!(fs::read(target.join("meeting notes from january.txt")).unwrap(), pattern(700, 2));
+
+    assert_eq!(fs::read(&source).unwrap(), before);
+}
+
+#[test]
+fn reports_reused_and_fragmented_files() {
+    let dir = scratch_dir("states");
+    let mut image = ExfatImage::new();
+    let reused = image.write_file(Dir::Root, "reused.bin", &pattern(4 * CLUSTER_SIZE, 3));
+    let fragmented =
+        image.write_file_chained(Dir::Root, "fragmented.bin", &pattern(2 * CLUSTER_SIZE, 4), &[100, 90]);
+    image.delete(&reused);
+    image.delete(&fragmented);
+    image.write_file_chained(Dir::Root, "new.bin", &pattern(CLUSTER_SIZE, 5), &reused.clusters[3..]);
+    let source = save(&dir, "card.img", &image.build());
+
+    let listing = exfat::list_deleted_files(&source).unwrap();
+    let reused_entry = find(&listing.files, "\\reused.bin");
+    assert_eq!(reused_entry.data_state, DataState::Partial);
+    let fragmented_entry = find(&listing.files, "\\fragmented.bin");
+    assert!(!fragmented_entry.contiguous);
+    assert_eq!(fragmented_entry.data_state, DataState::Unknown);
+
+    let target = dir.join("out");
+    let ids = std::slice::from_ref(&fragmented_entry.id);
+    let summary = exfat::recover_files(&source, ids, &target.to_string_lossy(), |_| {}).unwrap();
+    assert!(summary.recovered.is_empty());
+    assert!(summary.failed[0].error.contains("fragmented"), "{}", summary.failed[0].error);
+}
+
+#[test]
+fn lists_files_inside_deleted_folders() {
+    let dir = scratch_dir("folders");
+    let mut image = ExfatImage::new();
+    let trip = image.mkdir(Dir::Root, "Trip");
+    let photo = image.write_file(trip.dir.unwrap(), "photo.jpg", &pattern(5000, 6));
+    image.delete(&photo);
+    image.delete(&trip);
+    let source = save(&dir, "card.img", &image.build());
+
+    let listing = exfat::list_deleted_files(&source).unwrap();
+    let file = find(&listing.files, "\\Trip\\photo.jpg");
+    assert!(file.in_deleted_folder);
+    assert_eq!(file.data_state, DataState::Intact);
+
+    let target = dir.join("out");
+    let ids = std::slice::from_ref(&file.id);
+    let summary = exfat::recover_files(&source, ids, &target.to_string_lossy(), |_| {}).unwrap();
+    assert_eq!(summary.recovered.len(), 1);
+    assert_eq!(fs::read(target.join("Trip").join("photo.jpg")).unwrap(), pattern(5000, 6));
+}
+
+#[test]
+fn skips_deleted_folders_whose_cluster_was_reused() {
+    let dir = scratch_dir("reused-folders");
+    let mut image = ExfatImage::new();
+    let old = image.mkdir(Dir::Root, "Old");
+    let stale = image.write_file(old.dir.unwrap(), "old.txt", &pattern(100, 1));
+    image.delete(&stale);
+    image.delete(&old);
+    // Listed after the deleted folder, and now in its cluster
+    let new = image.mkdir_at(Dir::Root, "New", old.clusters[0]);
+    let draft = image.write_file(new.dir.unwrap(), "draft.txt", &pattern(100, 2));
+    image.delete(&draft);
+    let source = save(&dir, "card.img", &image.build());
+
+    let listing = exfat::list_deleted_files(&source).unwrap();
+    let paths: Vec<&str> = listing.files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, ["\\New\\draft.txt"]);
+    assert!(!listing.files[0].in_deleted_folder);
+}
+
+#[test]
+fn rejects_volumes_that_are_not_exfat() {
+    let dir = scratch_dir("reject");
+    let path = dir.join("zeros.img");
+    fs::write(&path, vec![0u8; 64 * 1024]).unwrap();
+
+    let err = exfat::list_deleted_files(&path.to_string_lossy()).unwrap_err();
+    assert!(err.contains("Not an exFAT volume"), "{}", err);
+}
+
+#[test]
+fn preflight_offers_the_native_scanner_for_exfat_sources() {
+    let dir = scratch_dir("preflight");
+    let mut config = RecoveryConfig {
+        source: "E:".to_string(),
+        destination: dir.join("out").to_string_lossy().to_string(),
+        mode: "regular".to_string(),
+        filters: Vec::new(),
+        segment_mode: false,
+        signature_mode: false,
+        recover_non_deleted: false,
+        keep_both: false,
+        auto_accept: true,
+        recover_system_files: false,
+        keep_all_extensions: false,
+        source_fs: Some("exFAT".to_string()),
+        verbose_mode: false,
+        winfr_path: None,
+    };
+    let offered = |config: &RecoveryConfig| {
+        let report = preflight::run_preflight(config);
+        report.checks.into_iter().find(|c| c.action.is_some())
+    };
+
+    let check = offered(&config).unwrap();
+    assert!(check.ok && !check.warning, "{}", check.message);
+    let action = check.action.unwrap();
+    assert_eq!(action.command, "list_exfat_deleted_files");
+    assert_eq!(action.source, "E:");
+
+    // Where winfr tends to crash, the offer comes with a warning
+    config.mode = "extensive".to_string();
+    let check = offered(&config).unwrap();
+    assert!(check.warning && check.message.contains("0xC0000005"), "{}", check.message);
+
+    config.source_fs = Some("NTFS".to_string());
+    assert!(offered(&config).is_none());
+}
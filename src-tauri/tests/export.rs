@@ -0,0 +1,129 @@
+// Tests for exporting recovered files: layouts, renaming, collisions, verification and
+// what a move leaves behind.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/recovered_files.rs"]
+#[allow(dead_code)]
+mod recovered_files;
+
+use common::scratch_dir;
+use recovered_files::{recovered, recovery_folder, session, use_test_app_data, FOLDER};
+use std::fs;
+use tauri_app_lib::export::{self, CollisionPolicy, ExportLayout, ExportMode, ExportOptions};
+use tauri_app_lib::index;
+
+fn ids_of(dest: &str, names: &[&str]) -> Vec<String> {
+    index::all_files(dest)
+        .unwrap()
+        .into_iter()
+        .filter(|f| names.contains(&f.name.as_str()))
+        .map(|f| f.id)
+        .collect()
+}
+
+#[test]
+fn copies_keep_folders_rename_on_collision_and_verify() {
+    use_test_app_data();
+    let dir = scratch_dir("copy");
+    let dest = recovery_folder(&dir, &[("Users/Bob/report.pdf", b"%PDF-1.7 report"), ("notes.txt", b"notes")]);
+    let target = dir.join("out");
+    fs::create_dir_all(target.join("Users/Bob")).unwrap();
+    fs::write(target.join("Users/Bob/report.pdf"), b"already here").unwrap();
+
+    let options = ExportOptions { verify: true, ..ExportOptions::default() };
+    let mut progress = Vec::new();
+    let ids = ids_of(&dest, &["report.pdf", "notes.txt"]);
+    let summary = export::export_recovered_files(&dest, &ids, &target.to_string_lossy(), &options, |p| {
+        progress.push((p.current, p.total, p.bytes_done, p.bytes_total))
+    })
+    .unwrap();
+
+    assert_eq!((summary.exported.len(), summary.skipped.len(), summary.failed.len()), (2, 0, 0));
+    assert!(summary.exported.iter().all(|f| f.sha256.as_ref().is_some_and(|h| h.len() == 64)));
+    assert_eq!(fs::read(target.join("Users/Bob/report (1).pdf")).unwrap(), b"%PDF-1.7 report");
+    assert_eq!(fs::read(target.join("Users/Bob/report.pdf")).unwrap(), b"already here");
+    assert_eq!(fs::read(target.join("notes.txt")).unwrap(), b"notes");
+    assert_eq!(progress.last(), Some(&(2, 2, 20, 20)));
+
+    // Copies leave the recovered files and the index alone
+    assert!(recovered(&dir, "notes.txt").exists());
+    assert_eq!(index::all_files(&dest).unwrap().len(), 2);
+}
+
+#[test]
+fn moves_flatten_with_a_pattern_and_drop_moved_files_from_the_index() {
+    use_test_app_data();
+    let dir = scratch_dir("move");
+    let dest = recovery_folder(
+        &dir,
+        &[("a/photo.jpg", b"\xFF\xD8\xFF\xE0 one"), ("b/clip.mp4", b"clip"), ("c.txt", b"c")],
+    );
+    let target = dir.join("out");
+    fs::create_dir_all(&target).unwrap();
+    fs::write(target.join("Videos_clip.mp4"), b"keep me").unwrap();
+
+    let options = ExportOptions {
+        mode: ExportMode::Move,
+        layout: ExportLayout::Flatten,
+        rename_pattern: Some("{category}_{stem}".to_string()),
+        collision: CollisionPolicy::Skip,
+        verify: false,
+    };
+    let ids = ids_of(&dest, &["photo.jpg", "clip.mp4"]);
+    let summary = export::export_recovered_files(&dest, &ids, &target.to_string_lossy(), &options, |_| {}).unwrap();
+
+    assert_eq!((summary.exported.len(), summary.skipped.len()), (1, 1));
+    assert_eq!(fs::read(target.join("Images_photo.jpg")).unwrap(), b"\xFF\xD8\xFF\xE0 one");
+    assert_eq!(fs::read(target.join("Videos_clip.mp4")).unwrap(), b"keep me");
+    assert!(!recovered(&dir, "a/photo.jpg").exists());
+    assert!(recovered(&dir, "b/clip.mp4").exists());
+
+    let left: Vec<String> = index::all_files(&dest).unwrap().into_iter().map(|f| f.name).collect();
+    assert_eq!(left.len(), 2);
+    assert!(!left.contains(&"photo.jpg".to_string()), "{:?}", left);
+}
+
+#[test]
+fn targets_inside_the_recovery_folder_are_refused_however_they_are_written() {
+    use_test_app_data();
+    let dir = scratch_dir("inside");
+    let dest = recovery_folder(&dir, &[("a.txt", b"a")]);
+    let ids = ids_of(&dest, &["a.txt"]);
+
+    let sneaky = dir.join("elsewhere").join("..").join(FOLDER).join("out");
+    let err = export::export_recovered_files(&dest, &ids, &sneaky.to_string_lossy(), &ExportOptions::default(), |_| {})
+        .unwrap_err();
+    assert!(err.contains("outside the recovery folder"), "{}", err);
+    assert!(!recovered(&dir, "out").exists());
+
+    // Nor onto the source of the session the files came from
+    let image = dir.join("card.img");
+    fs::write(&image, b"source").unwrap();
+    let mut config = session(&dir).config;
+    config.source = image.to_string_lossy().to_string();
+    tauri_app_lib::session::begin(&config, &[]);
+    let err = export::export_recovered_files(&dest, &ids, &config.source, &ExportOptions::default(), |_| {})
+        .unwrap_err();
+    assert!(err.contains("source image"), "{}", err);
+    assert_eq!(fs::read(&image).unwrap(), b"source");
+
+    let target = dir.join("out").to_string_lossy().to_string();
+    let none = vec!["no-such-id".to_string()];
+    let err = export::export_recovered_files(&dest, &none, &target, &ExportOptions::default(), |_| {}).unwrap_err();
+    assert!(err.contains("None of the selected files"), "{}", err);
+}
+
+#[test]
+fn rename_patterns_fill_tokens_and_keep_names_valid() {
+    use_test_app_data();
+    let dir = scratch_dir("pattern");
+    let dest = recovery_folder(&dir, &[("Q3 report.pdf", b"%PDF")]);
+    let file = index::all_files(&dest).unwrap().remove(0);
+
+    assert_eq!(export::apply_rename_pattern("{category}-{stem}", &file), "Documents-Q3 report.pdf");
+    assert_eq!(export::apply_rename_pattern("{stem}.{ext}.bak", &file), "Q3 report.pdf.bak");
+    assert_eq!(export::apply_rename_pattern("a/b:c?", &file), "a_b_c_.pdf");
+    assert_eq!(export::apply_rename_pattern("{name}", &file), "Q3 report.pdf");
+}
@@ -0,0 +1,182 @@
+// Tests for the FAT12/16/32 undelete scanner, run against images from tests/support.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/fat_image.rs"]
+mod fat_image;
+
+use common::{find, pattern, run_tool, save, scratch_dir, text};
+use fat_image::{Dir, FatImage, FatKind, TIMESTAMP};
+use std::fs;
+use tauri_app_lib::fat::{self, FatType};
+
+// ── Tests ────────────────────────────────────────────────────────────
+
+#[test]
+fn lists_and_recovers_deleted_files_on_every_fat_type() {
+    let kinds = [(FatKind::Fat12, FatType::Fat12), (FatKind::Fat16, FatType::Fat16), (FatKind::Fat32, FatType::Fat32)];
+    for (kind, expected) in kinds {
+        let dir = scratch_dir(&format!("{:?}", kind));
+        let mut image = FatImage::new(kind);
+        image.label("SDCARD");
+        let dcim = image.mkdir(Dir::Root, "DCIM").dir.unwrap();
+        let short = image.write_file(dcim, "IMG_0001.JPG", &pattern(3000, 1));
+        let long = image.write_file(dcim, "Holiday photo 2024.jpeg", &pattern(5000, 2));
+        image.write_file(Dir::Root, "keep.txt", b"still here");
+        image.delete(&short);
+        image.delete(&long);
+        let source = save(&dir, "card.img", &image.build());
+
+        let listing = fat::list_deleted_files(&source).unwrap();
+        assert_eq!(listing.fat_type, expected);
+        assert_eq!(listing.cluster_size, image.cluster_size() as u64);
+        assert_eq!(listing.volume_label.as_deref(), Some("SDCARD"));
+        let paths: Vec<&str> = listing.files.iter().map(|f| f.path.as_str()).collect();
+        // The first letter of a deleted short name is lost
+        assert_eq!(paths, ["\\DCIM\\_MG_0001.JPG", "\\DCIM\\Holiday photo 2024.jpeg"], "{:?}", kind);
+
+        let holiday = find(&listing.files, "\\DCIM\\Holiday photo 2024.jpeg");
+        assert_eq!(holiday.name, "Holiday photo 2024.jpeg");
+        assert_eq!(holiday.size, 5000);
+        assert_eq!(holiday.start_cluster, long.clusters[0]);
+        assert_eq!(holiday.modified, Some(TIMESTAMP));
+        assert!(!holiday.in_deleted_folder);
+
+        let ids: Vec<String> = listing.files.iter().map(|f| f.id.clone()).collect();
+        let target = dir.join("out");
+        let summary = fat::recover_files(&source, &ids, &target.to_string_lossy(), |_| {}).unwrap();
+        assert!(summary.failed.is_empty(), "{:?}", summary.failed);
+        assert_eq!(fs::read(target.join("DCIM").join("_MG_0001.JPG")).unwrap(), pattern(3000, 1));
+        assert_eq!(fs::read(target.join("DCIM").join("Holiday photo 2024.jpeg")).unwrap(), pattern(5000, 2));
+    }
+}
+
+#[test]
+fn scores_confidence_by_reused_clusters() {
+    let dir = scratch_dir("confidence");
+    let mut image = FatImage::new(FatKind::Fat32);
+    let cluster = image.cluster_size();
+    let single = image.write_file(Dir::Root, "single.txt", &pattern(400, 3));
+    let free = image.write_file(Dir::Root, "free.bin", &pattern(4 * cluster, 4));
+    let half = image.write_file(Dir::Root, "half.bin", &pattern(4 * cluster, 5));
+    let gone = image.write_file(Dir::Root, "gone.bin", &pattern(2 * cluster, 6));
+    for node in [&single, &free, &half, &gone] {
+        image.delete(node);
+    }
+    image.write_file_at(Dir::Root, "new1.bin", &pattern(2 * cluster, 7), &half.clusters[2..]);
+    image.write_file_at(Dir::Root, "new2.bin", &pattern(2 * cluster, 8), &gone.clusters);
+    let listing = fat::list_deleted_files(&save(&dir, "card.img", &image.build())).unwrap();
+
+    let score = |path: &str| {
+        let file = find(&listing.files, path);
+        (file.confidence, file.reused_clusters)
+    };
+    assert_eq!(score("\\single.txt"), (100, 0));
+    assert_eq!(score("\\free.bin"), (90, 0));
+    assert_eq!(score("\\half.bin"), (40, 2));
+    assert_eq!(score("\\gone.bin"), (0, 2));
+}
+
+#[test]
+fn lists_files_inside_deleted_folders() {
+    let dir = scratch_dir("folders");
+    let mut image = FatImage::new(FatKind::Fat16);
+    let photos = image.mkdir(Dir::Root, "Photos");
+    let beach = image.write_file(photos.dir.unwrap(), "beach.jpg", &pattern(1500, 9));
+    image.delete(&beach);
+    image.delete(&photos);
+    let source = save(&dir, "card.img", &image.build());
+
+    let listing = fat::list_deleted_files(&source).unwrap();
+    let file = find(&listing.files, "\\Photos\\beach.jpg");
+    assert!(file.in_deleted_folder);
+    assert_eq!(file.confidence, 100);
+
+    let target = dir.join("out");
+    let ids = [file.id.clone(), "fat-1".to_string()];
+    let summary = fat::recover_files(&source, &ids, &target.to_string_lossy(), |_| {}).unwrap();
+    assert_eq!(summary.recovered.len(), 1);
+    assert_eq!(summary.failed.len(), 1);
+    assert_eq!(summary.failed[0].id, "fat-1");
+    assert_eq!(fs::read(target.join("Photos").join("beach.jpg")).unwrap(), pattern(1500, 9));
+}
+
+#[test]
+fn skips_deleted_folders_whose_cluster_was_reused() {
+    let dir = scratch_dir("reused-folders");
+    let mut image = FatImage::new(FatKind::Fat16);
+    let cluster = image.cluster_size();
+    let old = image.mkdir(Dir::Root, "Old");
+    let stale = image.write_file(old.dir.unwrap(), "old.txt", &pattern(100, 1));
+    image.delete(&stale);
+    image.delete(&old);
+    // Listed after the deleted folder, and now in its cluster
+    let new = image.mkdir_at(Dir::Root, "New", old.clusters[0]);
+    let draft = image.write_file(new.dir.unwrap(), "draft.txt", &pattern(100, 2));
+    image.delete(&draft);
+    // A deleted folder whose freed cluster was overwritten by a file, itself deleted since
+    let notes = image.mkdir(Dir::Root, "Notes");
+    image.delete(&notes);
+    let big = image.write_file_at(Dir::Root, "big.bin", &pattern(cluster, 3), &notes.clusters);
+    image.delete(&big);
+    let source = save(&dir, "card.img", &image.build());
+
+    let listing = fat::list_deleted_files(&source).unwrap();
+    let paths: Vec<&str> = listing.files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, ["\\big.bin", "\\New\\draft.txt"]);
+    assert!(!find(&listing.files, "\\New\\draft.txt").in_deleted_folder);
+}
+
+#[test]
+fn rejects_volumes_that_are_not_fat() {
+    let dir = scratch_dir("reject");
+    let path = dir.join("zeros.img");
+    fs::write(&path, vec![0u8; 64 * 1024]).unwrap();
+
+    let err = fat::list_deleted_files(&path.to_string_lossy()).unwrap_err();
+    assert!(err.contains("Not a FAT volume"), "{}", err);
+}
+
+#[test]
+fn recovers_files_deleted_by_mtools_on_a_mkfs_fat_volume() {
+    // FAT16 and FAT32 as formatted by dosfstools, with files written and deleted by mtools
+    for (bits, kib, expected) in [("16", "8192", FatType::Fat16), ("32", "40960", FatType::Fat32)] {
+        let dir = scratch_dir(&format!("mkfs-{}", bits));
+        let image = text(&dir.join("card.img"));
+        if !run_tool("mkfs.fat", &["-F", bits, "-n", "SDCARD", "-C", &image, kib]) {
+            return;
+        }
+        let photo = save(&dir, "photo.jpeg", &pattern(70_000, 1));
+        let short = save(&dir, "short.jpg", &pattern(3000, 2));
+        let keep = save(&dir, "keep.txt", b"still here");
+        let mtools = [
+            ("mmd", vec!["::/DCIM"]),
+            ("mcopy", vec![photo.as_str(), "::/DCIM/Holiday photo 2024.jpeg"]),
+            ("mcopy", vec![short.as_str(), "::/DCIM/IMG_0001.JPG"]),
+            ("mcopy", vec![keep.as_str(), "::/keep.txt"]),
+            ("mdel", vec!["::/DCIM/Holiday photo 2024.jpeg", "::/DCIM/IMG_0001.JPG"]),
+        ];
+        for (tool, args) in mtools {
+            let args: Vec<&str> = ["-i", image.as_str()].into_iter().chain(args).collect();
+            if !run_tool(tool, &args) {
+                return;
+            }
+        }
+
+        let listing = fat::list_deleted_files(&image).unwrap();
+        assert_eq!(listing.fat_type, expected);
+        assert_eq!(listing.volume_label.as_deref(), Some("SDCARD"));
+        let holiday = find(&listing.files, "\\DCIM\\Holiday photo 2024.jpeg");
+        assert_eq!(holiday.size, 70_000);
+        find(&listing.files, "\\DCIM\\_MG_0001.JPG");
+        assert_eq!(listing.files.len(), 2);
+
+        let ids: Vec<String> = listing.files.iter().map(|f| f.id.clone()).collect();
+        let target = dir.join("out");
+        let summary = fat::recover_files(&image, &ids, &text(&target), |_| {}).unwrap();
+        assert!(summary.failed.is_empty(), "{:?}", summary.failed);
+        assert_eq!(fs::read(target.join("DCIM").join("Holiday photo 2024.jpeg")).unwrap(), pattern(70_000, 1));
+        assert_eq!(fs::read(target.join("DCIM").join("_MG_0001.JPG")).unwrap(), pattern(3000, 2));
+    }
+}
@@ -0,0 +1,165 @@
+// Filter parsing, validation and the switch preview.
+
+#[path = "support/fake_engine.rs"]
+mod fake_engine;
+
+use fake_engine::fake_winfr;
+use tauri_app_lib::filters::{compile, parse_filter, preview_filters, FilterRule, IssueSeverity};
+use tauri_app_lib::recovery::RecoveryConfig;
+
+fn rule(input: &str) -> Option<FilterRule> {
+    let parsed = parse_filter(input);
+    assert!(parsed.issues.is_empty(), "{}: {:?}", input, parsed.issues);
+    parsed.rule
+}
+
+fn error_for(input: &str) -> String {
+    let parsed = parse_filter(input);
+    assert!(parsed.rule.is_none(), "{} should not parse", input);
+    assert_eq!(parsed.issues.len(), 1);
+    assert_eq!(parsed.issues[0].severity, IssueSeverity::Error);
+    parsed.issues[0].message.clone()
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+#[test]
+fn filters_are_parsed_into_rules() {
+    assert_eq!(rule("Images"), Some(FilterRule::Category { name: "Images".to_string() }));
+    assert_eq!(
+        rule("\\Users\\Bob\\Documents\\"),
+        Some(FilterRule::Path { path: "\\Users\\Bob\\Documents\\".to_string() })
+    );
+    assert_eq!(
+        rule("\"Users\\Bob\\My Pictures\\\""),
+        Some(FilterRule::Path { path: "Users\\Bob\\My Pictures\\".to_string() })
+    );
+    assert_eq!(
+        rule("Users\\Bob\\report.docx"),
+        Some(FilterRule::Path { path: "Users\\Bob\\report.docx".to_string() })
+    );
+    assert_eq!(rule("report*.docx"), Some(FilterRule::Name { pattern: "report*.docx".to_string() }));
+    assert_eq!(rule("*.pdf"), Some(FilterRule::Name { pattern: "*.pdf".to_string() }));
+    assert_eq!(
+        rule("*.pdf, *.DOCX"),
+        Some(FilterRule::Extensions { extensions: strings(&["pdf", "docx"]) })
+    );
+    assert_eq!(
+        rule(".jpg,.png,.JPG"),
+        Some(FilterRule::Extensions { extensions: strings(&["jpg", "png"]) })
+    );
+    assert_eq!(rule(".raw"), Some(FilterRule::Extensions { extensions: strings(&["raw"]) }));
+
+    let excluded = parse_filter("!*.tmp");
+    assert!(excluded.exclude);
+    assert_eq!(excluded.rule, Some(FilterRule::Name { pattern: "*.tmp".to_string() }));
+}
+
+#[test]
+fn invalid_filters_are_rejected_with_a_fix() {
+    assert_eq!(
+        error_for("\\Users\\Bob\\Documents"),
+        "Folder filters must end with a backslash: '\\Users\\Bob\\Documents\\'. \
+Without it winfr looks for a file named 'Documents'."
+    );
+    assert_eq!(error_for("Users/Bob/"), "Use backslashes in folder filters: 'Users\\Bob\\'.");
+    assert_eq!(
+        error_for("C:\\Users\\Bob\\"),
+        "Filter paths are relative to the source drive; drop 'C:' from 'C:\\Users\\Bob\\'."
+    );
+    assert_eq!(error_for("*.pd|f"), "'*.pd|f' contains characters that cannot appear in a file name.");
+    assert_eq!(error_for("report."), "File names cannot end with a dot or space: 'report.'.");
+    assert_eq!(error_for("  "), "Empty filter.");
+}
+
+#[test]
+fn bare_names_warn_that_they_match_exactly() {
+    let parsed = parse_filter("thesis");
+    assert_eq!(parsed.rule, Some(FilterRule::Name { pattern: "thesis".to_string() }));
+    assert_eq!(parsed.issues[0].severity, IssueSeverity::Warning);
+}
+
+#[test]
+fn exclusions_are_subtracted_from_included_patterns() {
+    let compiled = compile(&strings(&["Images", "!*.raw", "!.heic", "*.jpg"]));
+
+    assert_eq!(compiled.patterns, strings(&["*.jpg", "*.jpeg", "*.png", "*.gif", "*.bmp", "*.webp"]));
+    assert!(compiled.errors().is_empty());
+    assert!(compiled.issues.is_empty());
+}
+
+#[test]
+fn exclusions_winfr_cannot_express_are_errors() {
+    assert_eq!(
+        compile(&strings(&["!*.tmp"])).errors(),
+        vec!["'!*.tmp' cannot be applied without an include filter: winfr cannot exclude files on its own."]
+    );
+    assert_eq!(
+        compile(&strings(&["*.pdf", "!Users\\Temp\\"])).errors(),
+        vec!["winfr cannot exclude folders; remove '!Users\\Temp\\'."]
+    );
+    assert_eq!(
+        compile(&strings(&["*.pdf", "!*.pdf"])).errors(),
+        vec!["The exclusions remove every included pattern, so nothing would be recovered."]
+    );
+
+    let no_effect = compile(&strings(&["*.pdf", "!*.docx"]));
+    assert!(no_effect.errors().is_empty());
+    assert_eq!(no_effect.issues[0].severity, IssueSeverity::Warning);
+}
+
+#[test]
+fn exclusions_cannot_reach_into_included_paths() {
+    let compiled = compile(&strings(&["Images", "!*.raw", "\\Users\\Bob\\"]));
+
+    assert!(!compiled.patterns.contains(&"*.raw".to_string()));
+    assert!(compiled.errors().is_empty());
+    assert_eq!(compiled.issues.len(), 1);
+    assert_eq!(compiled.issues[0].severity, IssueSeverity::Warning);
+    assert_eq!(
+        compiled.issues[0].message,
+        "'!*.raw' cannot remove files from '\\Users\\Bob\\': winfr still recovers everything that path matches."
+    );
+
+    // Without a path include the exclusion is exact
+    assert!(compile(&strings(&["Images", "!*.raw"])).issues.is_empty());
+}
+
+#[test]
+fn preview_shows_the_exact_switches() {
+    let config = RecoveryConfig {
+        source: "C:".to_string(),
+        destination: "D:\\Recovered".to_string(),
+        mode: "regular".to_string(),
+        filters: strings(&["\\Users\\Bob\\Documents\\", "*.pdf, *.docx", "Documents", "!*.csv"]),
+        segment_mode: false,
+        signature_mode: false,
+        recover_non_deleted: false,
+        keep_both: false,
+        auto_accept: true,
+        recover_system_files: false,
+        keep_all_extensions: false,
+        source_fs: None,
+        verbose_mode: false,
+        winfr_path: Some(fake_winfr()),
+    };
+
+    let preview = preview_filters(&config);
+
+    assert!(preview.ok);
+    assert_eq!(
+        preview.switches,
+        strings(&[
+            "/n", "\\Users\\Bob\\Documents\\", "/n", "*.pdf", "/n", "*.docx", "/n", "*.doc", "/n", "*.xls", "/n",
+            "*.xlsx", "/n", "*.ppt", "/n", "*.pptx", "/n", "*.txt", "/n", "*.rtf", "/n", "*.odt",
+        ])
+    );
+    assert_eq!(&preview.args[..3], &strings(&["C:", "D:\\Recovered\\", "/regular"])[..]);
+    assert!(preview.args.ends_with(&preview.switches));
+    assert_eq!(preview.filters.len(), 4);
+
+    let broken = RecoveryConfig { filters: strings(&["\\Users\\Bob\\Documents"]), ..config };
+    assert!(!preview_filters(&broken).ok);
+}
@@ -0,0 +1,92 @@
+// Tests for hash set loading and known-good / known-bad classification.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+
+use common::scratch_dir;
+use std::fs;
+use std::sync::Mutex;
+use tauri_app_lib::hashing::{hash_reader, FileHashes};
+use tauri_app_lib::hashsets::{self, KnownStatus};
+
+// Loaded hash sets are shared by the whole process
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn hashes_of(data: &[u8]) -> FileHashes {
+    hash_reader(data).unwrap()
+}
+
+fn clear() {
+    for set in hashsets::list_hash_sets() {
+        hashsets::remove_hash_set(&set.id).unwrap();
+    }
+}
+
+#[test]
+fn plain_lists_and_nsrl_files_are_parsed() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    clear();
+    let dir = scratch_dir("parse");
+    let os = hashes_of(b"system file");
+    let tool = hashes_of(b"hacking tool");
+
+    // md5sum / sha256sum output, with comments, blank lines and upper-case digests
+    let plain = dir.join("good.txt");
+    let text = format!(
+        "# known OS files\n\n{}  kernel32.dll\n{} *ntdll.dll\nnot-a-hash\n",
+        os.md5.to_uppercase(),
+        os.sha256
+    );
+    fs::write(&plain, text).unwrap();
+    let info = hashsets::load_hash_set(&plain.to_string_lossy(), KnownStatus::KnownGood).unwrap();
+    assert_eq!((info.format.as_str(), info.entries, info.name.as_str()), ("plain", 2, "good.txt"));
+
+    // NSRL RDS: a BOM, a quoted header and quoted fields with commas in file names
+    let nsrl = dir.join("NSRLFile.txt");
+    let text = format!(
+        "\u{feff}\"SHA-1\",\"MD5\",\"CRC32\",\"FileName\"\n\"{}\",\"{}\",\"00000000\",\"tool, v2.exe\"\n",
+        tool.sha1.to_uppercase(),
+        tool.md5
+    );
+    fs::write(&nsrl, text).unwrap();
+    let info = hashsets::load_hash_set(&nsrl.to_string_lossy(), KnownStatus::KnownBad).unwrap();
+    assert_eq!((info.format.as_str(), info.entries), ("nsrl", 2));
+    assert_eq!(hashsets::list_hash_sets().len(), 2);
+
+    let empty = dir.join("empty.txt");
+    fs::write(&empty, "# nothing here\n").unwrap();
+    let error = hashsets::load_hash_set(&empty.to_string_lossy(), KnownStatus::KnownGood).unwrap_err();
+    assert!(error.contains("No MD5, SHA-1 or SHA-256 hashes"), "{}", error);
+    let error = hashsets::load_hash_set(&plain.to_string_lossy(), KnownStatus::Unknown).unwrap_err();
+    assert!(error.contains("known-good or known-bad"), "{}", error);
+    clear();
+}
+
+#[test]
+fn known_bad_wins_over_known_good() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    clear();
+    let dir = scratch_dir("classify");
+    let both = hashes_of(b"listed twice");
+    let good = hashes_of(b"only good");
+    let other = hashes_of(b"never listed");
+
+    let good_set = dir.join("good.txt");
+    fs::write(&good_set, format!("{}\n{}\n", both.sha1, good.sha256)).unwrap();
+    let bad_set = dir.join("bad.txt");
+    fs::write(&bad_set, format!("{}\n", both.md5)).unwrap();
+    hashsets::load_hash_set(&good_set.to_string_lossy(), KnownStatus::KnownGood).unwrap();
+    let bad = hashsets::load_hash_set(&bad_set.to_string_lossy(), KnownStatus::KnownBad).unwrap();
+
+    assert!(hashsets::has_hash_sets());
+    assert_eq!(hashsets::classify(&both), KnownStatus::KnownBad);
+    assert_eq!(hashsets::classify(&good), KnownStatus::KnownGood);
+    assert_eq!(hashsets::classify(&other), KnownStatus::Unknown);
+
+    hashsets::remove_hash_set(&bad.id).unwrap();
+    assert_eq!(hashsets::classify(&both), KnownStatus::KnownGood);
+    assert!(hashsets::remove_hash_set(&bad.id).is_err());
+    clear();
+    assert!(!hashsets::has_hash_sets());
+}
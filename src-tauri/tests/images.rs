@@ -0,0 +1,124 @@
+// Tests for disk image files as recovery sources: format detection and the volumes
+// offered to the native scanners, on images from the builders in tests/support.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/disk_image.rs"]
+#[allow(dead_code)]
+mod disk_image;
+#[path = "support/fat_image.rs"]
+#[allow(dead_code)]
+mod fat_image;
+
+use common::scratch_dir;
+use disk_image::{DiskImage, MIB, SECTOR_SIZE};
+use fat_image::{FatImage, FatKind};
+use std::fs;
+use std::path::Path;
+use tauri_app_lib::blockdev::open_device;
+use tauri_app_lib::fat;
+use tauri_app_lib::images::{self, ImageFormat};
+use tauri_app_lib::partitions::Filesystem;
+use tauri_app_lib::preflight;
+use tauri_app_lib::recovery::RecoveryConfig;
+
+fn config(source: &str, dest: &Path) -> RecoveryConfig {
+    RecoveryConfig {
+        source: source.to_string(),
+        destination: dest.to_string_lossy().to_string(),
+        mode: "regular".to_string(),
+        filters: Vec::new(),
+        segment_mode: false,
+        signature_mode: false,
+        recover_non_deleted: false,
+        keep_both: false,
+        auto_accept: true,
+        recover_system_files: false,
+        keep_all_extensions: false,
+        source_fs: None,
+        verbose_mode: true,
+        winfr_path: None,
+    }
+}
+
+#[test]
+fn detects_image_formats_by_extension() {
+    assert_eq!(images::image_format("D:\\Clones\\sd-card.IMG"), Some(ImageFormat::Raw));
+    assert_eq!(images::image_format("/clones/disk.dd|1048576"), Some(ImageFormat::Raw));
+    assert_eq!(images::image_format("D:\\VMs\\disk.vhd"), Some(ImageFormat::Vhd));
+    assert_eq!(images::image_format("D:\\Backup\\disk.vhdx"), Some(ImageFormat::Vhdx));
+    assert_eq!(images::image_format("E:"), None);
+    assert_eq!(images::image_format("D:\\notes.txt"), None);
+    // Ex01 evidence files are not read yet, so they are not offered as images
+    assert_eq!(images::image_format("D:\\Cases\\disk.Ex01"), None);
+    let err = images::inspect_source("D:\\Cases\\disk.Ex01").unwrap_err();
+    assert!(err.contains("Ex01 evidence file"), "{}", err);
+    let err = images::winfr_source("D:\\Cases\\disk.Ex01|1048576").unwrap_err();
+    assert!(err.contains("Export it as E01"), "{}", err);
+}
+
+#[test]
+fn raw_images_offer_their_volumes_to_the_native_scanners() {
+    let dir = scratch_dir("raw");
+    let mut disk = DiskImage::new(4 * MIB);
+    let mib = MIB / SECTOR_SIZE;
+    disk.partition_sector(0, &[(0x01, mib, mib)]);
+    let mut volume = FatImage::new(FatKind::Fat12);
+    let file = volume.write_file(fat_image::Dir::Root, "REPORT.PDF", &[3u8; 2048]);
+    volume.delete(&file);
+    disk.put(MIB, &volume.build());
+    let image = dir.join("card.img");
+    fs::write(&image, disk.build()).unwrap();
+    let source = image.to_string_lossy().to_string();
+
+    let info = images::inspect_source(&source).unwrap();
+    assert_eq!(info.format, Some(ImageFormat::Raw));
+    assert!(!info.winfr);
+    assert!(info.message.contains("native scanners"), "{}", info.message);
+    assert_eq!(info.volumes.len(), 1);
+    assert_eq!(info.volumes[0].filesystem, Some(Filesystem::Fat12));
+    let listing = fat::list_deleted_files(&info.volumes[0].source).unwrap();
+    assert_eq!(listing.files.len(), 1);
+
+    // Preflight explains why winfr cannot run on it
+    let report = preflight::run_preflight(&config(&source, &dir.join("out")));
+    let check = report.checks.iter().find(|c| c.name == "source").unwrap();
+    assert!(!check.ok && check.message.contains("native scanners"), "{}", check.message);
+
+    let err = images::inspect_source(&dir.join("missing.img").to_string_lossy()).unwrap_err();
+    assert!(err.contains("not found"), "{}", err);
+    let err = images::inspect_source("D:\\notes.txt").unwrap_err();
+    assert!(err.contains("neither a drive letter nor a disk image"), "{}", err);
+}
+
+#[test]
+fn split_raw_images_are_read_as_one_disk() {
+    let dir = scratch_dir("split");
+    let mut disk = DiskImage::new(4 * MIB);
+    let mib = MIB / SECTOR_SIZE;
+    disk.partition_sector(0, &[(0x01, mib, mib)]);
+    let mut volume = FatImage::new(FatKind::Fat12);
+    let file = volume.write_file(fat_image::Dir::Root, "REPORT.PDF", &[3u8; 2048]);
+    volume.delete(&file);
+    disk.put(MIB, &volume.build());
+    // Parts of 1.5 MiB, so the volume starts in the first part and runs into the second
+    let bytes = disk.build();
+    for (i, part) in bytes.chunks((3 * MIB / 2) as usize).enumerate() {
+        fs::write(dir.join(format!("card.{:03}", i + 1)), part).unwrap();
+    }
+    let source = dir.join("card.001").to_string_lossy().to_string();
+
+    let mut device = open_device(&source).unwrap();
+    assert_eq!(device.files().len(), 3);
+    assert_eq!(device.size(), Some(bytes.len() as u64));
+    let across = 3 * MIB / 2 - 100;
+    assert_eq!(device.read_at(across, 200).unwrap(), bytes[across as usize..across as usize + 200]);
+    assert!(device.read_at(4 * MIB - 100, 200).is_err());
+
+    let info = images::inspect_source(&source).unwrap();
+    assert_eq!(info.format, Some(ImageFormat::Raw));
+    assert_eq!(info.volumes.len(), 1);
+    let listing = fat::list_deleted_files(&info.volumes[0].source).unwrap();
+    assert_eq!(listing.files.len(), 1);
+}
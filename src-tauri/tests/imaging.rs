@@ -0,0 +1,235 @@
+// Tests for ddrescue-style imaging: reads with injected errors from the faulty reader
+// in tests/support, map files, resuming, and cancelling a run on the worker thread.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/faulty_reader.rs"]
+mod faulty_reader;
+
+use common::scratch_dir;
+use faulty_reader::FaultyReader;
+use std::fs;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri_app_lib::events::{EventSink, RecordingSink, RecoveryEvent, RecoveryStatus};
+use tauri_app_lib::imaging::{self, ImagingOptions};
+
+const SECTOR: u64 = 512;
+
+// ── Helpers ──────────────────────────────────────────────────────────
+
+// Every sector differs, so a misplaced write shows up
+fn disk_data(size: u64) -> Vec<u8> {
+    (0..size).map(|i| (i as u8) ^ ((i / SECTOR) as u8).wrapping_mul(31)).collect()
+}
+
+fn options(retries: u32) -> ImagingOptions {
+    ImagingOptions { sector_size: SECTOR, retries, ..Default::default() }
+}
+
+fn zeroed(mut data: Vec<u8>, pos: u64, len: u64) -> Vec<u8> {
+    data[pos as usize..(pos + len) as usize].fill(0);
+    data
+}
+
+fn last_status(events: &[RecoveryEvent]) -> Option<RecoveryStatus> {
+    events.iter().rev().find_map(|e| match e {
+        RecoveryEvent::Status { status, .. } => Some(*status),
+        _ => None,
+    })
+}
+
+// Forwards events to the test thread and cancels the run at its first progress event
+struct CancellingSink {
+    tx: Mutex<Sender<RecoveryEvent>>,
+    cancel: bool,
+}
+
+impl CancellingSink {
+    fn new(cancel: bool) -> (Arc<CancellingSink>, Receiver<RecoveryEvent>) {
+        let (tx, rx) = mpsc::channel();
+        (Arc::new(CancellingSink { tx: Mutex::new(tx), cancel }), rx)
+    }
+}
+
+impl EventSink for CancellingSink {
+    fn emit(&self, event: RecoveryEvent) {
+        if self.cancel && matches!(event, RecoveryEvent::Progress { .. }) {
+            let _ = imaging::cancel_imaging();
+        }
+        let _ = self.tx.lock().unwrap().send(event);
+    }
+}
+
+fn wait_for_end(rx: &Receiver<RecoveryEvent>) -> RecoveryStatus {
+    loop {
+        match rx.recv_timeout(Duration::from_secs(30)).unwrap() {
+            RecoveryEvent::Status { status, .. } if status != RecoveryStatus::Recovering => return status,
+            _ => {}
+        }
+    }
+}
+
+// ── Tests ────────────────────────────────────────────────────────────
+
+#[test]
+fn trims_scrapes_and_retries_around_bad_sectors() {
+    let dir = scratch_dir("passes");
+    let output = dir.join("disk.img").to_string_lossy().to_string();
+    let data = disk_data(1024 * 1024);
+    // Three dead sectors, and a weak one that reads on the third try
+    let mut reader = FaultyReader::new(data.clone()).bad(300 * SECTOR, 3 * SECTOR).weak(700 * SECTOR, SECTOR, 2);
+
+    let sink = RecordingSink::new();
+    let summary = imaging::image_from(&mut reader, "disk", &output, &options(2), &sink).unwrap();
+    assert_eq!(summary.size, 1024 * 1024);
+    assert_eq!(summary.bad_bytes, 3 * SECTOR);
+    assert_eq!(summary.bad_areas, 1);
+    assert_eq!(summary.rescued, 1024 * 1024 - 3 * SECTOR);
+    assert_eq!(summary.pending, 0);
+    assert!(!summary.resumed && !summary.aborted);
+    assert_eq!(fs::read(&output).unwrap(), zeroed(data, 300 * SECTOR, 3 * SECTOR));
+
+    // The good sectors around the dead ones were read once in the copy pass, then one
+    // sector at a time only up to the first error from either side
+    let dead = 300 * SECTOR..303 * SECTOR;
+    let chunk_reads = reader.reads.iter().filter(|(_, len)| *len as u64 > SECTOR).count();
+    assert_eq!(chunk_reads, 16);
+    assert!(reader.reads.iter().filter(|(pos, _)| dead.contains(pos)).count() >= 4);
+
+    let map = fs::read_to_string(&summary.map).unwrap();
+    assert!(map.starts_with("# Mapfile"), "{}", map);
+    let blocks: Vec<&str> = map.lines().filter(|l| !l.starts_with('#')).collect();
+    assert_eq!(
+        blocks,
+        [
+            "0x00025C00     +               5",
+            "0x00000000  0x00025800  +",
+            "0x00025800  0x00000600  -",
+            "0x00025E00  0x000DA200  +",
+        ]
+    );
+
+    let events = sink.events();
+    assert!(events.iter().any(|e| matches!(e, RecoveryEvent::Progress { .. })));
+    assert!(events.iter().any(|e| matches!(e, RecoveryEvent::Warning { message } if message.contains("1 area"))));
+    assert_eq!(last_status(&events), Some(RecoveryStatus::Completed));
+}
+
+#[test]
+fn resumes_from_the_map_file() {
+    let dir = scratch_dir("resume");
+    let output = dir.join("disk.img").to_string_lossy().to_string();
+    let data = disk_data(1024 * 1024);
+    let mut reader = FaultyReader::new(data.clone()).bad(300 * SECTOR, 3 * SECTOR);
+    let summary = imaging::image_from(&mut reader, "disk", &output, &options(0), &RecordingSink::new()).unwrap();
+    assert_eq!(summary.bad_bytes, 3 * SECTOR);
+
+    // Once the sectors read again, a retry reads only them
+    let mut reader = FaultyReader::new(data.clone());
+    let summary = imaging::image_from(&mut reader, "disk", &output, &options(1), &RecordingSink::new()).unwrap();
+    assert!(summary.resumed);
+    assert_eq!((summary.rescued, summary.bad_bytes), (1024 * 1024, 0));
+    assert_eq!(reader.reads, [(300 * SECTOR, 512), (301 * SECTOR, 512), (302 * SECTOR, 512)]);
+    assert_eq!(fs::read(&output).unwrap(), data);
+
+    // A map left by an interrupted run (ddrescue writes the same format)
+    let output = dir.join("half.img").to_string_lossy().to_string();
+    fs::write(&output, vec![0u8; 1024 * 1024]).unwrap();
+    let map = "# Mapfile. Created by GNU ddrescue\n0x00080000     ?               1\n\
+               0x00000000  0x00080000  +\n0x00080000  0x00080000  ?\n";
+    fs::write(imaging::map_path(&output), map).unwrap();
+    let mut reader = FaultyReader::new(data.clone());
+    let summary = imaging::image_from(&mut reader, "disk", &output, &options(0), &RecordingSink::new()).unwrap();
+    assert!(summary.resumed);
+    assert!(reader.reads.iter().all(|(pos, _)| *pos >= 0x80000));
+    let image = fs::read(&output).unwrap();
+    assert_eq!(image[..0x80000], vec![0u8; 0x80000]);
+    assert_eq!(image[0x80000..], data[0x80000..]);
+
+    // A map for another source is refused
+    let mut reader = FaultyReader::new(disk_data(2 * 1024 * 1024));
+    let err = imaging::image_from(&mut reader, "disk", &output, &options(0), &RecordingSink::new()).unwrap_err();
+    assert!(err.contains("written for a source of 1048576 bytes"), "{}", err);
+}
+
+#[test]
+fn progress_is_reported_often_but_the_map_is_saved_on_an_interval() {
+    // Notes at each progress event whether the map was on disk yet
+    struct MapWatcher {
+        map: String,
+        seen: Mutex<Vec<bool>>,
+    }
+    impl EventSink for MapWatcher {
+        fn emit(&self, event: RecoveryEvent) {
+            if matches!(event, RecoveryEvent::Progress { .. }) {
+                self.seen.lock().unwrap().push(std::path::Path::new(&self.map).exists());
+            }
+        }
+    }
+
+    let dir = scratch_dir("interval");
+    let output = dir.join("disk.img").to_string_lossy().to_string();
+    let data = disk_data(8 * 1024 * 1024);
+    let mut reader = FaultyReader::new(data.clone());
+    let sink = MapWatcher { map: imaging::map_path(&output), seen: Mutex::new(Vec::new()) };
+    imaging::image_from(&mut reader, "disk", &output, &options(0), &sink).unwrap();
+
+    // 128 chunk reads: two progress events on the way, then the final save
+    assert_eq!(*sink.seen.lock().unwrap(), [false, false, true]);
+    assert_eq!(fs::read(&output).unwrap(), data);
+}
+
+#[test]
+fn refuses_to_overwrite_files_or_disks() {
+    let dir = scratch_dir("refuse");
+    let source = dir.join("source.img").to_string_lossy().to_string();
+    fs::write(&source, disk_data(64 * 1024)).unwrap();
+    let image = |output: &str| {
+        let mut reader = FaultyReader::new(disk_data(64 * 1024));
+        imaging::image_from(&mut reader, &source, output, &options(0), &RecordingSink::new()).unwrap_err()
+    };
+
+    let err = image(&source);
+    assert!(err.contains("never to a disk"), "{}", err);
+    let err = image("\\\\.\\PhysicalDrive1");
+    assert!(err.contains("never to a disk"), "{}", err);
+
+    // An existing image without a map is not resumed or overwritten
+    let output = dir.join("old.img");
+    fs::write(&output, b"keep").unwrap();
+    let err = image(&output.to_string_lossy());
+    assert!(err.contains("Failed to create"), "{}", err);
+    assert_eq!(fs::read(&output).unwrap(), b"keep");
+}
+
+#[test]
+fn cancels_and_resumes_on_the_worker_thread() {
+    let dir = scratch_dir("cancel");
+    let source = dir.join("source.img").to_string_lossy().to_string();
+    let output = dir.join("copy.img").to_string_lossy().to_string();
+    let data = disk_data(8 * 1024 * 1024);
+    fs::write(&source, &data).unwrap();
+
+    let (sink, rx) = CancellingSink::new(true);
+    imaging::start_imaging_with(sink, source.clone(), output.clone(), ImagingOptions::default()).unwrap();
+    assert_eq!(wait_for_end(&rx), RecoveryStatus::Aborted);
+    let map = fs::read_to_string(imaging::map_path(&output)).unwrap();
+    assert!(map.lines().any(|l| l.ends_with('?')), "{}", map);
+
+    // The worker clears its busy flag just after the last event
+    let (sink, rx) = CancellingSink::new(false);
+    let mut started = imaging::start_imaging_with(sink.clone(), source.clone(), output.clone(), ImagingOptions::default());
+    for _ in 0..100 {
+        if started.is_ok() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+        started = imaging::start_imaging_with(sink.clone(), source.clone(), output.clone(), ImagingOptions::default());
+    }
+    started.unwrap();
+    assert_eq!(wait_for_end(&rx), RecoveryStatus::Completed);
+    assert_eq!(fs::read(&output).unwrap(), data);
+}
@@ -0,0 +1,156 @@
+// Tests for the recovered file index: incremental refresh, where it is stored and paging.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/recovered_files.rs"]
+#[allow(dead_code)]
+mod recovered_files;
+
+use common::scratch_dir;
+use recovered_files::{recovered, recovery_folder, use_test_app_data, FOLDER};
+use std::fs;
+use tauri_app_lib::appdata;
+use tauri_app_lib::index::{self, PageRequest};
+use walkdir::WalkDir;
+
+fn names(page: &index::Page) -> Vec<&str> {
+    page.files.iter().map(|f| f.name.as_str()).collect()
+}
+
+#[test]
+fn refresh_keeps_ids_of_unchanged_files_and_tracks_changes() {
+    use_test_app_data();
+    let dir = scratch_dir("refresh");
+    let dest = recovery_folder(
+        &dir,
+        &[("Users/Bob/report.pdf", b"%PDF-1.7 report"), ("photo.jpg", b"\xFF\xD8\xFF\xE0 photo"), ("notes.txt", b"hi")],
+    );
+
+    let first = index::refresh_index(&dest, |_| {}).unwrap();
+    assert_eq!((first.total, first.added, first.updated, first.removed), (3, 3, 0, 0));
+    assert!(first.root.ends_with(FOLDER), "{}", first.root);
+    let before = index::all_files(&dest).unwrap();
+    let report = before.iter().find(|f| f.name == "report.pdf").unwrap();
+    assert_eq!(report.path, "\\Users\\Bob\\report.pdf");
+    assert_eq!(report.category, "Documents");
+
+    // Nothing changed: every entry is reused
+    let again = index::refresh_index(&dest, |_| {}).unwrap();
+    assert_eq!((again.added, again.updated, again.removed), (0, 0, 0));
+
+    fs::write(recovered(&dir, "notes.txt"), b"now longer").unwrap();
+    fs::remove_file(recovered(&dir, "photo.jpg")).unwrap();
+    fs::write(recovered(&dir, "song.mp3"), b"ID3 song").unwrap();
+    let mut batches = 0;
+    let changed = index::refresh_index(&dest, |batch| batches += batch.len()).unwrap();
+    assert_eq!((changed.total, changed.added, changed.updated, changed.removed), (3, 1, 1, 1));
+    assert_eq!(batches, 2);
+
+    let after = index::all_files(&dest).unwrap();
+    let id_of = |files: &[tauri_app_lib::recovery::RecoveredFile], name: &str| {
+        files.iter().find(|f| f.name == name).map(|f| f.id.clone())
+    };
+    assert_eq!(id_of(&after, "report.pdf"), id_of(&before, "report.pdf"));
+    assert_eq!(id_of(&after, "notes.txt"), id_of(&before, "notes.txt"));
+    assert!(!before.iter().any(|f| Some(&f.id) == id_of(&after, "song.mp3").as_ref()));
+}
+
+#[test]
+fn index_is_kept_in_app_data_not_among_the_evidence() {
+    use_test_app_data();
+    let dir = scratch_dir("location");
+    let dest = recovery_folder(&dir, &[("a.txt", b"a"), ("b.txt", b"bb")]);
+    index::refresh_index(&dest, |_| {}).unwrap();
+
+    let evidence: Vec<String> = WalkDir::new(&dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(evidence.len(), 2, "{:?}", evidence);
+
+    let root = index::resolve_recovery_dir(&dest).unwrap();
+    let stored = WalkDir::new(appdata::data_dir().join("indexes"))
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| fs::read_to_string(e.path()).ok())
+        .any(|text| text.contains(&serde_json::to_string(&root.to_string_lossy()).unwrap()));
+    assert!(stored);
+}
+
+#[test]
+fn pages_are_filtered_sorted_and_sliced() {
+    use_test_app_data();
+    let dir = scratch_dir("page");
+    let dest = recovery_folder(
+        &dir,
+        &[
+            ("big.pdf", &[1u8; 3000]),
+            ("Small.PDF", &[1u8; 10]),
+            ("medium.docx", &[1u8; 500]),
+            ("clip.mp4", &[1u8; 2000]),
+        ],
+    );
+    index::refresh_index(&dest, |_| {}).unwrap();
+
+    let request = PageRequest {
+        sort_key: Some("size".to_string()),
+        descending: true,
+        categories: vec!["Documents".to_string()],
+        ..PageRequest::default()
+    };
+    let page = index::query_page(&dest, &request).unwrap();
+    assert_eq!(page.total, 3);
+    assert_eq!(names(&page), vec!["big.pdf", "medium.docx", "Small.PDF"]);
+
+    let sliced = index::query_page(&dest, &PageRequest { offset: 1, limit: 1, ..request.clone() }).unwrap();
+    assert_eq!((sliced.total, sliced.offset), (3, 1));
+    assert_eq!(names(&sliced), vec!["medium.docx"]);
+
+    let request = PageRequest {
+        sort_key: Some("name".to_string()),
+        name: Some("PDF".to_string()),
+        min_size: Some(5),
+        max_size: Some(2999),
+        ..PageRequest::default()
+    };
+    assert_eq!(names(&index::query_page(&dest, &request).unwrap()), vec!["Small.PDF"]);
+
+    // As the frontend sends it: no offset or limit means the whole list
+    let request: PageRequest = serde_json::from_str(r#"{ "sortKey": "category", "descending": true }"#).unwrap();
+    let page = index::query_page(&dest, &request).unwrap();
+    assert_eq!((page.total, page.offset), (4, 0));
+    assert_eq!(names(&page), vec!["clip.mp4", "Small.PDF", "medium.docx", "big.pdf"]);
+}
+
+#[test]
+fn hashing_and_refreshing_at_once_keep_each_others_changes() {
+    use_test_app_data();
+    let dir = scratch_dir("concurrent");
+    let files: Vec<(String, Vec<u8>)> = (0..200).map(|i| (format!("f{:03}.bin", i), vec![i as u8; 2000])).collect();
+    let listed: Vec<(&str, &[u8])> = files.iter().map(|(name, data)| (name.as_str(), data.as_slice())).collect();
+    let dest = recovery_folder(&dir, &listed);
+    index::refresh_index(&dest, |_| {}).unwrap();
+
+    let hashing = {
+        let dest = dest.clone();
+        std::thread::spawn(move || index::ensure_hashes(&dest).unwrap())
+    };
+    for i in 0..20 {
+        fs::write(recovered(&dir, &format!("new{}.txt", i)), b"new").unwrap();
+        index::refresh_index(&dest, |_| {}).unwrap();
+    }
+    hashing.join().unwrap();
+
+    let after = index::all_files(&dest).unwrap();
+    assert_eq!(after.len(), 220);
+    assert!(after.iter().filter(|f| f.name.starts_with('f')).all(|f| f.hashes.is_some()));
+    let tmp_left = WalkDir::new(appdata::data_dir().join("indexes"))
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .any(|e| e.file_name().to_string_lossy().ends_with(".tmp"));
+    assert!(!tmp_left);
+}
@@ -0,0 +1,256 @@
+// Tests for the read-only NTFS deleted-file lister, run against images from tests/support.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/ntfs_image.rs"]
+mod ntfs_image;
+
+use common::{find, pattern, run_tool, save, scratch_dir, text};
+use ntfs_image::{entry, NtfsImage, CLUSTER_SIZE, MFT_LCN, TIMESTAMP};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use tauri_app_lib::ntfs::{self, DataRun, DataState};
+
+// ── Helpers ──────────────────────────────────────────────────────────
+
+// Users\Bob with deleted files in every data state, a deleted folder, an orphan,
+// a fragmented file, a sparse file and a torn record
+fn sample() -> NtfsImage {
+    let mut image = NtfsImage::new(64);
+    image.add(entry(16, 5, "Users").directory());
+    image.add(entry(17, 16, "Bob").directory());
+    image.add(entry(18, 17, "report.txt").deleted().runs(&[(Some(20), 2)], &pattern(5000, 1)));
+    image.add(entry(19, 17, "note.txt").deleted().resident(b"hello ntfs"));
+    image.add(entry(20, 5, "old.log").deleted().runs(&[(Some(24), 1)], &pattern(4096, 2)));
+    image.add(entry(21, 5, "new.log").runs(&[(Some(24), 1)], &pattern(4096, 3)));
+    image.add(entry(22, 5, "half.bin").deleted().runs(&[(Some(26), 2)], &pattern(8192, 4)));
+    image.add(entry(23, 5, "other.bin").runs(&[(Some(27), 1)], &pattern(4096, 5)));
+    image.add(entry(24, 5, "Project").directory().deleted());
+    image.add(entry(25, 24, "plan.docx").deleted().runs(&[(Some(30), 1)], &pattern(3000, 6)));
+    image.add(entry(26, 17, "lost.jpg").deleted().parent_sequence(5).runs(&[(Some(31), 1)], &pattern(100, 7)));
+    image.add(entry(27, 5, "frag.bin").deleted().runs(&[(Some(40), 1), (Some(34), 1)], &pattern(8000, 8)));
+    image.add(entry(28, 5, "torn.txt").deleted().torn().resident(b"torn"));
+    image.add(entry(29, 5, "sparse.bin").deleted().runs(&[(None, 1), (Some(36), 1)], &pattern(8000, 9)));
+    image.add(entry(30, 5, "keep.txt").resident(b"still here"));
+    image
+}
+
+fn sample_image(dir: &Path) -> String {
+    let path = dir.join("volume.img");
+    fs::write(&path, sample().build()).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+// ntfs-3g cannot delete without mounting, so `name` is deleted by hand: its MFT record
+// is found by its name (skipping folders, whose index also holds it) and the in-use
+// flag cleared. Its clusters stay allocated in $Bitmap.
+fn mark_deleted(image: &str, name: &str) {
+    let mut bytes = fs::read(image).unwrap();
+    let utf16: Vec<u8> = name.encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+    let record = (0..bytes.len() / 1024)
+        .map(|i| i * 1024)
+        .find(|&at| {
+            let raw = &bytes[at..at + 1024];
+            &raw[0..4] == b"FILE" && raw[0x16] == 1 && raw.windows(utf16.len()).any(|w| w == utf16)
+        })
+        .unwrap_or_else(|| panic!("no MFT record for {}", name));
+    bytes[record + 0x16] &= !1;
+    fs::write(image, bytes).unwrap();
+}
+
+// ── Tests ────────────────────────────────────────────────────────────
+
+#[test]
+fn lists_deleted_files_with_paths_and_data_states() {
+    let dir = scratch_dir("list");
+    let listing = ntfs::list_deleted_files(&sample_image(&dir)).unwrap();
+
+    assert_eq!(listing.cluster_size, 4096);
+    assert_eq!(listing.record_size, 1024);
+    assert_eq!(listing.total_records, 32);
+    assert_eq!(listing.corrupt_records, 1);
+    assert_eq!(listing.unread_records, 0);
+
+    let paths: Vec<&str> = listing.files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "\\$OrphanFiles\\lost.jpg",
+            "\\frag.bin",
+            "\\half.bin",
+            "\\old.log",
+            "\\Project\\plan.docx",
+            "\\sparse.bin",
+            "\\Users\\Bob\\note.txt",
+            "\\Users\\Bob\\report.txt",
+        ]
+    );
+
+    let report = find(&listing.files, "\\Users\\Bob\\report.txt");
+    assert_eq!(report.record, 18);
+    assert_eq!(report.name, "report.txt");
+    assert_eq!(report.size, 5000);
+    assert_eq!(report.data_state, DataState::Intact);
+    assert_eq!(report.runs, vec![DataRun { lcn: Some(20), clusters: 2 }]);
+    assert_eq!(report.created, Some(TIMESTAMP));
+    assert_eq!(report.modified, Some(TIMESTAMP));
+
+    assert_eq!(find(&listing.files, "\\Users\\Bob\\note.txt").data_state, DataState::Resident);
+    assert_eq!(find(&listing.files, "\\old.log").data_state, DataState::Overwritten);
+    assert_eq!(find(&listing.files, "\\half.bin").data_state, DataState::Partial);
+    assert_eq!(find(&listing.files, "\\Project\\plan.docx").data_state, DataState::Intact);
+    assert_eq!(find(&listing.files, "\\sparse.bin").data_state, DataState::Intact);
+    assert_eq!(
+        find(&listing.files, "\\frag.bin").runs,
+        vec![DataRun { lcn: Some(40), clusters: 1 }, DataRun { lcn: Some(34), clusters: 1 }]
+    );
+}
+
+#[test]
+fn recovers_selected_files_byte_for_byte() {
+    let dir = scratch_dir("recover");
+    let source = sample_image(&dir);
+    let target = dir.join("out");
+    let before = fs::read(&source).unwrap();
+
+    let mut progress = Vec::new();
+    let summary = ntfs::recover_files(&source, &[18, 19, 27, 29, 25], &target.to_string_lossy(), |p| {
+        progress.push((p.files_done, p.files_total))
+    })
+    .unwrap();
+    assert!(summary.failed.is_empty(), "{:?}", summary.failed);
+    assert_eq!(summary.recovered.len(), 5);
+    assert_eq!(progress, [(1, 5), (2, 5), (3, 5), (4, 5), (5, 5)]);
+
+    let mut sparse = vec![0u8; 4096];
+    sparse.extend_from_slice(&pattern(8000, 9)[4096..]);
+    for (path, expected) in [
+        ("Users/Bob/report.txt", pattern(5000, 1)),
+        ("Users/Bob/note.txt", b"hello ntfs".to_vec()),
+        ("frag.bin", pattern(8000, 8)),
+        ("sparse.bin", sparse),
+        ("Project/plan.docx", pattern(3000, 6)),
+    ] {
+        let written = fs::read(target.join(path)).unwrap_or_else(|e| panic!("{}: {}", path, e));
+        assert_eq!(written, expected, "{}", path);
+        let native_path = path.replace('/', std::path::MAIN_SEPARATOR_STR);
+        let recovered = summary.recovered.iter().find(|r| r.target.ends_with(&native_path)).unwrap();
+        assert_eq!(recovered.sha256, format!("{:x}", Sha256::digest(&expected)));
+    }
+
+    // Listing and recovery never modify the source
+    assert_eq!(fs::read(&source).unwrap(), before);
+}
+
+#[test]
+fn follows_the_extents_of_a_fragmented_mft() {
+    let dir = scratch_dir("fragmented-mft");
+    // Records 16-31 move to clusters 48-51; record 15 holds their run
+    let mut image = sample();
+    image.fragment_mft(15, 48);
+    let source = save(&dir, "fragmented.img", &image.build());
+
+    let listing = ntfs::list_deleted_files(&source).unwrap();
+    let whole = ntfs::list_deleted_files(&sample_image(&dir)).unwrap();
+    assert_eq!(listing.total_records, 32);
+    assert_eq!(listing.unread_records, 0);
+    let paths = |l: &ntfs::NtfsListing| l.files.iter().map(|f| f.path.clone()).collect::<Vec<_>>();
+    assert_eq!(paths(&listing), paths(&whole));
+
+    let target = dir.join("out");
+    let summary = ntfs::recover_files(&source, &[18], &target.to_string_lossy(), |_| {}).unwrap();
+    assert!(summary.failed.is_empty(), "{:?}", summary.failed);
+    assert_eq!(fs::read(target.join("Users/Bob/report.txt")).unwrap(), pattern(5000, 1));
+
+    // Without record 15 the second extent cannot be found: its records are counted
+    // as unread and the first extent is still listed
+    let mut bytes = fs::read(&source).unwrap();
+    let at = (MFT_LCN * CLUSTER_SIZE) as usize + 15 * 1024;
+    bytes[at + 1023] ^= 0xFF;
+    fs::write(&source, bytes).unwrap();
+    let listing = ntfs::list_deleted_files(&source).unwrap();
+    assert_eq!(listing.total_records, 32);
+    assert_eq!(listing.unread_records, 16);
+    assert!(listing.files.is_empty());
+}
+
+#[test]
+fn recovers_files_when_the_bitmap_is_unreadable() {
+    let dir = scratch_dir("no-bitmap");
+    let source = sample_image(&dir);
+    // Tear the $Bitmap record (record 6) so the data states cannot be worked out
+    let mut image = fs::read(&source).unwrap();
+    let at = (MFT_LCN * CLUSTER_SIZE) as usize + 6 * 1024;
+    image[at + 1023] ^= 0xFF;
+    fs::write(&source, image).unwrap();
+
+    let listing = ntfs::list_deleted_files(&source).unwrap();
+    assert_eq!(find(&listing.files, "\\Users\\Bob\\report.txt").data_state, DataState::Unknown);
+    let target = dir.join("out");
+    let summary = ntfs::recover_files(&source, &[18, 27], &target.to_string_lossy(), |_| {}).unwrap();
+    assert!(summary.failed.is_empty(), "{:?}", summary.failed);
+    assert_eq!(fs::read(target.join("Users/Bob/report.txt")).unwrap(), pattern(5000, 1));
+    assert_eq!(fs::read(target.join("frag.bin")).unwrap(), pattern(8000, 8));
+}
+
+#[test]
+fn reports_records_that_are_not_deleted_files() {
+    let dir = scratch_dir("live");
+    let source = sample_image(&dir);
+    let target = dir.join("out");
+
+    let mut done = 0;
+    let summary = ntfs::recover_files(&source, &[30, 24, 99], &target.to_string_lossy(), |p| done = p.files_done).unwrap();
+    assert!(summary.recovered.is_empty());
+    let ids: Vec<&str> = summary.failed.iter().map(|f| f.id.as_str()).collect();
+    assert_eq!(ids, ["mft-30", "mft-24", "mft-99"]);
+    assert_eq!(done, 3);
+}
+
+#[test]
+fn rejects_volumes_that_are_not_ntfs() {
+    let dir = scratch_dir("reject");
+    let path = dir.join("zeros.img");
+    fs::write(&path, vec![0u8; 64 * 1024]).unwrap();
+
+    let err = ntfs::list_deleted_files(&path.to_string_lossy()).unwrap_err();
+    assert!(err.contains("Not an NTFS volume"), "{}", err);
+    assert!(ntfs::list_deleted_files(&dir.join("missing.img").to_string_lossy()).is_err());
+}
+
+#[test]
+fn recovers_files_from_a_mkntfs_volume() {
+    let dir = scratch_dir("mkntfs");
+    let image = dir.join("volume.img");
+    fs::File::create(&image).unwrap().set_len(16 * 1024 * 1024).unwrap();
+    let image = text(&image);
+    if !run_tool("mkntfs", &["-F", "-f", "-q", "-L", "DATA", &image]) {
+        return;
+    }
+    let report = save(&dir, "report.bin", &pattern(50_000, 1));
+    let note = save(&dir, "note.txt", b"hello ntfs");
+    for (local, name) in [(&report, "/report.bin"), (&note, "/note.txt")] {
+        if !run_tool("ntfscp", &["-f", &image, local, name]) {
+            return;
+        }
+    }
+    mark_deleted(&image, "report.bin");
+    mark_deleted(&image, "note.txt");
+
+    let listing = ntfs::list_deleted_files(&image).unwrap();
+    let paths: Vec<&str> = listing.files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, ["\\note.txt", "\\report.bin"]);
+    let report = find(&listing.files, "\\report.bin");
+    assert_eq!(report.size, 50_000);
+    assert_eq!(report.data_state, DataState::Overwritten);
+    assert_eq!(find(&listing.files, "\\note.txt").data_state, DataState::Resident);
+
+    let records: Vec<u64> = listing.files.iter().map(|f| f.record).collect();
+    let target = dir.join("out");
+    let summary = ntfs::recover_files(&image, &records, &text(&target), |_| {}).unwrap();
+    assert!(summary.failed.is_empty(), "{:?}", summary.failed);
+    assert_eq!(fs::read(target.join("report.bin")).unwrap(), pattern(50_000, 1));
+    assert_eq!(fs::read(target.join("note.txt")).unwrap(), b"hello ntfs");
+}
@@ -0,0 +1,170 @@
+// Tests for the lost partition search and repaired table writer, run against disk
+// images assembled from the volume builders in tests/support.
+
+// The image builders are shared with other tests; only part of each is used here
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/disk_image.rs"]
+#[allow(dead_code)]
+mod disk_image;
+#[path = "support/exfat_image.rs"]
+#[allow(dead_code)]
+mod exfat_image;
+#[path = "support/fat_image.rs"]
+#[allow(dead_code)]
+mod fat_image;
+#[path = "support/ntfs_image.rs"]
+#[allow(dead_code)]
+mod ntfs_image;
+
+use common::{save, scratch_dir};
+use disk_image::{DiskImage, MIB, SECTOR_SIZE};
+use exfat_image::ExfatImage;
+use fat_image::{FatImage, FatKind};
+use ntfs_image::NtfsImage;
+use std::fs;
+use std::path::Path;
+use tauri_app_lib::ntfs;
+use tauri_app_lib::partition_search::{self, Candidate, TableEntry};
+use tauri_app_lib::partitions::{self, Filesystem, PartitionScheme};
+
+// ── Helpers ──────────────────────────────────────────────────────────
+
+fn without_boot_sector(mut volume: Vec<u8>) -> Vec<u8> {
+    volume[..SECTOR_SIZE as usize].fill(0);
+    volume
+}
+
+// A 16 MiB disk whose partition table was wiped: NTFS at 1 MiB, exFAT at 4 MiB
+fn wiped_disk() -> DiskImage {
+    let mut disk = DiskImage::new(16 * MIB);
+    disk.put(MIB, &NtfsImage::new(512).build());
+    disk.put(4 * MIB, &ExfatImage::new().build());
+    disk
+}
+
+fn proposed(candidates: &[Candidate]) -> Vec<TableEntry> {
+    candidates
+        .iter()
+        .filter(|c| c.proposed)
+        .map(|c| TableEntry { offset: c.offset, size: c.size, filesystem: c.filesystem })
+        .collect()
+}
+
+// ── Tests ────────────────────────────────────────────────────────────
+
+#[test]
+fn finds_volumes_from_primary_and_backup_boot_sectors() {
+    let dir = scratch_dir("search");
+    let mut disk = DiskImage::new(48 * MIB);
+    disk.put(MIB, &NtfsImage::new(512).build());
+    // Only the backup boot sectors of these two survived
+    disk.put(4 * MIB, &without_boot_sector(ExfatImage::new().build()));
+    disk.put(13 * MIB, &without_boot_sector(FatImage::new(FatKind::Fat32).build()));
+    let source = save(&dir, "disk.img", &disk.build());
+
+    let mut updates = Vec::new();
+    let search = partition_search::search_partitions(&source, |p| updates.push(p.clone())).unwrap();
+    assert_eq!(search.current_scheme, PartitionScheme::None);
+    assert_eq!(search.suggested_scheme, PartitionScheme::Mbr);
+    assert_eq!(search.unreadable_bytes, 0);
+    let last = updates.last().unwrap();
+    assert_eq!(last.bytes_done, 48 * MIB);
+    assert_eq!(last.boot_sectors, 4);
+
+    let found: Vec<(u64, Filesystem, bool, bool, bool)> = search
+        .candidates
+        .iter()
+        .map(|c| (c.offset / MIB, c.filesystem, c.primary_boot, c.backup_boot, c.proposed))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (1, Filesystem::Ntfs, true, true, true),
+            (4, Filesystem::Exfat, false, true, true),
+            (13, Filesystem::Fat32, false, true, true),
+        ]
+    );
+    assert!(search.candidates.iter().all(|c| c.verified && !c.in_table));
+    assert_eq!(search.candidates[2].size, 34 * MIB);
+
+    let ntfs_volume = &search.candidates[0];
+    assert!(ntfs::list_deleted_files(&ntfs_volume.source).is_ok());
+}
+
+#[test]
+fn writes_repaired_tables_to_a_separate_file() {
+    let dir = scratch_dir("write");
+    let disk = wiped_disk();
+    let source = save(&dir, "disk.img", &disk.build());
+    let original = fs::read(&source).unwrap();
+    let search = partition_search::search_partitions(&source, |_| {}).unwrap();
+    let entries = proposed(&search.candidates);
+    assert_eq!(entries.len(), 2);
+
+    // MBR: one sector, to be copied over sector 0
+    let output = dir.join("mbr.bin").to_string_lossy().to_string();
+    let file = partition_search::write_partition_table(&source, &entries, PartitionScheme::Mbr, &output).unwrap();
+    assert_eq!(file.bytes, SECTOR_SIZE);
+    let mut repaired = disk.build();
+    repaired[..SECTOR_SIZE as usize].copy_from_slice(&fs::read(&output).unwrap());
+    let layout = partitions::list_partitions(&save(&dir, "mbr.img", &repaired), false).unwrap();
+    assert_eq!(layout.scheme, PartitionScheme::Mbr);
+    let types: Vec<(u64, &str, Option<Filesystem>)> =
+        layout.partitions.iter().map(|p| (p.offset / MIB, p.type_id.as_str(), p.filesystem)).collect();
+    assert_eq!(types, [(1, "0x07", Some(Filesystem::Ntfs)), (4, "0x07", Some(Filesystem::Exfat))]);
+
+    // GPT: protective MBR, header and entry array
+    let output = dir.join("gpt.bin").to_string_lossy().to_string();
+    let file = partition_search::write_partition_table(&source, &entries, PartitionScheme::Gpt, &output).unwrap();
+    assert_eq!(file.bytes, 34 * SECTOR_SIZE);
+    let mut repaired = disk.build();
+    repaired[..file.bytes as usize].copy_from_slice(&fs::read(&output).unwrap());
+    let layout = partitions::list_partitions(&save(&dir, "gpt.img", &repaired), false).unwrap();
+    assert_eq!(layout.scheme, PartitionScheme::Gpt);
+    assert!(layout.warnings.is_empty(), "{:?}", layout.warnings);
+    let names: Vec<(u64, Option<&str>, Option<Filesystem>)> =
+        layout.partitions.iter().map(|p| (p.offset / MIB, p.name.as_deref(), p.filesystem)).collect();
+    assert_eq!(
+        names,
+        [(1, Some("Recovered NTFS"), Some(Filesystem::Ntfs)), (4, Some("Recovered exFAT"), Some(Filesystem::Exfat))]
+    );
+
+    // The source itself is never touched
+    assert_eq!(fs::read(&source).unwrap(), original);
+}
+
+#[test]
+fn refuses_to_overwrite_the_source_or_write_invalid_tables() {
+    let dir = scratch_dir("refuse");
+    let source = save(&dir, "disk.img", &wiped_disk().build());
+    let original = fs::read(&source).unwrap();
+    let entry = |offset: u64, size: u64| TableEntry { offset, size, filesystem: Filesystem::Ntfs };
+    let output = dir.join("table.bin").to_string_lossy().to_string();
+    let write = |entries: &[TableEntry], scheme: PartitionScheme, output: &str| {
+        partition_search::write_partition_table(&source, entries, scheme, output).unwrap_err()
+    };
+
+    let err = write(&[entry(MIB, MIB)], PartitionScheme::Mbr, &source);
+    assert!(err.contains("separate file"), "{}", err);
+    let err = write(&[entry(MIB, MIB)], PartitionScheme::Mbr, "\\\\.\\PhysicalDrive0");
+    assert!(err.contains("separate file"), "{}", err);
+    let err = write(&[entry(MIB, 2 * MIB), entry(2 * MIB, MIB)], PartitionScheme::Mbr, &output);
+    assert!(err.contains("overlap"), "{}", err);
+    let err = write(&[entry(MIB + 100, MIB)], PartitionScheme::Mbr, &output);
+    assert!(err.contains("aligned"), "{}", err);
+    let err = write(&[entry(8 * MIB, 16 * MIB)], PartitionScheme::Mbr, &output);
+    assert!(err.contains("past the end"), "{}", err);
+    let five: Vec<TableEntry> = (1..=5).map(|i| entry(i * MIB, MIB)).collect();
+    let err = write(&five, PartitionScheme::Mbr, &output);
+    assert!(err.contains("at most 4"), "{}", err);
+    assert!(!Path::new(&output).exists());
+
+    // An existing file is never overwritten
+    fs::write(&output, b"keep").unwrap();
+    let err = write(&[entry(MIB, MIB)], PartitionScheme::Mbr, &output);
+    assert!(err.contains("Failed to create"), "{}", err);
+    assert_eq!(fs::read(&output).unwrap(), b"keep");
+    assert_eq!(fs::read(&source).unwrap(), original);
+}
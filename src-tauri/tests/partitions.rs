@@ -0,0 +1,170 @@
+// Tests for the MBR/GPT partition parser and lost partition detection, run against
+// disk images assembled from the volume builders in tests/support.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/disk_image.rs"]
+mod disk_image;
+// The volume builders are shared with their own tests; only part of each is used here
+#[path = "support/exfat_image.rs"]
+#[allow(dead_code)]
+mod exfat_image;
+#[path = "support/fat_image.rs"]
+#[allow(dead_code)]
+mod fat_image;
+#[path = "support/ntfs_image.rs"]
+#[allow(dead_code)]
+mod ntfs_image;
+
+use common::{save, scratch_dir};
+use disk_image::{DiskImage, GptEntry, BASIC_DATA, EFI_SYSTEM, MIB, SECTOR_SIZE};
+use exfat_image::ExfatImage;
+use fat_image::{FatImage, FatKind};
+use ntfs_image::NtfsImage;
+use std::fs;
+use tauri_app_lib::exfat;
+use tauri_app_lib::partitions::{self, Filesystem, Partition, PartitionOrigin, PartitionScheme};
+
+// ── Helpers ──────────────────────────────────────────────────────────
+
+// exFAT volume (8 MiB) holding one deleted file
+fn camera_volume() -> Vec<u8> {
+    let mut image = ExfatImage::new();
+    let clip = image.write_file(exfat_image::Dir::Root, "clip.mp4", &[7u8; 6000]);
+    image.delete(&clip);
+    image.build()
+}
+
+fn summary(partitions: &[Partition]) -> Vec<(PartitionOrigin, u64, Option<Filesystem>)> {
+    partitions.iter().map(|p| (p.origin, p.offset / MIB, p.filesystem)).collect()
+}
+
+// ── Tests ────────────────────────────────────────────────────────────
+
+#[test]
+fn reads_primary_and_logical_mbr_partitions() {
+    let dir = scratch_dir("mbr");
+    let mut disk = DiskImage::new(16 * MIB);
+    let mib = MIB / SECTOR_SIZE;
+    // FAT12 at 1 MiB; extended partition from 2 MiB holding exFAT at 3 MiB and NTFS at 12 MiB
+    disk.partition_sector(0, &[(0x01, mib, mib), (0x0F, 2 * mib, 14 * mib)]);
+    disk.partition_sector(2 * mib, &[(0x07, mib, 8 * mib), (0x05, 9 * mib, 3 * mib)]);
+    disk.partition_sector(11 * mib, &[(0x07, mib, 2 * mib)]);
+    disk.put(MIB, &FatImage::new(FatKind::Fat12).build());
+    disk.put(3 * MIB, &camera_volume());
+    disk.put(12 * MIB, &NtfsImage::new(512).build());
+    let source = save(&dir, "disk.img", &disk.build());
+
+    let layout = partitions::list_partitions(&source, false).unwrap();
+    assert_eq!(layout.scheme, PartitionScheme::Mbr);
+    assert_eq!(layout.size, Some(16 * MIB));
+    assert!(layout.warnings.is_empty(), "{:?}", layout.warnings);
+    assert_eq!(
+        summary(&layout.partitions),
+        [
+            (PartitionOrigin::Mbr, 1, Some(Filesystem::Fat12)),
+            (PartitionOrigin::MbrLogical, 3, Some(Filesystem::Exfat)),
+            (PartitionOrigin::MbrLogical, 12, Some(Filesystem::Ntfs)),
+        ]
+    );
+    let camera = &layout.partitions[1];
+    assert_eq!(camera.size, 8 * MIB);
+    assert_eq!(camera.volume_size, Some(8 * MIB));
+    assert_eq!(camera.type_id, "0x07");
+    assert_eq!(layout.partitions[2].volume_size, Some(2 * MIB));
+
+    // The partition source goes straight to the native scanners
+    let listing = exfat::list_deleted_files(&camera.source).unwrap();
+    assert_eq!(listing.files.len(), 1);
+    let target = dir.join("out");
+    let ids = [listing.files[0].id.clone()];
+    let recovered = exfat::recover_files(&camera.source, &ids, &target.to_string_lossy(), |_| {}).unwrap();
+    assert!(recovered.failed.is_empty(), "{:?}", recovered.failed);
+    assert_eq!(fs::read(target.join("clip.mp4")).unwrap(), vec![7u8; 6000]);
+}
+
+#[test]
+fn reads_gpt_and_falls_back_to_the_backup_header() {
+    let dir = scratch_dir("gpt");
+    let mut disk = DiskImage::new(16 * MIB);
+    let mib = MIB / SECTOR_SIZE;
+    disk.gpt(
+        "6B1F3E2A-1111-4C4C-8E8E-0123456789AB",
+        &[
+            GptEntry { type_guid: EFI_SYSTEM, first: mib, last: 2 * mib - 1, name: "EFI system partition" },
+            GptEntry { type_guid: BASIC_DATA, first: 2 * mib, last: 10 * mib - 1, name: "Photos" },
+        ],
+    );
+    disk.put(MIB, &FatImage::new(FatKind::Fat12).build());
+    disk.put(2 * MIB, &camera_volume());
+
+    let layout = partitions::list_partitions(&save(&dir, "disk.img", &disk.build()), false).unwrap();
+    assert_eq!(layout.scheme, PartitionScheme::Gpt);
+    assert_eq!(layout.disk_id.as_deref(), Some("6B1F3E2A-1111-4C4C-8E8E-0123456789AB"));
+    assert!(layout.warnings.is_empty(), "{:?}", layout.warnings);
+    let names: Vec<(&str, &str, Option<Filesystem>)> =
+        layout.partitions.iter().map(|p| (p.type_name.as_str(), p.name.as_deref().unwrap(), p.filesystem)).collect();
+    assert_eq!(
+        names,
+        [
+            ("EFI system", "EFI system partition", Some(Filesystem::Fat12)),
+            ("Microsoft basic data", "Photos", Some(Filesystem::Exfat)),
+        ]
+    );
+    assert_eq!(layout.partitions[1].type_id, BASIC_DATA);
+    assert_eq!(layout.partitions[1].size, 8 * MIB);
+
+    // A damaged primary header is replaced by the copy in the last sector
+    disk.corrupt(SECTOR_SIZE + 40);
+    let layout = partitions::list_partitions(&save(&dir, "disk.img", &disk.build()), false).unwrap();
+    assert_eq!(layout.partitions.len(), 2);
+    assert!(layout.warnings.iter().any(|w| w.contains("checksum")), "{:?}", layout.warnings);
+    assert!(layout.warnings.iter().any(|w| w.contains("backup copy")), "{:?}", layout.warnings);
+}
+
+#[test]
+fn finds_lost_partitions_outside_the_table() {
+    let dir = scratch_dir("lost");
+    let mut disk = DiskImage::new(16 * MIB);
+    let mib = MIB / SECTOR_SIZE;
+    // The exFAT partition at 4 MiB was deleted from the table
+    disk.partition_sector(0, &[(0x01, mib, mib)]);
+    disk.put(MIB, &FatImage::new(FatKind::Fat12).build());
+    disk.put(4 * MIB, &camera_volume());
+    let source = save(&dir, "disk.img", &disk.build());
+
+    let layout = partitions::list_partitions(&source, false).unwrap();
+    assert_eq!(layout.partitions.len(), 1);
+
+    let layout = partitions::list_partitions(&source, true).unwrap();
+    assert_eq!(
+        summary(&layout.partitions),
+        [(PartitionOrigin::Mbr, 1, Some(Filesystem::Fat12)), (PartitionOrigin::Lost, 4, Some(Filesystem::Exfat))]
+    );
+    let lost = &layout.partitions[1];
+    assert_eq!(lost.size, 8 * MIB);
+    assert_eq!(exfat::list_deleted_files(&lost.source).unwrap().files.len(), 1);
+
+    // A wiped table on an XP-era disk: the first partition started at sector 63
+    let mut disk = DiskImage::new(4 * MIB);
+    disk.put(63 * SECTOR_SIZE, &FatImage::new(FatKind::Fat12).build());
+    let layout = partitions::list_partitions(&save(&dir, "disk.img", &disk.build()), true).unwrap();
+    assert_eq!(layout.scheme, PartitionScheme::None);
+    assert!(layout.warnings.iter().any(|w| w.contains("no partition table")), "{:?}", layout.warnings);
+    assert_eq!(layout.partitions.len(), 1);
+    assert_eq!(layout.partitions[0].offset, 63 * SECTOR_SIZE);
+    assert_eq!(layout.partitions[0].filesystem, Some(Filesystem::Fat12));
+}
+
+#[test]
+fn treats_an_unpartitioned_volume_as_one_partition() {
+    let dir = scratch_dir("whole");
+    let path = dir.join("stick.img");
+    fs::write(&path, FatImage::new(FatKind::Fat16).build()).unwrap();
+
+    let layout = partitions::list_partitions(&path.to_string_lossy(), true).unwrap();
+    assert_eq!(layout.scheme, PartitionScheme::None);
+    assert_eq!(summary(&layout.partitions), [(PartitionOrigin::WholeDisk, 0, Some(Filesystem::Fat16))]);
+    assert_eq!(layout.partitions[0].size, 16 * MIB);
+}
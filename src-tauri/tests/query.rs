@@ -0,0 +1,89 @@
+// Tests for the query expressions the results view sends to query_recovered_files.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/recovered_files.rs"]
+#[allow(dead_code)]
+mod recovered_files;
+
+use common::{scratch_dir, ScratchDir};
+use recovered_files::{recovery_folder, use_test_app_data};
+use serde_json::json;
+use tauri_app_lib::index::{self, PageRequest};
+use tauri_app_lib::query::{self, FileQuery};
+
+fn search(dest: &str, query: serde_json::Value) -> Vec<String> {
+    let query: FileQuery = serde_json::from_value(query).unwrap();
+    let request = PageRequest { sort_key: Some("name".to_string()), ..PageRequest::default() };
+    let page = query::query_recovered_files(dest, &query, &request).unwrap();
+    page.files.into_iter().map(|f| f.name).collect()
+}
+
+// The folder is removed when the returned guard drops
+fn sample(name: &str) -> (ScratchDir, String) {
+    use_test_app_data();
+    let dir = scratch_dir(name);
+    let dest = recovery_folder(
+        &dir,
+        &[
+            ("Users/Alice/Documents/plan.docx", &[b'P', b'K', 3, 4, 1, 1, 1, 1]),
+            ("Users/Alice/Documents/big.docx", &[b'P', b'K', 3, 4].repeat(30_000)),
+            ("Users/Alice/Documents Old/old.docx", &[b'P', b'K', 3, 4].repeat(30_000)),
+            ("Users/Bob/big.docx", &[b'P', b'K', 3, 4].repeat(30_000)),
+            ("Users/Bob/photo.jpg", b"not a jpeg at all"),
+            ("Users/Bob/blank.pdf", &[0u8; 600]),
+        ],
+    );
+    index::refresh_index(&dest, |_| {}).unwrap();
+    (dir, dest)
+}
+
+#[test]
+fn combined_terms_find_large_documents_under_a_folder() {
+    let (_dir, dest) = sample("combined");
+    let found = search(
+        &dest,
+        json!({ "op": "and", "terms": [
+            { "op": "extension", "values": [".DOCX"] },
+            { "op": "size", "min": 102400 },
+            { "op": "pathPrefix", "prefix": "C:\\Users\\Alice\\Documents\\" },
+        ] }),
+    );
+    // "Documents Old" shares the prefix text but is a different folder
+    assert_eq!(found, vec!["big.docx"]);
+
+    let found = search(
+        &dest,
+        json!({ "op": "or", "terms": [
+            { "op": "category", "values": ["images"] },
+            { "op": "not", "term": { "op": "pathPrefix", "prefix": "/Users/" } },
+        ] }),
+    );
+    assert_eq!(found, vec!["photo.jpg"]);
+}
+
+#[test]
+fn names_match_globs_and_regexes_ignoring_case() {
+    let (_dir, dest) = sample("names");
+    assert_eq!(search(&dest, json!({ "op": "name", "pattern": "B*.DOCX" })), vec!["big.docx", "big.docx"]);
+    assert_eq!(search(&dest, json!({ "op": "name", "pattern": "pla?.docx" })), vec!["plan.docx"]);
+    assert_eq!(search(&dest, json!({ "op": "name", "pattern": "^(old|blank)\\.", "regex": true })), vec![
+        "blank.pdf", "old.docx"
+    ]);
+
+    let query: FileQuery = serde_json::from_value(json!({ "op": "name", "pattern": "(", "regex": true })).unwrap();
+    let err = query::query_recovered_files(&dest, &query, &PageRequest::default()).unwrap_err();
+    assert!(err.contains("Invalid name pattern"), "{}", err);
+}
+
+#[test]
+fn integrity_and_modified_terms_use_the_indexed_values() {
+    let (_dir, dest) = sample("integrity");
+    assert_eq!(search(&dest, json!({ "op": "integrity", "values": ["mismatch"] })), vec!["photo.jpg"]);
+    assert_eq!(search(&dest, json!({ "op": "integrity", "values": ["zero-filled"] })), vec!["blank.pdf"]);
+
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    assert_eq!(search(&dest, json!({ "op": "modified", "after": now - 3600 })).len(), 6);
+    assert!(search(&dest, json!({ "op": "modified", "before": now - 3600 })).is_empty());
+}
@@ -0,0 +1,330 @@
+// End-to-end tests for the recovery flow, driven by the fake winfr in tests/support.
+
+#[path = "support/fake_engine.rs"]
+mod fake_engine;
+
+use fake_engine::fake_winfr;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri_app_lib::events::{ChannelSink, LogSource, RecoveryEvent, RecoveryStatus};
+use tauri_app_lib::recovery::{self, RecoveryConfig};
+use tauri_app_lib::session;
+
+const FOLDER: &str = "Recovery_20240101_120000";
+
+// Only one recovery may run per process
+static SERIAL: Mutex<()> = Mutex::new(());
+
+// ── Helpers ──────────────────────────────────────────────────────────
+
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/winfr/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn destination(test: &str, scenario: Value) -> PathBuf {
+    let dest = std::env::temp_dir().join(format!("winfr-pro-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dest);
+    fs::create_dir_all(&dest).unwrap();
+    fs::write(dest.join("fake_winfr.json"), scenario.to_string()).unwrap();
+    dest
+}
+
+fn config(dest: &Path) -> RecoveryConfig {
+    RecoveryConfig {
+        source: "C:".to_string(),
+        destination: dest.to_string_lossy().to_string(),
+        mode: "regular".to_string(),
+        filters: Vec::new(),
+        segment_mode: false,
+        signature_mode: false,
+        recover_non_deleted: false,
+        keep_both: false,
+        auto_accept: true,
+        recover_system_files: false,
+        keep_all_extensions: false,
+        source_fs: None,
+        verbose_mode: false,
+        winfr_path: Some(fake_winfr()),
+    }
+}
+
+fn start(config: RecoveryConfig) -> Receiver<RecoveryEvent> {
+    let (tx, rx) = mpsc::channel();
+    recovery::start_recovery_with(Arc::new(ChannelSink::new(tx)), config).expect("recovery should start");
+    rx
+}
+
+// Collects events up to and including `Exited`
+fn collect(rx: &Receiver<RecoveryEvent>) -> Vec<RecoveryEvent> {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let mut events = Vec::new();
+    while let Ok(event) = rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        let done = matches!(event, RecoveryEvent::Exited { .. });
+        events.push(event);
+        if done {
+            return events;
+        }
+    }
+    panic!("recovery did not exit; events so far: {:?}", events);
+}
+
+fn logs(events: &[RecoveryEvent]) -> Vec<String> {
+    events
+        .iter()
+        .filter_map(|e| match e {
+            RecoveryEvent::Log { message, .. } => Some(message.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn final_status(events: &[RecoveryEvent]) -> Option<RecoveryStatus> {
+    events.iter().rev().find_map(|e| match e {
+        RecoveryEvent::Status { status, .. } => Some(*status),
+        _ => None,
+    })
+}
+
+// ── Tests ────────────────────────────────────────────────────────────
+
+#[test]
+fn successful_recovery_streams_progress_and_files_can_be_scanned() {
+    let _guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let dest = destination(
+        "success",
+        json!({
+            "transcript": fixture("regular_ntfs.txt"),
+            "recoveryFolder": FOLDER,
+            "files": [
+                { "path": "Users\\kb\\Pictures\\holiday.jpg", "size": 4096, "header": "FFD8FFE0" },
+                { "path": "Users\\kb\\Documents\\notes.pdf", "size": 2048, "header": "255044462D" },
+                { "path": "Users\\kb\\Music\\song.mp3", "size": 1024, "header": "494433" }
+            ]
+        }),
+    );
+
+    let events = collect(&start(config(&dest)));
+
+    let percents: Vec<f64> = events
+        .iter()
+        .filter_map(|e| match e {
+            RecoveryEvent::Progress { percent, .. } => Some(*percent),
+            _ => None,
+        })
+        .collect();
+    assert!(!percents.is_empty(), "no progress events: {:?}", events);
+    assert!(percents.windows(2).all(|w| w[0] <= w[1]), "progress went backwards: {:?}", percents);
+    assert_eq!(percents.last(), Some(&100.0));
+
+    assert!(events.contains(&RecoveryEvent::RecoveryFolder { folder: FOLDER.to_string() }));
+    assert!(logs(&events).iter().any(|l| l.starts_with("Source drive: C:")));
+    assert_eq!(final_status(&events), Some(RecoveryStatus::Completed));
+    assert_eq!(events.last(), Some(&RecoveryEvent::Exited { code: Some(0) }));
+
+    let saved = session::load(&dest.to_string_lossy()).expect("session is persisted");
+    assert_eq!(saved.status, "completed");
+    assert_eq!(saved.recovery_folder.as_deref(), Some(FOLDER));
+
+    let files = recovery::scan_recovered_files(&dest.to_string_lossy(), None).unwrap();
+    let mut names: Vec<(String, String)> = files.iter().map(|f| (f.name.clone(), f.category.clone())).collect();
+    names.sort();
+    assert_eq!(
+        names,
+        vec![
+            ("holiday.jpg".to_string(), "Images".to_string()),
+            ("notes.pdf".to_string(), "Documents".to_string()),
+            ("song.mp3".to_string(), "Audio".to_string()),
+        ]
+    );
+
+    let _ = fs::remove_dir_all(&dest);
+}
+
+#[test]
+fn non_zero_exit_is_reported_as_error() {
+    let _guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let dest = destination(
+        "exit-code",
+        json!({ "stderr": ["Destination folder cannot be on the source drive"], "exitCode": 2 }),
+    );
+
+    let events = collect(&start(config(&dest)));
+
+    assert!(events.contains(&RecoveryEvent::Log {
+        source: LogSource::Stderr,
+        message: "Destination folder cannot be on the source drive".to_string(),
+    }));
+    assert!(logs(&events).contains(&"Recovery process exited with code: 2".to_string()));
+    assert_eq!(final_status(&events), Some(RecoveryStatus::Error));
+    assert_eq!(events.last(), Some(&RecoveryEvent::Exited { code: Some(2) }));
+    assert_eq!(session::load(&dest.to_string_lossy()).unwrap().status, "error");
+
+    let _ = fs::remove_dir_all(&dest);
+}
+
+#[test]
+fn crash_replays_recorded_utf16_transcript_and_is_diagnosed() {
+    let _guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let dest = destination(
+        "crash",
+        json!({ "transcript": fixture("extensive_exfat_crash.utf16.txt"), "crash": true }),
+    );
+
+    let mut exfat = config(&dest);
+    exfat.mode = "extensive".to_string();
+    exfat.source_fs = Some("exFAT".to_string());
+    let events = collect(&start(exfat));
+
+    // Carriage-return separated progress updates are split into separate events
+    let progress_lines: Vec<&str> = events
+        .iter()
+        .filter_map(|e| match e {
+            RecoveryEvent::Progress { line, .. } => Some(line.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(progress_lines.last(), Some(&"Scanning disk:  37%"));
+    assert!(logs(&events).iter().any(|l| l == "Mode: Extensive"));
+
+    assert!(logs(&events).iter().any(|l| l.starts_with("CRASH DETECTED")));
+    assert_eq!(final_status(&events), Some(RecoveryStatus::Error));
+    assert!(matches!(events.last(), Some(RecoveryEvent::Exited { .. })));
+
+    let _ = fs::remove_dir_all(&dest);
+}
+
+#[test]
+fn cancel_kills_winfr_and_reports_abort() {
+    let _guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let dest = destination(
+        "cancel",
+        json!({ "transcript": fixture("regular_ntfs.txt"), "recoveryFolder": FOLDER, "holdMs": 60000 }),
+    );
+
+    let started = Instant::now();
+    let rx = start(config(&dest));
+
+    // Wait until winfr is producing output before cancelling
+    let mut events = Vec::new();
+    while let Ok(event) = rx.recv_timeout(Duration::from_secs(10)) {
+        let streaming = matches!(event, RecoveryEvent::Log { source: LogSource::Stdout, .. });
+        events.push(event);
+        if streaming {
+            break;
+        }
+    }
+    assert!(recovery::cancel_recovery().is_ok());
+    assert!(recovery::cancel_recovery().is_err(), "second cancel should find nothing running");
+    // The cancelled run holds its slot until its waiter has reported the abort
+    let (tx, _rx) = mpsc::channel();
+    let err = recovery::start_recovery_with(Arc::new(ChannelSink::new(tx)), config(&dest)).unwrap_err();
+    assert!(err.contains("already in progress"), "{}", err);
+    events.extend(collect(&rx));
+
+    assert!(started.elapsed() < Duration::from_secs(30), "winfr was not killed");
+    assert_eq!(final_status(&events), Some(RecoveryStatus::Aborted));
+    assert!(logs(&events).iter().any(|l| l.contains("OPERATION ABORTED")));
+    assert_eq!(session::load(&dest.to_string_lossy()).unwrap().status, "aborted");
+
+    let _ = fs::remove_dir_all(&dest);
+}
+
+#[test]
+fn signature_filters_that_cannot_be_honored_emit_warnings() {
+    let _guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let dest = destination("signature-warning", json!({}));
+
+    let mut signature = config(&dest);
+    signature.signature_mode = true;
+    signature.filters = vec!["*.jpg".to_string(), "*.psd".to_string()];
+    let events = collect(&start(signature));
+
+    assert!(events.contains(&RecoveryEvent::Warning {
+        message: "No signature group covers .psd; '*.psd' was ignored.".to_string(),
+    }));
+    assert!(logs(&events).iter().any(|l| l.ends_with("/signature /a /y:JPEG")), "{:?}", logs(&events));
+
+    let _ = fs::remove_dir_all(&dest);
+}
+
+#[test]
+fn invalid_filters_are_refused_before_winfr_starts() {
+    let _guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let dest = destination("invalid-filter", json!({}));
+
+    let mut typo = config(&dest);
+    typo.filters = vec!["Users\\Bob\\Documents".to_string()];
+    let (tx, _rx) = mpsc::channel();
+    let err = recovery::start_recovery_with(Arc::new(ChannelSink::new(tx)), typo).unwrap_err();
+    assert!(err.starts_with("Invalid filters: Folder filters must end with a backslash"), "{}", err);
+
+    // Nothing was started, so the next recovery is not blocked
+    let events = collect(&start(config(&dest)));
+    assert_eq!(events.last(), Some(&RecoveryEvent::Exited { code: Some(0) }));
+
+    let _ = fs::remove_dir_all(&dest);
+}
+
+#[test]
+fn missing_engine_fails_to_start_and_does_not_block_the_next_run() {
+    let _guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let dest = destination("missing-engine", json!({}));
+
+    let mut missing = config(&dest);
+    missing.winfr_path = Some(dest.join("no-such-winfr").to_string_lossy().to_string());
+    let (tx, _rx) = mpsc::channel();
+    let err = recovery::start_recovery_with(Arc::new(ChannelSink::new(tx)), missing).unwrap_err();
+    assert!(err.starts_with("Failed to launch winfr"), "{}", err);
+
+    let events = collect(&start(config(&dest)));
+    assert_eq!(events.last(), Some(&RecoveryEvent::Exited { code: Some(0) }));
+
+    let _ = fs::remove_dir_all(&dest);
+}
+
+#[test]
+fn raw_image_sources_are_refused_before_winfr_starts() {
+    let _guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let dest = destination("raw-image", json!({}));
+    let image = dest.join("disk.img");
+    fs::write(&image, vec![0u8; 4096]).unwrap();
+
+    let mut raw = config(&dest);
+    raw.source = image.to_string_lossy().to_string();
+    let (tx, rx) = mpsc::channel();
+    let err = recovery::start_recovery_with(Arc::new(ChannelSink::new(tx)), raw).unwrap_err();
+    assert!(err.contains("native scanners"), "{}", err);
+    assert!(rx.try_recv().is_err());
+
+    let events = collect(&start(config(&dest)));
+    assert_eq!(events.last(), Some(&RecoveryEvent::Exited { code: Some(0) }));
+
+    let _ = fs::remove_dir_all(&dest);
+}
+
+#[test]
+fn destinations_on_the_source_drive_are_refused_before_anything_is_written() {
+    let _guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let dest = destination("same-drive", json!({}));
+
+    // Only Windows can say which volume a destination is on
+    if cfg!(windows) {
+        let mut same_drive = config(&dest);
+        same_drive.destination = "c:\\Recovered".to_string();
+        let (tx, rx) = mpsc::channel();
+        let err = recovery::start_recovery_with(Arc::new(ChannelSink::new(tx)), same_drive).unwrap_err();
+        assert!(err.contains("source drive C:"), "{}", err);
+        assert!(rx.try_recv().is_err());
+        assert!(!Path::new("c:\\Recovered").exists());
+    }
+
+    let events = collect(&start(config(&dest)));
+    assert_eq!(events.last(), Some(&RecoveryEvent::Exited { code: Some(0) }));
+    assert_eq!(session::current().unwrap().source_integrity, None);
+
+    let _ = fs::remove_dir_all(&dest);
+}
@@ -0,0 +1,69 @@
+// Tests for the recovery report in its JSON, CSV and HTML forms.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/recovered_files.rs"]
+#[allow(dead_code)]
+mod recovered_files;
+
+use common::scratch_dir;
+use recovered_files::{recovery_folder, session, use_test_app_data};
+use std::fs;
+use tauri_app_lib::report;
+
+#[test]
+fn json_report_sums_the_recovered_files_by_category() {
+    use_test_app_data();
+    let dir = scratch_dir("json");
+    recovery_folder(&dir, &[("Users/Bob/a.pdf", &[1u8; 100]), ("b.pdf", &[1u8; 20]), ("c.csv", &[1u8; 3])]);
+    let output = dir.join("report.json");
+    report::export_report(&session(&dir), "JSON", &output.to_string_lossy()).unwrap();
+
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+    assert_eq!(json["totalFiles"], 3);
+    assert_eq!(json["totalSize"], 123);
+    assert_eq!(json["durationSecs"], 3725);
+    assert!(json["recoveryDir"].as_str().unwrap().ends_with(recovered_files::FOLDER));
+    assert!(json["commandLine"].as_str().unwrap().starts_with("winfr E: "));
+    assert_eq!(json["session"]["id"], "session-1704110400");
+    assert_eq!(json["categories"], serde_json::json!([{ "category": "Documents", "count": 3, "size": 123 }]));
+}
+
+#[test]
+fn csv_cells_are_quoted_and_formulas_defused() {
+    use_test_app_data();
+    let dir = scratch_dir("csv");
+    recovery_folder(&dir, &[("=SUM(A1,A2).csv", b"1"), ("-2+3.pdf", b"2"), ("@cmd.pdf", b"3"), ("plain.pdf", b"4")]);
+    let report = report::build_report(&session(&dir)).unwrap();
+    let csv = report::render_report(&report, "csv").unwrap();
+
+    let lines: Vec<&str> = csv.split("\r\n").collect();
+    assert!(lines[0].starts_with("id,name,path,size,category,modified,integrity"), "{}", lines[0]);
+    assert_eq!(lines.len(), 6, "{:?}", lines);
+    let row = |name: &str| lines.iter().find(|l| l.contains(name)).copied().unwrap_or_default();
+    assert!(row("SUM").contains(",\"'=SUM(A1,A2).csv\",\"\\=SUM(A1,A2).csv\","), "{}", row("SUM"));
+    assert!(row("2+3").contains(",'-2+3.pdf,\\-2+3.pdf,"), "{}", row("2+3"));
+    assert!(row("@cmd").contains(",'@cmd.pdf,\\@cmd.pdf,"), "{}", row("@cmd"));
+    assert!(row("plain").contains(",plain.pdf,\\plain.pdf,1,Documents,"), "{}", row("plain"));
+}
+
+#[test]
+fn html_report_escapes_names_and_shows_the_session() {
+    use_test_app_data();
+    let dir = scratch_dir("html");
+    recovery_folder(&dir, &[("a<b>&c.pdf", &[1u8; 2048])]);
+    let report = report::build_report(&session(&dir)).unwrap();
+    let html = report::render_report(&report, "html").unwrap();
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("a&lt;b&gt;&amp;c.pdf"));
+    assert!(!html.contains("a<b>"));
+    assert!(html.contains("<tr><th>Duration</th><td>01:02:05</td></tr>"));
+    assert!(html.contains("<tr><th>Files recovered</th><td>1</td></tr>"));
+    assert!(html.contains("<tr><th>Filters</th><td>*.pdf, *.csv</td></tr>"));
+    assert!(html.contains("2.0 KB"));
+
+    let err = report::render_report(&report, "pdf").unwrap_err();
+    assert_eq!(err, "Unsupported report format: pdf");
+}
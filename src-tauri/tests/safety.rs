@@ -0,0 +1,214 @@
+// Tests for write blocking: targets are refused when they land on the source drive or on
+// the source image, also through links, and image sources are hashed before and after.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/ewf_image.rs"]
+#[allow(dead_code)]
+mod ewf_image;
+#[path = "support/faulty_reader.rs"]
+#[allow(dead_code)]
+mod faulty_reader;
+#[path = "support/vhd_image.rs"]
+#[allow(dead_code)]
+mod vhd_image;
+
+use common::{pattern, save, scratch_dir, text};
+use ewf_image::{EwfImage, CHUNK_SIZE};
+use faulty_reader::FaultyReader;
+use std::fs;
+use std::path::Path;
+use tauri_app_lib::events::RecordingSink;
+use tauri_app_lib::imaging::{self, ImagingOptions};
+use tauri_app_lib::preflight;
+use tauri_app_lib::recovery::RecoveryConfig;
+use tauri_app_lib::safety;
+use vhd_image::VirtualDisk;
+
+// ── Helpers ──────────────────────────────────────────────────────────
+
+#[cfg(unix)]
+fn link(original: &Path, link: &Path) {
+    std::os::unix::fs::symlink(original, link).unwrap();
+}
+
+#[cfg(windows)]
+fn link(original: &Path, link: &Path) {
+    if original.is_dir() {
+        std::os::windows::fs::symlink_dir(original, link).unwrap();
+    } else {
+        std::os::windows::fs::symlink_file(original, link).unwrap();
+    }
+}
+
+fn config(source: &str, destination: &str) -> RecoveryConfig {
+    RecoveryConfig {
+        source: source.to_string(),
+        destination: destination.to_string(),
+        mode: "regular".to_string(),
+        filters: Vec::new(),
+        segment_mode: false,
+        signature_mode: false,
+        recover_non_deleted: false,
+        keep_both: false,
+        auto_accept: true,
+        recover_system_files: false,
+        keep_all_extensions: false,
+        source_fs: None,
+        verbose_mode: false,
+        winfr_path: None,
+    }
+}
+
+// ── Tests ────────────────────────────────────────────────────────────
+
+#[test]
+fn targets_on_the_source_drive_are_refused() {
+    // Windows is asked which volume a target is on, so only a drive that exists can be
+    // matched: the system drive always does
+    if cfg!(windows) {
+        let refused = [
+            ("C:", "c:\\Recovered"),
+            ("C:\\", "C:\\Recovered\\photos"),
+            ("\\\\.\\C:", "c:/out"),
+            ("C:", "\\\\?\\C:\\out"),
+            ("C:", "\\\\localhost\\c$\\out"),
+            ("C:", "\\\\?\\UNC\\127.0.0.1\\C$\\out"),
+        ];
+        for (source, target) in refused {
+            let err = safety::ensure_target_outside(source, target).unwrap_err();
+            assert!(err.contains("source drive C:"), "{} -> {}: {}", source, target, err);
+        }
+
+        // Preflight fails the destination with the same reason
+        let report = preflight::run_preflight(&config("C:", "c:\\Recovered"));
+        let check = report.checks.iter().find(|c| c.name == "destination").unwrap();
+        assert!(!check.ok && check.message.contains("different drive"), "{}", check.message);
+    }
+
+    let allowed = [("E:", "D:\\Recovered"), ("E:", "\\\\fileserver\\e$\\out"), ("\\\\.\\PhysicalDrive1", "E:\\x")];
+    for (source, target) in allowed {
+        assert!(safety::ensure_target_outside(source, target).is_ok(), "{} -> {}", source, target);
+    }
+}
+
+#[test]
+fn links_to_the_source_image_are_resolved() {
+    let dir = scratch_dir("links");
+    let image = dir.join("card.img");
+    fs::write(&image, vec![5u8; 64 * 1024]).unwrap();
+    let source = text(&image);
+
+    // Paths that do not exist yet keep their missing part, and ".." is applied after
+    // resolving what exists
+    let resolved = safety::resolve_target(&text(&dir.join("new").join("..").join("card.img")));
+    assert_eq!(resolved, fs::canonicalize(&image).unwrap());
+    let resolved = safety::resolve_target(&text(&dir.join("new").join("deeper")));
+    assert_eq!(resolved, fs::canonicalize(&dir).unwrap().join("new").join("deeper"));
+
+    link(&image, &dir.join("alias.img"));
+    fs::create_dir(dir.join("real")).unwrap();
+    link(&dir, &dir.join("real").join("up"));
+    for target in [dir.join("alias.img"), dir.join("real").join("up").join("card.img")] {
+        let err = safety::ensure_target_outside(&source, &text(&target)).unwrap_err();
+        assert!(err.contains("source image"), "{}", err);
+    }
+    assert!(safety::ensure_target_outside(&source, &text(&dir.join("real").join("up").join("out"))).is_ok());
+    assert!(safety::ensure_target_outside(&format!("{}|1048576", source), &text(&dir.join("alias.img"))).is_err());
+
+    // A resumed image run cannot write through a link onto its own source
+    let output = dir.join("alias.img");
+    fs::write(dir.join("alias.img.map"), "# Mapfile\n0x00000000     ?               1\n").unwrap();
+    let mut reader = FaultyReader::new(vec![5u8; 64 * 1024]);
+    let options = ImagingOptions { sector_size: 512, retries: 0, ..Default::default() };
+    let err = imaging::image_from(&mut reader, &source, &text(&output), &options, &RecordingSink::new()).unwrap_err();
+    assert!(err.contains("source image"), "{}", err);
+    assert!(reader.reads.is_empty());
+    assert_eq!(fs::read(&image).unwrap(), vec![5u8; 64 * 1024]);
+}
+
+#[test]
+fn image_sources_are_hashed_before_and_after() {
+    let dir = scratch_dir("hashes");
+    assert!(safety::hash_source_image("E:", |_| {}).unwrap().is_none());
+
+    let image = dir.join("disk.img");
+    fs::write(&image, vec![1u8; 128 * 1024]).unwrap();
+    let mut integrity = safety::hash_source_image(&format!("{}|0", text(&image)), |_| {}).unwrap().unwrap();
+    assert_eq!(integrity.image, text(&image));
+    assert_eq!(integrity.files.len(), 1);
+    assert_eq!(integrity.files[0].path, text(&image));
+    assert_eq!(integrity.unchanged, None);
+    safety::verify_source_image(&mut integrity, |_| {});
+    assert_eq!(integrity.unchanged, Some(true));
+    assert_eq!(integrity.files[0].after.as_ref(), Some(&integrity.files[0].before));
+
+    let mut changed = safety::hash_source_image(&text(&image), |_| {}).unwrap().unwrap();
+    fs::write(&image, vec![2u8; 128 * 1024]).unwrap();
+    safety::verify_source_image(&mut changed, |_| {});
+    assert_eq!(changed.unchanged, Some(false));
+
+    let mut missing = safety::hash_source_image(&text(&image), |_| {}).unwrap().unwrap();
+    fs::remove_file(&image).unwrap();
+    safety::verify_source_image(&mut missing, |_| {});
+    assert_eq!(missing.unchanged, None);
+    assert!(missing.error.is_some());
+}
+
+#[test]
+fn every_segment_and_parent_disk_is_hashed() {
+    let dir = scratch_dir("segments");
+    let evidence = EwfImage::new(pattern(8 * CHUNK_SIZE, 3)).segment_chunks(2).save(&dir, "card");
+    let mut integrity = safety::hash_source_image(&evidence, |_| {}).unwrap().unwrap();
+    let files: Vec<&str> = integrity.files.iter().map(|f| f.path.as_str()).collect();
+    let segments: Vec<String> = (1..=4).map(|i| text(&dir.join(format!("card.E{:02}", i)))).collect();
+    assert_eq!(files, segments);
+
+    // A change to a later segment is caught, not only to the .E01
+    let mut third = fs::read(&segments[2]).unwrap();
+    let last = third.len() - 1;
+    third[last] ^= 0xff;
+    fs::write(&segments[2], third).unwrap();
+    safety::verify_source_image(&mut integrity, |_| {});
+    assert_eq!(integrity.unchanged, Some(false));
+    assert_eq!(integrity.files[0].after.as_ref(), Some(&integrity.files[0].before));
+    assert_ne!(integrity.files[2].after.as_ref(), Some(&integrity.files[2].before));
+
+    fs::create_dir(dir.join("base")).unwrap();
+    let mut parent = VirtualDisk::new(4 * 1024 * 1024).id(7);
+    parent.write(0, &pattern(4096, 4));
+    let parent = save(&dir.join("base"), "parent.vhd", &parent.dynamic_vhd(2 * 1024 * 1024));
+    let child = VirtualDisk::new(4 * 1024 * 1024).id(9).child_of("base\\parent.vhd", 7);
+    let child = save(&dir, "child.vhd", &child.dynamic_vhd(2 * 1024 * 1024));
+    let integrity = safety::hash_source_image(&format!("{}|1048576", child), |_| {}).unwrap().unwrap();
+    let files: Vec<&str> = integrity.files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(files, [child.as_str(), parent.as_str()]);
+}
+
+#[test]
+fn native_sessions_hash_the_image_before_and_after() {
+    let dir = scratch_dir("session");
+    let image = save(&dir, "disk.img", &vec![3u8; 3 * 1024 * 1024]);
+    let mut progress = Vec::new();
+    let (read, integrity) = safety::hashed_session(
+        &image,
+        |p| progress.push((p.bytes_done, p.bytes_total)),
+        || Ok(fs::read(&image).unwrap().len()),
+    )
+    .unwrap();
+    assert_eq!(read, 3 * 1024 * 1024);
+    assert_eq!(integrity.unwrap().unchanged, Some(true));
+    // Once before and once after the session
+    assert_eq!(progress.iter().filter(|(done, total)| done == total).count(), 2, "{:?}", progress);
+
+    // An image that cannot be hashed is not read at all
+    let mut ran = false;
+    let err = safety::hashed_session(&text(&dir.join("gone.img")), |_| {}, || {
+        ran = true;
+        Ok(())
+    })
+    .unwrap_err();
+    assert!(err.starts_with("Failed to hash the source image"), "{}", err);
+    assert!(!ran);
+}
@@ -0,0 +1,80 @@
+// Signature group listing and filter mapping for signature mode.
+
+use tauri_app_lib::signatures::{builtin_groups, parse_groups, select_groups, SignatureGroup};
+
+fn fixture_lines(name: &str) -> Vec<String> {
+    let path = format!("{}/tests/fixtures/winfr/{}", env!("CARGO_MANIFEST_DIR"), name);
+    std::fs::read_to_string(path).unwrap().lines().map(|l| l.trim().to_string()).collect()
+}
+
+fn select(filters: &[&str]) -> (Vec<String>, Vec<String>) {
+    let filters: Vec<String> = filters.iter().map(|f| f.to_string()).collect();
+    let selection = select_groups(&filters, &builtin_groups());
+    (selection.groups, selection.warnings)
+}
+
+#[test]
+fn winfr_group_listing_matches_the_builtin_table() {
+    assert_eq!(parse_groups(&fixture_lines("signature_groups.txt")), builtin_groups());
+}
+
+#[test]
+fn groups_added_by_newer_winfr_are_picked_up() {
+    let lines = vec!["HEIC:   heic, heif".to_string(), "Version: 0.2.0.0".to_string()];
+    let groups = parse_groups(&lines);
+
+    assert_eq!(
+        groups,
+        vec![SignatureGroup { name: "HEIC".to_string(), extensions: vec!["heic".to_string(), "heif".to_string()] }]
+    );
+    let selection = select_groups(&["*.heic".to_string()], &groups);
+    assert_eq!(selection.groups, vec!["HEIC"]);
+    assert!(selection.warnings.is_empty());
+}
+
+#[test]
+fn categories_map_to_every_covering_group() {
+    let (groups, warnings) = select(&["Videos", "Audio"]);
+
+    assert_eq!(groups, vec!["ASF", "MP3", "MPEG"]);
+    assert_eq!(
+        warnings,
+        vec![
+            "Videos: .avi, .mkv, .flv, .webm cannot be recovered in signature mode.",
+            "Audio: .wav, .flac, .aac, .ogg cannot be recovered in signature mode.",
+        ]
+    );
+}
+
+#[test]
+fn group_names_and_extension_filters_are_honored() {
+    let (groups, warnings) = select(&["png", "*.docx", "*.MOV"]);
+
+    assert_eq!(groups, vec!["MPEG", "PNG", "ZIP"]);
+    assert!(warnings.is_empty(), "{:?}", warnings);
+}
+
+#[test]
+fn filters_that_cannot_be_honored_are_reported() {
+    let (groups, warnings) = select(&["invoice*.pdf", "Users\\Bob\\Documents\\", "*.psd", "notes", "*.*"]);
+
+    assert_eq!(groups, vec!["PDF"]);
+    assert_eq!(
+        warnings,
+        vec![
+            "'invoice*.pdf' matches every .pdf file in signature mode (PDF group); file names cannot be filtered.",
+            "Path filter 'Users\\Bob\\Documents\\' cannot be used in signature mode and was ignored.",
+            "No signature group covers .psd; '*.psd' was ignored.",
+            "'notes' has no file extension to match in signature mode and was ignored.",
+            "'*.*' has no file extension to match in signature mode and was ignored.",
+        ]
+    );
+}
+
+#[test]
+fn unmatched_filters_warn_that_everything_will_be_recovered() {
+    let (groups, warnings) = select(&["*.psd"]);
+
+    assert!(groups.is_empty());
+    assert_eq!(warnings.last().map(String::as_str), Some("No filter maps to a signature group, so every group will be recovered."));
+}
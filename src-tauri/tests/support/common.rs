@@ -0,0 +1,114 @@
+// Helpers shared by the integration tests: scratch folders that are removed again when
+// the test ends (also when it fails), test data, and lookups in the scanners' listings.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri_app_lib::{exfat, fat, ntfs};
+
+// ── Scratch folders ──────────────────────────────────────────────────
+
+pub struct ScratchDir(PathBuf);
+
+impl Deref for ScratchDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for ScratchDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+// An empty folder named after the test binary and `test`, unique to this process
+pub fn scratch_dir(test: &str) -> ScratchDir {
+    let name = format!("winfr-pro-{}-{}-{}", env!("CARGO_CRATE_NAME"), test, std::process::id());
+    let dir = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    ScratchDir(dir)
+}
+
+// ── Data ─────────────────────────────────────────────────────────────
+
+// Content that differs per byte and per seed, so a misplaced cluster shows up
+pub fn pattern(len: usize, seed: u8) -> Vec<u8> {
+    (0..len).map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed)).collect()
+}
+
+pub fn text(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+// Writes `bytes` to `dir/name` and returns the path as a source string
+pub fn save(dir: &Path, name: &str, bytes: &[u8]) -> String {
+    let path = dir.join(name);
+    fs::write(&path, bytes).unwrap();
+    text(&path)
+}
+
+// ── Real volumes ─────────────────────────────────────────────────────
+
+// Runs a file system tool such as mkfs.fat or mkntfs. Returns false when it is not
+// installed, so tests on real volumes are skipped on machines without it.
+pub fn run_tool(program: &str, args: &[&str]) -> bool {
+    match Command::new(program).args(args).env("MTOOLS_SKIP_CHECK", "1").output() {
+        Ok(output) => {
+            assert!(
+                output.status.success(),
+                "{} {:?} failed: {}",
+                program,
+                args,
+                String::from_utf8_lossy(&output.stderr)
+            );
+            true
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            eprintln!("skipped: {} is not installed", program);
+            false
+        }
+        Err(e) => panic!("Failed to run {}: {}", program, e),
+    }
+}
+
+// ── Listings ─────────────────────────────────────────────────────────
+
+pub trait Listed {
+    fn listed_path(&self) -> &str;
+}
+
+impl Listed for ntfs::DeletedFile {
+    fn listed_path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Listed for fat::FatDeletedFile {
+    fn listed_path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Listed for exfat::ExfatDeletedFile {
+    fn listed_path(&self) -> &str {
+        &self.path
+    }
+}
+
+pub fn find<'a, T: Listed>(files: &'a [T], path: &str) -> &'a T {
+    files.iter().find(|f| f.listed_path() == path).unwrap_or_else(|| {
+        panic!("{} not listed in {:?}", path, files.iter().map(|f| f.listed_path()).collect::<Vec<_>>())
+    })
+}
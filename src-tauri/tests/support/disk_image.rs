@@ -0,0 +1,120 @@
+// Builds partitioned disk images in memory for the partitions tests: MBR partition
+// sectors (including extended boot records) and GPT with both header copies. Volumes
+// from the other image builders are copied in at their partition offsets.
+
+pub const SECTOR_SIZE: u64 = 512;
+pub const MIB: u64 = 1024 * 1024;
+
+pub const EFI_SYSTEM: &str = "C12A7328-F81F-11D2-BA4B-00A0C93EC93B";
+pub const BASIC_DATA: &str = "EBD0A0A2-B9E5-4433-87C0-68B6B72699C7";
+
+const GPT_ENTRIES: usize = 128;
+const GPT_ENTRY_SIZE: usize = 128;
+const GPT_ENTRY_SECTORS: u64 = (GPT_ENTRIES * GPT_ENTRY_SIZE) as u64 / SECTOR_SIZE;
+
+pub struct GptEntry {
+    pub type_guid: &'static str,
+    pub first: u64, // sectors, inclusive
+    pub last: u64,
+    pub name: &'static str,
+}
+
+pub struct DiskImage {
+    image: Vec<u8>,
+}
+
+impl DiskImage {
+    pub fn new(size: u64) -> Self {
+        DiskImage { image: vec![0u8; size as usize] }
+    }
+
+    pub fn put(&mut self, offset: u64, data: &[u8]) {
+        let at = offset as usize;
+        self.image[at..at + data.len()].copy_from_slice(data);
+    }
+
+    // Writes a partition sector at `lba` with (type, first sector, sectors) entries. First
+    // sectors are absolute in the MBR and relative in extended boot records.
+    pub fn partition_sector(&mut self, lba: u64, entries: &[(u8, u64, u64)]) {
+        let at = (lba * SECTOR_SIZE) as usize;
+        for (i, (kind, first, sectors)) in entries.iter().enumerate() {
+            let e = at + 0x1BE + i * 16;
+            self.image[e + 4] = *kind;
+            self.image[e + 8..e + 12].copy_from_slice(&(*first as u32).to_le_bytes());
+            self.image[e + 12..e + 16].copy_from_slice(&(*sectors as u32).to_le_bytes());
+        }
+        self.image[at + 0x1FE] = 0x55;
+        self.image[at + 0x1FF] = 0xAA;
+    }
+
+    pub fn gpt(&mut self, disk_guid: &str, partitions: &[GptEntry]) {
+        let last_lba = self.image.len() as u64 / SECTOR_SIZE - 1;
+        self.partition_sector(0, &[(0xEE, 1, last_lba.min(u32::MAX as u64))]);
+
+        let mut entries = vec![0u8; GPT_ENTRIES * GPT_ENTRY_SIZE];
+        for (i, p) in partitions.iter().enumerate() {
+            let e = &mut entries[i * GPT_ENTRY_SIZE..(i + 1) * GPT_ENTRY_SIZE];
+            e[0..16].copy_from_slice(&guid_bytes(p.type_guid));
+            e[16] = i as u8 + 1; // unique partition GUID
+            e[32..40].copy_from_slice(&p.first.to_le_bytes());
+            e[40..48].copy_from_slice(&p.last.to_le_bytes());
+            for (c, unit) in p.name.encode_utf16().enumerate() {
+                e[56 + c * 2..58 + c * 2].copy_from_slice(&unit.to_le_bytes());
+            }
+        }
+        let entries_crc = crc32(&entries);
+        let backup_entries = last_lba - GPT_ENTRY_SECTORS;
+
+        for (lba, alternate, entries_lba) in [(1, last_lba, 2), (last_lba, 1, backup_entries)] {
+            let mut h = vec![0u8; SECTOR_SIZE as usize];
+            h[0..8].copy_from_slice(b"EFI PART");
+            h[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+            h[12..16].copy_from_slice(&92u32.to_le_bytes());
+            h[24..32].copy_from_slice(&lba.to_le_bytes());
+            h[32..40].copy_from_slice(&alternate.to_le_bytes());
+            h[40..48].copy_from_slice(&(2 + GPT_ENTRY_SECTORS).to_le_bytes());
+            h[48..56].copy_from_slice(&(backup_entries - 1).to_le_bytes());
+            h[56..72].copy_from_slice(&guid_bytes(disk_guid));
+            h[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+            h[80..84].copy_from_slice(&(GPT_ENTRIES as u32).to_le_bytes());
+            h[84..88].copy_from_slice(&(GPT_ENTRY_SIZE as u32).to_le_bytes());
+            h[88..92].copy_from_slice(&entries_crc.to_le_bytes());
+            let crc = crc32(&h[..92]);
+            h[16..20].copy_from_slice(&crc.to_le_bytes());
+            self.put(lba * SECTOR_SIZE, &h);
+            self.put(entries_lba * SECTOR_SIZE, &entries);
+        }
+    }
+
+    pub fn corrupt(&mut self, offset: u64) {
+        self.image[offset as usize] ^= 0xFF;
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        self.image.clone()
+    }
+}
+
+fn guid_bytes(guid: &str) -> [u8; 16] {
+    let hex: String = guid.chars().filter(|c| *c != '-').collect();
+    let mut raw = [0u8; 16];
+    for (i, byte) in raw.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+    }
+    // The first three groups are stored little-endian
+    raw[0..4].reverse();
+    raw[4..6].reverse();
+    raw[6..8].reverse();
+    raw
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
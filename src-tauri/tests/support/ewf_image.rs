@@ -0,0 +1,191 @@
+// Builds EnCase (E01) evidence files in memory for the ewf tests: a header and volume
+// section, then per segment one sectors section with its table and table2 copy, and a
+// digest section with the disk's MD5 and SHA-1 before "done" in the last segment.
+// Chunks are zlib-compressed unless that does not make them smaller, as EnCase does.
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use md5::Md5;
+use sha1::Sha1;
+use sha2::Digest;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+pub const SECTOR_SIZE: usize = 512;
+pub const CHUNK_SECTORS: usize = 64;
+pub const CHUNK_SIZE: usize = SECTOR_SIZE * CHUNK_SECTORS;
+
+const SIGNATURE: &[u8] = b"EVF\x09\x0d\x0a\xff\x00";
+const DESCRIPTOR_SIZE: usize = 76;
+
+pub struct EwfImage {
+    data: Vec<u8>,
+    segment_chunks: usize,
+    stored_md5: Option<Vec<u8>>,
+    stored_sha1: Option<Vec<u8>>,
+    damaged_table: bool,
+    oversized_chunk: Option<usize>,
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+impl EwfImage {
+    // `data` is the acquired disk; its length must be a whole number of sectors
+    pub fn new(data: Vec<u8>) -> Self {
+        let stored_md5 = Some(Md5::digest(&data).to_vec());
+        let stored_sha1 = Some(Sha1::digest(&data).to_vec());
+        EwfImage { data, segment_chunks: usize::MAX, stored_md5, stored_sha1, damaged_table: false, oversized_chunk: None }
+    }
+
+    pub fn segment_chunks(mut self, chunks: usize) -> Self {
+        self.segment_chunks = chunks;
+        self
+    }
+
+    pub fn stored_hashes(mut self, md5: Option<Vec<u8>>, sha1: Option<Vec<u8>>) -> Self {
+        self.stored_md5 = md5;
+        self.stored_sha1 = sha1;
+        self
+    }
+
+    // The first table fails its checksum, so readers must use table2
+    pub fn damaged_table(mut self) -> Self {
+        self.damaged_table = true;
+        self
+    }
+
+    // Chunk `index` inflates to four times the chunk size, like a damaged or hostile chunk
+    pub fn oversized_chunk(mut self, index: usize) -> Self {
+        self.oversized_chunk = Some(index);
+        self
+    }
+
+    pub fn build(&self) -> Vec<Vec<u8>> {
+        let chunks: Vec<&[u8]> = self.data.chunks(CHUNK_SIZE).collect();
+        let groups: Vec<&[&[u8]]> = chunks.chunks(self.segment_chunks).collect();
+        let mut segments = Vec::new();
+        for (i, group) in groups.iter().enumerate() {
+            let mut file = SIGNATURE.to_vec();
+            file.push(1);
+            file.extend(((i + 1) as u16).to_le_bytes());
+            file.extend([0, 0]);
+            if i == 0 {
+                let mut header = ZlibEncoder::new(Vec::new(), Compression::fast());
+                header.write_all(b"1\nmain\nc\tn\ta\te\tt\n1\t1\tcamera card\texaminer\tnotes\n\n").unwrap();
+                section(&mut file, "header", &header.finish().unwrap());
+                section(&mut file, "volume", &self.volume());
+            }
+
+            let sectors_start = file.len() + DESCRIPTOR_SIZE;
+            let mut stored = Vec::new();
+            let mut entries = Vec::new();
+            for (j, chunk) in group.iter().enumerate() {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+                if self.oversized_chunk == Some(i * self.segment_chunks + j) {
+                    encoder.write_all(&chunk.repeat(4)).unwrap();
+                } else {
+                    encoder.write_all(chunk).unwrap();
+                }
+                let compressed = encoder.finish().unwrap();
+                let offset = stored.len() as u32;
+                if compressed.len() < chunk.len() {
+                    entries.push(offset | 0x8000_0000);
+                    stored.extend(compressed);
+                } else {
+                    entries.push(offset);
+                    stored.extend_from_slice(chunk);
+                    stored.extend(adler32(chunk).to_le_bytes());
+                }
+            }
+            section(&mut file, "sectors", &stored);
+            let table = table(&entries, sectors_start as u64);
+            let mut first = table.clone();
+            if i == 0 && self.damaged_table {
+                first[20] ^= 0xFF;
+            }
+            section(&mut file, "table", &first);
+            section(&mut file, "table2", &table);
+
+            if i + 1 == groups.len() {
+                if let Some(md5) = &self.stored_md5 {
+                    let mut digest = vec![0u8; 80];
+                    digest[..16].copy_from_slice(md5);
+                    if let Some(sha1) = &self.stored_sha1 {
+                        digest[16..36].copy_from_slice(sha1);
+                    }
+                    let checksum = adler32(&digest[..76]);
+                    digest[76..].copy_from_slice(&checksum.to_le_bytes());
+                    section(&mut file, "digest", &digest);
+                }
+                end_section(&mut file, "done");
+            } else {
+                end_section(&mut file, "next");
+            }
+            segments.push(file);
+        }
+        segments
+    }
+
+    // Writes the segments next to each other as <base>.E01, <base>.E02, ...; returns the first
+    pub fn save(&self, dir: &Path, base: &str) -> String {
+        let segments = self.build();
+        for (i, segment) in segments.iter().enumerate() {
+            fs::write(dir.join(format!("{}.E{:02}", base, i + 1)), segment).unwrap();
+        }
+        dir.join(format!("{}.E01", base)).to_string_lossy().to_string()
+    }
+
+    fn volume(&self) -> Vec<u8> {
+        let mut volume = vec![0u8; 1052];
+        volume[0] = 0x01;
+        volume[4..8].copy_from_slice(&(self.data.len().div_ceil(CHUNK_SIZE) as u32).to_le_bytes());
+        volume[8..12].copy_from_slice(&(CHUNK_SECTORS as u32).to_le_bytes());
+        volume[12..16].copy_from_slice(&(SECTOR_SIZE as u32).to_le_bytes());
+        volume[16..24].copy_from_slice(&((self.data.len() / SECTOR_SIZE) as u64).to_le_bytes());
+        volume[52] = 0x01; // fast compression
+        let checksum = adler32(&volume[..1048]);
+        volume[1048..].copy_from_slice(&checksum.to_le_bytes());
+        volume
+    }
+}
+
+fn table(entries: &[u32], base: u64) -> Vec<u8> {
+    let mut table = vec![0u8; 24];
+    table[0..4].copy_from_slice(&(entries.len() as u32).to_le_bytes());
+    table[8..16].copy_from_slice(&base.to_le_bytes());
+    let checksum = adler32(&table[..20]);
+    table[20..24].copy_from_slice(&checksum.to_le_bytes());
+    let raw: Vec<u8> = entries.iter().flat_map(|e| e.to_le_bytes()).collect();
+    table.extend(&raw);
+    table.extend(adler32(&raw).to_le_bytes());
+    table
+}
+
+fn descriptor(kind: &str, next: u64, size: u64) -> Vec<u8> {
+    let mut d = vec![0u8; DESCRIPTOR_SIZE];
+    d[..kind.len()].copy_from_slice(kind.as_bytes());
+    d[16..24].copy_from_slice(&next.to_le_bytes());
+    d[24..32].copy_from_slice(&size.to_le_bytes());
+    let checksum = adler32(&d[..72]);
+    d[72..76].copy_from_slice(&checksum.to_le_bytes());
+    d
+}
+
+fn section(file: &mut Vec<u8>, kind: &str, data: &[u8]) {
+    let size = (DESCRIPTOR_SIZE + data.len()) as u64;
+    file.extend(descriptor(kind, file.len() as u64 + size, size));
+    file.extend_from_slice(data);
+}
+
+// "next" and "done" point at themselves
+fn end_section(file: &mut Vec<u8>, kind: &str) {
+    file.extend(descriptor(kind, file.len() as u64, DESCRIPTOR_SIZE as u64));
+}
@@ -0,0 +1,256 @@
+// Builds small exFAT images in memory for the exfat tests: 512-byte sectors, 4 KiB
+// clusters, allocation bitmap in cluster 2, up-case table in 3, root directory in 4.
+// Deletion clears the InUse bit of every entry in the set and the bitmap bits only,
+// as Windows does.
+
+use std::collections::HashMap;
+
+pub const CLUSTER_SIZE: usize = 4096;
+// 2024-01-01 12:00:00, stored as DOS date and time
+pub const TIMESTAMP: u64 = 1_704_110_400;
+const DOS_TIMESTAMP: u32 = (((44 << 9) | (1 << 5) | 1) << 16) | (12 << 11);
+
+const SECTOR_SIZE: usize = 512;
+const TOTAL_SECTORS: usize = 16384;
+const FAT_OFFSET: usize = 32; // sectors
+const FAT_LENGTH: usize = 16;
+const HEAP_OFFSET: usize = 64;
+const CLUSTER_COUNT: usize = (TOTAL_SECTORS - HEAP_OFFSET) / (CLUSTER_SIZE / SECTOR_SIZE);
+const BITMAP_CLUSTER: u32 = 2;
+const UPCASE_CLUSTER: u32 = 3;
+const ROOT_CLUSTER: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dir {
+    Root,
+    Cluster(u32),
+}
+
+pub struct Node {
+    pub entries: Vec<usize>, // byte offsets of the entry set
+    pub clusters: Vec<u32>,
+    pub dir: Option<Dir>,
+}
+
+pub struct ExfatImage {
+    image: Vec<u8>,
+    fat: Vec<u32>,
+    allocated: Vec<bool>,
+    next_cluster: u32,
+    next_slot: HashMap<Dir, usize>,
+}
+
+impl Default for ExfatImage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExfatImage {
+    pub fn new() -> Self {
+        let mut image = ExfatImage {
+            image: vec![0u8; TOTAL_SECTORS * SECTOR_SIZE],
+            fat: vec![0u32; CLUSTER_COUNT + 2],
+            allocated: vec![false; CLUSTER_COUNT + 2],
+            next_cluster: 2,
+            next_slot: HashMap::new(),
+        };
+        image.fat[0] = 0xFFFF_FFF8;
+        image.fat[1] = 0xFFFF_FFFF;
+        for _ in [BITMAP_CLUSTER, UPCASE_CLUSTER, ROOT_CLUSTER] {
+            let cluster = image.allocate(1)[0];
+            image.fat[cluster as usize] = 0xFFFF_FFFF;
+        }
+        image.write_boot_sector();
+
+        // Allocation bitmap and up-case table entries lead the root directory
+        let at = image.slot(Dir::Root);
+        let e = &mut image.image[at..at + 32];
+        e[0] = 0x81;
+        e[20..24].copy_from_slice(&BITMAP_CLUSTER.to_le_bytes());
+        e[24..32].copy_from_slice(&((CLUSTER_COUNT as u64).div_ceil(8)).to_le_bytes());
+        let at = image.slot(Dir::Root);
+        let e = &mut image.image[at..at + 32];
+        e[0] = 0x82;
+        e[20..24].copy_from_slice(&UPCASE_CLUSTER.to_le_bytes());
+        e[24..32].copy_from_slice(&(CLUSTER_SIZE as u64).to_le_bytes());
+        image
+    }
+
+    pub fn label(&mut self, label: &str) {
+        let units: Vec<u16> = label.encode_utf16().collect();
+        let at = self.slot(Dir::Root);
+        let e = &mut self.image[at..at + 32];
+        e[0] = 0x83;
+        e[1] = units.len() as u8;
+        for (i, unit) in units.iter().enumerate() {
+            e[2 + i * 2..4 + i * 2].copy_from_slice(&unit.to_le_bytes());
+        }
+    }
+
+    pub fn mkdir(&mut self, parent: Dir, name: &str) -> Node {
+        let cluster = self.allocate(1)[0];
+        self.mkdir_at(parent, name, cluster)
+    }
+
+    // Creates a folder in `cluster`, which may have belonged to something deleted;
+    // like Windows, the cluster is zeroed first
+    pub fn mkdir_at(&mut self, parent: Dir, name: &str, cluster: u32) -> Node {
+        self.allocated[cluster as usize] = true;
+        let at = self.cluster_offset(cluster);
+        self.image[at..at + CLUSTER_SIZE].fill(0);
+        self.next_slot.insert(Dir::Cluster(cluster), 0);
+        let entries = self.add_entry_set(parent, name, 0x10, cluster, CLUSTER_SIZE as u64, true);
+        Node { entries, clusters: vec![cluster], dir: Some(Dir::Cluster(cluster)) }
+    }
+
+    // Contiguous file, written with NoFatChain like Windows does for unfragmented files
+    pub fn write_file(&mut self, parent: Dir, name: &str, data: &[u8]) -> Node {
+        let clusters = self.allocate(data.len().div_ceil(CLUSTER_SIZE).max(1));
+        self.write_data(data, &clusters);
+        let entries = self.add_entry_set(parent, name, 0x20, clusters[0], data.len() as u64, true);
+        Node { entries, clusters, dir: None }
+    }
+
+    // File in the given clusters, linked through the FAT
+    pub fn write_file_chained(&mut self, parent: Dir, name: &str, data: &[u8], clusters: &[u32]) -> Node {
+        for (i, cluster) in clusters.iter().enumerate() {
+            self.allocated[*cluster as usize] = true;
+            self.fat[*cluster as usize] = clusters.get(i + 1).copied().unwrap_or(0xFFFF_FFFF);
+        }
+        self.write_data(data, clusters);
+        let entries = self.add_entry_set(parent, name, 0x20, clusters[0], data.len() as u64, false);
+        Node { entries, clusters: clusters.to_vec(), dir: None }
+    }
+
+    pub fn delete(&mut self, node: &Node) {
+        for at in &node.entries {
+            self.image[*at] &= 0x7F;
+        }
+        for cluster in &node.clusters {
+            self.allocated[*cluster as usize] = false;
+        }
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut image = self.image.clone();
+        let fat_at = FAT_OFFSET * SECTOR_SIZE;
+        for (cluster, value) in self.fat.iter().enumerate() {
+            image[fat_at + cluster * 4..fat_at + cluster * 4 + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        let bitmap_at = self.cluster_offset(BITMAP_CLUSTER);
+        for (cluster, used) in self.allocated.iter().enumerate().skip(2) {
+            if *used {
+                image[bitmap_at + (cluster - 2) / 8] |= 1 << ((cluster - 2) % 8);
+            }
+        }
+        image
+    }
+
+    fn write_boot_sector(&mut self) {
+        let b = &mut self.image[..SECTOR_SIZE];
+        b[0..3].copy_from_slice(&[0xEB, 0x76, 0x90]);
+        b[3..11].copy_from_slice(b"EXFAT   ");
+        b[0x48..0x50].copy_from_slice(&(TOTAL_SECTORS as u64).to_le_bytes());
+        b[0x50..0x54].copy_from_slice(&(FAT_OFFSET as u32).to_le_bytes());
+        b[0x54..0x58].copy_from_slice(&(FAT_LENGTH as u32).to_le_bytes());
+        b[0x58..0x5C].copy_from_slice(&(HEAP_OFFSET as u32).to_le_bytes());
+        b[0x5C..0x60].copy_from_slice(&(CLUSTER_COUNT as u32).to_le_bytes());
+        b[0x60..0x64].copy_from_slice(&ROOT_CLUSTER.to_le_bytes());
+        b[0x68..0x6A].copy_from_slice(&0x0100u16.to_le_bytes());
+        b[0x6C] = 9; // 512-byte sectors
+        b[0x6D] = 3; // 8 sectors per cluster
+        b[0x6E] = 1;
+        b[0x1FE] = 0x55;
+        b[0x1FF] = 0xAA;
+        // Backup boot region
+        self.image.copy_within(..SECTOR_SIZE, 12 * SECTOR_SIZE);
+    }
+
+    fn allocate(&mut self, count: usize) -> Vec<u32> {
+        let clusters: Vec<u32> = (self.next_cluster..self.next_cluster + count as u32).collect();
+        self.next_cluster += count as u32;
+        for cluster in &clusters {
+            self.allocated[*cluster as usize] = true;
+        }
+        clusters
+    }
+
+    fn write_data(&mut self, data: &[u8], clusters: &[u32]) {
+        for (i, chunk) in data.chunks(CLUSTER_SIZE).enumerate() {
+            let at = self.cluster_offset(clusters[i]);
+            self.image[at..at + chunk.len()].copy_from_slice(chunk);
+        }
+    }
+
+    fn cluster_offset(&self, cluster: u32) -> usize {
+        HEAP_OFFSET * SECTOR_SIZE + (cluster as usize - 2) * CLUSTER_SIZE
+    }
+
+    fn slot(&mut self, dir: Dir) -> usize {
+        let index = self.next_slot.entry(dir).or_insert(0);
+        let at = *index * 32;
+        *index += 1;
+        assert!(at < CLUSTER_SIZE, "directory cluster full");
+        let cluster = match dir {
+            Dir::Root => ROOT_CLUSTER,
+            Dir::Cluster(c) => c,
+        };
+        self.cluster_offset(cluster) + at
+    }
+
+    fn add_entry_set(
+        &mut self,
+        parent: Dir,
+        name: &str,
+        attributes: u16,
+        first_cluster: u32,
+        length: u64,
+        no_fat_chain: bool,
+    ) -> Vec<usize> {
+        let units: Vec<u16> = name.encode_utf16().collect();
+        let name_entries = units.len().div_ceil(15);
+        let mut set = vec![[0u8; 32]; 2 + name_entries];
+
+        set[0][0] = 0x85;
+        set[0][1] = (1 + name_entries) as u8;
+        set[0][4..6].copy_from_slice(&attributes.to_le_bytes());
+        for pos in [8, 12, 16] {
+            set[0][pos..pos + 4].copy_from_slice(&DOS_TIMESTAMP.to_le_bytes());
+        }
+
+        set[1][0] = 0xC0;
+        set[1][1] = 0x01 | if no_fat_chain { 0x02 } else { 0 };
+        set[1][3] = units.len() as u8;
+        set[1][8..16].copy_from_slice(&length.to_le_bytes());
+        set[1][20..24].copy_from_slice(&first_cluster.to_le_bytes());
+        set[1][24..32].copy_from_slice(&length.to_le_bytes());
+
+        for (i, chunk) in units.chunks(15).enumerate() {
+            let e = &mut set[2 + i];
+            e[0] = 0xC1;
+            for (c, unit) in chunk.iter().enumerate() {
+                e[2 + c * 2..4 + c * 2].copy_from_slice(&unit.to_le_bytes());
+            }
+        }
+
+        let mut checksum: u16 = 0;
+        for (index, entry) in set.iter().enumerate() {
+            for (pos, byte) in entry.iter().enumerate() {
+                if index == 0 && (pos == 2 || pos == 3) {
+                    continue;
+                }
+                checksum = checksum.rotate_right(1).wrapping_add(*byte as u16);
+            }
+        }
+        set[0][2..4].copy_from_slice(&checksum.to_le_bytes());
+
+        let mut offsets = Vec::new();
+        for entry in set {
+            let at = self.slot(parent);
+            self.image[at..at + 32].copy_from_slice(&entry);
+            offsets.push(at);
+        }
+        offsets
+    }
+}
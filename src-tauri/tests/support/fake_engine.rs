@@ -0,0 +1,18 @@
+// The fake winfr in tests/support, installed under the name a configured engine must have.
+
+use std::fs;
+use std::sync::OnceLock;
+
+// Full path of a copy of the fake winfr named winfr.exe, made once per test binary
+pub fn fake_winfr() -> String {
+    static PATH: OnceLock<String> = OnceLock::new();
+    PATH.get_or_init(|| {
+        let name = format!("winfr-pro-{}-engine-{}", env!("CARGO_CRATE_NAME"), std::process::id());
+        let dir = std::env::temp_dir().join(name);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("winfr.exe");
+        fs::copy(env!("CARGO_BIN_EXE_fake-winfr"), &path).unwrap();
+        path.to_string_lossy().to_string()
+    })
+    .clone()
+}
@@ -0,0 +1,168 @@
+// Stand-in for winfr.exe used by the integration tests.
+//
+// `/?`, `/!` and `/#` print the help screens of winfr 0.1.20151. Otherwise it is invoked with the
+// same arguments as winfr (`<source> <destination> /mode ...`) and its behaviour is
+// read from `fake_winfr.json` in the destination folder:
+//
+//   {
+//     "transcript": "path/to/transcript.txt",  // replayed on stdout as UTF-16LE
+//     "stderr": ["line", ...],                 // written to stderr as UTF-16LE
+//     "lineDelayMs": 0,                        // pause after each stdout line
+//     "recoveryFolder": "Recovery_20240101_120000",
+//     "files": [{ "path": "Users\\kb\\a.jpg", "size": 1024, "header": "FFD8FF" }],
+//     "holdMs": 0,                             // keep running before exiting (cancel tests)
+//     "exitCode": 0,
+//     "crash": false                           // die like winfr's 0xC0000005 access violation
+//   }
+//
+// Transcripts that start with a UTF-16LE BOM are replayed byte for byte; anything else is
+// read as UTF-8 and re-encoded. `{dest}` and `{folder}` are substituted in every line.
+
+use serde::Deserialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+const SCENARIO_FILE: &str = "fake_winfr.json";
+const HELP: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/winfr/help_modern.txt"));
+const ADVANCED_HELP: &str =
+    include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/winfr/help_modern_advanced.txt"));
+const SIGNATURE_GROUPS: &str =
+    include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/winfr/signature_groups.txt"));
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Scenario {
+    transcript: Option<String>,
+    stderr: Vec<String>,
+    #[serde(rename = "lineDelayMs")]
+    line_delay_ms: u64,
+    #[serde(rename = "recoveryFolder")]
+    recovery_folder: Option<String>,
+    files: Vec<FakeFile>,
+    #[serde(rename = "holdMs")]
+    hold_ms: u64,
+    #[serde(rename = "exitCode")]
+    exit_code: i32,
+    crash: bool,
+}
+
+#[derive(Deserialize)]
+struct FakeFile {
+    path: String,
+    size: u64,
+    #[serde(default)]
+    header: String, // hex bytes written before the filler
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let help = match args.first().map(String::as_str) {
+        Some("/?") => Some(HELP),
+        Some("/!") => Some(ADVANCED_HELP),
+        Some("/#") => Some(SIGNATURE_GROUPS),
+        _ => None,
+    };
+    if let Some(text) = help {
+        let text: String = text.lines().map(|line| format!("{}\r\n", line)).collect();
+        let _ = std::io::stdout().write_all(&utf16le(&text));
+        return;
+    }
+
+    let Some(destination) = args.get(1) else {
+        eprintln!("usage: fake-winfr <source> <destination> [switches]");
+        std::process::exit(2);
+    };
+    let destination = PathBuf::from(destination.trim_end_matches('\\'));
+
+    let scenario: Scenario = match fs::read_to_string(destination.join(SCENARIO_FILE)) {
+        Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+            eprintln!("invalid {}: {}", SCENARIO_FILE, e);
+            std::process::exit(2);
+        }),
+        Err(_) => Scenario::default(),
+    };
+    let folder = scenario.recovery_folder.clone().unwrap_or_default();
+
+    if !folder.is_empty() {
+        for file in &scenario.files {
+            write_file(&destination.join(&folder), file);
+        }
+    }
+
+    let dest_text = destination.to_string_lossy().to_string();
+    let substitute = |line: &str| line.replace("{dest}", &dest_text).replace("{folder}", &folder);
+
+    let mut stdout = std::io::stdout();
+    if let Some(transcript) = &scenario.transcript {
+        let bytes = fs::read(transcript).unwrap_or_else(|e| {
+            eprintln!("cannot read transcript {}: {}", transcript, e);
+            std::process::exit(2);
+        });
+        if bytes.starts_with(&[0xFF, 0xFE]) {
+            let _ = stdout.write_all(&bytes);
+            let _ = stdout.flush();
+        } else {
+            for line in String::from_utf8_lossy(&bytes).lines() {
+                let _ = stdout.write_all(&utf16le(&format!("{}\r\n", substitute(line))));
+                let _ = stdout.flush();
+                if scenario.line_delay_ms > 0 {
+                    thread::sleep(Duration::from_millis(scenario.line_delay_ms));
+                }
+            }
+        }
+    }
+
+    let mut stderr = std::io::stderr();
+    for line in &scenario.stderr {
+        let _ = stderr.write_all(&utf16le(&format!("{}\r\n", substitute(line))));
+    }
+    let _ = stderr.flush();
+
+    if scenario.hold_ms > 0 {
+        thread::sleep(Duration::from_millis(scenario.hold_ms));
+    }
+
+    if scenario.crash {
+        crash();
+    }
+    std::process::exit(scenario.exit_code);
+}
+
+fn write_file(root: &Path, file: &FakeFile) {
+    let relative: PathBuf = file.path.split(['\\', '/']).filter(|p| !p.is_empty()).collect();
+    let path = root.join(relative);
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+
+    let mut content: Vec<u8> = (0..file.header.len() / 2)
+        .filter_map(|i| u8::from_str_radix(&file.header[i * 2..i * 2 + 2], 16).ok())
+        .collect();
+    let mut filler = b"winfr-pro".iter().cycle();
+    while (content.len() as u64) < file.size {
+        content.push(*filler.next().unwrap_or(&0));
+    }
+    content.truncate(file.size as usize);
+
+    if let Err(e) = fs::write(&path, content) {
+        eprintln!("cannot write {}: {}", path.display(), e);
+        std::process::exit(2);
+    }
+}
+
+fn utf16le(text: &str) -> Vec<u8> {
+    text.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect()
+}
+
+#[cfg(windows)]
+fn crash() -> ! {
+    std::process::exit(-1073741819); // 0xC0000005
+}
+
+#[cfg(not(windows))]
+fn crash() -> ! {
+    std::process::abort();
+}
@@ -0,0 +1,345 @@
+// Builds small FAT12/16/32 images in memory for the fat tests, laid out the way
+// mkfs.fat and Windows write them: two FAT copies, long file names, and deletion
+// that only marks entries with 0xE5 and frees the cluster chain.
+
+use std::collections::HashMap;
+
+pub const SECTOR_SIZE: usize = 512;
+// 2024-01-01 12:00:00, stored as DOS date and time
+pub const TIMESTAMP: u64 = 1_704_110_400;
+const DOS_DATE: u16 = (44 << 9) | (1 << 5) | 1;
+const DOS_TIME: u16 = 12 << 11;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatKind {
+    Fat12,
+    Fat16,
+    Fat32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dir {
+    Root,
+    Cluster(u32),
+}
+
+pub struct Node {
+    pub entries: Vec<usize>, // byte offsets of the long name and short name entries
+    pub clusters: Vec<u32>,
+    pub dir: Option<Dir>,
+}
+
+pub struct FatImage {
+    kind: FatKind,
+    image: Vec<u8>,
+    fat: Vec<u32>,
+    cluster_size: usize,
+    fat_offset: usize,
+    fat_bytes: usize,
+    root_offset: usize,
+    root_entries: usize,
+    data_offset: usize,
+    next_cluster: u32,
+    next_slot: HashMap<Dir, usize>,
+    aliases: u32,
+}
+
+impl FatImage {
+    pub fn new(kind: FatKind) -> Self {
+        // (total sectors, sectors per cluster, reserved sectors, root entries)
+        let (total, spc, reserved, root_entries) = match kind {
+            FatKind::Fat12 => (2048usize, 1usize, 1usize, 224usize),
+            FatKind::Fat16 => (32768, 4, 1, 512),
+            FatKind::Fat32 => (69632, 1, 32, 0),
+        };
+        let bits = match kind {
+            FatKind::Fat12 => 12,
+            FatKind::Fat16 => 16,
+            FatKind::Fat32 => 32,
+        };
+        let root_sectors = (root_entries * 32).div_ceil(SECTOR_SIZE);
+        let estimate = (total - reserved - root_sectors) / spc + 2;
+        let fat_sectors = (estimate * bits / 8).div_ceil(SECTOR_SIZE);
+        let data_sector = reserved + 2 * fat_sectors + root_sectors;
+        let clusters = (total - data_sector) / spc;
+
+        let mut image = FatImage {
+            kind,
+            image: vec![0u8; total * SECTOR_SIZE],
+            fat: vec![0u32; clusters + 2],
+            cluster_size: spc * SECTOR_SIZE,
+            fat_offset: reserved * SECTOR_SIZE,
+            fat_bytes: fat_sectors * SECTOR_SIZE,
+            root_offset: (reserved + 2 * fat_sectors) * SECTOR_SIZE,
+            root_entries,
+            data_offset: data_sector * SECTOR_SIZE,
+            next_cluster: 2,
+            next_slot: HashMap::new(),
+            aliases: 0,
+        };
+        image.fat[0] = 0x0FFF_FFF8;
+        image.fat[1] = 0x0FFF_FFFF;
+        if kind == FatKind::Fat32 {
+            image.allocate(1); // root directory in cluster 2
+        }
+        image.write_boot_sector(total, spc, reserved, root_entries, fat_sectors);
+        image
+    }
+
+    pub fn cluster_size(&self) -> usize {
+        self.cluster_size
+    }
+
+    pub fn label(&mut self, label: &str) {
+        let mut name = [b' '; 11];
+        name[..label.len()].copy_from_slice(label.as_bytes());
+        let at = self.slot(Dir::Root);
+        self.write_short_entry(at, &name, 0x08, 0, 0);
+    }
+
+    pub fn mkdir(&mut self, parent: Dir, name: &str) -> Node {
+        let cluster = self.allocate(1)[0];
+        self.mkdir_at(parent, name, cluster)
+    }
+
+    // Creates a folder in `cluster`, which may have belonged to something deleted;
+    // like Windows, the cluster is zeroed first
+    pub fn mkdir_at(&mut self, parent: Dir, name: &str, cluster: u32) -> Node {
+        self.fat[cluster as usize] = 0x0FFF_FFFF;
+        let dir = Dir::Cluster(cluster);
+        let entries = self.add_entry(parent, name, 0x10, cluster, 0);
+
+        let at = self.cluster_offset(cluster);
+        self.image[at..at + self.cluster_size].fill(0);
+        self.write_short_entry(at, b".          ", 0x10, cluster, 0);
+        let parent_cluster = match parent {
+            Dir::Cluster(c) if !(self.kind == FatKind::Fat32 && c == 2) => c,
+            _ => 0,
+        };
+        self.write_short_entry(at + 32, b"..         ", 0x10, parent_cluster, 0);
+        self.next_slot.insert(dir, 2);
+        Node { entries, clusters: vec![cluster], dir: Some(dir) }
+    }
+
+    pub fn write_file(&mut self, parent: Dir, name: &str, data: &[u8]) -> Node {
+        let clusters = self.allocate(data.len().div_ceil(self.cluster_size).max(1));
+        self.write_file_at(parent, name, data, &clusters)
+    }
+
+    // Writes `data` into the given clusters, chained in that order
+    pub fn write_file_at(&mut self, parent: Dir, name: &str, data: &[u8], clusters: &[u32]) -> Node {
+        for (i, cluster) in clusters.iter().enumerate() {
+            self.fat[*cluster as usize] = clusters.get(i + 1).copied().unwrap_or(0x0FFF_FFFF);
+            let start = i * self.cluster_size;
+            if start < data.len() {
+                let end = (start + self.cluster_size).min(data.len());
+                let at = self.cluster_offset(*cluster);
+                self.image[at..at + end - start].copy_from_slice(&data[start..end]);
+            }
+        }
+        let entries = self.add_entry(parent, name, 0x20, clusters[0], data.len() as u32);
+        Node { entries, clusters: clusters.to_vec(), dir: None }
+    }
+
+    // What Windows does on delete: mark every entry and free the chain, data untouched
+    pub fn delete(&mut self, node: &Node) {
+        for at in &node.entries {
+            self.image[*at] = 0xE5;
+        }
+        for cluster in &node.clusters {
+            self.fat[*cluster as usize] = 0;
+        }
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut image = self.image.clone();
+        let mut table = vec![0u8; self.fat_bytes];
+        for (cluster, value) in self.fat.iter().enumerate() {
+            match self.kind {
+                FatKind::Fat12 => {
+                    let at = cluster + cluster / 2;
+                    let value = (*value & 0x0FFF) as u16;
+                    if cluster & 1 == 0 {
+                        table[at] = value as u8;
+                        table[at + 1] = (table[at + 1] & 0xF0) | (value >> 8) as u8;
+                    } else {
+                        table[at] = (table[at] & 0x0F) | ((value & 0x0F) << 4) as u8;
+                        table[at + 1] = (value >> 4) as u8;
+                    }
+                }
+                FatKind::Fat16 => table[cluster * 2..cluster * 2 + 2].copy_from_slice(&(*value as u16).to_le_bytes()),
+                FatKind::Fat32 => table[cluster * 4..cluster * 4 + 4].copy_from_slice(&value.to_le_bytes()),
+            }
+        }
+        for copy in 0..2 {
+            let at = self.fat_offset + copy * self.fat_bytes;
+            image[at..at + self.fat_bytes].copy_from_slice(&table);
+        }
+        image
+    }
+
+    fn write_boot_sector(
+        &mut self,
+        total: usize,
+        spc: usize,
+        reserved: usize,
+        root_entries: usize,
+        fat_sectors: usize,
+    ) {
+        let b = &mut self.image[..SECTOR_SIZE];
+        b[0..3].copy_from_slice(&[0xEB, 0x3C, 0x90]);
+        b[3..11].copy_from_slice(b"MSWIN4.1");
+        b[0x0B..0x0D].copy_from_slice(&(SECTOR_SIZE as u16).to_le_bytes());
+        b[0x0D] = spc as u8;
+        b[0x0E..0x10].copy_from_slice(&(reserved as u16).to_le_bytes());
+        b[0x10] = 2;
+        b[0x11..0x13].copy_from_slice(&(root_entries as u16).to_le_bytes());
+        if total < 0x10000 {
+            b[0x13..0x15].copy_from_slice(&(total as u16).to_le_bytes());
+        } else {
+            b[0x20..0x24].copy_from_slice(&(total as u32).to_le_bytes());
+        }
+        b[0x15] = 0xF8;
+        let (signature_at, fs_type): (usize, &[u8; 8]) = match self.kind {
+            FatKind::Fat32 => {
+                b[0x24..0x28].copy_from_slice(&(fat_sectors as u32).to_le_bytes());
+                b[0x2C..0x30].copy_from_slice(&2u32.to_le_bytes());
+                b[0x30..0x32].copy_from_slice(&1u16.to_le_bytes());
+                b[0x32..0x34].copy_from_slice(&6u16.to_le_bytes());
+                (0x42, b"FAT32   ")
+            }
+            kind => {
+                b[0x16..0x18].copy_from_slice(&(fat_sectors as u16).to_le_bytes());
+                (0x26, if kind == FatKind::Fat12 { b"FAT12   " } else { b"FAT16   " })
+            }
+        };
+        b[signature_at] = 0x29;
+        b[signature_at + 5..signature_at + 16].copy_from_slice(b"NO NAME    ");
+        b[signature_at + 16..signature_at + 24].copy_from_slice(fs_type);
+        b[0x1FE] = 0x55;
+        b[0x1FF] = 0xAA;
+        if self.kind == FatKind::Fat32 {
+            self.image.copy_within(..SECTOR_SIZE, 6 * SECTOR_SIZE);
+        }
+    }
+
+    fn allocate(&mut self, count: usize) -> Vec<u32> {
+        let clusters: Vec<u32> = (self.next_cluster..self.next_cluster + count as u32).collect();
+        self.next_cluster += count as u32;
+        for (i, cluster) in clusters.iter().enumerate() {
+            self.fat[*cluster as usize] = clusters.get(i + 1).copied().unwrap_or(0x0FFF_FFFF);
+        }
+        clusters
+    }
+
+    fn cluster_offset(&self, cluster: u32) -> usize {
+        self.data_offset + (cluster as usize - 2) * self.cluster_size
+    }
+
+    fn slot(&mut self, dir: Dir) -> usize {
+        let index = self.next_slot.entry(dir).or_insert(0);
+        let at = *index * 32;
+        *index += 1;
+        match dir {
+            Dir::Root if self.kind != FatKind::Fat32 => {
+                assert!(at < self.root_entries * 32, "root directory full");
+                self.root_offset + at
+            }
+            Dir::Root => self.cluster_offset(2) + at,
+            Dir::Cluster(c) => {
+                assert!(at < self.cluster_size, "directory cluster full");
+                self.cluster_offset(c) + at
+            }
+        }
+    }
+
+    fn add_entry(&mut self, parent: Dir, name: &str, attr: u8, cluster: u32, size: u32) -> Vec<usize> {
+        let mut offsets = Vec::new();
+        let short = match plain_short_name(name) {
+            Some(short) => short,
+            None => {
+                self.aliases += 1;
+                let short = alias(name, self.aliases);
+                let checksum = short.iter().fold(0u8, |sum, b| sum.rotate_right(1).wrapping_add(*b));
+                let mut units: Vec<u16> = name.encode_utf16().collect();
+                // Terminated with 0x0000 unless it fills the last part exactly, then 0xFFFF padding
+                let padded = units.len().div_ceil(13) * 13;
+                if units.len() < padded {
+                    units.push(0);
+                }
+                units.resize(padded, 0xFFFF);
+                let parts = units.len() / 13;
+                for ordinal in (1..=parts).rev() {
+                    let chunk = &units[(ordinal - 1) * 13..ordinal * 13];
+                    let at = self.slot(parent);
+                    let e = &mut self.image[at..at + 32];
+                    e[0] = ordinal as u8 | if ordinal == parts { 0x40 } else { 0 };
+                    e[11] = 0x0F;
+                    e[13] = checksum;
+                    for (i, unit) in chunk.iter().enumerate() {
+                        let pos = match i {
+                            0..=4 => 1 + i * 2,
+                            5..=10 => 14 + (i - 5) * 2,
+                            _ => 28 + (i - 11) * 2,
+                        };
+                        e[pos..pos + 2].copy_from_slice(&unit.to_le_bytes());
+                    }
+                    offsets.push(at);
+                }
+                short
+            }
+        };
+        let at = self.slot(parent);
+        self.write_short_entry(at, &short, attr, cluster, size);
+        offsets.push(at);
+        offsets
+    }
+
+    fn write_short_entry(&mut self, at: usize, name: &[u8; 11], attr: u8, cluster: u32, size: u32) {
+        let e = &mut self.image[at..at + 32];
+        e[0..11].copy_from_slice(name);
+        e[11] = attr;
+        for pos in [14, 22] {
+            e[pos..pos + 2].copy_from_slice(&DOS_TIME.to_le_bytes());
+        }
+        for pos in [16, 18, 24] {
+            e[pos..pos + 2].copy_from_slice(&DOS_DATE.to_le_bytes());
+        }
+        e[20..22].copy_from_slice(&((cluster >> 16) as u16).to_le_bytes());
+        e[26..28].copy_from_slice(&(cluster as u16).to_le_bytes());
+        e[28..32].copy_from_slice(&size.to_le_bytes());
+    }
+}
+
+// Upper-case 8.3 names are stored without a long name
+fn plain_short_name(name: &str) -> Option<[u8; 11]> {
+    let (stem, ext) = name.split_once('.').unwrap_or((name, ""));
+    let valid = |s: &str, max: usize| {
+        s.len() <= max && s.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+    };
+    if stem.is_empty() || !valid(stem, 8) || !valid(ext, 3) {
+        return None;
+    }
+    let mut short = [b' '; 11];
+    short[..stem.len()].copy_from_slice(stem.as_bytes());
+    short[8..8 + ext.len()].copy_from_slice(ext.as_bytes());
+    Some(short)
+}
+
+// "Holiday photo.jpeg" -> "HOLIDA~1JPE"
+fn alias(name: &str, n: u32) -> [u8; 11] {
+    let (stem, ext) = name.rsplit_once('.').unwrap_or((name, ""));
+    let clean = |s: &str| -> Vec<u8> {
+        s.chars().filter(|c| c.is_ascii_alphanumeric()).map(|c| c.to_ascii_uppercase() as u8).collect()
+    };
+    let tail = format!("~{}", n);
+    let mut base = clean(stem);
+    base.truncate(8 - tail.len());
+    base.extend_from_slice(tail.as_bytes());
+    let mut short = [b' '; 11];
+    short[..base.len()].copy_from_slice(&base);
+    let ext = clean(ext);
+    let ext = &ext[..ext.len().min(3)];
+    short[8..8 + ext.len()].copy_from_slice(ext);
+    short
+}
@@ -0,0 +1,59 @@
+// An in-memory source for the imaging tests that fails reads touching injected bad
+// ranges, like a disk with unreadable sectors. Weak ranges recover after a number of
+// failed reads. Every read is logged so tests can check what was read again.
+
+use tauri_app_lib::blockdev::BlockDevice;
+
+struct Fault {
+    pos: u64,
+    len: u64,
+    failures_left: Option<u32>, // None: never readable
+}
+
+pub struct FaultyReader {
+    data: Vec<u8>,
+    faults: Vec<Fault>,
+    pub reads: Vec<(u64, usize)>,
+}
+
+impl FaultyReader {
+    pub fn new(data: Vec<u8>) -> Self {
+        FaultyReader { data, faults: Vec::new(), reads: Vec::new() }
+    }
+
+    pub fn bad(mut self, pos: u64, len: u64) -> Self {
+        self.faults.push(Fault { pos, len, failures_left: None });
+        self
+    }
+
+    pub fn weak(mut self, pos: u64, len: u64, failures: u32) -> Self {
+        self.faults.push(Fault { pos, len, failures_left: Some(failures) });
+        self
+    }
+}
+
+impl BlockDevice for FaultyReader {
+    fn size(&mut self) -> Option<u64> {
+        Some(self.data.len() as u64)
+    }
+
+    fn read_at(&mut self, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+        self.reads.push((offset, len));
+        let end = offset + len as u64;
+        let mut failed = false;
+        for fault in self.faults.iter_mut().filter(|f| f.pos < end && offset < f.pos + f.len) {
+            match &mut fault.failures_left {
+                None => failed = true,
+                Some(0) => {}
+                Some(left) => {
+                    *left -= 1;
+                    failed = true;
+                }
+            }
+        }
+        if failed {
+            return Err(format!("I/O error at offset {}", offset));
+        }
+        Ok(self.data[offset as usize..end as usize].to_vec())
+    }
+}
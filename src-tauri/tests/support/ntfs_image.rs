@@ -0,0 +1,372 @@
+// Builds small NTFS images in memory for the ntfs tests. mkntfs is not available on
+// every CI runner, so the structures it would write are laid out here directly:
+// 512-byte sectors, 4 KiB clusters, 1 KiB MFT records with update sequence fixups.
+
+pub const CLUSTER_SIZE: u64 = 4096;
+pub const MFT_LCN: u64 = 4;
+pub const BITMAP_LCN: u64 = 12;
+pub const FIRST_DATA_LCN: u64 = 16;
+
+const SECTOR_SIZE: usize = 512;
+const RECORD_SIZE: usize = 1024;
+const MFT_RECORDS: u64 = 32;
+const ROOT_RECORD: u64 = 5;
+const MFT_SPLIT: u64 = 16; // first record of the second extent of a fragmented $MFT
+const SPLIT_VCN: u64 = MFT_SPLIT * RECORD_SIZE as u64 / CLUSTER_SIZE;
+// 2024-01-01T00:00:00Z
+pub const TIMESTAMP: u64 = 1_704_067_200;
+
+pub enum Content {
+    None,
+    Resident(Vec<u8>),
+    // (lcn, clusters) pairs; a None lcn is a sparse run
+    Runs { runs: Vec<(Option<u64>, u64)>, data: Vec<u8> },
+}
+
+pub struct Entry {
+    pub record: u64,
+    pub parent: u64,
+    pub parent_sequence: Option<u16>, // defaults to the parent's original sequence
+    pub name: String,
+    pub directory: bool,
+    pub deleted: bool,
+    pub torn: bool, // corrupt the fixup of the second sector
+    pub content: Content,
+}
+
+pub fn entry(record: u64, parent: u64, name: &str) -> Entry {
+    Entry {
+        record,
+        parent,
+        parent_sequence: None,
+        name: name.to_string(),
+        directory: false,
+        deleted: false,
+        torn: false,
+        content: Content::None,
+    }
+}
+
+impl Entry {
+    pub fn directory(mut self) -> Self {
+        self.directory = true;
+        self
+    }
+
+    pub fn deleted(mut self) -> Self {
+        self.deleted = true;
+        self
+    }
+
+    pub fn torn(mut self) -> Self {
+        self.torn = true;
+        self
+    }
+
+    pub fn parent_sequence(mut self, sequence: u16) -> Self {
+        self.parent_sequence = Some(sequence);
+        self
+    }
+
+    pub fn resident(mut self, data: &[u8]) -> Self {
+        self.content = Content::Resident(data.to_vec());
+        self
+    }
+
+    pub fn runs(mut self, runs: &[(Option<u64>, u64)], data: &[u8]) -> Self {
+        self.content = Content::Runs { runs: runs.to_vec(), data: data.to_vec() };
+        self
+    }
+}
+
+pub struct NtfsImage {
+    clusters: u64,
+    entries: Vec<Entry>,
+    used: Vec<bool>,
+    mft_split: Option<(u64, u64)>, // extension record, LCN of the second $MFT extent
+}
+
+impl NtfsImage {
+    pub fn new(clusters: u64) -> Self {
+        let mut used = vec![false; clusters as usize];
+        for flag in used.iter_mut().take(FIRST_DATA_LCN as usize) {
+            *flag = true;
+        }
+        NtfsImage { clusters, entries: Vec::new(), used, mft_split: None }
+    }
+
+    // Splits the $MFT into two extents: records from MFT_SPLIT on move to `lcn`, and the
+    // run for them is kept in `extension` (a record in the first extent), which the
+    // $MFT's $ATTRIBUTE_LIST points to
+    pub fn fragment_mft(&mut self, extension: u64, lcn: u64) {
+        for cluster in lcn..lcn + MFT_RECORDS * RECORD_SIZE as u64 / CLUSTER_SIZE - SPLIT_VCN {
+            self.used[cluster as usize] = true;
+        }
+        self.mft_split = Some((extension, lcn));
+    }
+
+    pub fn add(&mut self, entry: Entry) {
+        // Only live files keep their clusters allocated
+        if let (false, Content::Runs { runs, .. }) = (entry.deleted, &entry.content) {
+            for (lcn, count) in runs {
+                if let Some(lcn) = lcn {
+                    for cluster in *lcn..lcn + count {
+                        self.used[cluster as usize] = true;
+                    }
+                }
+            }
+        }
+        self.entries.push(entry);
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut image = vec![0u8; (self.clusters * CLUSTER_SIZE) as usize];
+        image[..SECTOR_SIZE].copy_from_slice(&self.boot_sector());
+        // Backup boot sector in the last sector, outside the volume's clusters
+        let backup = image.len() - SECTOR_SIZE;
+        image[backup..].copy_from_slice(&self.boot_sector());
+
+        let mft_bytes = MFT_RECORDS * RECORD_SIZE as u64;
+        let mut system = [
+            entry(0, ROOT_RECORD, "$MFT").runs(&[(Some(MFT_LCN), mft_bytes / CLUSTER_SIZE)], &[]),
+            entry(ROOT_RECORD, ROOT_RECORD, ".").directory(),
+            entry(6, ROOT_RECORD, "$Bitmap").runs(&[(Some(BITMAP_LCN), 1)], &[]),
+        ];
+        if let Content::Runs { data, .. } = &mut system[0].content {
+            data.resize(mft_bytes as usize, 0);
+        }
+        let bitmap = self.bitmap();
+        if let Content::Runs { data, .. } = &mut system[2].content {
+            *data = bitmap;
+        }
+
+        if let Some((extension, lcn)) = self.mft_split {
+            let clusters = MFT_RECORDS * RECORD_SIZE as u64 / CLUSTER_SIZE - SPLIT_VCN;
+            let data = non_resident_attribute(0x80, SPLIT_VCN, &[(Some(lcn), clusters)], 0);
+            let at = self.record_offset(extension);
+            image[at..at + RECORD_SIZE].copy_from_slice(&finish_record(1, 0x01, 1 << 48, &data));
+        }
+
+        for entry in system.iter().chain(self.entries.iter()) {
+            let mut record = self.record(entry);
+            if entry.torn {
+                record[SECTOR_SIZE * 2 - 1] ^= 0xFF;
+            }
+            let at = self.record_offset(entry.record);
+            image[at..at + RECORD_SIZE].copy_from_slice(&record);
+
+            // The $MFT data itself is the record area written above
+            if entry.record == 0 {
+                continue;
+            }
+            if let Content::Runs { runs, data } = &entry.content {
+                let mut offset = 0;
+                for (lcn, count) in runs {
+                    let len = (count * CLUSTER_SIZE) as usize;
+                    if let Some(lcn) = lcn {
+                        let end = (offset + len).min(data.len());
+                        if offset < end {
+                            let at = (lcn * CLUSTER_SIZE) as usize;
+                            image[at..at + end - offset].copy_from_slice(&data[offset..end]);
+                        }
+                    }
+                    offset += len;
+                }
+            }
+        }
+        image
+    }
+
+    fn record_offset(&self, record: u64) -> usize {
+        let (lcn, index) = match self.mft_split {
+            Some((_, lcn)) if record >= MFT_SPLIT => (lcn, record - MFT_SPLIT),
+            _ => (MFT_LCN, record),
+        };
+        (lcn * CLUSTER_SIZE) as usize + index as usize * RECORD_SIZE
+    }
+
+    fn boot_sector(&self) -> Vec<u8> {
+        let mut b = vec![0u8; SECTOR_SIZE];
+        b[0..3].copy_from_slice(&[0xEB, 0x52, 0x90]);
+        b[3..11].copy_from_slice(b"NTFS    ");
+        b[0x0B..0x0D].copy_from_slice(&(SECTOR_SIZE as u16).to_le_bytes());
+        b[0x0D] = (CLUSTER_SIZE / SECTOR_SIZE as u64) as u8;
+        b[0x15] = 0xF8;
+        let sectors = self.clusters * CLUSTER_SIZE / SECTOR_SIZE as u64 - 1;
+        b[0x28..0x30].copy_from_slice(&sectors.to_le_bytes());
+        b[0x30..0x38].copy_from_slice(&MFT_LCN.to_le_bytes());
+        b[0x38..0x40].copy_from_slice(&2u64.to_le_bytes());
+        b[0x40] = (-10i8) as u8; // 2^10 byte records
+        b[0x44] = 1;
+        b[0x1FE] = 0x55;
+        b[0x1FF] = 0xAA;
+        b
+    }
+
+    fn bitmap(&self) -> Vec<u8> {
+        let mut bitmap = vec![0u8; (self.clusters as usize).div_ceil(8)];
+        for (cluster, used) in self.used.iter().enumerate() {
+            if *used {
+                bitmap[cluster / 8] |= 1 << (cluster % 8);
+            }
+        }
+        bitmap
+    }
+
+    fn record(&self, entry: &Entry) -> Vec<u8> {
+        let filetime = (TIMESTAMP + 11_644_473_600) * 10_000_000;
+        let mut attrs = Vec::new();
+
+        let mut info = Vec::new();
+        for _ in 0..4 {
+            info.extend_from_slice(&filetime.to_le_bytes());
+        }
+        info.extend_from_slice(&[0u8; 16]);
+        attrs.extend(resident_attribute(0x10, &info));
+
+        if let (0, Some((extension, _))) = (entry.record, self.mft_split) {
+            let mut list = Vec::new();
+            let entries = [(0x10u32, 0, 0), (0x30, 0, 0), (0x80, 0, 0), (0x80, SPLIT_VCN, extension)];
+            for (kind, start_vcn, record) in entries {
+                let mut e = vec![0u8; 0x20];
+                e[0..4].copy_from_slice(&kind.to_le_bytes());
+                e[4..6].copy_from_slice(&0x20u16.to_le_bytes());
+                e[7] = 0x1A;
+                e[8..16].copy_from_slice(&start_vcn.to_le_bytes());
+                e[16..24].copy_from_slice(&(record | 1 << 48).to_le_bytes());
+                list.extend(e);
+            }
+            attrs.extend(resident_attribute(0x20, &list));
+        }
+
+        let parent_sequence = entry.parent_sequence.unwrap_or(1);
+        let parent_ref = entry.parent | (u64::from(parent_sequence) << 48);
+        let units: Vec<u16> = entry.name.encode_utf16().collect();
+        let mut name = Vec::new();
+        name.extend_from_slice(&parent_ref.to_le_bytes());
+        for _ in 0..4 {
+            name.extend_from_slice(&filetime.to_le_bytes());
+        }
+        name.extend_from_slice(&[0u8; 24]);
+        name.push(units.len() as u8);
+        name.push(1); // Win32 namespace
+        for unit in units {
+            name.extend_from_slice(&unit.to_le_bytes());
+        }
+        attrs.extend(resident_attribute(0x30, &name));
+
+        match &entry.content {
+            Content::None => {}
+            Content::Resident(data) => attrs.extend(resident_attribute(0x80, data)),
+            Content::Runs { runs, data } => {
+                // A split $MFT keeps only its first extent in the base record
+                let runs = match (entry.record, self.mft_split) {
+                    (0, Some(_)) => vec![(Some(MFT_LCN), SPLIT_VCN)],
+                    _ => runs.clone(),
+                };
+                attrs.extend(non_resident_attribute(0x80, 0, &runs, data.len() as u64))
+            }
+        }
+
+        // NTFS bumps the sequence number when a record is freed
+        let sequence: u16 = if entry.deleted { 2 } else { 1 };
+        let flags = u16::from(!entry.deleted) | if entry.directory { 0x02 } else { 0 };
+        finish_record(sequence, flags, 0, &attrs)
+    }
+}
+
+// Wraps attributes in a record header and applies the update sequence fixups
+fn finish_record(sequence: u16, flags: u16, base: u64, attrs: &[u8]) -> Vec<u8> {
+    let mut r = vec![0u8; RECORD_SIZE];
+    r[0..4].copy_from_slice(b"FILE");
+    r[0x04..0x06].copy_from_slice(&0x30u16.to_le_bytes()); // update sequence array
+    r[0x06..0x08].copy_from_slice(&3u16.to_le_bytes());
+    r[0x10..0x12].copy_from_slice(&sequence.to_le_bytes());
+    r[0x12..0x14].copy_from_slice(&1u16.to_le_bytes());
+    r[0x14..0x16].copy_from_slice(&0x38u16.to_le_bytes());
+    r[0x16..0x18].copy_from_slice(&flags.to_le_bytes());
+    r[0x1C..0x20].copy_from_slice(&(RECORD_SIZE as u32).to_le_bytes());
+    r[0x20..0x28].copy_from_slice(&base.to_le_bytes());
+
+    let end = 0x38 + attrs.len();
+    r[0x38..end].copy_from_slice(attrs);
+    r[end..end + 4].copy_from_slice(&0xFFFF_FFFFu32.to_le_bytes());
+    r[0x18..0x1C].copy_from_slice(&((end + 8) as u32).to_le_bytes());
+
+    // Move the last two bytes of each sector into the update sequence array
+    let usn = [0x01, 0x00];
+    r[0x30..0x32].copy_from_slice(&usn);
+    for i in 1..=2 {
+        let end = i * SECTOR_SIZE;
+        let saved = [r[end - 2], r[end - 1]];
+        r[0x30 + i * 2..0x32 + i * 2].copy_from_slice(&saved);
+        r[end - 2..end].copy_from_slice(&usn);
+    }
+    r
+}
+
+fn resident_attribute(kind: u32, value: &[u8]) -> Vec<u8> {
+    let length = (0x18 + value.len()).div_ceil(8) * 8;
+    let mut a = vec![0u8; length];
+    a[0..4].copy_from_slice(&kind.to_le_bytes());
+    a[4..8].copy_from_slice(&(length as u32).to_le_bytes());
+    a[0x0A..0x0C].copy_from_slice(&0x18u16.to_le_bytes());
+    a[0x10..0x14].copy_from_slice(&(value.len() as u32).to_le_bytes());
+    a[0x14..0x16].copy_from_slice(&0x18u16.to_le_bytes());
+    a[0x18..0x18 + value.len()].copy_from_slice(value);
+    a
+}
+
+fn non_resident_attribute(kind: u32, start_vcn: u64, runs: &[(Option<u64>, u64)], size: u64) -> Vec<u8> {
+    let runlist = encode_runlist(runs);
+    let length = (0x40 + runlist.len()).div_ceil(8) * 8;
+    let clusters: u64 = runs.iter().map(|(_, count)| count).sum();
+    let mut a = vec![0u8; length];
+    a[0..4].copy_from_slice(&kind.to_le_bytes());
+    a[4..8].copy_from_slice(&(length as u32).to_le_bytes());
+    a[8] = 1;
+    a[0x0A..0x0C].copy_from_slice(&0x40u16.to_le_bytes());
+    a[0x10..0x18].copy_from_slice(&start_vcn.to_le_bytes());
+    a[0x18..0x20].copy_from_slice(&(start_vcn + clusters).saturating_sub(1).to_le_bytes());
+    a[0x20..0x22].copy_from_slice(&0x40u16.to_le_bytes());
+    a[0x28..0x30].copy_from_slice(&(clusters * CLUSTER_SIZE).to_le_bytes());
+    a[0x30..0x38].copy_from_slice(&size.to_le_bytes());
+    a[0x38..0x40].copy_from_slice(&size.to_le_bytes());
+    a[0x40..0x40 + runlist.len()].copy_from_slice(&runlist);
+    a
+}
+
+fn encode_runlist(runs: &[(Option<u64>, u64)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut previous: i64 = 0;
+    for (lcn, count) in runs {
+        let length = minimal_bytes(*count as i64, false);
+        let delta = lcn.map(|l| {
+            let delta = l as i64 - previous;
+            previous = l as i64;
+            delta
+        });
+        let offset = delta.map(|d| minimal_bytes(d, true)).unwrap_or_default();
+        out.push((offset.len() as u8) << 4 | length.len() as u8);
+        out.extend(length);
+        out.extend(offset);
+    }
+    out.push(0);
+    out
+}
+
+fn minimal_bytes(value: i64, signed: bool) -> Vec<u8> {
+    let bytes = value.to_le_bytes();
+    for n in 1..=8 {
+        let fits = if signed {
+            let shift = 64 - 8 * n as u32;
+            n == 8 || (value << shift) >> shift == value
+        } else {
+            n == 8 || (value as u64) >> (8 * n) == 0
+        };
+        if fits {
+            return bytes[..n].to_vec();
+        }
+    }
+    bytes.to_vec()
+}
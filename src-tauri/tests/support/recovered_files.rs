@@ -0,0 +1,68 @@
+// A destination folder as winfr leaves it, for the tests of everything that reads
+// recovered files: a "Recovery_<date>_<time>" folder with files at the given paths.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+use tauri_app_lib::appdata;
+use tauri_app_lib::recovery::RecoveryConfig;
+use tauri_app_lib::session::RecoverySession;
+
+pub const FOLDER: &str = "Recovery_20240101_120000";
+
+// Writes `files` ("Users/Bob/a.pdf", contents) under `dest`/FOLDER and returns `dest`
+pub fn recovery_folder(dest: &Path, files: &[(&str, &[u8])]) -> String {
+    for (path, data) in files {
+        let path = dest.join(FOLDER).join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+    dest.to_string_lossy().to_string()
+}
+
+pub fn recovered(dest: &Path, path: &str) -> PathBuf {
+    dest.join(FOLDER).join(path)
+}
+
+// Points the app data folder (indexes, audit key) at a folder of this test binary, so
+// tests never touch the user's
+pub fn use_test_app_data() {
+    static SET: Once = Once::new();
+    SET.call_once(|| {
+        let name = format!("winfr-pro-{}-appdata-{}", env!("CARGO_CRATE_NAME"), std::process::id());
+        appdata::set_data_dir(&std::env::temp_dir().join(name));
+    });
+}
+
+// A finished session that recovered into `dest`/FOLDER
+pub fn session(dest: &Path) -> RecoverySession {
+    RecoverySession {
+        id: "session-1704110400".to_string(),
+        config: RecoveryConfig {
+            source: "E:".to_string(),
+            destination: dest.to_string_lossy().to_string(),
+            mode: "extensive".to_string(),
+            filters: vec!["*.pdf".to_string(), "*.csv".to_string()],
+            segment_mode: false,
+            signature_mode: false,
+            recover_non_deleted: false,
+            keep_both: false,
+            auto_accept: true,
+            recover_system_files: false,
+            keep_all_extensions: false,
+            source_fs: Some("NTFS".to_string()),
+            verbose_mode: false,
+            winfr_path: None,
+        },
+        args: ["E:", &dest.to_string_lossy(), "/extensive", "/n", "*.pdf", "/n", "*.csv"]
+            .iter()
+            .map(|a| a.to_string())
+            .collect(),
+        started_at: 1_704_110_400,
+        finished_at: Some(1_704_110_400 + 3725),
+        status: "completed".to_string(),
+        exit_code: Some(0),
+        recovery_folder: Some(FOLDER.to_string()),
+        source_integrity: None,
+    }
+}
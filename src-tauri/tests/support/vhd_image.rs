@@ -0,0 +1,358 @@
+// Builds VHD (fixed, dynamic, differencing) and VHDX (plain and differencing) containers
+// in memory for the block device tests. The virtual disk is written with `write`; only
+// blocks holding written sectors are stored, and differencing disks keep a sector bitmap
+// of exactly the sectors written to them.
+
+pub const SECTOR: u64 = 512;
+const MIB: u64 = 1024 * 1024;
+
+const VHDX_BLOCK_SIZE: u64 = MIB;
+const VHDX_CHUNK_RATIO: u64 = (1 << 23) * SECTOR / VHDX_BLOCK_SIZE;
+const BAT_REGION: &str = "2DC27766-F623-4200-9D64-115E9BFD4A08";
+const METADATA_REGION: &str = "8B7CA206-4790-4B9A-B8FE-575F050F886E";
+const FILE_PARAMETERS: &str = "CAA16737-FA36-4D43-B3B6-33F0AA44E76B";
+const VIRTUAL_DISK_SIZE: &str = "2FA54224-CD1B-4876-B211-5DBED83BF4B8";
+const LOGICAL_SECTOR_SIZE: &str = "8141BF1D-A96F-4709-BA47-F233A8FAAB5F";
+const PHYSICAL_SECTOR_SIZE: &str = "CDA348C7-445D-4471-9CC9-E9885251C556";
+const PARENT_LOCATOR: &str = "A8D35F2D-B30B-454D-ABF7-D3D84834AB0C";
+const VHDX_LOCATOR_TYPE: &str = "B04AEFB7-D19E-4A81-B789-25B8E9445913";
+
+pub struct VirtualDisk {
+    data: Vec<u8>,
+    written: Vec<bool>, // per sector
+    id: u8,             // VHD unique ID / VHDX data write GUID, derived from one byte
+    parent: Option<(String, u8)>, // path relative to the child, parent's id
+    pending_log: bool,
+    metadata_length: u64,          // VHDX metadata region length in the region table
+    locator_length: Option<usize>, // VHDX parent locator cut to this many bytes
+}
+
+impl VirtualDisk {
+    pub fn new(size: u64) -> Self {
+        VirtualDisk {
+            data: vec![0u8; size as usize],
+            written: vec![false; (size / SECTOR) as usize],
+            id: 1,
+            parent: None,
+            pending_log: false,
+            metadata_length: MIB,
+            locator_length: None,
+        }
+    }
+
+    pub fn write(&mut self, offset: u64, data: &[u8]) {
+        self.data[offset as usize..offset as usize + data.len()].copy_from_slice(data);
+        let first = offset / SECTOR;
+        let last = (offset + data.len() as u64).div_ceil(SECTOR);
+        self.written[first as usize..last as usize].fill(true);
+    }
+
+    pub fn id(mut self, id: u8) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn child_of(mut self, relative_path: &str, parent_id: u8) -> Self {
+        self.parent = Some((relative_path.to_string(), parent_id));
+        self
+    }
+
+    // A VHDX whose log was never replayed, as after a crash
+    pub fn pending_log(mut self) -> Self {
+        self.pending_log = true;
+        self
+    }
+
+    // A VHDX whose metadata region is cut to `length` bytes
+    pub fn metadata_length(mut self, length: u64) -> Self {
+        self.metadata_length = length;
+        self
+    }
+
+    // A differencing VHDX whose parent locator is cut to `length` bytes
+    pub fn locator_length(mut self, length: usize) -> Self {
+        self.locator_length = Some(length);
+        self
+    }
+
+    fn size(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    // Blocks a non-differencing disk must store: any with a non-zero byte
+    fn block_needed(&self, block: u64, block_size: u64) -> bool {
+        let range = (block * block_size) as usize..((block + 1) * block_size).min(self.size()) as usize;
+        if self.parent.is_some() {
+            self.written[range.start / SECTOR as usize..range.end.div_ceil(SECTOR as usize)].iter().any(|w| *w)
+        } else {
+            self.data[range].iter().any(|b| *b != 0)
+        }
+    }
+
+    // ── VHD ──────────────────────────────────────────────────────────
+
+    pub fn fixed_vhd(&self) -> Vec<u8> {
+        let mut image = self.data.clone();
+        image.extend(self.vhd_footer(2, u64::MAX));
+        image
+    }
+
+    pub fn dynamic_vhd(&self, block_size: u64) -> Vec<u8> {
+        let blocks = self.size().div_ceil(block_size);
+        let bitmap_bytes = (block_size / SECTOR / 8).next_multiple_of(SECTOR);
+        let bat_offset = 512 + 1024;
+        let locator_offset = bat_offset + (blocks * 4).next_multiple_of(SECTOR);
+        let mut image = vec![0u8; (locator_offset + SECTOR) as usize];
+
+        let mut header = vec![0u8; 1024];
+        header[0..8].copy_from_slice(b"cxsparse");
+        header[8..16].copy_from_slice(&u64::MAX.to_be_bytes());
+        header[16..24].copy_from_slice(&bat_offset.to_be_bytes());
+        header[24..28].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+        header[28..32].copy_from_slice(&(blocks as u32).to_be_bytes());
+        header[32..36].copy_from_slice(&(block_size as u32).to_be_bytes());
+        if let Some((path, parent_id)) = &self.parent {
+            header[40..56].copy_from_slice(&[*parent_id; 16]);
+            let name = path.rsplit(['\\', '/']).next().unwrap();
+            for (i, unit) in name.encode_utf16().enumerate() {
+                header[64 + i * 2..66 + i * 2].copy_from_slice(&unit.to_be_bytes());
+            }
+            let locator: Vec<u8> = path.encode_utf16().flat_map(|u| u.to_le_bytes()).collect();
+            header[576..580].copy_from_slice(b"W2ru");
+            header[580..584].copy_from_slice(&1u32.to_be_bytes());
+            header[584..588].copy_from_slice(&(locator.len() as u32).to_be_bytes());
+            header[592..600].copy_from_slice(&locator_offset.to_be_bytes());
+            image[locator_offset as usize..locator_offset as usize + locator.len()].copy_from_slice(&locator);
+        }
+        let checksum = vhd_checksum(&header);
+        header[36..40].copy_from_slice(&checksum.to_be_bytes());
+        image[512..1536].copy_from_slice(&header);
+
+        for block in 0..blocks {
+            let entry = if self.block_needed(block, block_size) {
+                let at = image.len() as u64;
+                let mut bitmap = vec![0u8; bitmap_bytes as usize];
+                let mut data = vec![0u8; block_size as usize];
+                for s in 0..block_size / SECTOR {
+                    let sector = block * block_size / SECTOR + s;
+                    let written = self.written.get(sector as usize).copied().unwrap_or(false);
+                    // Plain dynamic disks mark every sector of an allocated block
+                    if written || self.parent.is_none() {
+                        bitmap[(s / 8) as usize] |= 0x80 >> (s % 8);
+                    }
+                    if (sector * SECTOR) < self.size() {
+                        let from = (sector * SECTOR) as usize;
+                        data[(s * SECTOR) as usize..((s + 1) * SECTOR) as usize]
+                            .copy_from_slice(&self.data[from..from + SECTOR as usize]);
+                    }
+                }
+                image.extend(bitmap);
+                image.extend(data);
+                (at / SECTOR) as u32
+            } else {
+                u32::MAX
+            };
+            let e = (bat_offset + block * 4) as usize;
+            image[e..e + 4].copy_from_slice(&entry.to_be_bytes());
+        }
+
+        let disk_type = if self.parent.is_some() { 4 } else { 3 };
+        let footer = self.vhd_footer(disk_type, 512);
+        image[0..512].copy_from_slice(&footer);
+        image.extend(footer);
+        image
+    }
+
+    fn vhd_footer(&self, disk_type: u32, data_offset: u64) -> Vec<u8> {
+        let mut f = vec![0u8; 512];
+        f[0..8].copy_from_slice(b"conectix");
+        f[8..12].copy_from_slice(&2u32.to_be_bytes());
+        f[12..16].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+        f[16..24].copy_from_slice(&data_offset.to_be_bytes());
+        f[28..32].copy_from_slice(b"wpro");
+        f[36..40].copy_from_slice(b"Wi2k");
+        f[40..48].copy_from_slice(&self.size().to_be_bytes());
+        f[48..56].copy_from_slice(&self.size().to_be_bytes());
+        f[60..64].copy_from_slice(&disk_type.to_be_bytes());
+        f[68..84].copy_from_slice(&[self.id; 16]);
+        let checksum = vhd_checksum(&f);
+        f[64..68].copy_from_slice(&checksum.to_be_bytes());
+        f
+    }
+
+    // ── VHDX ─────────────────────────────────────────────────────────
+
+    pub fn vhdx(&self) -> Vec<u8> {
+        let size = self.size();
+        let data_blocks = size.div_ceil(VHDX_BLOCK_SIZE);
+        let chunks = data_blocks.div_ceil(VHDX_CHUNK_RATIO);
+        let entries = if self.parent.is_some() {
+            chunks * (VHDX_CHUNK_RATIO + 1)
+        } else {
+            data_blocks + (data_blocks - 1) / VHDX_CHUNK_RATIO
+        };
+        let metadata_offset = 2 * MIB;
+        let bat_offset = 3 * MIB;
+        let bat_length = (entries * 8).next_multiple_of(MIB);
+        let mut image = vec![0u8; (bat_offset + bat_length) as usize];
+
+        image[0..8].copy_from_slice(b"vhdxfile");
+        for (i, at) in [64 * 1024usize, 128 * 1024].iter().enumerate() {
+            let mut h = vec![0u8; 4096];
+            h[0..4].copy_from_slice(b"head");
+            h[8..16].copy_from_slice(&(i as u64 + 1).to_le_bytes());
+            h[32..48].copy_from_slice(&[self.id; 16]);
+            if self.pending_log {
+                h[48..64].copy_from_slice(&[0xAB; 16]);
+            }
+            h[66..68].copy_from_slice(&1u16.to_le_bytes());
+            h[68..72].copy_from_slice(&(MIB as u32).to_le_bytes());
+            h[72..80].copy_from_slice(&MIB.to_le_bytes());
+            let crc = crc32c(&h);
+            h[4..8].copy_from_slice(&crc.to_le_bytes());
+            image[*at..*at + 4096].copy_from_slice(&h);
+        }
+
+        let mut regions = vec![0u8; 64 * 1024];
+        regions[0..4].copy_from_slice(b"regi");
+        regions[8..12].copy_from_slice(&2u32.to_le_bytes());
+        let region_entries = [(BAT_REGION, bat_offset, bat_length), (METADATA_REGION, metadata_offset, self.metadata_length)];
+        for (i, (guid, offset, length)) in region_entries.iter().enumerate() {
+            let e = 16 + i * 32;
+            regions[e..e + 16].copy_from_slice(&guid_bytes(guid));
+            regions[e + 16..e + 24].copy_from_slice(&offset.to_le_bytes());
+            regions[e + 24..e + 28].copy_from_slice(&(*length as u32).to_le_bytes());
+            regions[e + 28..e + 32].copy_from_slice(&1u32.to_le_bytes());
+        }
+        let crc = crc32c(&regions);
+        regions[4..8].copy_from_slice(&crc.to_le_bytes());
+        for at in [192 * 1024usize, 256 * 1024] {
+            image[at..at + regions.len()].copy_from_slice(&regions);
+        }
+
+        // Metadata items live 64 KiB into the region
+        let has_parent = if self.parent.is_some() { 2u32 } else { 0 };
+        let mut items: Vec<(&str, Vec<u8>)> = vec![
+            (FILE_PARAMETERS, [(VHDX_BLOCK_SIZE as u32).to_le_bytes(), has_parent.to_le_bytes()].concat()),
+            (VIRTUAL_DISK_SIZE, size.to_le_bytes().to_vec()),
+            (LOGICAL_SECTOR_SIZE, (SECTOR as u32).to_le_bytes().to_vec()),
+            (PHYSICAL_SECTOR_SIZE, 4096u32.to_le_bytes().to_vec()),
+        ];
+        if let Some((path, parent_id)) = &self.parent {
+            let mut locator = parent_locator(path, *parent_id);
+            locator.truncate(self.locator_length.unwrap_or(locator.len()));
+            items.push((PARENT_LOCATOR, locator));
+        }
+        let m = metadata_offset as usize;
+        image[m..m + 8].copy_from_slice(b"metadata");
+        image[m + 10..m + 12].copy_from_slice(&(items.len() as u16).to_le_bytes());
+        let mut item_offset = 64 * 1024;
+        for (i, (guid, value)) in items.iter().enumerate() {
+            let e = m + 32 + i * 32;
+            image[e..e + 16].copy_from_slice(&guid_bytes(guid));
+            image[e + 16..e + 20].copy_from_slice(&(item_offset as u32).to_le_bytes());
+            image[e + 20..e + 24].copy_from_slice(&(value.len() as u32).to_le_bytes());
+            image[e + 24..e + 28].copy_from_slice(&4u32.to_le_bytes());
+            image[m + item_offset..m + item_offset + value.len()].copy_from_slice(value);
+            item_offset += value.len().next_multiple_of(8);
+        }
+
+        let mut bat = vec![0u64; entries as usize];
+        let mut bitmaps: Vec<Option<Vec<u8>>> = vec![None; chunks as usize];
+        for block in 0..data_blocks {
+            if !self.block_needed(block, VHDX_BLOCK_SIZE) {
+                continue;
+            }
+            let first = block * VHDX_BLOCK_SIZE / SECTOR;
+            let sectors = (VHDX_BLOCK_SIZE.min(size - block * VHDX_BLOCK_SIZE)) / SECTOR;
+            let all_written = self.written[first as usize..(first + sectors) as usize].iter().all(|w| *w);
+            let state = if self.parent.is_none() || all_written { 6 } else { 7 };
+            if state == 7 {
+                let chunk = block / VHDX_CHUNK_RATIO;
+                let bitmap = bitmaps[chunk as usize].get_or_insert_with(|| vec![0u8; MIB as usize]);
+                let chunk_first = chunk * VHDX_CHUNK_RATIO * VHDX_BLOCK_SIZE / SECTOR;
+                for s in first..first + sectors {
+                    if self.written[s as usize] {
+                        let bit = s - chunk_first;
+                        bitmap[(bit / 8) as usize] |= 1 << (bit % 8);
+                    }
+                }
+            }
+            let at = image.len() as u64;
+            let mut payload = vec![0u8; VHDX_BLOCK_SIZE as usize];
+            let start = (block * VHDX_BLOCK_SIZE) as usize;
+            let end = (start + VHDX_BLOCK_SIZE as usize).min(size as usize);
+            payload[..end - start].copy_from_slice(&self.data[start..end]);
+            image.extend(payload);
+            bat[(block + block / VHDX_CHUNK_RATIO) as usize] = at | state;
+        }
+        for (chunk, bitmap) in bitmaps.into_iter().enumerate() {
+            if let Some(bitmap) = bitmap {
+                let at = image.len() as u64;
+                image.extend(bitmap);
+                bat[chunk * (VHDX_CHUNK_RATIO as usize + 1) + VHDX_CHUNK_RATIO as usize] = at | 6;
+            }
+        }
+        for (i, entry) in bat.iter().enumerate() {
+            let e = bat_offset as usize + i * 8;
+            image[e..e + 8].copy_from_slice(&entry.to_le_bytes());
+        }
+        image
+    }
+}
+
+fn parent_locator(path: &str, parent_id: u8) -> Vec<u8> {
+    let linkage = format!("{{{}}}", format_guid(&[parent_id; 16]));
+    let pairs = [("parent_linkage", linkage), ("relative_path", path.to_string())];
+    let mut locator = vec![0u8; 20 + pairs.len() * 12];
+    locator[0..16].copy_from_slice(&guid_bytes(VHDX_LOCATOR_TYPE));
+    locator[18..20].copy_from_slice(&(pairs.len() as u16).to_le_bytes());
+    for (i, (key, value)) in pairs.iter().enumerate() {
+        let key: Vec<u8> = key.encode_utf16().flat_map(|u| u.to_le_bytes()).collect();
+        let value: Vec<u8> = value.encode_utf16().flat_map(|u| u.to_le_bytes()).collect();
+        let e = 20 + i * 12;
+        let key_at = locator.len();
+        locator.extend(&key);
+        let value_at = locator.len();
+        locator.extend(&value);
+        locator[e..e + 4].copy_from_slice(&(key_at as u32).to_le_bytes());
+        locator[e + 4..e + 8].copy_from_slice(&(value_at as u32).to_le_bytes());
+        locator[e + 8..e + 10].copy_from_slice(&(key.len() as u16).to_le_bytes());
+        locator[e + 10..e + 12].copy_from_slice(&(value.len() as u16).to_le_bytes());
+    }
+    locator
+}
+
+fn vhd_checksum(b: &[u8]) -> u32 {
+    !b.iter().fold(0u32, |sum, byte| sum.wrapping_add(*byte as u32))
+}
+
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0x82F6_3B78 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn guid_bytes(guid: &str) -> [u8; 16] {
+    let hex: String = guid.chars().filter(|c| *c != '-').collect();
+    let mut raw = [0u8; 16];
+    for (i, byte) in raw.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+    }
+    // The first three groups are stored little-endian
+    raw[0..4].reverse();
+    raw[4..6].reverse();
+    raw[6..8].reverse();
+    raw
+}
+
+fn format_guid(b: &[u8; 16]) -> String {
+    let hex: Vec<String> = b.iter().map(|x| format!("{:02X}", x)).collect();
+    let le = |r: std::ops::Range<usize>| hex[r].iter().rev().cloned().collect::<String>();
+    format!("{}-{}-{}-{}-{}", le(0..4), le(4..6), le(6..8), hex[8..10].concat(), hex[10..16].concat())
+}
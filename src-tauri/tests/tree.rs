@@ -0,0 +1,67 @@
+// Tests for the lazily expanded folder tree of recovered files.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/recovered_files.rs"]
+#[allow(dead_code)]
+mod recovered_files;
+
+use common::scratch_dir;
+use recovered_files::{recovery_folder, use_test_app_data};
+use tauri_app_lib::index;
+use tauri_app_lib::tree::{self, TreeLevel};
+
+fn children(level: &TreeLevel) -> Vec<(&str, bool, u64, u64)> {
+    level.children.iter().map(|n| (n.name.as_str(), n.is_dir, n.file_count, n.total_size)).collect()
+}
+
+#[test]
+fn levels_list_folders_first_with_recursive_totals() {
+    use_test_app_data();
+    let dir = scratch_dir("levels");
+    let dest = recovery_folder(
+        &dir,
+        &[
+            ("Users/Alice/Documents/plan.docx", &[1u8; 100]),
+            ("Users/Alice/photo.jpg", &[1u8; 40]),
+            ("Users/Bob/notes.txt", &[1u8; 7]),
+            ("boot.ini", &[1u8; 3]),
+        ],
+    );
+    index::refresh_index(&dest, |_| {}).unwrap();
+
+    let root = tree::get_recovered_tree(&dest, "\\").unwrap();
+    assert_eq!((root.path.as_str(), root.file_count, root.total_size), ("\\", 4, 150));
+    assert_eq!(children(&root), vec![("Users", true, 3, 147), ("boot.ini", false, 1, 3)]);
+    assert_eq!(root.children[0].path, "\\Users");
+    assert!(root.children[1].file.is_some());
+
+    // Paths are matched without regard to case or separator style
+    let alice = tree::get_recovered_tree(&dest, "/users/ALICE/").unwrap();
+    assert_eq!(alice.path, "\\users\\ALICE");
+    assert_eq!(children(&alice), vec![("Documents", true, 1, 100), ("photo.jpg", false, 1, 40)]);
+    assert_eq!(alice.children[0].path, "\\Users\\Alice\\Documents");
+
+    let missing = tree::get_recovered_tree(&dest, "\\Users\\Carol").unwrap();
+    assert_eq!((missing.file_count, missing.children.len()), (0, 0));
+}
+
+#[test]
+fn flat_signature_output_is_grouped_by_category() {
+    use_test_app_data();
+    let dir = scratch_dir("flat");
+    let dest = recovery_folder(
+        &dir,
+        &[("00001.jpg", &[1u8; 10]), ("00002.png", &[1u8; 20]), ("00003.pdf", &[1u8; 5])],
+    );
+    index::refresh_index(&dest, |_| {}).unwrap();
+
+    let root = tree::get_recovered_tree(&dest, "\\").unwrap();
+    assert_eq!(children(&root), vec![("[Documents]", true, 1, 5), ("[Images]", true, 2, 30)]);
+    assert!(root.children.iter().all(|n| n.is_virtual && n.has_children));
+
+    let images = tree::get_recovered_tree(&dest, &root.children[1].path).unwrap();
+    assert_eq!(children(&images), vec![("00001.jpg", false, 1, 10), ("00002.png", false, 1, 20)]);
+    assert!(images.children.iter().all(|n| !n.is_virtual));
+}
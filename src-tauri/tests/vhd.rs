@@ -0,0 +1,210 @@
+// Tests for the VHD and VHDX block devices: fixed, dynamic and differencing containers
+// from the builder in tests/support must read back as the raw disk they were made from,
+// for the partition parser, the native scanners and imaging alike.
+
+#[path = "support/common.rs"]
+#[allow(dead_code)]
+mod common;
+#[path = "support/disk_image.rs"]
+#[allow(dead_code)]
+mod disk_image;
+#[path = "support/exfat_image.rs"]
+#[allow(dead_code)]
+mod exfat_image;
+#[path = "support/vhd_image.rs"]
+mod vhd_image;
+
+use common::{save, scratch_dir};
+use disk_image::{DiskImage, MIB, SECTOR_SIZE};
+use exfat_image::{Dir, ExfatImage};
+use std::fs;
+use tauri_app_lib::blockdev;
+use tauri_app_lib::events::RecordingSink;
+use tauri_app_lib::exfat;
+use tauri_app_lib::images;
+use tauri_app_lib::imaging::{self, ImagingOptions};
+use tauri_app_lib::partitions::{self, Filesystem};
+use vhd_image::VirtualDisk;
+
+// ── Helpers ──────────────────────────────────────────────────────────
+
+// 16 MiB disk with an MBR and an exFAT volume at 1 MiB holding one deleted clip
+fn camera_disk() -> Vec<u8> {
+    let mut disk = DiskImage::new(16 * MIB);
+    let mib = MIB / SECTOR_SIZE;
+    disk.partition_sector(0, &[(0x07, mib, 8 * mib)]);
+    let mut volume = ExfatImage::new();
+    let clip = volume.write_file(Dir::Root, "clip.mp4", &[7u8; 6000]);
+    volume.delete(&clip);
+    disk.put(MIB, &volume.build());
+    disk.build()
+}
+
+fn virtual_disk(data: &[u8]) -> VirtualDisk {
+    let mut disk = VirtualDisk::new(data.len() as u64);
+    disk.write(0, data);
+    disk
+}
+
+fn read_all(path: &str) -> Vec<u8> {
+    let mut device = blockdev::open_device(path).unwrap();
+    let size = device.size().unwrap();
+    device.read_at(0, size as usize).unwrap()
+}
+
+fn open_error(path: &str) -> String {
+    match blockdev::open_device(path) {
+        Ok(_) => panic!("{} opened", path),
+        Err(e) => e,
+    }
+}
+
+// The parent after a guest wrote into the middle of a sector run, across a block edge
+fn modified(parent: &[u8]) -> (Vec<u8>, Vec<(u64, Vec<u8>)>) {
+    let writes = vec![(2 * MIB - 1536, vec![0xA5; 4096]), (9 * MIB + 512, vec![0x5A; 512])];
+    let mut data = parent.to_vec();
+    for (offset, bytes) in &writes {
+        data[*offset as usize..*offset as usize + bytes.len()].copy_from_slice(bytes);
+    }
+    (data, writes)
+}
+
+fn assert_camera_volume(path: &str) {
+    let layout = partitions::list_partitions(path, false).unwrap();
+    assert_eq!(layout.size, Some(16 * MIB));
+    assert_eq!(layout.partitions.len(), 1);
+    let camera = &layout.partitions[0];
+    assert_eq!(camera.filesystem, Some(Filesystem::Exfat));
+    let listing = exfat::list_deleted_files(&camera.source).unwrap();
+    assert_eq!(listing.files.len(), 1);
+    assert_eq!(listing.files[0].path, "\\clip.mp4");
+}
+
+// ── Tests ────────────────────────────────────────────────────────────
+
+#[test]
+fn fixed_and_dynamic_vhds_read_as_the_raw_disk() {
+    let dir = scratch_dir("vhd");
+    let raw = camera_disk();
+    let disk = virtual_disk(&raw);
+    let fixed = save(&dir, "fixed.vhd", &disk.fixed_vhd());
+    let dynamic = save(&dir, "dynamic.vhd", &disk.dynamic_vhd(2 * MIB));
+
+    for path in [&fixed, &dynamic] {
+        assert!(read_all(path) == raw, "{} differs from the raw disk", path);
+        assert_camera_volume(path);
+    }
+    // Unallocated blocks take no space in the file
+    assert!(fs::metadata(&dynamic).unwrap().len() < raw.len() as u64);
+
+    // Unaligned reads straddling a block edge
+    let mut device = blockdev::open_device(&dynamic).unwrap();
+    assert_eq!(device.read_at(2 * MIB - 3, 10).unwrap(), raw[2 * MIB as usize - 3..2 * MIB as usize + 7]);
+    let past = device.read_at(16 * MIB - 512, 1024).unwrap_err();
+    assert!(past.contains("Read past the end"), "{}", past);
+}
+
+#[test]
+fn vhdx_reads_as_the_raw_disk_and_images_back_to_it() {
+    let dir = scratch_dir("vhdx");
+    let raw = camera_disk();
+    let path = save(&dir, "disk.vhdx", &virtual_disk(&raw).vhdx());
+
+    assert!(read_all(&path) == raw);
+    assert_camera_volume(&path);
+    let info = images::inspect_source(&path).unwrap();
+    assert_eq!(info.volumes.len(), 1);
+    assert_eq!(info.volumes[0].filesystem, Some(Filesystem::Exfat));
+
+    // Imaging a VHDX writes out the plain disk
+    let output = dir.join("disk.img").to_string_lossy().to_string();
+    let mut device = blockdev::open_device(&path).unwrap();
+    let options = ImagingOptions { sector_size: 512, ..Default::default() };
+    let summary = imaging::image_from(device.as_mut(), &path, &output, &options, &RecordingSink::new()).unwrap();
+    assert_eq!(summary.bad_bytes, 0);
+    assert!(fs::read(&output).unwrap() == raw);
+}
+
+#[test]
+fn differencing_disks_overlay_their_parent() {
+    let dir = scratch_dir("diff");
+    let raw = camera_disk();
+    let (expected, writes) = modified(&raw);
+    let mut child = VirtualDisk::new(raw.len() as u64);
+    for (offset, bytes) in &writes {
+        child.write(*offset, bytes);
+    }
+
+    fs::create_dir_all(dir.join("base")).unwrap();
+    let parent = virtual_disk(&raw).id(7);
+    save(&dir.join("base"), "parent.vhd", &parent.dynamic_vhd(2 * MIB));
+    save(&dir.join("base"), "parent.vhdx", &parent.vhdx());
+    let child = child.id(9).child_of("base\\parent.vhd", 7);
+    let vhd = save(&dir, "child.vhd", &child.dynamic_vhd(2 * MIB));
+    let vhdx = save(&dir, "child.vhdx", &child.child_of(".\\base\\parent.vhdx", 7).vhdx());
+
+    for path in [&vhd, &vhdx] {
+        assert!(read_all(path) == expected, "{} differs from the modified disk", path);
+        assert_camera_volume(path);
+    }
+    // Sector-sized reads on each side of the written range
+    let mut device = blockdev::open_device(&vhdx).unwrap();
+    let at = 2 * MIB - 2048;
+    assert_eq!(device.read_at(at, 1024).unwrap(), expected[at as usize..at as usize + 1024]);
+}
+
+#[test]
+fn differencing_disks_refuse_a_missing_or_changed_parent() {
+    let dir = scratch_dir("parent");
+    let raw = camera_disk();
+    let child = VirtualDisk::new(raw.len() as u64).child_of("parent.vhd", 7);
+    let vhd = save(&dir, "child.vhd", &child.dynamic_vhd(2 * MIB));
+    let vhdx = save(&dir, "child.vhdx", &child.child_of("parent.vhdx", 7).vhdx());
+
+    for path in [&vhd, &vhdx] {
+        let error = open_error(path);
+        assert!(error.contains("was not found"), "{}", error);
+    }
+
+    // Parents that were written to after the children were made
+    let parent = virtual_disk(&raw).id(8);
+    save(&dir, "parent.vhd", &parent.dynamic_vhd(2 * MIB));
+    save(&dir, "parent.vhdx", &parent.vhdx());
+    for path in [&vhd, &vhdx] {
+        let error = open_error(path);
+        assert!(error.contains("has changed since"), "{}", error);
+    }
+}
+
+#[test]
+fn damaged_or_unreplayed_containers_are_refused() {
+    let dir = scratch_dir("damaged");
+    let raw = camera_disk();
+    let disk = virtual_disk(&raw);
+
+    let pending = save(&dir, "pending.vhdx", &disk.pending_log().vhdx());
+    let error = open_error(&pending);
+    assert!(error.contains("never applied"), "{}", error);
+
+    // A dynamic VHD survives a damaged trailing footer by using the copy at the start
+    let mut image = virtual_disk(&raw).dynamic_vhd(2 * MIB);
+    let footer = image.len() - 512;
+    image[footer + 70] ^= 0xFF;
+    let patched = save(&dir, "patched.vhd", &image);
+    assert!(read_all(&patched) == raw);
+
+    // Neither copy
+    image[70] ^= 0xFF;
+    let broken = save(&dir, "broken.vhd", &image);
+    let error = open_error(&broken);
+    assert!(error.contains("no valid VHD footer"), "{}", error);
+
+    // VHDX metadata too short to hold its own item count or a parent locator's
+    let short = save(&dir, "short.vhdx", &virtual_disk(&raw).metadata_length(10).vhdx());
+    let child = virtual_disk(&raw).child_of("parent.vhdx", 7).locator_length(18);
+    let orphan = save(&dir, "orphan.vhdx", &child.vhdx());
+    for path in [&short, &orphan] {
+        let error = open_error(path);
+        assert!(error.contains("invalid disk parameters"), "{}", error);
+    }
+}